@@ -1,5 +1,6 @@
 use hex;
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::io;
@@ -62,6 +63,86 @@ fn download_and_verify(
     }
 }
 
+// Lens calibration data from the lensfun project (CC BY-SA 3.0), bundled as a resource.
+const LENSFUN_DB_URL: &str = "https://raw.githubusercontent.com/lensfun/lensfun/v0.3.4/data/db/";
+const LENSFUN_DB_FILES: &[&str] = &[
+    "slr-canon.xml",
+    "slr-nikon.xml",
+    "slr-sony.xml",
+    "slr-pentax.xml",
+    "slr-olympus.xml",
+    "slr-sigma.xml",
+    "slr-tamron.xml",
+    "slr-tokina.xml",
+    "slr-samyang.xml",
+    "mil-canon.xml",
+    "mil-nikon.xml",
+    "mil-sony.xml",
+    "mil-fujifilm.xml",
+    "mil-olympus.xml",
+    "mil-panasonic.xml",
+    "mil-samyang.xml",
+    "mil-sigma.xml",
+    "mil-tamron.xml",
+    "generic.xml",
+];
+
+// Each file is pinned by its sha256 in resources/lensfun/SHA256SUMS (`sha256sum` format);
+// files without a pinned hash are never downloaded.
+fn read_lensfun_checksums(lensfun_dir: &Path) -> HashMap<String, String> {
+    let checksums_path = lensfun_dir.join("SHA256SUMS");
+    println!("cargo:rerun-if-changed={}", checksums_path.display());
+
+    fs::read_to_string(&checksums_path)
+        .unwrap_or_default()
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let hash = parts.next()?;
+            let file = parts.next()?.trim_start_matches('*');
+            Some((file.to_string(), hash.to_lowercase()))
+        })
+        .collect()
+}
+
+fn fetch_lensfun_database(resources_dir: &Path) {
+    let lensfun_dir = resources_dir.join("lensfun");
+    fs::create_dir_all(&lensfun_dir).unwrap();
+    let checksums = read_lensfun_checksums(&lensfun_dir);
+
+    let mut unpinned = Vec::new();
+    for file in LENSFUN_DB_FILES {
+        let Some(expected_hash) = checksums.get(*file) else {
+            unpinned.push(*file);
+            continue;
+        };
+        let dest_path = lensfun_dir.join(file);
+        if dest_path.exists() {
+            if let Ok(true) = verify_sha256(&dest_path, expected_hash) {
+                continue;
+            }
+            fs::remove_file(&dest_path).unwrap();
+        }
+
+        let url = format!("{}{}", LENSFUN_DB_URL, file);
+        // Lens profiles are optional, so an offline build only loses this part of the database.
+        if let Err(e) = download_and_verify(&url, &dest_path, expected_hash) {
+            println!(
+                "cargo:warning=Could not download lens database {}: {}",
+                file, e
+            );
+        }
+    }
+
+    if !unpinned.is_empty() {
+        println!(
+            "cargo:warning=Skipping lens database files without a pinned checksum: {}",
+            unpinned.join(", ")
+        );
+    }
+}
+
 fn main() {
     let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap();
     let target_arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap();
@@ -145,6 +226,8 @@ fn main() {
         }
     }
 
+    fetch_lensfun_database(&resources_dir);
+
     println!("cargo:rerun-if-changed=build.rs");
 
     tauri_build::build()
@@ -17,6 +17,9 @@ use tokenizers::Tokenizer;
 use tokio::sync::Mutex as TokioMutex;
 
 use crate::file_management;
+use crate::liquify::LiquifyMesh;
+use crate::perspective::PerspectiveParams;
+use crate::projection::ProjectionParams;
 
 const ENCODER_URL: &str = "https://huggingface.co/CyberTimon/RapidRAW-Models/resolve/main/vit_t_encoder.onnx?download=true";
 const DECODER_URL: &str = "https://huggingface.co/CyberTimon/RapidRAW-Models/resolve/main/vit_t_decoder.onnx?download=true";
@@ -526,6 +529,12 @@ pub struct AiSubjectMaskParameters {
     pub flip_vertical: Option<bool>,
     #[serde(default)]
     pub orientation_steps: Option<u8>,
+    #[serde(default)]
+    pub perspective: Option<PerspectiveParams>,
+    #[serde(default)]
+    pub projection: Option<ProjectionParams>,
+    #[serde(default)]
+    pub liquify: Option<LiquifyMesh>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -541,6 +550,12 @@ pub struct AiSkyMaskParameters {
     pub flip_vertical: Option<bool>,
     #[serde(default)]
     pub orientation_steps: Option<u8>,
+    #[serde(default)]
+    pub perspective: Option<PerspectiveParams>,
+    #[serde(default)]
+    pub projection: Option<ProjectionParams>,
+    #[serde(default)]
+    pub liquify: Option<LiquifyMesh>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -556,4 +571,10 @@ pub struct AiForegroundMaskParameters {
     pub flip_vertical: Option<bool>,
     #[serde(default)]
     pub orientation_steps: Option<u8>,
+    #[serde(default)]
+    pub perspective: Option<PerspectiveParams>,
+    #[serde(default)]
+    pub projection: Option<ProjectionParams>,
+    #[serde(default)]
+    pub liquify: Option<LiquifyMesh>,
 }
\ No newline at end of file
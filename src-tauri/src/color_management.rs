@@ -0,0 +1,294 @@
+use anyhow::{Result, anyhow};
+use image::{DynamicImage, ImageBuffer, Rgba};
+use lcms2::{CIExyY, CIExyYTRIPLE, Intent, Locale, MLU, Profile, Tag, TagSignature, ToneCurve};
+use nalgebra::{Matrix3, Vector3};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const BRADFORD: [f64; 9] = [
+    0.8951, 0.2664, -0.1614, -0.7502, 1.7135, 0.0367, 0.0389, -0.0685, 1.0296,
+];
+
+const SRGB_PRIMARIES: [[f64; 2]; 3] = [[0.64, 0.33], [0.30, 0.60], [0.15, 0.06]];
+const D65_CHROMATICITY: [f64; 2] = [0.3127, 0.3290];
+const D50_CHROMATICITY: [f64; 2] = [0.3457, 0.3585];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum OutputColorSpace {
+    #[default]
+    Srgb,
+    DisplayP3,
+    AdobeRgb,
+    ProPhotoRgb,
+    Rec2020,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum RenderingIntent {
+    #[default]
+    Perceptual,
+    RelativeColorimetric,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum TransferFunction {
+    Srgb,
+    Gamma(f64),
+    Rec709,
+}
+
+impl TransferFunction {
+    fn encode(self, linear: f64) -> f64 {
+        let v = linear.clamp(0.0, 1.0);
+        match self {
+            TransferFunction::Srgb => {
+                if v <= 0.0031308 {
+                    v * 12.92
+                } else {
+                    1.055 * v.powf(1.0 / 2.4) - 0.055
+                }
+            }
+            TransferFunction::Gamma(gamma) => v.powf(1.0 / gamma),
+            TransferFunction::Rec709 => {
+                if v < 0.018 {
+                    v * 4.5
+                } else {
+                    1.099 * v.powf(0.45) - 0.099
+                }
+            }
+        }
+    }
+
+    // IEC 61966-2-1 style parametric curves (lcms type 4) for the profile TRCs.
+    fn tone_curve(self) -> Result<ToneCurve> {
+        let parametric = |gamma: f64, scale: f64, linear_slope: f64, threshold: f64| {
+            ToneCurve::new_parametric(
+                4,
+                &[
+                    gamma,
+                    1.0 / scale,
+                    (scale - 1.0) / scale,
+                    1.0 / linear_slope,
+                    threshold,
+                ],
+            )
+            .map_err(|e| anyhow!("Failed to build tone curve: {}", e))
+        };
+        match self {
+            TransferFunction::Srgb => parametric(2.4, 1.055, 12.92, 0.04045),
+            TransferFunction::Gamma(gamma) => Ok(ToneCurve::new(gamma)),
+            TransferFunction::Rec709 => parametric(1.0 / 0.45, 1.099, 4.5, 0.081),
+        }
+    }
+
+    fn decode(self, encoded: f64) -> f64 {
+        let v = encoded.clamp(0.0, 1.0);
+        match self {
+            TransferFunction::Srgb => {
+                if v <= 0.04045 {
+                    v / 12.92
+                } else {
+                    ((v + 0.055) / 1.055).powf(2.4)
+                }
+            }
+            TransferFunction::Gamma(gamma) => v.powf(gamma),
+            TransferFunction::Rec709 => {
+                if v < 0.081 {
+                    v / 4.5
+                } else {
+                    ((v + 0.099) / 1.099).powf(1.0 / 0.45)
+                }
+            }
+        }
+    }
+}
+
+struct ColorSpaceDefinition {
+    name: &'static str,
+    primaries: [[f64; 2]; 3],
+    white: [f64; 2],
+    transfer: TransferFunction,
+}
+
+impl OutputColorSpace {
+    fn definition(self) -> ColorSpaceDefinition {
+        match self {
+            OutputColorSpace::Srgb => ColorSpaceDefinition {
+                name: "sRGB",
+                primaries: SRGB_PRIMARIES,
+                white: D65_CHROMATICITY,
+                transfer: TransferFunction::Srgb,
+            },
+            OutputColorSpace::DisplayP3 => ColorSpaceDefinition {
+                name: "Display P3",
+                primaries: [[0.680, 0.320], [0.265, 0.690], [0.150, 0.060]],
+                white: D65_CHROMATICITY,
+                transfer: TransferFunction::Srgb,
+            },
+            OutputColorSpace::AdobeRgb => ColorSpaceDefinition {
+                name: "Adobe RGB (1998)",
+                primaries: [[0.64, 0.33], [0.21, 0.71], [0.15, 0.06]],
+                white: D65_CHROMATICITY,
+                transfer: TransferFunction::Gamma(563.0 / 256.0),
+            },
+            OutputColorSpace::ProPhotoRgb => ColorSpaceDefinition {
+                name: "ProPhoto RGB",
+                primaries: [[0.7347, 0.2653], [0.1596, 0.8404], [0.0366, 0.0001]],
+                white: D50_CHROMATICITY,
+                transfer: TransferFunction::Gamma(1.8),
+            },
+            OutputColorSpace::Rec2020 => ColorSpaceDefinition {
+                name: "Rec. 2020",
+                primaries: [[0.708, 0.292], [0.170, 0.797], [0.131, 0.046]],
+                white: D65_CHROMATICITY,
+                transfer: TransferFunction::Rec709,
+            },
+        }
+    }
+
+    // EXIF only knows sRGB; everything else is tagged uncalibrated and relies
+    // on the embedded profile.
+    pub fn exif_color_space(self) -> u16 {
+        match self {
+            OutputColorSpace::Srgb => 1,
+            _ => 0xFFFF,
+        }
+    }
+}
+
+fn xy_to_xyz(xy: [f64; 2]) -> Vector3<f64> {
+    Vector3::new(xy[0] / xy[1], 1.0, (1.0 - xy[0] - xy[1]) / xy[1])
+}
+
+fn rgb_to_xyz_matrix(primaries: [[f64; 2]; 3], white: [f64; 2]) -> Matrix3<f64> {
+    let columns = Matrix3::from_columns(&[
+        xy_to_xyz(primaries[0]),
+        xy_to_xyz(primaries[1]),
+        xy_to_xyz(primaries[2]),
+    ]);
+    let scale = columns
+        .try_inverse()
+        .map(|inverse| inverse * xy_to_xyz(white))
+        .unwrap_or_else(|| Vector3::new(1.0, 1.0, 1.0));
+    columns * Matrix3::from_diagonal(&scale)
+}
+
+fn bradford_adaptation(from: Vector3<f64>, to: Vector3<f64>) -> Matrix3<f64> {
+    let bradford = Matrix3::from_row_slice(&BRADFORD);
+    let Some(bradford_inverse) = bradford.try_inverse() else {
+        return Matrix3::identity();
+    };
+    let (from_cone, to_cone) = (bradford * from, bradford * to);
+    let gain = Matrix3::from_diagonal(&to_cone.component_div(&from_cone));
+    bradford_inverse * gain * bradford
+}
+
+// Maps linear values in the pipeline's working space (sRGB primaries, D65)
+// to linear values in the output space.
+fn working_to_output_matrix(space: &ColorSpaceDefinition) -> Matrix3<f64> {
+    let working_to_xyz = rgb_to_xyz_matrix(SRGB_PRIMARIES, D65_CHROMATICITY);
+    let adaptation = bradford_adaptation(xy_to_xyz(D65_CHROMATICITY), xy_to_xyz(space.white));
+    let xyz_to_output = rgb_to_xyz_matrix(space.primaries, space.white)
+        .try_inverse()
+        .unwrap_or_else(Matrix3::identity);
+    xyz_to_output * adaptation * working_to_xyz
+}
+
+pub fn working_to_rec2020_matrix() -> Matrix3<f64> {
+    working_to_output_matrix(&OutputColorSpace::Rec2020.definition())
+}
+
+pub fn srgb_to_linear(encoded: f32) -> f32 {
+    TransferFunction::Srgb.decode(encoded as f64) as f32
+}
+
+// Perceptual pulls out-of-gamut colors toward their own luminance so hue is
+// kept; relative colorimetric clips each channel independently.
+fn map_into_gamut(rgb: Vector3<f64>, luma: &Vector3<f64>, intent: RenderingIntent) -> Vector3<f64> {
+    if intent == RenderingIntent::Perceptual {
+        let y = luma.dot(&rgb).clamp(0.0, 1.0);
+        let mut t: f64 = 1.0;
+        for c in rgb.iter() {
+            if *c < 0.0 {
+                t = t.min(y / (y - c));
+            } else if *c > 1.0 && y < 1.0 {
+                t = t.min((1.0 - y) / (c - y));
+            }
+        }
+        let mapped = rgb.map(|c| y + (c - y) * t.max(0.0));
+        return mapped.map(|c| c.clamp(0.0, 1.0));
+    }
+    rgb.map(|c| c.clamp(0.0, 1.0))
+}
+
+// Expects the display-referred sRGB render, ideally the undithered float one
+// so the output transfer curve is applied before anything is quantized.
+pub fn convert_to_output_space(
+    image: DynamicImage,
+    color_space: OutputColorSpace,
+    intent: RenderingIntent,
+) -> DynamicImage {
+    if color_space == OutputColorSpace::Srgb {
+        return image;
+    }
+
+    let space = color_space.definition();
+    let matrix = working_to_output_matrix(&space);
+    let luma = rgb_to_xyz_matrix(space.primaries, space.white)
+        .row(1)
+        .transpose();
+
+    let source = image.to_rgba32f();
+    let (width, height) = source.dimensions();
+    let mut output = ImageBuffer::<Rgba<u16>, Vec<u16>>::new(width, height);
+    output
+        .par_chunks_mut(4)
+        .zip(source.par_chunks(4))
+        .for_each(|(dst, src)| {
+            let linear = Vector3::new(
+                TransferFunction::Srgb.decode(src[0] as f64),
+                TransferFunction::Srgb.decode(src[1] as f64),
+                TransferFunction::Srgb.decode(src[2] as f64),
+            );
+            let mapped = map_into_gamut(matrix * linear, &luma, intent);
+            for (channel, value) in mapped.iter().enumerate() {
+                dst[channel] = (space.transfer.encode(*value) * u16::MAX as f64).round() as u16;
+            }
+            dst[3] = (src[3].clamp(0.0, 1.0) * u16::MAX as f32).round() as u16;
+        });
+
+    DynamicImage::ImageRgba16(output)
+}
+
+// Matrix/TRC display profile for the output space, built by lcms.
+pub fn icc_profile(color_space: OutputColorSpace, intent: RenderingIntent) -> Result<Vec<u8>> {
+    let space = color_space.definition();
+    let xy = |c: [f64; 2]| CIExyY {
+        x: c[0],
+        y: c[1],
+        Y: 1.0,
+    };
+    let primaries = CIExyYTRIPLE {
+        Red: xy(space.primaries[0]),
+        Green: xy(space.primaries[1]),
+        Blue: xy(space.primaries[2]),
+    };
+    let curve = space.transfer.tone_curve()?;
+
+    let mut profile = Profile::new_rgb(&xy(space.white), &primaries, &[&curve, &curve, &curve])
+        .map_err(|e| anyhow!("Failed to build ICC profile: {}", e))?;
+    profile.set_header_rendering_intent(match intent {
+        RenderingIntent::Perceptual => Intent::Perceptual,
+        RenderingIntent::RelativeColorimetric => Intent::RelativeColorimetric,
+    });
+
+    let mut description = MLU::new(1);
+    description.set_text_ascii(space.name, Locale::new("en_US"));
+    profile.write_tag(TagSignature::ProfileDescriptionTag, Tag::MLU(&description));
+
+    profile
+        .icc()
+        .map_err(|e| anyhow!("Failed to serialize ICC profile: {}", e))
+}
@@ -0,0 +1,305 @@
+use serde_json::Value;
+
+use crate::image_processing::Crop;
+use crate::perspective::{self, PerspectiveParams};
+use crate::projection::{self, ProjectionMapper, ProjectionParams};
+
+type Point = (f64, f64);
+
+const SEARCH_ITERATIONS: usize = 40;
+const INSIDE_TOLERANCE: f64 = 0.5;
+const PROJECTION_BOUNDARY_SAMPLES: usize = 96;
+
+pub fn is_constrain_crop_enabled(adjustments: &Value) -> bool {
+    adjustments["constrainCrop"].as_bool().unwrap_or(false)
+}
+
+fn needs_constraint(adjustments: &Value) -> bool {
+    adjustments["rotation"].as_f64().unwrap_or(0.0) % 360.0 != 0.0
+        || perspective::is_perspective_active(adjustments)
+        || projection::is_projection_active(adjustments)
+}
+
+// Keeps the part of a convex polygon where a * x + b * y <= c.
+fn clip_half_plane(polygon: &[Point], a: f64, b: f64, c: f64) -> Vec<Point> {
+    let inside = |p: &Point| a * p.0 + b * p.1 <= c;
+    let mut output = Vec::with_capacity(polygon.len() + 1);
+    for (i, current) in polygon.iter().enumerate() {
+        let next = &polygon[(i + 1) % polygon.len()];
+        if inside(current) {
+            output.push(*current);
+        }
+        if inside(current) != inside(next) {
+            let denom = a * (next.0 - current.0) + b * (next.1 - current.1);
+            if denom.abs() > 1e-12 {
+                let t = (c - a * current.0 - b * current.1) / denom;
+                output.push((
+                    current.0 + t * (next.0 - current.0),
+                    current.1 + t * (next.1 - current.1),
+                ));
+            }
+        }
+    }
+    output
+}
+
+fn clip_to_canvas(polygon: &[Point], width: f64, height: f64) -> Vec<Point> {
+    let polygon = clip_half_plane(polygon, -1.0, 0.0, 0.0);
+    let polygon = clip_half_plane(&polygon, 1.0, 0.0, width);
+    let polygon = clip_half_plane(&polygon, 0.0, -1.0, 0.0);
+    clip_half_plane(&polygon, 0.0, 1.0, height)
+}
+
+fn centroid(polygon: &[Point]) -> Point {
+    let n = polygon.len().max(1) as f64;
+    polygon
+        .iter()
+        .fold((0.0, 0.0), |acc, p| (acc.0 + p.0 / n, acc.1 + p.1 / n))
+}
+
+// Outward edge constraints (a, b, c) with a * x + b * y <= c inside.
+fn half_planes(polygon: &[Point]) -> Vec<(f64, f64, f64)> {
+    let center = centroid(polygon);
+    (0..polygon.len())
+        .filter_map(|i| {
+            let p = polygon[i];
+            let q = polygon[(i + 1) % polygon.len()];
+            let (mut a, mut b) = (q.1 - p.1, p.0 - q.0);
+            let length = (a * a + b * b).sqrt();
+            if length < 1e-9 {
+                return None;
+            }
+            if a * (center.0 - p.0) + b * (center.1 - p.1) > 0.0 {
+                a = -a;
+                b = -b;
+            }
+            let (a, b) = (a / length, b / length);
+            Some((a, b, a * p.0 + b * p.1))
+        })
+        .collect()
+}
+
+// Traces the edge of the area a projection fills by searching outwards from
+// the centre along evenly spaced rays for the last point that still samples
+// the source image. The polygon is star-shaped around the centre, so the
+// intersection of its edge half-planes stays inside the covered area.
+fn projected_boundary(mapper: &ProjectionMapper, w: f64, h: f64) -> Vec<Point> {
+    let (cx, cy) = (w / 2.0, h / 2.0);
+    let covered = |x: f64, y: f64| {
+        x >= 0.0
+            && y >= 0.0
+            && x <= w
+            && y <= h
+            && mapper
+                .source_point(x, y)
+                .is_some_and(|(sx, sy)| sx >= 0.0 && sy >= 0.0 && sx <= w && sy <= h)
+    };
+
+    (0..PROJECTION_BOUNDARY_SAMPLES)
+        .map(|i| {
+            let angle = i as f64 / PROJECTION_BOUNDARY_SAMPLES as f64 * std::f64::consts::TAU;
+            let (dy, dx) = angle.sin_cos();
+            let reach = [(dx, w - cx, cx), (dy, h - cy, cy)]
+                .iter()
+                .filter(|(d, _, _)| d.abs() > 1e-12)
+                .map(|&(d, pos, neg)| if d > 0.0 { pos / d } else { neg / -d })
+                .fold(f64::MAX, f64::min);
+
+            let (mut lo, mut hi) = (0.0, reach);
+            if covered(cx + dx * hi, cy + dy * hi) {
+                lo = hi;
+            } else {
+                for _ in 0..SEARCH_ITERATIONS {
+                    let mid = (lo + hi) / 2.0;
+                    if covered(cx + dx * mid, cy + dy * mid) {
+                        lo = mid;
+                    } else {
+                        hi = mid;
+                    }
+                }
+            }
+            (cx + dx * lo, cy + dy * lo)
+        })
+        .collect()
+}
+
+// The part of the output canvas that is covered by image pixels once the
+// projection, the fine rotation and the perspective warp have been applied.
+pub fn valid_region(width: u32, height: u32, adjustments: &Value) -> Vec<Point> {
+    let (w, h) = (width as f64, height as f64);
+    let (cx, cy) = (w / 2.0, h / 2.0);
+    let angle = adjustments["rotation"].as_f64().unwrap_or(0.0).to_radians();
+    let (sin, cos) = angle.sin_cos();
+
+    let projected = match ProjectionParams::from_adjustments(adjustments).mapper(width, height) {
+        Some(mapper) => projected_boundary(&mapper, w, h),
+        None => vec![(0.0, 0.0), (w, 0.0), (w, h), (0.0, h)],
+    };
+    let rotated: Vec<Point> = projected
+        .iter()
+        .map(|&(x, y)| {
+            let (dx, dy) = (x - cx, y - cy);
+            (cx + dx * cos - dy * sin, cy + dx * sin + dy * cos)
+        })
+        .collect();
+    let region = clip_to_canvas(&rotated, w, h);
+
+    let params = PerspectiveParams::from_adjustments(adjustments);
+    if params.is_identity() {
+        return region;
+    }
+    let matrix = params.matrix(width, height);
+    let warped: Vec<Point> = region
+        .iter()
+        .filter_map(|p| perspective::project(&matrix, p.0, p.1))
+        .collect();
+    clip_to_canvas(&warped, w, h)
+}
+
+// Centres at which a rectangle of the given size fits inside the region.
+fn feasible_centers(planes: &[(f64, f64, f64)], bounds: &[Point], half: Point) -> Vec<Point> {
+    planes.iter().fold(bounds.to_vec(), |polygon, &(a, b, c)| {
+        let reach = a.abs() * half.0 + b.abs() * half.1;
+        clip_half_plane(&polygon, a, b, c - reach)
+    })
+}
+
+fn closest_point(polygon: &[Point], target: Point) -> Option<Point> {
+    if polygon.len() < 3 {
+        return polygon.first().copied();
+    }
+    if half_planes(polygon)
+        .iter()
+        .all(|&(a, b, c)| a * target.0 + b * target.1 <= c + 1e-9)
+    {
+        return Some(target);
+    }
+    (0..polygon.len())
+        .map(|i| {
+            let (p, q) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+            let (dx, dy) = (q.0 - p.0, q.1 - p.1);
+            let length_sq = dx * dx + dy * dy;
+            let t = if length_sq > 0.0 {
+                (((target.0 - p.0) * dx + (target.1 - p.1) * dy) / length_sq).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            (p.0 + t * dx, p.1 + t * dy)
+        })
+        .min_by(|a, b| {
+            let da = (a.0 - target.0).powi(2) + (a.1 - target.1).powi(2);
+            let db = (b.0 - target.0).powi(2) + (b.1 - target.1).powi(2);
+            da.total_cmp(&db)
+        })
+}
+
+fn to_pixel_crop(center: Point, width: f64, height: f64) -> Crop {
+    let x = (center.0 - width / 2.0).ceil();
+    let y = (center.1 - height / 2.0).ceil();
+    Crop {
+        x,
+        y,
+        width: ((center.0 + width / 2.0).floor() - x).max(1.0),
+        height: ((center.1 + height / 2.0).floor() - y).max(1.0),
+    }
+}
+
+// Largest axis-aligned rectangle of the given aspect ratio inside the region.
+// With a preferred crop the result is no larger than it and stays as close to
+// its centre as the region allows.
+pub fn largest_crop(region: &[Point], aspect: f64, preferred: Option<&Crop>) -> Option<Crop> {
+    if region.len() < 3 || !aspect.is_finite() || aspect <= 0.0 {
+        return None;
+    }
+
+    let planes = half_planes(region);
+    let (min_x, max_x, min_y, max_y) =
+        region
+            .iter()
+            .fold((f64::MAX, f64::MIN, f64::MAX, f64::MIN), |acc, p| {
+                (
+                    acc.0.min(p.0),
+                    acc.1.max(p.0),
+                    acc.2.min(p.1),
+                    acc.3.max(p.1),
+                )
+            });
+    let bounds = [
+        (min_x, min_y),
+        (max_x, min_y),
+        (max_x, max_y),
+        (min_x, max_y),
+    ];
+    let fits = |height: f64| {
+        feasible_centers(&planes, &bounds, (aspect * height / 2.0, height / 2.0)).len() >= 3
+    };
+
+    let (mut lo, mut hi) = (0.0, (max_y - min_y).min((max_x - min_x) / aspect));
+    for _ in 0..SEARCH_ITERATIONS {
+        let mid = (lo + hi) / 2.0;
+        if fits(mid) {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    if lo < 1.0 {
+        return None;
+    }
+
+    let (height, target) = match preferred {
+        Some(crop) => (
+            lo.min(crop.height),
+            (crop.x + crop.width / 2.0, crop.y + crop.height / 2.0),
+        ),
+        None => (lo, centroid(region)),
+    };
+    let width = aspect * height;
+    let centers = feasible_centers(&planes, &bounds, (width / 2.0, height / 2.0));
+    let center = closest_point(&centers, target)?;
+    Some(to_pixel_crop(center, width, height))
+}
+
+fn crop_inside(planes: &[(f64, f64, f64)], crop: &Crop) -> bool {
+    let corners = [
+        (crop.x, crop.y),
+        (crop.x + crop.width, crop.y),
+        (crop.x + crop.width, crop.y + crop.height),
+        (crop.x, crop.y + crop.height),
+    ];
+    planes.iter().all(|&(a, b, c)| {
+        corners
+            .iter()
+            .all(|p| a * p.0 + b * p.1 <= c + INSIDE_TOLERANCE)
+    })
+}
+
+pub fn crop_aspect(width: u32, height: u32, adjustments: &Value, crop: Option<&Crop>) -> f64 {
+    crop.filter(|c| c.width > 0.0 && c.height > 0.0)
+        .map(|c| c.width / c.height)
+        .or_else(|| adjustments["aspectRatio"].as_f64().filter(|a| *a > 0.0))
+        .unwrap_or(width as f64 / height.max(1) as f64)
+}
+
+// Shrinks or moves the crop so no transparent borders from projection,
+// rotation or perspective end up in the output. Crops already inside are left alone.
+pub fn constrain_crop(
+    width: u32,
+    height: u32,
+    adjustments: &Value,
+    crop: Option<Crop>,
+) -> Option<Crop> {
+    if !is_constrain_crop_enabled(adjustments) || !needs_constraint(adjustments) {
+        return crop;
+    }
+
+    let region = valid_region(width, height, adjustments);
+    if let Some(c) = &crop {
+        if crop_inside(&half_planes(&region), c) {
+            return crop;
+        }
+    }
+
+    let aspect = crop_aspect(width, height, adjustments, crop.as_ref());
+    largest_crop(&region, aspect, crop.as_ref()).or(crop)
+}
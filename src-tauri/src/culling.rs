@@ -1,13 +1,15 @@
-use image::{GenericImageView, GrayImage, imageops};
+use image::{DynamicImage, GenericImageView, GrayImage, imageops};
 use image_hasher::{HashAlg, HasherConfig};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
-use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use tauri::{AppHandle, Emitter};
 
+use crate::file_management::load_settings;
+use crate::formats::is_raw_file;
 use crate::image_loader;
+use crate::raw_processing;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -120,14 +122,44 @@ fn calculate_exposure_metric(image: &GrayImage) -> f64 {
     (1.0f64 - penalty).max(0.0)
 }
 
-fn analyze_image(path: &str, hasher: &image_hasher::Hasher) -> Result<ImageAnalysisData, String> {
-    const ANALYSIS_DIM: u32 = 512;
+const ANALYSIS_DIM: u32 = 512;
+
+// Returns None for a RAW file without an embedded preview at least as large as
+// the analysis size.
+fn load_analysis_image(
+    path: &str,
+    use_embedded_preview: bool,
+) -> Result<Option<(DynamicImage, (u32, u32))>, String> {
     let file_bytes = std::fs::read(path).map_err(|e| e.to_string())?;
 
-    let img = image_loader::load_base_image_from_bytes(&file_bytes, path, false, 2.5)
-        .map_err(|e| e.to_string())?;
+    if use_embedded_preview && is_raw_file(path) {
+        let Ok(preview) = raw_processing::extract_embedded_preview(&file_bytes, ANALYSIS_DIM)
+        else {
+            return Ok(None);
+        };
+        let dimensions = raw_processing::read_raw_dimensions(&file_bytes)
+            .unwrap_or_else(|_| preview.dimensions());
+        return Ok(Some((preview, dimensions)));
+    }
+
+    let img = image_loader::load_base_image_from_bytes(
+        &file_bytes,
+        path,
+        false,
+        2.5,
+        &serde_json::Value::Null,
+    )
+    .map_err(|e| e.to_string())?;
+    let dimensions = img.dimensions();
+    Ok(Some((img, dimensions)))
+}
 
-    let (width, height) = img.dimensions();
+fn analyze_image(
+    path: &str,
+    img: DynamicImage,
+    (width, height): (u32, u32),
+    hasher: &image_hasher::Hasher,
+) -> ImageAnalysisData {
     let thumbnail = img.thumbnail(ANALYSIS_DIM, ANALYSIS_DIM);
     let gray_thumbnail = thumbnail.to_luma8();
 
@@ -154,7 +186,7 @@ fn analyze_image(path: &str, hasher: &image_hasher::Hasher) -> Result<ImageAnaly
 
     let hash = hasher.hash_image(&thumbnail);
 
-    Ok(ImageAnalysisData {
+    ImageAnalysisData {
         hash,
         result: ImageAnalysisResult {
             path: path.to_string(),
@@ -165,7 +197,41 @@ fn analyze_image(path: &str, hasher: &image_hasher::Hasher) -> Result<ImageAnaly
             width,
             height,
         },
-    })
+    }
+}
+
+// Scores from embedded previews and from full develops don't rank against each
+// other, so a preview run gives up (None) as soon as one RAW file lacks a usable
+// preview and the caller redoes the whole run with full develops.
+fn analyze_all(
+    paths: &[String],
+    hasher: &image_hasher::Hasher,
+    use_embedded_preview: bool,
+    app_handle: &AppHandle,
+) -> Option<Vec<Result<ImageAnalysisData, (String, String)>>> {
+    let total_count = paths.len();
+    let completed_count = AtomicUsize::new(0);
+
+    paths
+        .par_iter()
+        .map(|path| {
+            let analysis = match load_analysis_image(path, use_embedded_preview) {
+                Ok(Some((img, dimensions))) => Ok(analyze_image(path, img, dimensions, hasher)),
+                Ok(None) => return None,
+                Err(e) => Err((path.to_string(), e)),
+            };
+            let completed = completed_count.fetch_add(1, Ordering::Relaxed) + 1;
+            let _ = app_handle.emit(
+                "culling-progress",
+                CullingProgress {
+                    current: completed,
+                    total: total_count,
+                    stage: "Analyzing images...".to_string(),
+                },
+            );
+            Some(analysis)
+        })
+        .collect()
 }
 
 #[tauri::command]
@@ -179,29 +245,27 @@ pub async fn cull_images(
     }
 
     let total_count = paths.len();
-    let completed_count = Arc::new(AtomicUsize::new(0));
     let _ = app_handle.emit("culling-start", total_count);
 
+    let use_embedded_preview = !load_settings(app_handle.clone())
+        .unwrap_or_default()
+        .force_full_raw_development
+        .unwrap_or(false);
+
     let hasher = HasherConfig::new()
         .hash_alg(HashAlg::DoubleGradient)
         .hash_size(16, 16)
         .to_hasher();
 
-    let analysis_results: Vec<Result<ImageAnalysisData, (String, String)>> = paths
-        .par_iter()
-        .map(|path| {
-            let completed = completed_count.fetch_add(1, Ordering::Relaxed) + 1;
-            let _ = app_handle.emit(
-                "culling-progress",
-                CullingProgress {
-                    current: completed,
-                    total: total_count,
-                    stage: "Analyzing images...".to_string(),
-                },
-            );
-            analyze_image(path, &hasher).map_err(|e| (path.to_string(), e))
-        })
-        .collect();
+    let preview_results = if use_embedded_preview {
+        analyze_all(&paths, &hasher, true, &app_handle)
+    } else {
+        None
+    };
+    let analysis_results = match preview_results {
+        Some(results) => results,
+        None => analyze_all(&paths, &hasher, false, &app_handle).unwrap_or_default(),
+    };
 
     let mut successful_analyses = Vec::new();
     let mut failed_paths = Vec::new();
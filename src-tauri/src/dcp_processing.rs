@@ -0,0 +1,701 @@
+use anyhow::{Result, anyhow};
+use nalgebra::{Matrix3, Vector3};
+use once_cell::sync::Lazy;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+const TAG_UNIQUE_CAMERA_MODEL: u16 = 50708;
+const TAG_COLOR_MATRIX_1: u16 = 50721;
+const TAG_COLOR_MATRIX_2: u16 = 50722;
+const TAG_CALIBRATION_ILLUMINANT_1: u16 = 50778;
+const TAG_CALIBRATION_ILLUMINANT_2: u16 = 50779;
+const TAG_PROFILE_NAME: u16 = 50936;
+const TAG_PROFILE_HUE_SAT_MAP_DIMS: u16 = 50937;
+const TAG_PROFILE_HUE_SAT_MAP_DATA_1: u16 = 50938;
+const TAG_PROFILE_HUE_SAT_MAP_DATA_2: u16 = 50939;
+const TAG_PROFILE_TONE_CURVE: u16 = 50940;
+const TAG_FORWARD_MATRIX_1: u16 = 50964;
+const TAG_FORWARD_MATRIX_2: u16 = 50965;
+const TAG_PROFILE_LOOK_TABLE_DIMS: u16 = 50981;
+const TAG_PROFILE_LOOK_TABLE_DATA: u16 = 50982;
+const TAG_PROFILE_HUE_SAT_MAP_ENCODING: u16 = 51107;
+const TAG_PROFILE_LOOK_TABLE_ENCODING: u16 = 51108;
+const TAG_BASELINE_EXPOSURE_OFFSET: u16 = 51109;
+
+const D50_WHITE: [f32; 3] = [0.9642, 1.0, 0.8249];
+const D65_WHITE: [f32; 3] = [0.95047, 1.0, 1.08883];
+
+const BRADFORD: [f32; 9] = [
+    0.8951, 0.2664, -0.1614, -0.7502, 1.7135, 0.0367, 0.0389, -0.0685, 1.0296,
+];
+const PROPHOTO_TO_XYZ_D50: [f32; 9] = [
+    0.7976749, 0.1351917, 0.0313534, 0.2880402, 0.7118741, 0.0000857, 0.0, 0.0, 0.8252100,
+];
+const XYZ_D65_TO_SRGB: [f32; 9] = [
+    3.2404542, -1.5371385, -0.4985314, -0.9692660, 1.8760108, 0.0415560, 0.0556434, -0.2040259,
+    1.0572252,
+];
+
+type CachedDcp = (Option<SystemTime>, Arc<DcpProfile>);
+
+static DCP_CACHE: Lazy<Mutex<HashMap<String, CachedDcp>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone)]
+pub struct HueSatTable {
+    hue_divisions: usize,
+    sat_divisions: usize,
+    val_divisions: usize,
+    srgb_encoded: bool,
+    data: Vec<[f32; 3]>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DcpProfile {
+    pub name: String,
+    pub camera_model: String,
+    illuminants: [u16; 2],
+    color_matrices: [Option<Matrix3<f32>>; 2],
+    forward_matrices: [Option<Matrix3<f32>>; 2],
+    hue_sat_maps: [Option<HueSatTable>; 2],
+    look_table: Option<HueSatTable>,
+    tone_curve: Option<Vec<(f32, f32)>>,
+    baseline_exposure_offset: f32,
+}
+
+struct IfdEntry {
+    field_type: u16,
+    count: usize,
+    offset: usize,
+}
+
+struct DcpReader<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+    entries: HashMap<u16, IfdEntry>,
+}
+
+impl<'a> DcpReader<'a> {
+    fn new(data: &'a [u8]) -> Result<Self> {
+        if data.len() < 8 {
+            return Err(anyhow!("File too small to be a DCP profile"));
+        }
+        let little_endian = match &data[0..2] {
+            b"II" => true,
+            b"MM" => false,
+            _ => return Err(anyhow!("Invalid DCP byte order marker")),
+        };
+
+        let mut reader = Self {
+            data,
+            little_endian,
+            entries: HashMap::new(),
+        };
+
+        let magic = reader.u16_at(2)?;
+        if magic != 0x4352 && magic != 42 {
+            return Err(anyhow!("Unexpected DCP magic number {:#x}", magic));
+        }
+
+        let ifd_offset = reader.u32_at(4)? as usize;
+        let entry_count = reader.u16_at(ifd_offset)? as usize;
+        for i in 0..entry_count {
+            let entry_offset = ifd_offset + 2 + i * 12;
+            let tag = reader.u16_at(entry_offset)?;
+            let field_type = reader.u16_at(entry_offset + 2)?;
+            let count = reader.u32_at(entry_offset + 4)? as usize;
+            let byte_len = type_size(field_type) * count;
+            let offset = if byte_len <= 4 {
+                entry_offset + 8
+            } else {
+                reader.u32_at(entry_offset + 8)? as usize
+            };
+            if offset + byte_len > data.len() {
+                return Err(anyhow!("DCP tag {} points outside of the file", tag));
+            }
+            reader.entries.insert(
+                tag,
+                IfdEntry {
+                    field_type,
+                    count,
+                    offset,
+                },
+            );
+        }
+
+        Ok(reader)
+    }
+
+    fn bytes<const N: usize>(&self, offset: usize) -> Result<[u8; N]> {
+        self.data
+            .get(offset..offset + N)
+            .and_then(|s| s.try_into().ok())
+            .ok_or_else(|| anyhow!("Unexpected end of DCP data at offset {}", offset))
+    }
+
+    fn u16_at(&self, offset: usize) -> Result<u16> {
+        let b = self.bytes::<2>(offset)?;
+        Ok(if self.little_endian {
+            u16::from_le_bytes(b)
+        } else {
+            u16::from_be_bytes(b)
+        })
+    }
+
+    fn u32_at(&self, offset: usize) -> Result<u32> {
+        let b = self.bytes::<4>(offset)?;
+        Ok(if self.little_endian {
+            u32::from_le_bytes(b)
+        } else {
+            u32::from_be_bytes(b)
+        })
+    }
+
+    fn f64_at(&self, offset: usize) -> Result<f64> {
+        let b = self.bytes::<8>(offset)?;
+        Ok(if self.little_endian {
+            f64::from_le_bytes(b)
+        } else {
+            f64::from_be_bytes(b)
+        })
+    }
+
+    fn numbers(&self, tag: u16) -> Option<Vec<f32>> {
+        let entry = self.entries.get(&tag)?;
+        let size = type_size(entry.field_type);
+        (0..entry.count)
+            .map(|i| {
+                let offset = entry.offset + i * size;
+                let value = match entry.field_type {
+                    1 | 7 => self.data[offset] as f32,
+                    3 => self.u16_at(offset).ok()? as f32,
+                    4 => self.u32_at(offset).ok()? as f32,
+                    5 => {
+                        let num = self.u32_at(offset).ok()? as f32;
+                        let den = self.u32_at(offset + 4).ok()? as f32;
+                        if den == 0.0 { 0.0 } else { num / den }
+                    }
+                    8 => self.u16_at(offset).ok()? as i16 as f32,
+                    9 => self.u32_at(offset).ok()? as i32 as f32,
+                    10 => {
+                        let num = self.u32_at(offset).ok()? as i32 as f32;
+                        let den = self.u32_at(offset + 4).ok()? as i32 as f32;
+                        if den == 0.0 { 0.0 } else { num / den }
+                    }
+                    11 => f32::from_bits(self.u32_at(offset).ok()?),
+                    12 => self.f64_at(offset).ok()? as f32,
+                    _ => return None,
+                };
+                Some(value)
+            })
+            .collect()
+    }
+
+    fn string(&self, tag: u16) -> Option<String> {
+        let entry = self.entries.get(&tag)?;
+        let raw = self.data.get(entry.offset..entry.offset + entry.count)?;
+        let end = raw.iter().position(|&b| b == 0).unwrap_or(raw.len());
+        Some(String::from_utf8_lossy(&raw[..end]).trim().to_string())
+    }
+
+    fn matrix(&self, tag: u16) -> Option<Matrix3<f32>> {
+        let values = self.numbers(tag)?;
+        if values.len() < 9 {
+            return None;
+        }
+        Some(Matrix3::from_row_slice(&values[..9]))
+    }
+
+    fn hue_sat_table(
+        &self,
+        dims_tag: u16,
+        data_tag: u16,
+        encoding_tag: u16,
+    ) -> Option<HueSatTable> {
+        let dims = self.numbers(dims_tag)?;
+        if dims.len() < 2 {
+            return None;
+        }
+        let hue_divisions = dims[0] as usize;
+        let sat_divisions = dims[1] as usize;
+        let val_divisions = dims.get(2).map(|&v| v as usize).unwrap_or(1).max(1);
+        if hue_divisions == 0 || sat_divisions == 0 {
+            return None;
+        }
+
+        let values = self.numbers(data_tag)?;
+        let expected = hue_divisions * sat_divisions * val_divisions * 3;
+        if values.len() < expected {
+            return None;
+        }
+
+        let srgb_encoded = self
+            .numbers(encoding_tag)
+            .and_then(|v| v.first().copied())
+            .map_or(false, |v| v == 1.0);
+
+        Some(HueSatTable {
+            hue_divisions,
+            sat_divisions,
+            val_divisions,
+            srgb_encoded,
+            data: values[..expected]
+                .chunks_exact(3)
+                .map(|c| [c[0], c[1], c[2]])
+                .collect(),
+        })
+    }
+}
+
+fn type_size(field_type: u16) -> usize {
+    match field_type {
+        1 | 2 | 6 | 7 => 1,
+        3 | 8 => 2,
+        4 | 9 | 11 => 4,
+        5 | 10 | 12 => 8,
+        _ => 1,
+    }
+}
+
+pub fn read_tiff_number(data: &[u8], tag: u16) -> Option<f32> {
+    DcpReader::new(data).ok()?.numbers(tag)?.first().copied()
+}
+
+pub fn read_tiff_string(data: &[u8], tag: u16) -> Option<String> {
+    DcpReader::new(data).ok()?.string(tag)
+}
+
+pub fn parse_dcp_file(path: &str) -> Result<DcpProfile> {
+    let data = std::fs::read(path)?;
+    let reader = DcpReader::new(&data)?;
+
+    let color_matrices = [
+        reader.matrix(TAG_COLOR_MATRIX_1),
+        reader.matrix(TAG_COLOR_MATRIX_2),
+    ];
+    if color_matrices[0].is_none() {
+        return Err(anyhow!("DCP profile has no ColorMatrix1"));
+    }
+
+    let illuminant = |tag| {
+        reader
+            .numbers(tag)
+            .and_then(|v| v.first().copied())
+            .map_or(0, |v| v as u16)
+    };
+
+    let hue_sat_maps = [
+        reader.hue_sat_table(
+            TAG_PROFILE_HUE_SAT_MAP_DIMS,
+            TAG_PROFILE_HUE_SAT_MAP_DATA_1,
+            TAG_PROFILE_HUE_SAT_MAP_ENCODING,
+        ),
+        reader.hue_sat_table(
+            TAG_PROFILE_HUE_SAT_MAP_DIMS,
+            TAG_PROFILE_HUE_SAT_MAP_DATA_2,
+            TAG_PROFILE_HUE_SAT_MAP_ENCODING,
+        ),
+    ];
+
+    let tone_curve = reader.numbers(TAG_PROFILE_TONE_CURVE).and_then(|v| {
+        let points: Vec<(f32, f32)> = v.chunks_exact(2).map(|p| (p[0], p[1])).collect();
+        if points.len() >= 2 {
+            Some(points)
+        } else {
+            None
+        }
+    });
+
+    Ok(DcpProfile {
+        name: reader.string(TAG_PROFILE_NAME).unwrap_or_default(),
+        camera_model: reader.string(TAG_UNIQUE_CAMERA_MODEL).unwrap_or_default(),
+        illuminants: [
+            illuminant(TAG_CALIBRATION_ILLUMINANT_1),
+            illuminant(TAG_CALIBRATION_ILLUMINANT_2),
+        ],
+        color_matrices,
+        forward_matrices: [
+            reader.matrix(TAG_FORWARD_MATRIX_1),
+            reader.matrix(TAG_FORWARD_MATRIX_2),
+        ],
+        hue_sat_maps,
+        look_table: reader.hue_sat_table(
+            TAG_PROFILE_LOOK_TABLE_DIMS,
+            TAG_PROFILE_LOOK_TABLE_DATA,
+            TAG_PROFILE_LOOK_TABLE_ENCODING,
+        ),
+        tone_curve,
+        baseline_exposure_offset: reader
+            .numbers(TAG_BASELINE_EXPOSURE_OFFSET)
+            .and_then(|v| v.first().copied())
+            .unwrap_or(0.0),
+    })
+}
+
+fn file_mtime(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+pub fn get_or_load_dcp(path: &str) -> Result<Arc<DcpProfile>> {
+    if let Some((mtime, profile)) = DCP_CACHE.lock().unwrap().get(path) {
+        if *mtime == file_mtime(path) {
+            return Ok(profile.clone());
+        }
+    }
+    Ok(cache_dcp(path, parse_dcp_file(path)?))
+}
+
+pub fn cache_dcp(path: &str, profile: DcpProfile) -> Arc<DcpProfile> {
+    let profile = Arc::new(profile);
+    DCP_CACHE
+        .lock()
+        .unwrap()
+        .insert(path.to_string(), (file_mtime(path), profile.clone()));
+    profile
+}
+
+fn illuminant_temperature(illuminant: u16) -> f32 {
+    match illuminant {
+        2 | 14 => 4150.0,
+        3 | 17 => 2856.0,
+        10 | 19 => 6774.0,
+        11 | 22 => 7504.0,
+        12 => 6430.0,
+        13 | 23 => 5003.0,
+        15 => 3525.0,
+        18 => 4874.0,
+        20 => 5503.0,
+        21 => 6504.0,
+        24 => 3200.0,
+        _ => 5500.0,
+    }
+}
+
+fn xyz_to_cct(xyz: &Vector3<f32>) -> f32 {
+    let sum = xyz.x + xyz.y + xyz.z;
+    if sum <= 1e-6 {
+        return 5000.0;
+    }
+    let x = xyz.x / sum;
+    let y = xyz.y / sum;
+    let n = (x - 0.3320) / (0.1858 - y);
+    (449.0 * n.powi(3) + 3525.0 * n.powi(2) + 6823.3 * n + 5520.33).clamp(2000.0, 50000.0)
+}
+
+fn bradford_adaptation(source_white: &Vector3<f32>, target_white: &Vector3<f32>) -> Matrix3<f32> {
+    let bradford = Matrix3::from_row_slice(&BRADFORD);
+    let bradford_inv = bradford.try_inverse().unwrap_or_else(Matrix3::identity);
+    let source_cone = bradford * source_white;
+    let target_cone = bradford * target_white;
+    let scale = Matrix3::from_diagonal(&Vector3::new(
+        target_cone.x / source_cone.x.max(1e-6),
+        target_cone.y / source_cone.y.max(1e-6),
+        target_cone.z / source_cone.z.max(1e-6),
+    ));
+    bradford_inv * scale * bradford
+}
+
+fn prophoto_to_srgb() -> Matrix3<f32> {
+    Matrix3::from_row_slice(&XYZ_D65_TO_SRGB)
+        * bradford_adaptation(&Vector3::from(D50_WHITE), &Vector3::from(D65_WHITE))
+        * Matrix3::from_row_slice(&PROPHOTO_TO_XYZ_D50)
+}
+
+fn blend_matrices(matrices: &[Option<Matrix3<f32>>; 2], weight: f32) -> Option<Matrix3<f32>> {
+    match matrices {
+        [Some(a), Some(b)] => Some(a * weight + b * (1.0 - weight)),
+        [Some(a), None] => Some(*a),
+        [None, Some(b)] => Some(*b),
+        [None, None] => None,
+    }
+}
+
+fn blend_tables(tables: &[Option<HueSatTable>; 2], weight: f32) -> Option<HueSatTable> {
+    match tables {
+        [Some(a), Some(b)] if a.data.len() == b.data.len() => {
+            let mut blended = a.clone();
+            for (out, other) in blended.data.iter_mut().zip(&b.data) {
+                for c in 0..3 {
+                    out[c] = out[c] * weight + other[c] * (1.0 - weight);
+                }
+            }
+            Some(blended)
+        }
+        [Some(a), _] => Some(a.clone()),
+        [None, Some(b)] => Some(b.clone()),
+        [None, None] => None,
+    }
+}
+
+impl DcpProfile {
+    fn illuminant_weight(&self, camera_neutral: &Vector3<f32>) -> f32 {
+        if self.color_matrices[1].is_none() {
+            return 1.0;
+        }
+        let t1 = illuminant_temperature(self.illuminants[0]);
+        let t2 = illuminant_temperature(self.illuminants[1]);
+        if (1.0 / t1 - 1.0 / t2).abs() < 1e-9 {
+            return 1.0;
+        }
+
+        let mut weight = 0.5;
+        for _ in 0..4 {
+            let Some(color_matrix) = blend_matrices(&self.color_matrices, weight) else {
+                break;
+            };
+            let Some(camera_to_xyz) = color_matrix.try_inverse() else {
+                break;
+            };
+            let cct = xyz_to_cct(&(camera_to_xyz * camera_neutral));
+            weight = ((1.0 / cct - 1.0 / t2) / (1.0 / t1 - 1.0 / t2)).clamp(0.0, 1.0);
+        }
+        weight
+    }
+
+    fn camera_to_xyz_d50(&self, camera_neutral: &Vector3<f32>, weight: f32) -> Matrix3<f32> {
+        if let Some(forward_matrix) = blend_matrices(&self.forward_matrices, weight) {
+            return forward_matrix;
+        }
+
+        let Some(camera_to_xyz) =
+            blend_matrices(&self.color_matrices, weight).and_then(|m| m.try_inverse())
+        else {
+            return Matrix3::identity();
+        };
+        let neutral_xyz = camera_to_xyz * camera_neutral;
+        let adaptation = bradford_adaptation(&neutral_xyz, &Vector3::from(D50_WHITE));
+        adaptation * camera_to_xyz * Matrix3::from_diagonal(camera_neutral)
+    }
+
+    pub fn has_look_table(&self) -> bool {
+        self.look_table.is_some()
+    }
+
+    pub fn has_tone_curve(&self) -> bool {
+        self.tone_curve.is_some()
+    }
+
+    pub fn is_dual_illuminant(&self) -> bool {
+        self.color_matrices[1].is_some()
+    }
+
+    // UniqueCameraModel is usually "<Make> <Model>", while decoders may report the full maker
+    // name or repeat it in the model, so compare on alphanumerics only. Untagged profiles
+    // match nothing.
+    pub fn matches_camera(&self, make: &str, model: &str) -> bool {
+        let normalize = |s: &str| -> String {
+            s.chars()
+                .filter(|c| c.is_ascii_alphanumeric())
+                .map(|c| c.to_ascii_lowercase())
+                .collect()
+        };
+        let profile_camera = normalize(&self.camera_model);
+        if profile_camera.is_empty() {
+            return false;
+        }
+        let make = normalize(make);
+        let model = normalize(model);
+        !model.is_empty()
+            && (profile_camera == model
+                || profile_camera == format!("{}{}", make, model)
+                || profile_camera.ends_with(&model))
+    }
+
+    fn camera_to_prophoto(&self, neutral: &Vector3<f32>, weight: f32) -> Matrix3<f32> {
+        let xyz_to_prophoto = Matrix3::from_row_slice(&PROPHOTO_TO_XYZ_D50)
+            .try_inverse()
+            .unwrap_or_else(Matrix3::identity);
+        xyz_to_prophoto * self.camera_to_xyz_d50(neutral, weight)
+    }
+
+    // Matrix part of `apply` for white-balanced camera RGB, leaving out the
+    // hue/sat maps, look table and tone curve.
+    pub fn camera_to_srgb(&self, camera_neutral: [f32; 3]) -> Matrix3<f32> {
+        let neutral = Vector3::from(camera_neutral);
+        let weight = self.illuminant_weight(&neutral);
+        prophoto_to_srgb() * self.camera_to_prophoto(&neutral, weight)
+    }
+
+    pub fn apply(&self, pixels: &mut [[f32; 3]], camera_neutral: [f32; 3], apply_tone_curve: bool) {
+        let neutral = Vector3::from(camera_neutral);
+        let weight = self.illuminant_weight(&neutral);
+
+        let camera_to_prophoto = self.camera_to_prophoto(&neutral, weight);
+        let prophoto_to_srgb = prophoto_to_srgb();
+
+        let hue_sat_map = blend_tables(&self.hue_sat_maps, weight);
+        let exposure_scale = 2.0f32.powf(self.baseline_exposure_offset);
+        let tone_curve = if apply_tone_curve {
+            self.tone_curve.as_deref()
+        } else {
+            None
+        };
+
+        pixels.par_iter_mut().for_each(|p| {
+            let camera = Vector3::new(p[0], p[1], p[2]);
+            let prophoto = (camera_to_prophoto * camera).map(|c| c.max(0.0));
+            let mut rgb = [prophoto.x, prophoto.y, prophoto.z];
+
+            if let Some(table) = &hue_sat_map {
+                rgb = apply_hue_sat_table(table, rgb);
+            }
+            if exposure_scale != 1.0 {
+                rgb = rgb.map(|c| c * exposure_scale);
+            }
+            if let Some(table) = &self.look_table {
+                rgb = apply_hue_sat_table(table, rgb);
+            }
+            if let Some(curve) = tone_curve {
+                rgb = apply_rgb_tone(curve, rgb);
+            }
+
+            let srgb = prophoto_to_srgb * Vector3::new(rgb[0], rgb[1], rgb[2]);
+            *p = [srgb.x.max(0.0), srgb.y.max(0.0), srgb.z.max(0.0)];
+        });
+    }
+}
+
+fn rgb_to_hsv(rgb: [f32; 3]) -> (f32, f32, f32) {
+    let [r, g, b] = rgb;
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+    if max <= 0.0 || delta <= 0.0 {
+        return (0.0, 0.0, max);
+    }
+    let s = delta / max;
+    let mut h = if r == max {
+        (g - b) / delta
+    } else if g == max {
+        2.0 + (b - r) / delta
+    } else {
+        4.0 + (r - g) / delta
+    };
+    if h < 0.0 {
+        h += 6.0;
+    }
+    (h, s, max)
+}
+
+fn hsv_to_rgb(h: f32, s: f32, v: f32) -> [f32; 3] {
+    if s <= 0.0 {
+        return [v, v, v];
+    }
+    let h = h.rem_euclid(6.0);
+    let i = h.floor();
+    let f = h - i;
+    let p = v * (1.0 - s);
+    let q = v * (1.0 - s * f);
+    let t = v * (1.0 - s * (1.0 - f));
+    match i as u32 {
+        0 => [v, t, p],
+        1 => [q, v, p],
+        2 => [p, v, t],
+        3 => [p, q, v],
+        4 => [t, p, v],
+        _ => [v, p, q],
+    }
+}
+
+fn srgb_encode(v: f32) -> f32 {
+    if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn apply_hue_sat_table(table: &HueSatTable, rgb: [f32; 3]) -> [f32; 3] {
+    let (h, s, v) = rgb_to_hsv(rgb);
+
+    let hue_pos = h * table.hue_divisions as f32 / 6.0;
+    let h0 = hue_pos.floor() as usize % table.hue_divisions;
+    let h1 = (h0 + 1) % table.hue_divisions;
+    let hf = hue_pos - hue_pos.floor();
+
+    let axis = |value: f32, divisions: usize| -> (usize, usize, f32) {
+        if divisions <= 1 {
+            return (0, 0, 0.0);
+        }
+        let pos = value.clamp(0.0, 1.0) * (divisions - 1) as f32;
+        let i0 = (pos.floor() as usize).min(divisions - 2);
+        (i0, i0 + 1, pos - i0 as f32)
+    };
+    let (s0, s1, sf) = axis(s, table.sat_divisions);
+    let lookup_v = if table.srgb_encoded {
+        srgb_encode(v)
+    } else {
+        v
+    };
+    let (v0, v1, vf) = axis(lookup_v, table.val_divisions);
+
+    let sample = |vi: usize, hi: usize, si: usize| {
+        table.data[(vi * table.hue_divisions + hi) * table.sat_divisions + si]
+    };
+    let lerp = |a: [f32; 3], b: [f32; 3], t: f32| -> [f32; 3] {
+        [
+            a[0] + (b[0] - a[0]) * t,
+            a[1] + (b[1] - a[1]) * t,
+            a[2] + (b[2] - a[2]) * t,
+        ]
+    };
+    let plane = |vi: usize| {
+        lerp(
+            lerp(sample(vi, h0, s0), sample(vi, h0, s1), sf),
+            lerp(sample(vi, h1, s0), sample(vi, h1, s1), sf),
+            hf,
+        )
+    };
+    let [hue_shift, sat_scale, val_scale] = lerp(plane(v0), plane(v1), vf);
+
+    hsv_to_rgb(
+        h + hue_shift * 6.0 / 360.0,
+        (s * sat_scale).clamp(0.0, 1.0),
+        v * val_scale,
+    )
+}
+
+fn evaluate_curve(curve: &[(f32, f32)], x: f32) -> f32 {
+    let (last_x, last_y) = curve[curve.len() - 1];
+    if x >= last_x {
+        return last_y + (x - last_x);
+    }
+    if x <= curve[0].0 {
+        return curve[0].1;
+    }
+    let idx = curve.partition_point(|&(px, _)| px <= x).max(1);
+    let (x0, y0) = curve[idx - 1];
+    let (x1, y1) = curve[idx];
+    if x1 - x0 <= 1e-9 {
+        y0
+    } else {
+        y0 + (y1 - y0) * (x - x0) / (x1 - x0)
+    }
+}
+
+fn apply_rgb_tone(curve: &[(f32, f32)], rgb: [f32; 3]) -> [f32; 3] {
+    let mut order = [0usize, 1, 2];
+    order.sort_by(|&a, &b| {
+        rgb[b]
+            .partial_cmp(&rgb[a])
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let (max_i, mid_i, min_i) = (order[0], order[1], order[2]);
+
+    let max_v = rgb[max_i];
+    let min_v = rgb[min_i];
+    let new_max = evaluate_curve(curve, max_v);
+    let new_min = evaluate_curve(curve, min_v);
+
+    let mut out = [0.0f32; 3];
+    out[max_i] = new_max;
+    out[min_i] = new_min;
+    out[mid_i] = if max_v - min_v > 1e-9 {
+        new_min + (new_max - new_min) * (rgb[mid_i] - min_v) / (max_v - min_v)
+    } else {
+        new_max
+    };
+    out
+}
@@ -1,5 +1,4 @@
-use crate::formats::is_raw_file;
-use crate::image_loader::load_base_image_from_bytes;
+use crate::image_loader::{is_linear_source, load_base_image_from_bytes};
 use crate::image_processing::apply_cpu_default_raw_processing;
 use base64::{engine::general_purpose, Engine as _};
 use image::{DynamicImage, GenericImageView, ImageFormat, Rgb, Rgb32FImage};
@@ -44,6 +43,7 @@ impl Bm3dParams {
 pub fn denoise_image(
     path_str: String,
     intensity: f32,
+    adjustments: &serde_json::Value,
     app_handle: AppHandle,
 ) -> Result<(DynamicImage, String), String> {
     let path = Path::new(&path_str);
@@ -51,14 +51,15 @@ pub fn denoise_image(
         return Err("File not found".to_string());
     }
 
-    let is_raw = is_raw_file(&path_str);
+    let is_raw = is_linear_source(&path_str);
 
     let _ = app_handle.emit("denoise-progress", "Loading image...");
 
     let file_bytes = fs::read(path).map_err(|e| e.to_string())?;
 
-    let mut dynamic_img = load_base_image_from_bytes(&file_bytes, &path_str, false, 2.5)
-        .map_err(|e| e.to_string())?;
+    let mut dynamic_img =
+        load_base_image_from_bytes(&file_bytes, &path_str, false, 2.5, adjustments)
+            .map_err(|e| e.to_string())?;
 
     if is_raw {
         let _ = app_handle.emit("denoise-progress", "Preparing RAW data...");
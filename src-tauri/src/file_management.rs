@@ -30,6 +30,7 @@ use uuid::Uuid;
 use walkdir::WalkDir;
 
 use crate::AppState;
+use crate::ai_processing::run_sky_seg_model;
 use crate::formats::{is_raw_file, is_supported_image_file};
 use crate::gpu_processing;
 use crate::image_loader;
@@ -40,7 +41,9 @@ use crate::image_processing::{
 };
 use crate::mask_generation::{MaskDefinition, generate_mask_bitmap};
 use crate::preset_converter;
+use crate::raw_utils::calibration::CalibrationFrames;
 use crate::tagging::COLOR_TAG_PREFIX;
+use crate::upright;
 
 const THUMBNAIL_WIDTH: u32 = 640;
 
@@ -200,7 +203,7 @@ fn default_included_adjustments() -> HashSet<String> {
         "negativeGreenBalance", "negativeRedBalance", "saturation", "sectionVisibility",
         "shadows", "sharpness", "showClipping", "structure", "temperature", "tint",
         "toneMapper", "vibrance", "vignetteAmount", "vignetteFeather", "vignetteMidpoint",
-        "vignetteRoundness", "whites",
+        "vignetteRoundness", "whiteBalanceKelvin", "whiteBalanceTint", "whites",
     ]
     .iter()
     .map(|s| s.to_string())
@@ -278,6 +281,11 @@ pub struct AppSettings {
     pub processing_backend: Option<String>,
     #[serde(default)]
     pub linux_gpu_optimization: Option<bool>,
+    // When false, culling only reads embedded previews if every RAW file in the
+    // run has one at least as large as the analysis size; otherwise the whole
+    // run is fully developed so scores stay comparable.
+    #[serde(default)]
+    pub force_full_raw_development: Option<bool>,
 }
 
 fn default_adjustment_visibility() -> HashMap<String, bool> {
@@ -329,6 +337,7 @@ impl Default for AppSettings {
             linux_gpu_optimization: Some(true),
             #[cfg(not(target_os = "linux"))]
             linux_gpu_optimization: Some(false),
+            force_full_raw_development: Some(false),
         }
     }
 }
@@ -421,6 +430,63 @@ pub async fn read_exif_for_paths(
     Ok(exif_data)
 }
 
+fn raw_sidecars_in_dir(path: &str) -> Result<Vec<PathBuf>, String> {
+    if !Path::new(path).is_dir() {
+        return Err(format!("Not a folder: {}", path));
+    }
+    Ok(list_images_in_dir(path.to_string())?
+        .into_iter()
+        .filter(|image| is_raw_file(&parse_virtual_path(&image.path).0.to_string_lossy()))
+        .map(|image| parse_virtual_path(&image.path).1)
+        .collect())
+}
+
+fn read_sidecar_metadata(sidecar_path: &Path) -> ImageMetadata {
+    fs::read_to_string(sidecar_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn get_folder_calibration(path: String) -> Result<CalibrationFrames, String> {
+    let mut common: Option<CalibrationFrames> = None;
+    for sidecar_path in raw_sidecars_in_dir(&path)? {
+        let metadata = read_sidecar_metadata(&sidecar_path);
+        let frames = CalibrationFrames::from_adjustments(&metadata.adjustments);
+        match &common {
+            Some(existing) if *existing != frames => return Ok(CalibrationFrames::default()),
+            Some(_) => {}
+            None => common = Some(frames),
+        }
+    }
+    Ok(common.unwrap_or_default())
+}
+
+#[tauri::command]
+pub fn set_folder_calibration(path: String, frames: CalibrationFrames) -> Result<(), String> {
+    raw_sidecars_in_dir(&path)?
+        .par_iter()
+        .try_for_each(|sidecar_path| {
+            let mut metadata = read_sidecar_metadata(sidecar_path);
+            if !metadata.adjustments.is_object() {
+                metadata.adjustments = serde_json::json!({});
+            }
+            if let Some(map) = metadata.adjustments.as_object_mut() {
+                map.insert(
+                    "darkFramePaths".to_string(),
+                    serde_json::json!(frames.dark_frame_paths),
+                );
+                map.insert(
+                    "flatFieldPaths".to_string(),
+                    serde_json::json!(frames.flat_field_paths),
+                );
+            }
+            let json_string = serde_json::to_string_pretty(&metadata).map_err(|e| e.to_string())?;
+            fs::write(sidecar_path, json_string).map_err(|e| e.to_string())
+        })
+}
+
 #[tauri::command]
 pub fn list_images_in_dir(path: String) -> Result<Vec<ImageFile>, String> {
     let entries = fs::read_dir(&path).map_err(|e| e.to_string())?;
@@ -724,7 +790,7 @@ pub fn generate_thumbnail_data(
 ) -> anyhow::Result<DynamicImage> {
     let (source_path, sidecar_path) = parse_virtual_path(path_str);
     let source_path_str = source_path.to_string_lossy().to_string();
-    let is_raw = is_raw_file(&source_path_str);
+    let is_raw = image_loader::is_linear_source(&source_path_str);
 
     let metadata: Option<ImageMetadata> = fs::read_to_string(sidecar_path)
         .ok()
@@ -737,8 +803,25 @@ pub fn generate_thumbnail_data(
     let settings = crate::file_management::load_settings(app_handle.clone()).unwrap_or_default();
     let highlight_compression = settings.raw_highlight_compression.unwrap_or(2.5);
 
+    let is_unedited =
+        adjustments.is_null() || adjustments.as_object().map_or(false, |map| map.is_empty());
+    let embedded_preview = if is_raw
+        && is_unedited
+        && preloaded_image.is_none()
+        && !settings.force_full_raw_development.unwrap_or(false)
+    {
+        read_file_mapped(&source_path).ok().and_then(|mmap| {
+            crate::raw_processing::extract_embedded_preview(&mmap, THUMBNAIL_WIDTH).ok()
+        })
+    } else {
+        None
+    };
+
+    let is_raw = thumbnail_is_linear(is_raw, embedded_preview.is_some());
     let composite_image = if let Some(img) = preloaded_image {
         image_loader::composite_patches_on_image(img, &adjustments)?
+    } else if let Some(preview) = embedded_preview {
+        preview
     } else {
         match read_file_mapped(&source_path) {
             Ok(mmap) => image_loader::load_and_composite(
@@ -774,13 +857,12 @@ pub fn generate_thumbnail_data(
             const THUMBNAIL_PROCESSING_DIM: u32 = 1280;
             let orientation_steps =
                 meta.adjustments["orientationSteps"].as_u64().unwrap_or(0) as u8;
-            let coarse_rotated_image = apply_coarse_rotation(composite_image, orientation_steps);
-            let (full_w, full_h) = coarse_rotated_image.dimensions();
+            let (full_w, full_h) = composite_image.dimensions();
 
             let (processing_base, scale_for_gpu) =
                 if full_w > THUMBNAIL_PROCESSING_DIM || full_h > THUMBNAIL_PROCESSING_DIM {
                     let base = crate::image_processing::downscale_f32_image(
-                        &coarse_rotated_image,
+                        &composite_image,
                         THUMBNAIL_PROCESSING_DIM,
                         THUMBNAIL_PROCESSING_DIM,
                     );
@@ -791,7 +873,7 @@ pub fn generate_thumbnail_data(
                     };
                     (base, scale)
                 } else {
-                    (coarse_rotated_image.clone(), 1.0)
+                    (composite_image, 1.0)
                 };
 
             let rotation_degrees = meta.adjustments["rotation"].as_f64().unwrap_or(0.0) as f32;
@@ -800,27 +882,47 @@ pub fn generate_thumbnail_data(
                 .unwrap_or(false);
             let flip_vertical = meta.adjustments["flipVertical"].as_bool().unwrap_or(false);
 
-            let flipped_image = apply_flip(processing_base, flip_horizontal, flip_vertical);
-            let rotated_image = apply_rotation(&flipped_image, rotation_degrees);
+            let corrected_image = crate::lens_correction::apply_lens_correction(
+                processing_base,
+                &meta.adjustments,
+                is_raw,
+            );
+            let coarse_rotated_image = apply_coarse_rotation(corrected_image, orientation_steps);
+            let flipped_image = apply_flip(coarse_rotated_image, flip_horizontal, flip_vertical);
+            let projected_image =
+                crate::projection::apply_projection(flipped_image, &meta.adjustments);
+            let rotated_image = apply_rotation(&projected_image, rotation_degrees);
+            let perspective_image =
+                crate::perspective::apply_perspective(rotated_image, &meta.adjustments);
+            let transformed_image =
+                crate::liquify::apply_liquify(perspective_image, &meta.adjustments);
 
             let crop_data: Option<Crop> =
                 serde_json::from_value(meta.adjustments["crop"].clone()).ok();
-            let scaled_crop_json = if let Some(c) = &crop_data {
-                serde_json::to_value(Crop {
-                    x: c.x * scale_for_gpu as f64,
-                    y: c.y * scale_for_gpu as f64,
-                    width: c.width * scale_for_gpu as f64,
-                    height: c.height * scale_for_gpu as f64,
-                })
-                .unwrap_or(serde_json::Value::Null)
-            } else {
-                serde_json::Value::Null
-            };
+            let scaled_crop = crop_data.map(|c| Crop {
+                x: c.x * scale_for_gpu as f64,
+                y: c.y * scale_for_gpu as f64,
+                width: c.width * scale_for_gpu as f64,
+                height: c.height * scale_for_gpu as f64,
+            });
+            let scaled_crop = crate::crop_constraint::constrain_crop(
+                transformed_image.width(),
+                transformed_image.height(),
+                &meta.adjustments,
+                scaled_crop,
+            );
+            let scaled_crop_json =
+                serde_json::to_value(scaled_crop).unwrap_or(serde_json::Value::Null);
 
-            let cropped_preview = apply_crop(rotated_image, &scaled_crop_json);
+            let cropped_preview = apply_crop(transformed_image, &scaled_crop_json);
             let (preview_w, preview_h) = cropped_preview.dimensions();
 
-            let unscaled_crop_offset = crop_data.map_or((0.0, 0.0), |c| (c.x as f32, c.y as f32));
+            let unscaled_crop_offset = scaled_crop.map_or((0.0, 0.0), |c| {
+                (
+                    (c.x / scale_for_gpu as f64) as f32,
+                    (c.y / scale_for_gpu as f64) as f32,
+                )
+            });
 
             let mask_definitions: Vec<MaskDefinition> = meta
                 .adjustments
@@ -881,17 +983,32 @@ pub fn generate_thumbnail_data(
         }
     }
 
-    let mut final_image = composite_image;
+    Ok(finish_unprocessed_thumbnail(
+        composite_image,
+        &adjustments,
+        is_raw,
+    ))
+}
+
+// Embedded previews are camera-rendered JPEGs, so they are never treated as
+// linear sensor data even when they come from a RAW file.
+fn thumbnail_is_linear(is_raw: bool, used_embedded_preview: bool) -> bool {
+    is_raw && !used_embedded_preview
+}
 
-    if is_raw && adjustments.is_null() {
+fn finish_unprocessed_thumbnail(
+    image: DynamicImage,
+    adjustments: &Value,
+    is_linear: bool,
+) -> DynamicImage {
+    let mut final_image = image;
+
+    if is_linear && adjustments.is_null() {
         apply_cpu_default_raw_processing(&mut final_image);
     }
 
     let fallback_orientation_steps = adjustments["orientationSteps"].as_u64().unwrap_or(0) as u8;
-    Ok(apply_coarse_rotation(
-        final_image,
-        fallback_orientation_steps,
-    ))
+    apply_coarse_rotation(final_image, fallback_orientation_steps)
 }
 
 fn encode_thumbnail(image: &DynamicImage) -> Result<Vec<u8>> {
@@ -1551,36 +1668,68 @@ pub fn reset_adjustments_for_paths(
 #[tauri::command]
 pub fn apply_auto_adjustments_to_paths(
     paths: Vec<String>,
+    include_straighten: Option<bool>,
     app_handle: AppHandle,
 ) -> Result<(), String> {
+    let include_straighten = include_straighten.unwrap_or(false);
     let settings = load_settings(app_handle.clone()).unwrap_or_default();
     let highlight_compression = settings.raw_highlight_compression.unwrap_or(2.5);
+    let sky_models = if include_straighten {
+        app_handle
+            .state::<AppState>()
+            .ai_state
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|ai_state| ai_state.models.clone())
+    } else {
+        None
+    };
 
     paths.par_iter().for_each(|path| {
         let result: Result<(), String> = (|| {
             let (source_path, sidecar_path) = parse_virtual_path(path);
             let source_path_str = source_path.to_string_lossy().to_string();
 
+            let mut existing_metadata: ImageMetadata = if sidecar_path.exists() {
+                fs::read_to_string(&sidecar_path)
+                    .ok()
+                    .and_then(|content| serde_json::from_str(&content).ok())
+                    .unwrap_or_default()
+            } else {
+                ImageMetadata::default()
+            };
+
             let file_bytes = fs::read(&source_path).map_err(|e| e.to_string())?;
             let image = image_loader::load_base_image_from_bytes(
                 &file_bytes,
                 &source_path_str,
                 false,
                 highlight_compression,
+                &existing_metadata.adjustments,
             )
             .map_err(|e| e.to_string())?;
 
             let auto_results = perform_auto_analysis(&image);
-            let auto_adjustments_json = auto_results_to_json(&auto_results);
-
-            let mut existing_metadata: ImageMetadata = if sidecar_path.exists() {
-                fs::read_to_string(&sidecar_path)
-                    .ok()
-                    .and_then(|content| serde_json::from_str(&content).ok())
-                    .unwrap_or_default()
-            } else {
-                ImageMetadata::default()
-            };
+            let mut auto_adjustments_json = auto_results_to_json(&auto_results);
+
+            if include_straighten {
+                let is_raw = image_loader::is_linear_source(&source_path_str);
+                let preview =
+                    upright::straighten_preview(&image, &existing_metadata.adjustments, is_raw);
+                let sky_mask = sky_models.as_ref().and_then(|models| {
+                    run_sky_seg_model(&preview, &models.sky_seg)
+                        .map_err(|e| {
+                            log::warn!("Sky segmentation for auto straighten failed: {}", e)
+                        })
+                        .ok()
+                });
+                let estimate = upright::estimate_straighten(&preview, is_raw, sky_mask.as_ref());
+                if estimate.accepted {
+                    auto_adjustments_json["rotation"] = serde_json::json!(estimate.rotation);
+                    auto_adjustments_json["crop"] = Value::Null;
+                }
+            }
 
             if existing_metadata.adjustments.is_null() {
                 existing_metadata.adjustments = serde_json::json!({});
@@ -2484,4 +2633,28 @@ pub fn create_virtual_copy(source_virtual_path: String) -> Result<String, String
     }
 
     Ok(new_virtual_path)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn embedded_preview_without_sidecar_skips_default_raw_processing() {
+        let preview = DynamicImage::ImageRgb8(RgbImage::from_pixel(4, 3, Rgb([128, 96, 64])));
+
+        let thumbnail = finish_unprocessed_thumbnail(
+            preview.clone(),
+            &Value::Null,
+            thumbnail_is_linear(true, true),
+        );
+        assert_eq!(thumbnail.to_rgb8(), preview.to_rgb8());
+
+        let developed = finish_unprocessed_thumbnail(
+            preview.clone(),
+            &Value::Null,
+            thumbnail_is_linear(true, false),
+        );
+        assert_ne!(developed.to_rgb8(), preview.to_rgb8());
+    }
+}
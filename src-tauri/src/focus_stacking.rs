@@ -0,0 +1,346 @@
+use image::{DynamicImage, GrayImage, Rgb32FImage};
+use nalgebra::{Matrix3, Point2};
+use rayon::prelude::*;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+use tauri::{AppHandle, Emitter};
+
+use crate::image_loader::is_linear_source;
+use crate::image_processing::apply_cpu_default_raw_processing;
+use crate::panorama_stitching::Feature;
+use crate::panorama_utils::processing;
+
+const SHARPNESS_WINDOW_RADIUS: usize = 4;
+const BLEND_RADIUS: usize = 3;
+
+struct Frame {
+    filename: String,
+    image: Rgb32FImage,
+    features: Vec<Feature>,
+    scale_factor: f64,
+}
+
+fn check_cancelled(cancel_token: &AtomicBool) -> Result<(), String> {
+    if cancel_token.load(Ordering::SeqCst) {
+        Err("Focus stack cancelled.".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+fn short_name(filename: &str) -> String {
+    Path::new(filename)
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+fn load_frame(filename: &str, brief_pairs: &[(Point2<i32>, Point2<i32>)]) -> Result<Frame, String> {
+    let file_bytes =
+        fs::read(filename).map_err(|e| format!("Failed to read image {}: {}", filename, e))?;
+
+    let mut dynamic_image = crate::image_loader::load_base_image_from_bytes(
+        &file_bytes,
+        filename,
+        false,
+        2.5,
+        &serde_json::Value::Null,
+    )
+    .map_err(|e| format!("Failed to load image {}: {}", filename, e))?;
+
+    if is_linear_source(filename) {
+        apply_cpu_default_raw_processing(&mut dynamic_image);
+    }
+
+    let image = dynamic_image.to_rgb32f();
+    let gray_full = image::imageops::colorops::grayscale(&dynamic_image.to_rgb8());
+    let (w, h) = gray_full.dimensions();
+    let (new_w, new_h, scale_factor) = processing::calculate_downscale_dimensions(w, h);
+    let gray_small = image::imageops::resize(
+        &gray_full,
+        new_w,
+        new_h,
+        image::imageops::FilterType::Triangle,
+    );
+
+    Ok(Frame {
+        filename: filename.to_string(),
+        image,
+        features: processing::find_features(&gray_small, brief_pairs),
+        scale_factor,
+    })
+}
+
+fn pairwise_homography(frames: &[Frame], from: usize, to: usize) -> Option<Matrix3<f64>> {
+    processing::align_to_reference(
+        &frames[from].features,
+        frames[from].scale_factor,
+        &frames[to].features,
+        frames[to].scale_factor,
+    )
+}
+
+// Adjacent frames differ least in focus and magnification, so homographies are
+// chained outwards from the reference instead of matched against it directly.
+fn chain_homographies(
+    frames: &[Frame],
+    ref_index: usize,
+    app_handle: &AppHandle,
+) -> Vec<Matrix3<f64>> {
+    let pairwise: Vec<Option<Matrix3<f64>>> = (0..frames.len())
+        .into_par_iter()
+        .map(|i| match i.cmp(&ref_index) {
+            std::cmp::Ordering::Equal => Some(Matrix3::identity()),
+            std::cmp::Ordering::Greater => pairwise_homography(frames, i - 1, i),
+            std::cmp::Ordering::Less => pairwise_homography(frames, i + 1, i),
+        })
+        .collect();
+
+    let mut homographies = vec![Matrix3::identity(); frames.len()];
+    let resolve = |i: usize, previous: usize, homographies: &mut Vec<Matrix3<f64>>| {
+        let chained = pairwise[i].map(|h| h * homographies[previous]);
+        homographies[i] = chained
+            .or_else(|| pairwise_homography(frames, ref_index, i))
+            .unwrap_or_else(|| {
+                let warning = format!(
+                    "Could not align '{}', stacking it unaligned.",
+                    short_name(&frames[i].filename)
+                );
+                log::warn!("{}", warning);
+                let _ = app_handle.emit("focus-stack-warning", warning);
+                Matrix3::identity()
+            });
+    };
+    for i in ref_index + 1..frames.len() {
+        resolve(i, i - 1, &mut homographies);
+    }
+    for i in (0..ref_index).rev() {
+        resolve(i, i + 1, &mut homographies);
+    }
+
+    for (i, h) in homographies.iter().enumerate() {
+        let scale = (h[(0, 0)] * h[(1, 1)] - h[(0, 1)] * h[(1, 0)]).abs().sqrt();
+        println!(
+            "  - '{}' magnification relative to reference: {:.4}",
+            short_name(&frames[i].filename),
+            1.0 / scale.max(1e-6)
+        );
+    }
+    homographies
+}
+
+fn box_blur(data: &[f32], width: usize, height: usize, radius: usize) -> Vec<f32> {
+    let horizontal: Vec<f32> = (0..height)
+        .into_par_iter()
+        .flat_map_iter(|y| {
+            let row = &data[y * width..(y + 1) * width];
+            (0..width).map(move |x| {
+                let x0 = x.saturating_sub(radius);
+                let x1 = (x + radius).min(width - 1);
+                row[x0..=x1].iter().sum::<f32>() / (x1 - x0 + 1) as f32
+            })
+        })
+        .collect();
+    (0..height)
+        .into_par_iter()
+        .flat_map_iter(|y| {
+            let y0 = y.saturating_sub(radius);
+            let y1 = (y + radius).min(height - 1);
+            let horizontal = &horizontal;
+            (0..width).map(move |x| {
+                (y0..=y1).map(|yy| horizontal[yy * width + x]).sum::<f32>() / (y1 - y0 + 1) as f32
+            })
+        })
+        .collect()
+}
+
+// Local variance of the Laplacian, the per-pixel counterpart of the whole-image
+// measure used for culling.
+fn sharpness_map(frame: &[Option<[f32; 3]>], width: usize, height: usize) -> Vec<f32> {
+    let gray: Vec<f32> = frame
+        .iter()
+        .map(|p| p.map_or(0.0, |p| 0.299 * p[0] + 0.587 * p[1] + 0.114 * p[2]))
+        .collect();
+
+    let laplacian: Vec<f32> = (0..width * height)
+        .into_par_iter()
+        .map(|i| {
+            let (x, y) = (i % width, i / width);
+            if x == 0 || y == 0 || x == width - 1 || y == height - 1 {
+                return 0.0;
+            }
+            gray[i - width] + gray[i + width] + gray[i - 1] + gray[i + 1] - 4.0 * gray[i]
+        })
+        .collect();
+
+    let mean = box_blur(&laplacian, width, height, SHARPNESS_WINDOW_RADIUS);
+    let squared: Vec<f32> = laplacian.par_iter().map(|v| v * v).collect();
+    let mean_squared = box_blur(&squared, width, height, SHARPNESS_WINDOW_RADIUS);
+
+    mean_squared
+        .par_iter()
+        .zip(mean.par_iter())
+        .zip(frame.par_iter())
+        .map(|((&ms, &m), p)| {
+            if p.is_some() {
+                (ms - m * m).max(0.0)
+            } else {
+                -1.0
+            }
+        })
+        .collect()
+}
+
+fn common_valid_rect(
+    valid: &[bool],
+    width: usize,
+    height: usize,
+) -> Option<(usize, usize, usize, usize)> {
+    let (mut x0, mut y0, mut x1, mut y1) = (0, 0, width, height);
+    while x0 < x1 && y0 < y1 {
+        let top = (x0..x1).all(|x| valid[y0 * width + x]);
+        let bottom = (x0..x1).all(|x| valid[(y1 - 1) * width + x]);
+        let left = (y0..y1).all(|y| valid[y * width + x0]);
+        let right = (y0..y1).all(|y| valid[y * width + x1 - 1]);
+        if top && bottom && left && right {
+            return Some((x0, y0, x1 - x0, y1 - y0));
+        }
+        if !top {
+            y0 += 1;
+        }
+        if !bottom {
+            y1 -= 1;
+        }
+        if !left {
+            x0 += 1;
+        }
+        if !right {
+            x1 -= 1;
+        }
+    }
+    None
+}
+
+// Frames are stacked in file-name order, which for a camera or rail sequence is the order
+// the focus was stepped in. The middle frame becomes the reference and homographies are
+// chained through focus-adjacent neighbours, so the selection must not skip around.
+pub fn stack_images(
+    mut image_paths: Vec<String>,
+    app_handle: AppHandle,
+    cancel_token: Arc<AtomicBool>,
+) -> Result<(DynamicImage, GrayImage), String> {
+    if image_paths.len() < 2 {
+        return Err("At least two images are required for focus stacking.".to_string());
+    }
+    image_paths.sort_by_key(|path| short_name(path));
+
+    let _ = app_handle.emit("focus-stack-progress", "Starting focus stack...");
+    println!("Starting focus stack for {} images...", image_paths.len());
+
+    let start_time = Instant::now();
+    let brief_pairs = processing::generate_brief_pairs();
+    let frame_results: Vec<Result<Frame, String>> = image_paths
+        .par_iter()
+        .map(|filename| {
+            check_cancelled(&cancel_token)?;
+            let _ = app_handle.emit(
+                "focus-stack-progress",
+                format!("Processing '{}'", short_name(filename)),
+            );
+            load_frame(filename, &brief_pairs)
+        })
+        .collect();
+    let frames: Vec<Frame> = frame_results.into_iter().collect::<Result<_, _>>()?;
+    println!("Images loaded in {:.2?}", start_time.elapsed());
+    check_cancelled(&cancel_token)?;
+
+    let start_time = Instant::now();
+    let _ = app_handle.emit("focus-stack-progress", "Aligning frames...");
+    let ref_index = frames.len() / 2;
+    let (width, height) = frames[ref_index].image.dimensions();
+    let homographies = chain_homographies(&frames, ref_index, &app_handle);
+    println!("Alignment completed in {:.2?}", start_time.elapsed());
+    check_cancelled(&cancel_token)?;
+
+    // Warped frames are not kept around: the first pass only records the sharpest frame per
+    // pixel and the common valid area, the second pass warps again to blend.
+    let start_time = Instant::now();
+    let _ = app_handle.emit("focus-stack-progress", "Measuring sharpness...");
+    let (w, h) = (width as usize, height as usize);
+    let mut best_sharpness = vec![-1.0f32; w * h];
+    let mut depth = vec![0u16; w * h];
+    let mut valid = vec![true; w * h];
+    for (i, (frame, homography)) in frames.iter().zip(homographies.iter()).enumerate() {
+        check_cancelled(&cancel_token)?;
+        let warped = processing::warp_to_reference(&frame.image, homography, width, height);
+        let sharpness = sharpness_map(&warped, w, h);
+        best_sharpness
+            .par_iter_mut()
+            .zip(depth.par_iter_mut())
+            .zip(valid.par_iter_mut())
+            .zip(sharpness.par_iter().zip(warped.par_iter()))
+            .for_each(|(((best, d), is_valid), (&s, p))| {
+                if s > *best {
+                    *best = s;
+                    *d = i as u16;
+                }
+                *is_valid &= p.is_some();
+            });
+    }
+    drop(best_sharpness);
+    println!("Sharpness measured in {:.2?}", start_time.elapsed());
+
+    let (crop_x, crop_y, crop_w, crop_h) = common_valid_rect(&valid, w, h)
+        .ok_or_else(|| "Aligned frames do not share a common area.".to_string())?;
+    drop(valid);
+
+    let _ = app_handle.emit("focus-stack-progress", "Blending in-focus regions...");
+    let mut accumulated = vec![0.0f32; w * h * 3];
+    let mut total_weight = vec![0.0f32; w * h];
+    for (i, (frame, homography)) in frames.iter().zip(homographies.iter()).enumerate() {
+        check_cancelled(&cancel_token)?;
+        let selection: Vec<f32> = depth
+            .par_iter()
+            .map(|&d| if d as usize == i { 1.0 } else { 0.0 })
+            .collect();
+        let weights = box_blur(&selection, w, h, BLEND_RADIUS);
+        if weights.iter().all(|&weight| weight <= 0.0) {
+            continue;
+        }
+        let warped = processing::warp_to_reference(&frame.image, homography, width, height);
+        accumulated
+            .par_chunks_mut(3)
+            .zip(total_weight.par_iter_mut())
+            .enumerate()
+            .for_each(|(px, (acc, total))| {
+                let (Some(p), weight) = (warped[px], weights[px]) else {
+                    return;
+                };
+                if weight > 0.0 {
+                    for c in 0..3 {
+                        acc[c] += p[c] * weight;
+                    }
+                    *total += weight;
+                }
+            });
+    }
+    drop(frames);
+
+    let _ = app_handle.emit("focus-stack-progress", "Finalizing image...");
+    let max_depth = (image_paths.len() - 1).max(1) as f32;
+    let stacked = Rgb32FImage::from_fn(crop_w as u32, crop_h as u32, |x, y| {
+        let px = (y as usize + crop_y) * w + x as usize + crop_x;
+        let total = total_weight[px].max(1e-6);
+        image::Rgb(std::array::from_fn(|c| accumulated[px * 3 + c] / total))
+    });
+    let depth_map = GrayImage::from_fn(crop_w as u32, crop_h as u32, |x, y| {
+        let px = (y as usize + crop_y) * w + x as usize + crop_x;
+        image::Luma([(depth[px] as f32 / max_depth * 255.0).round() as u8])
+    });
+
+    Ok((DynamicImage::ImageRgb32F(stacked), depth_map))
+}
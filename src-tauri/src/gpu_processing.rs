@@ -3,7 +3,7 @@ use std::time::Instant;
 
 use bytemuck;
 use half::f16;
-use image::{DynamicImage, GenericImageView, ImageBuffer, Luma, Rgba};
+use image::{DynamicImage, GenericImageView, ImageBuffer, Luma, Rgba, Rgba32FImage};
 use wgpu::util::{DeviceExt, TextureDataOrder};
 
 use crate::image_processing::{AllAdjustments, GpuContext};
@@ -60,7 +60,8 @@ fn read_texture_data(
     texture: &wgpu::Texture,
     size: wgpu::Extent3d,
 ) -> Result<Vec<u8>, String> {
-    let unpadded_bytes_per_row = 4 * size.width;
+    let bytes_per_pixel = texture.format().block_copy_size(None).unwrap_or(4);
+    let unpadded_bytes_per_row = bytes_per_pixel * size.width;
     let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
     let padded_bytes_per_row = (unpadded_bytes_per_row + align - 1) & !(align - 1);
     let output_buffer_size = (padded_bytes_per_row * size.height) as u64;
@@ -152,6 +153,7 @@ struct GpuProcessor<'a> {
     lut_texture_view: wgpu::TextureView,
     lut_sampler: wgpu::Sampler,
     mask_views: Vec<wgpu::TextureView>,
+    output_format: wgpu::TextureFormat,
 }
 
 impl<'a> GpuProcessor<'a> {
@@ -161,6 +163,7 @@ impl<'a> GpuProcessor<'a> {
         height: u32,
         mask_bitmaps: &[ImageBuffer<Luma<u8>, Vec<u8>>],
         lut: Option<Arc<Lut>>,
+        output_format: wgpu::TextureFormat,
     ) -> Result<Self, String> {
         let device = &context.device;
         let queue = &context.queue;
@@ -238,9 +241,18 @@ impl<'a> GpuProcessor<'a> {
             mapped_at_creation: false,
         });
 
+        // The output binding is declared for 8-bit targets; float renders swap
+        // in a matching storage format.
+        let shader_source = match output_format {
+            wgpu::TextureFormat::Rgba16Float => include_str!("shaders/shader.wgsl").replace(
+                "texture_storage_2d<rgba8unorm, write>",
+                "texture_storage_2d<rgba16float, write>",
+            ),
+            _ => include_str!("shaders/shader.wgsl").to_string(),
+        };
         let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Image Processing Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/shader.wgsl").into()),
+            source: wgpu::ShaderSource::Wgsl(shader_source.into()),
         });
 
         let mut bind_group_layout_entries = vec![
@@ -259,7 +271,7 @@ impl<'a> GpuProcessor<'a> {
                 visibility: wgpu::ShaderStages::COMPUTE,
                 ty: wgpu::BindingType::StorageTexture {
                     access: wgpu::StorageTextureAccess::WriteOnly,
-                    format: wgpu::TextureFormat::Rgba8Unorm,
+                    format: output_format,
                     view_dimension: wgpu::TextureViewDimension::D2,
                 },
                 count: None,
@@ -475,6 +487,7 @@ impl<'a> GpuProcessor<'a> {
             lut_texture_view,
             lut_sampler,
             mask_views,
+            output_format,
         })
     }
 
@@ -541,13 +554,14 @@ impl<'a> GpuProcessor<'a> {
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8Unorm,
+            format: self.output_format,
             usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
             view_formats: &[],
         });
         let output_texture_view = output_texture.create_view(&Default::default());
 
-        let mut final_pixels = vec![0u8; (width * height * 4) as usize];
+        let bytes_per_pixel = self.output_format.block_copy_size(None).unwrap_or(4);
+        let mut final_pixels = vec![0u8; (width * height * bytes_per_pixel) as usize];
         let tiles_x = (width + TILE_SIZE - 1) / TILE_SIZE;
         let tiles_y = (height + TILE_SIZE - 1) / TILE_SIZE;
 
@@ -651,6 +665,8 @@ impl<'a> GpuProcessor<'a> {
                 let did_create_structure_blur = create_blur(40.0, &structure_blur_view);
 
                 let mut tile_adjustments = adjustments;
+                tile_adjustments.global.float_output =
+                    (self.output_format == wgpu::TextureFormat::Rgba16Float) as u32;
                 tile_adjustments.tile_offset_x = input_x_start;
                 tile_adjustments.tile_offset_y = input_y_start;
                 queue.write_buffer(
@@ -740,10 +756,11 @@ impl<'a> GpuProcessor<'a> {
 
                 for row in 0..tile_height {
                     let final_y = y_start + row;
-                    let final_row_offset = (final_y * width + x_start) as usize * 4;
+                    let final_row_offset = ((final_y * width + x_start) * bytes_per_pixel) as usize;
                     let source_y = crop_y_start + row;
-                    let source_row_offset = (source_y * input_width + crop_x_start) as usize * 4;
-                    let copy_bytes = (tile_width * 4) as usize;
+                    let source_row_offset =
+                        ((source_y * input_width + crop_x_start) * bytes_per_pixel) as usize;
+                    let copy_bytes = (tile_width * bytes_per_pixel) as usize;
 
                     final_pixels[final_row_offset..final_row_offset + copy_bytes].copy_from_slice(
                         &processed_tile_data[source_row_offset..source_row_offset + copy_bytes],
@@ -764,6 +781,7 @@ pub fn run_gpu_processing(
     adjustments: AllAdjustments,
     mask_bitmaps: &[ImageBuffer<Luma<u8>, Vec<u8>>],
     lut: Option<Arc<Lut>>,
+    output_format: wgpu::TextureFormat,
 ) -> Result<Vec<u8>, String> {
     let start_time = Instant::now();
     let max_dim = context.limits.max_texture_dimension_2d;
@@ -775,7 +793,7 @@ pub fn run_gpu_processing(
         ));
     }
 
-    let processor = GpuProcessor::new(context, width, height, mask_bitmaps, lut)?;
+    let processor = GpuProcessor::new(context, width, height, mask_bitmaps, lut, output_format)?;
     let final_pixels = processor.run(input_texture_view, width, height, adjustments)?;
 
     let duration = start_time.elapsed();
@@ -797,6 +815,55 @@ pub fn process_and_get_dynamic_image(
     mask_bitmaps: &[ImageBuffer<Luma<u8>, Vec<u8>>],
     lut: Option<Arc<Lut>>,
     caller_id: &str,
+) -> Result<DynamicImage, String> {
+    process_on_gpu(
+        context,
+        state,
+        base_image,
+        transform_hash,
+        all_adjustments,
+        mask_bitmaps,
+        lut,
+        caller_id,
+        wgpu::TextureFormat::Rgba8Unorm,
+    )
+}
+
+// Same render as process_and_get_dynamic_image, read back as undithered
+// floats so later color conversions are not limited to 8 bits.
+pub fn process_and_get_float_image(
+    context: &GpuContext,
+    state: &tauri::State<AppState>,
+    base_image: &DynamicImage,
+    transform_hash: u64,
+    all_adjustments: AllAdjustments,
+    mask_bitmaps: &[ImageBuffer<Luma<u8>, Vec<u8>>],
+    lut: Option<Arc<Lut>>,
+    caller_id: &str,
+) -> Result<DynamicImage, String> {
+    process_on_gpu(
+        context,
+        state,
+        base_image,
+        transform_hash,
+        all_adjustments,
+        mask_bitmaps,
+        lut,
+        caller_id,
+        wgpu::TextureFormat::Rgba16Float,
+    )
+}
+
+fn process_on_gpu(
+    context: &GpuContext,
+    state: &tauri::State<AppState>,
+    base_image: &DynamicImage,
+    transform_hash: u64,
+    all_adjustments: AllAdjustments,
+    mask_bitmaps: &[ImageBuffer<Luma<u8>, Vec<u8>>],
+    lut: Option<Arc<Lut>>,
+    caller_id: &str,
+    output_format: wgpu::TextureFormat,
 ) -> Result<DynamicImage, String> {
     let (width, height) = base_image.dimensions();
     log::info!(
@@ -871,8 +938,19 @@ pub fn process_and_get_dynamic_image(
         all_adjustments,
         mask_bitmaps,
         lut,
+        output_format,
     )?;
 
+    if output_format == wgpu::TextureFormat::Rgba16Float {
+        let floats: Vec<f32> = processed_pixels
+            .chunks_exact(2)
+            .map(|b| f16::from_le_bytes([b[0], b[1]]).to_f32())
+            .collect();
+        let img_buf = Rgba32FImage::from_raw(width, height, floats)
+            .ok_or("Failed to create image buffer from GPU data")?;
+        return Ok(DynamicImage::ImageRgba32F(img_buf));
+    }
+
     let img_buf = ImageBuffer::<Rgba<u8>, Vec<u8>>::from_raw(width, height, processed_pixels)
         .ok_or("Failed to create image buffer from GPU data")?;
     Ok(DynamicImage::ImageRgba8(img_buf))
@@ -0,0 +1,404 @@
+use std::io::Cursor;
+
+use anyhow::{Result, anyhow};
+use avif_serialize::Aviffy;
+use avif_serialize::constants::{ColorPrimaries, MatrixCoefficients, TransferCharacteristics};
+use image::codecs::jpeg::JpegEncoder;
+use image::{GrayImage, Rgba32FImage};
+use nalgebra::Vector3;
+use rav1e::prelude::{
+    ChromaSampling, ColorDescription, Config, ContentLight, Context, EncoderConfig, EncoderStatus,
+    PixelRange,
+};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::color_management;
+
+// BT.2408 reference white, used to place SDR white inside the HDR signal.
+const SDR_WHITE_NITS: f32 = 203.0;
+const HLG_PEAK_NITS: f32 = 1000.0;
+const PQ_PEAK_NITS: f32 = 10000.0;
+
+const REC2020_LUMA: [f32; 3] = [0.2627, 0.6780, 0.0593];
+
+const XMP_NAMESPACE: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const ISO_21496_1_NAMESPACE: &[u8] = b"urn:iso:std:iso:ts:21496:-1\0";
+const ISO_FRACTION_DENOMINATOR: u32 = 10000;
+
+const MPF_SEGMENT_LEN: usize = 90;
+const MPF_HEADER_OFFSET: usize = 8;
+
+const AVIF_SPEED: u8 = 6;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum HdrTransfer {
+    #[default]
+    Pq,
+    Hlg,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HdrExportSettings {
+    pub headroom_stops: f32,
+    #[serde(default)]
+    pub transfer: HdrTransfer,
+}
+
+impl HdrExportSettings {
+    // Linear multiple of SDR white the brightest highlights may reach.
+    pub fn headroom(&self) -> f32 {
+        2.0f32.powf(self.headroom_stops.clamp(0.5, 6.0))
+    }
+}
+
+fn pq_encode(nits: f32) -> f32 {
+    const M1: f32 = 0.1593017578125;
+    const M2: f32 = 78.84375;
+    const C1: f32 = 0.8359375;
+    const C2: f32 = 18.8515625;
+    const C3: f32 = 18.6875;
+    let y = (nits / PQ_PEAK_NITS).clamp(0.0, 1.0).powf(M1);
+    ((C1 + C2 * y) / (1.0 + C3 * y)).powf(M2)
+}
+
+fn hlg_encode(scene: f32) -> f32 {
+    const A: f32 = 0.17883277;
+    const B: f32 = 0.28466892;
+    const C: f32 = 0.55991073;
+    let e = scene.clamp(0.0, 1.0);
+    if e <= 1.0 / 12.0 {
+        (3.0 * e).sqrt()
+    } else {
+        A * (12.0 * e - B).ln() + C
+    }
+}
+
+// Takes Rec.2020 linear light relative to SDR white and returns the non-linear
+// signal for the chosen transfer.
+fn encode_hdr_signal(rgb: [f32; 3], transfer: HdrTransfer) -> [f32; 3] {
+    match transfer {
+        HdrTransfer::Pq => rgb.map(|c| pq_encode(c * SDR_WHITE_NITS)),
+        HdrTransfer::Hlg => {
+            // Undo the reference 1000 nit system gamma so the display OOTF lands
+            // SDR white back at 203 nits.
+            let display = rgb.map(|c| c.max(0.0) * SDR_WHITE_NITS / HLG_PEAK_NITS);
+            let luma = REC2020_LUMA[0] * display[0]
+                + REC2020_LUMA[1] * display[1]
+                + REC2020_LUMA[2] * display[2];
+            let scale = if luma > 0.0 {
+                luma.powf(1.0 / 1.2 - 1.0)
+            } else {
+                1.0
+            };
+            display.map(|c| hlg_encode(c * scale))
+        }
+    }
+}
+
+fn push_segment(buffer: &mut Vec<u8>, marker: u8, namespace: &[u8], payload: &[u8]) {
+    let len = 2 + namespace.len() + payload.len();
+    buffer.extend_from_slice(&[0xFF, marker]);
+    buffer.extend_from_slice(&(len as u16).to_be_bytes());
+    buffer.extend_from_slice(namespace);
+    buffer.extend_from_slice(payload);
+}
+
+fn primary_xmp(gain_map_len: usize) -> String {
+    format!(
+        r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+  <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+    <rdf:Description
+        xmlns:Container="http://ns.google.com/photos/1.0/container/"
+        xmlns:Item="http://ns.google.com/photos/1.0/container/item/"
+        xmlns:hdrgm="http://ns.adobe.com/hdr-gain-map/1.0/"
+        hdrgm:Version="1.0">
+      <Container:Directory>
+        <rdf:Seq>
+          <rdf:li rdf:parseType="Resource">
+            <Container:Item Item:Semantic="Primary" Item:Mime="image/jpeg"/>
+          </rdf:li>
+          <rdf:li rdf:parseType="Resource">
+            <Container:Item Item:Semantic="GainMap" Item:Mime="image/jpeg" Item:Length="{}"/>
+          </rdf:li>
+        </rdf:Seq>
+      </Container:Directory>
+    </rdf:Description>
+  </rdf:RDF>
+</x:xmpmeta>"#,
+        gain_map_len
+    )
+}
+
+fn gain_map_xmp(log2_headroom: f32) -> String {
+    format!(
+        r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+  <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+    <rdf:Description
+        xmlns:hdrgm="http://ns.adobe.com/hdr-gain-map/1.0/"
+        hdrgm:Version="1.0"
+        hdrgm:GainMapMin="0"
+        hdrgm:GainMapMax="{0}"
+        hdrgm:Gamma="1"
+        hdrgm:OffsetSDR="0"
+        hdrgm:OffsetHDR="0"
+        hdrgm:HDRCapacityMin="0"
+        hdrgm:HDRCapacityMax="{0}"
+        hdrgm:BaseRenditionIsHDR="False"/>
+  </rdf:RDF>
+</x:xmpmeta>"#,
+        log2_headroom
+    )
+}
+
+// ISO 21496-1 binary metadata for a single channel gain map applied in the
+// base image's color space.
+fn iso_gain_map_metadata(log2_headroom: f32) -> Vec<u8> {
+    let fraction = |value: f32| (value * ISO_FRACTION_DENOMINATOR as f32).round() as i32;
+    let mut data = Vec::new();
+    data.extend_from_slice(&0u16.to_be_bytes());
+    data.extend_from_slice(&0u16.to_be_bytes());
+    data.push(0x40);
+
+    for headroom in [0.0, log2_headroom] {
+        data.extend_from_slice(&(fraction(headroom) as u32).to_be_bytes());
+        data.extend_from_slice(&ISO_FRACTION_DENOMINATOR.to_be_bytes());
+    }
+
+    let gain_map_min = fraction(0.0);
+    let gain_map_max = fraction(log2_headroom);
+    let gamma = fraction(1.0) as u32;
+    for value in [gain_map_min, gain_map_max] {
+        data.extend_from_slice(&value.to_be_bytes());
+        data.extend_from_slice(&ISO_FRACTION_DENOMINATOR.to_be_bytes());
+    }
+    data.extend_from_slice(&gamma.to_be_bytes());
+    data.extend_from_slice(&ISO_FRACTION_DENOMINATOR.to_be_bytes());
+    for offset in [0i32, 0i32] {
+        data.extend_from_slice(&offset.to_be_bytes());
+        data.extend_from_slice(&ISO_FRACTION_DENOMINATOR.to_be_bytes());
+    }
+    data
+}
+
+// Multi-Picture Format index so readers can locate the gain map image that
+// follows the primary image's EOI.
+fn mpf_payload(primary_len: u32, gain_map_len: u32, gain_map_offset: u32) -> Vec<u8> {
+    const ENTRY_COUNT: u16 = 3;
+    let entries_offset = 8 + 2 + ENTRY_COUNT as u32 * 12 + 4;
+
+    let mut data = Vec::new();
+    data.extend_from_slice(b"MPF\0");
+    data.extend_from_slice(b"MM\0\x2A");
+    data.extend_from_slice(&8u32.to_be_bytes());
+    data.extend_from_slice(&ENTRY_COUNT.to_be_bytes());
+
+    let mut push_tag = |tag: u16, kind: u16, count: u32, value: [u8; 4]| {
+        data.extend_from_slice(&tag.to_be_bytes());
+        data.extend_from_slice(&kind.to_be_bytes());
+        data.extend_from_slice(&count.to_be_bytes());
+        data.extend_from_slice(&value);
+    };
+    push_tag(0xB000, 7, 4, *b"0100");
+    push_tag(0xB001, 4, 1, 2u32.to_be_bytes());
+    push_tag(0xB002, 7, 32, entries_offset.to_be_bytes());
+    data.extend_from_slice(&0u32.to_be_bytes());
+
+    for (attribute, size, offset) in [
+        (0x0003_0000u32, primary_len, 0u32),
+        (0, gain_map_len, gain_map_offset),
+    ] {
+        data.extend_from_slice(&attribute.to_be_bytes());
+        data.extend_from_slice(&size.to_be_bytes());
+        data.extend_from_slice(&offset.to_be_bytes());
+        data.extend_from_slice(&0u16.to_be_bytes());
+        data.extend_from_slice(&0u16.to_be_bytes());
+    }
+    data
+}
+
+// Wraps an already encoded SDR JPEG into an Ultra HDR / ISO 21496-1 file with
+// the gain map appended as a secondary JPEG.
+pub fn encode_ultra_hdr_jpeg(
+    primary_jpeg: &[u8],
+    gain_map: &GrayImage,
+    settings: &HdrExportSettings,
+    jpeg_quality: u8,
+) -> Result<Vec<u8>> {
+    if !primary_jpeg.starts_with(&[0xFF, 0xD8]) {
+        return Err(anyhow!("Primary image is not a JPEG"));
+    }
+    let log2_headroom = settings.headroom().log2();
+
+    let mut gain_map_jpeg = Vec::new();
+    gain_map.write_with_encoder(JpegEncoder::new_with_quality(
+        &mut Cursor::new(&mut gain_map_jpeg),
+        jpeg_quality,
+    ))?;
+
+    let mut secondary = vec![0xFF, 0xD8];
+    push_segment(
+        &mut secondary,
+        0xE1,
+        XMP_NAMESPACE,
+        gain_map_xmp(log2_headroom).as_bytes(),
+    );
+    push_segment(
+        &mut secondary,
+        0xE2,
+        ISO_21496_1_NAMESPACE,
+        &iso_gain_map_metadata(log2_headroom),
+    );
+    secondary.extend_from_slice(&gain_map_jpeg[2..]);
+
+    let mut header = vec![0xFF, 0xD8];
+    push_segment(
+        &mut header,
+        0xE1,
+        XMP_NAMESPACE,
+        primary_xmp(secondary.len()).as_bytes(),
+    );
+    push_segment(&mut header, 0xE2, ISO_21496_1_NAMESPACE, &[0, 0, 0, 0]);
+
+    let mpf_header_position = header.len() + MPF_HEADER_OFFSET;
+    let primary_len = header.len() + MPF_SEGMENT_LEN + primary_jpeg.len() - 2;
+    let gain_map_offset = primary_len - mpf_header_position;
+    push_segment(
+        &mut header,
+        0xE2,
+        &[],
+        &mpf_payload(
+            primary_len as u32,
+            secondary.len() as u32,
+            gain_map_offset as u32,
+        ),
+    );
+
+    let mut output = Vec::with_capacity(primary_len + secondary.len());
+    output.extend_from_slice(&header);
+    output.extend_from_slice(&primary_jpeg[2..]);
+    output.extend_from_slice(&secondary);
+    Ok(output)
+}
+
+fn encode_av1_10bit(
+    width: u32,
+    height: u32,
+    planes: [&[u16]; 3],
+    transfer: HdrTransfer,
+    content_light: Option<ContentLight>,
+    quality: u8,
+) -> Result<Vec<u8>> {
+    let mut encoder_config = EncoderConfig::with_speed_preset(AVIF_SPEED);
+    encoder_config.width = width as usize;
+    encoder_config.height = height as usize;
+    encoder_config.bit_depth = 10;
+    encoder_config.chroma_sampling = ChromaSampling::Cs444;
+    encoder_config.pixel_range = PixelRange::Full;
+    encoder_config.still_picture = true;
+    encoder_config.quantizer = (255.0 * (1.0 - quality.min(100) as f32 / 100.0)).round() as usize;
+    encoder_config.color_description = Some(ColorDescription {
+        color_primaries: rav1e::prelude::ColorPrimaries::BT2020,
+        transfer_characteristics: match transfer {
+            HdrTransfer::Pq => rav1e::prelude::TransferCharacteristics::SMPTE2084,
+            HdrTransfer::Hlg => rav1e::prelude::TransferCharacteristics::HLG,
+        },
+        matrix_coefficients: rav1e::prelude::MatrixCoefficients::BT2020NCL,
+    });
+    encoder_config.content_light = content_light;
+
+    let config = Config::new().with_encoder_config(encoder_config);
+    let mut context: Context<u16> = config
+        .new_context()
+        .map_err(|e| anyhow!("Failed to create AV1 encoder: {}", e))?;
+
+    let mut frame = context.new_frame();
+    for (plane, data) in frame.planes.iter_mut().zip(planes) {
+        plane.copy_from_raw_u8(bytemuck::cast_slice(data), width as usize * 2, 2);
+    }
+    context
+        .send_frame(frame)
+        .map_err(|e| anyhow!("Failed to send frame to AV1 encoder: {}", e))?;
+    context.flush();
+
+    let mut av1_data = Vec::new();
+    loop {
+        match context.receive_packet() {
+            Ok(packet) => av1_data.extend_from_slice(&packet.data),
+            Err(EncoderStatus::Encoded) => {}
+            Err(EncoderStatus::LimitReached) => break,
+            Err(e) => return Err(anyhow!("AV1 encoding failed: {}", e)),
+        }
+    }
+    Ok(av1_data)
+}
+
+// Writes the linear HDR render (sRGB primaries, 1.0 = SDR white) as a 10-bit
+// Rec.2020 AVIF with PQ or HLG transfer.
+pub fn encode_hdr_avif(
+    hdr_image: &Rgba32FImage,
+    settings: &HdrExportSettings,
+    quality: u8,
+) -> Result<Vec<u8>> {
+    let (width, height) = hdr_image.dimensions();
+    let matrix = color_management::working_to_rec2020_matrix().cast::<f32>();
+
+    let pixel_count = (width * height) as usize;
+    let mut y_plane = vec![0u16; pixel_count];
+    let mut cb_plane = vec![0u16; pixel_count];
+    let mut cr_plane = vec![0u16; pixel_count];
+
+    let (max_nits, total_nits) = y_plane
+        .par_iter_mut()
+        .zip(cb_plane.par_iter_mut())
+        .zip(cr_plane.par_iter_mut())
+        .zip(hdr_image.par_chunks(4))
+        .map(|(((y, cb), cr), src)| {
+            let linear = Vector3::new(src[0], src[1], src[2]);
+            let hdr = (matrix * linear).map(|c| c.max(0.0));
+            let [r, g, b] = encode_hdr_signal([hdr.x, hdr.y, hdr.z], settings.transfer);
+
+            let luma = REC2020_LUMA[0] * r + REC2020_LUMA[1] * g + REC2020_LUMA[2] * b;
+            let to_code =
+                |v: f32, offset: f32| ((v + offset) * 1023.0).round().clamp(0.0, 1023.0) as u16;
+            *y = to_code(luma, 0.0);
+            *cb = to_code((b - luma) / 1.8814, 0.5);
+            *cr = to_code((r - luma) / 1.4746, 0.5);
+
+            let nits = hdr.max() * SDR_WHITE_NITS;
+            (nits, nits as f64)
+        })
+        .reduce(|| (0.0, 0.0), |a, b| (a.0.max(b.0), a.1 + b.1));
+
+    let content_light = match settings.transfer {
+        HdrTransfer::Pq => Some(ContentLight {
+            max_content_light_level: max_nits.min(PQ_PEAK_NITS) as u16,
+            max_frame_average_light_level: (total_nits / pixel_count.max(1) as f64) as u16,
+        }),
+        HdrTransfer::Hlg => None,
+    };
+
+    let av1_data = encode_av1_10bit(
+        width,
+        height,
+        [&y_plane, &cb_plane, &cr_plane],
+        settings.transfer,
+        content_light,
+        quality,
+    )?;
+
+    let mut aviffy = Aviffy::new();
+    aviffy
+        .set_seq_profile(1)
+        .set_chroma_subsampling((false, false))
+        .set_full_color_range(true)
+        .set_color_primaries(ColorPrimaries::Bt2020)
+        .set_transfer_characteristics(match settings.transfer {
+            HdrTransfer::Pq => TransferCharacteristics::Smpte2084,
+            HdrTransfer::Hlg => TransferCharacteristics::Hlg,
+        })
+        .set_matrix_coefficients(MatrixCoefficients::Bt2020Ncl);
+    Ok(aviffy.to_vec(&av1_data, None, width, height, 10))
+}
@@ -0,0 +1,392 @@
+use exif::{In, Reader as ExifReader, Tag};
+use image::{DynamicImage, GrayImage, Rgb32FImage};
+use nalgebra::{Matrix3, Point2};
+use rayon::prelude::*;
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+use tauri::{AppHandle, Emitter};
+
+use crate::image_loader::is_linear_source;
+use crate::panorama_stitching::Feature;
+use crate::panorama_utils::processing;
+
+const CLIP_THRESHOLD: f32 = 0.97;
+const NOISE_THRESHOLD: f32 = 0.002;
+const GHOST_LOG2_THRESHOLD: f32 = 0.7;
+const GHOST_DILATION_RADIUS: i64 = 4;
+
+struct Frame {
+    filename: String,
+    image: Rgb32FImage,
+    exposure: Option<f64>,
+}
+
+fn check_cancelled(cancel_token: &AtomicBool) -> Result<(), String> {
+    if cancel_token.load(Ordering::SeqCst) {
+        Err("HDR merge cancelled.".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+fn short_name(filename: &str) -> String {
+    Path::new(filename)
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+fn read_exposure(file_bytes: &[u8]) -> Option<f64> {
+    let exif = ExifReader::new()
+        .read_from_container(&mut Cursor::new(file_bytes))
+        .ok()?;
+    let rational = |tag: Tag| {
+        exif.get_field(tag, In::PRIMARY)
+            .and_then(|field| match &field.value {
+                exif::Value::Rational(values) if !values.is_empty() => Some(values[0].to_f64()),
+                _ => None,
+            })
+            .filter(|v| v.is_finite() && *v > 0.0)
+    };
+
+    let exposure_time = rational(Tag::ExposureTime)?;
+    let f_number = rational(Tag::FNumber).unwrap_or(1.0);
+    let iso = exif
+        .get_field(Tag::PhotographicSensitivity, In::PRIMARY)
+        .and_then(|field| field.value.get_uint(0))
+        .filter(|&v| v > 0)
+        .map_or(100.0, |v| v as f64);
+
+    Some(exposure_time * (iso / 100.0) / (f_number * f_number))
+}
+
+fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn load_linear_frame(filename: &str) -> Result<Frame, String> {
+    let file_bytes =
+        fs::read(filename).map_err(|e| format!("Failed to read image {}: {}", filename, e))?;
+    let exposure = read_exposure(&file_bytes);
+
+    // Clipped highlights keep each frame's response linear up to the sensor clip point, which
+    // is what the merge weights expect.
+    let dynamic_image = crate::image_loader::load_base_image_from_bytes(
+        &file_bytes,
+        filename,
+        false,
+        2.5,
+        &serde_json::json!({ "highlightReconstruction": "clip" }),
+    )
+    .map_err(|e| format!("Failed to load image {}: {}", filename, e))?;
+
+    let is_linear = is_linear_source(filename)
+        || Path::new(filename)
+            .extension()
+            .and_then(|s| s.to_str())
+            .map_or(false, |s| s.eq_ignore_ascii_case("exr"));
+    let mut image = dynamic_image.to_rgb32f();
+    if !is_linear {
+        let data: &mut [f32] = &mut image;
+        data.par_iter_mut().for_each(|v| *v = srgb_to_linear(*v));
+    }
+
+    Ok(Frame {
+        filename: filename.to_string(),
+        image,
+        exposure,
+    })
+}
+
+#[inline]
+fn luminance(p: &[f32; 3]) -> f32 {
+    0.2126 * p[0] + 0.7152 * p[1] + 0.0722 * p[2]
+}
+
+#[inline]
+fn max_channel(p: &[f32; 3]) -> f32 {
+    p[0].max(p[1]).max(p[2])
+}
+
+fn estimate_relative_exposure(frame: &Rgb32FImage, reference: &Rgb32FImage) -> f64 {
+    if frame.dimensions() != reference.dimensions() {
+        return 1.0;
+    }
+    let mut ratios: Vec<f32> = frame
+        .pixels()
+        .zip(reference.pixels())
+        .step_by(7)
+        .filter_map(|(a, b)| {
+            let (a, b) = (a.0, b.0);
+            let valid = |p: &[f32; 3]| {
+                max_channel(p) < CLIP_THRESHOLD && luminance(p) > NOISE_THRESHOLD * 10.0
+            };
+            (valid(&a) && valid(&b)).then(|| luminance(&a) / luminance(&b))
+        })
+        .filter(|ratio| ratio.is_finite())
+        .collect();
+    if ratios.is_empty() {
+        return 1.0;
+    }
+    let mid = ratios.len() / 2;
+    ratios.select_nth_unstable_by(mid, f32::total_cmp);
+    ratios[mid] as f64
+}
+
+fn normalized_gray(image: &Rgb32FImage, exposure_scale: f32) -> GrayImage {
+    let (w, h) = image.dimensions();
+    GrayImage::from_fn(w, h, |x, y| {
+        let p = image.get_pixel(x, y).0;
+        let v = linear_to_srgb((luminance(&p) * exposure_scale).clamp(0.0, 1.0));
+        image::Luma([(v * 255.0).round() as u8])
+    })
+}
+
+fn detect_features(
+    image: &Rgb32FImage,
+    exposure_scale: f32,
+    brief_pairs: &[(Point2<i32>, Point2<i32>)],
+) -> (Vec<Feature>, f64) {
+    let gray_full = normalized_gray(image, exposure_scale);
+    let (w, h) = gray_full.dimensions();
+    let (new_w, new_h, scale_factor) = processing::calculate_downscale_dimensions(w, h);
+    let gray_small = image::imageops::resize(
+        &gray_full,
+        new_w,
+        new_h,
+        image::imageops::FilterType::Triangle,
+    );
+    (
+        processing::find_features(&gray_small, brief_pairs),
+        scale_factor,
+    )
+}
+
+fn exposure_weight(p: &[f32; 3]) -> f32 {
+    let m = max_channel(p);
+    if m >= CLIP_THRESHOLD || m <= NOISE_THRESHOLD {
+        return 0.0;
+    }
+    let t = 2.0 * m / CLIP_THRESHOLD - 1.0;
+    (1.0 - t.powi(12)).max(0.0)
+}
+
+fn dilate_mask(mask: &[bool], width: usize, height: usize, radius: i64) -> Vec<bool> {
+    let horizontal: Vec<bool> = (0..width * height)
+        .into_par_iter()
+        .map(|i| {
+            let (x, y) = ((i % width) as i64, i / width);
+            (-radius..=radius).any(|dx| {
+                let nx = x + dx;
+                nx >= 0 && (nx as usize) < width && mask[y * width + nx as usize]
+            })
+        })
+        .collect();
+    (0..width * height)
+        .into_par_iter()
+        .map(|i| {
+            let (x, y) = (i % width, (i / width) as i64);
+            (-radius..=radius).any(|dy| {
+                let ny = y + dy;
+                ny >= 0 && (ny as usize) < height && horizontal[ny as usize * width + x]
+            })
+        })
+        .collect()
+}
+
+pub fn merge_hdr(
+    image_paths: Vec<String>,
+    app_handle: AppHandle,
+    cancel_token: Arc<AtomicBool>,
+) -> Result<DynamicImage, String> {
+    if image_paths.len() < 2 {
+        return Err("At least two bracketed images are required for an HDR merge.".to_string());
+    }
+
+    let _ = app_handle.emit("hdr-progress", "Starting HDR merge...");
+    println!("Starting HDR merge for {} images...", image_paths.len());
+
+    let start_time = Instant::now();
+    let _ = app_handle.emit("hdr-progress", "Loading images in linear light...");
+    let frame_results: Vec<Result<Frame, String>> = image_paths
+        .par_iter()
+        .map(|filename| {
+            let _ = app_handle.emit(
+                "hdr-progress",
+                format!("Loading '{}'", short_name(filename)),
+            );
+            load_linear_frame(filename)
+        })
+        .collect();
+    let frames: Vec<Frame> = frame_results.into_iter().collect::<Result<_, _>>()?;
+    println!("Images loaded in {:.2?}", start_time.elapsed());
+    check_cancelled(&cancel_token)?;
+
+    let mut order: Vec<usize> = (0..frames.len()).collect();
+    order.sort_by(|&a, &b| {
+        let ea = frames[a].exposure.unwrap_or(1.0);
+        let eb = frames[b].exposure.unwrap_or(1.0);
+        ea.partial_cmp(&eb).unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let ref_index = order[order.len() / 2];
+    let reference = &frames[ref_index];
+    let (width, height) = reference.image.dimensions();
+
+    let all_have_exif = frames.iter().all(|f| f.exposure.is_some());
+    let relative_exposures: Vec<f64> = frames
+        .iter()
+        .map(
+            |frame| match (all_have_exif, frame.exposure, reference.exposure) {
+                (true, Some(e), Some(e_ref)) => e / e_ref,
+                _ => estimate_relative_exposure(&frame.image, &reference.image),
+            },
+        )
+        .collect();
+    println!(
+        "Reference frame '{}', relative exposures: {:?}",
+        short_name(&reference.filename),
+        relative_exposures
+    );
+
+    let start_time = Instant::now();
+    let _ = app_handle.emit("hdr-progress", "Aligning frames...");
+    let brief_pairs = processing::generate_brief_pairs();
+    let features: Vec<(Vec<Feature>, f64)> = frames
+        .par_iter()
+        .zip(relative_exposures.par_iter())
+        .map(|(frame, &rel)| detect_features(&frame.image, (1.0 / rel) as f32, &brief_pairs))
+        .collect();
+    check_cancelled(&cancel_token)?;
+
+    let homographies: Vec<Matrix3<f64>> = (0..frames.len())
+        .into_par_iter()
+        .map(|i| {
+            if i == ref_index {
+                return Matrix3::identity();
+            }
+            processing::align_to_reference(
+                &features[ref_index].0,
+                features[ref_index].1,
+                &features[i].0,
+                features[i].1,
+            )
+            .unwrap_or_else(|| {
+                let warning = format!(
+                    "Could not align '{}', merging it unaligned.",
+                    short_name(&frames[i].filename)
+                );
+                println!("{}", warning);
+                let _ = app_handle.emit("hdr-warning", warning);
+                Matrix3::identity()
+            })
+        })
+        .collect();
+    drop(features);
+    println!("Alignment completed in {:.2?}", start_time.elapsed());
+    check_cancelled(&cancel_token)?;
+
+    let _ = app_handle.emit("hdr-progress", "Warping frames...");
+    let mut warped: Vec<Vec<Option<[f32; 3]>>> = Vec::with_capacity(frames.len());
+    for (i, frame) in frames.iter().enumerate() {
+        warped.push(processing::warp_to_reference(
+            &frame.image,
+            &homographies[i],
+            width,
+            height,
+        ));
+        check_cancelled(&cancel_token)?;
+    }
+    drop(frames);
+
+    let start_time = Instant::now();
+    let _ = app_handle.emit("hdr-progress", "Removing ghosts...");
+    let (w, h) = (width as usize, height as usize);
+    let ghost_masks: Vec<Vec<bool>> = (0..warped.len())
+        .map(|i| {
+            if i == ref_index {
+                return vec![false; w * h];
+            }
+            let raw_mask: Vec<bool> = (0..w * h)
+                .into_par_iter()
+                .map(|px| {
+                    let (Some(r), Some(p)) = (warped[ref_index][px], warped[i][px]) else {
+                        return false;
+                    };
+                    if exposure_weight(&r) <= 0.0 || exposure_weight(&p) <= 0.0 {
+                        return false;
+                    }
+                    let ref_lum = luminance(&r).max(1e-6);
+                    let lum = (luminance(&p) / relative_exposures[i] as f32).max(1e-6);
+                    (lum / ref_lum).log2().abs() > GHOST_LOG2_THRESHOLD
+                })
+                .collect();
+            dilate_mask(&raw_mask, w, h, GHOST_DILATION_RADIUS)
+        })
+        .collect();
+    println!("Ghost detection completed in {:.2?}", start_time.elapsed());
+    check_cancelled(&cancel_token)?;
+
+    let _ = app_handle.emit("hdr-progress", "Merging exposures...");
+    let darkest = order[0];
+    let brightest = order[order.len() - 1];
+    let merged: Vec<f32> = (0..w * h)
+        .into_par_iter()
+        .flat_map_iter(|px| {
+            let mut sum = [0.0f32; 3];
+            let mut total_weight = 0.0f32;
+            for i in 0..warped.len() {
+                if ghost_masks[i][px] {
+                    continue;
+                }
+                let Some(p) = warped[i][px] else { continue };
+                let rel = relative_exposures[i] as f32;
+                let weight = exposure_weight(&p) * rel.sqrt();
+                if weight <= 0.0 {
+                    continue;
+                }
+                for c in 0..3 {
+                    sum[c] += weight * p[c] / rel;
+                }
+                total_weight += weight;
+            }
+
+            let radiance = if total_weight > 1e-6 {
+                sum.map(|v| v / total_weight)
+            } else {
+                let fallback = warped[ref_index][px]
+                    .filter(|p| max_channel(p) >= CLIP_THRESHOLD)
+                    .and_then(|_| warped[darkest][px].map(|p| (p, darkest)))
+                    .or_else(|| warped[brightest][px].map(|p| (p, brightest)))
+                    .or_else(|| warped[ref_index][px].map(|p| (p, ref_index)));
+                match fallback {
+                    Some((p, i)) => p.map(|v| v / relative_exposures[i] as f32),
+                    None => [0.0; 3],
+                }
+            };
+            radiance.into_iter().map(|v| v.max(0.0))
+        })
+        .collect();
+
+    let _ = app_handle.emit("hdr-progress", "Finalizing HDR image...");
+    let merged_image = Rgb32FImage::from_raw(width, height, merged)
+        .ok_or_else(|| "Failed to assemble merged HDR image.".to_string())?;
+    Ok(DynamicImage::ImageRgb32F(merged_image))
+}
+
@@ -16,6 +16,9 @@ use serde::Deserialize;
 use serde_json::{Value, from_value};
 use std::panic;
 
+// Written into the ImageDescription of float TIFFs that hold scene-linear data (e.g. HDR merges).
+pub const SCENE_LINEAR_DESCRIPTION: &str = "RapidRAW scene-linear";
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct PatchMaskInfo {
@@ -34,8 +37,13 @@ pub fn load_and_composite(
     use_fast_raw_dev: bool,
     highlight_compression: f32,
 ) -> Result<DynamicImage> {
-    let base_image =
-        load_base_image_from_bytes(base_image, path, use_fast_raw_dev, highlight_compression)?;
+    let base_image = load_base_image_from_bytes(
+        base_image,
+        path,
+        use_fast_raw_dev,
+        highlight_compression,
+        adjustments,
+    )?;
     composite_patches_on_image(&base_image, adjustments)
 }
 
@@ -98,6 +106,7 @@ pub fn load_base_image_from_bytes(
     path_for_ext_check: &str,
     use_fast_raw_dev: bool,
     highlight_compression: f32,
+    adjustments: &Value,
 ) -> Result<DynamicImage> {
     let path = std::path::Path::new(path_for_ext_check);
     if path.extension().and_then(|s| s.to_str()).map_or(false, |s| s.eq_ignore_ascii_case("exr")) {
@@ -113,7 +122,9 @@ pub fn load_base_image_from_bytes(
     }
 
     if is_raw_file(path_for_ext_check) {
-        match panic::catch_unwind(|| develop_raw_image(bytes, use_fast_raw_dev, highlight_compression)) {
+        match panic::catch_unwind(|| {
+            develop_raw_image(bytes, use_fast_raw_dev, highlight_compression, adjustments)
+        }) {
             Ok(Ok(image)) => Ok(image),
             Ok(Err(e)) => {
                 log::warn!("Error developing RAW file '{}': {}", path_for_ext_check, e);
@@ -129,6 +140,44 @@ pub fn load_base_image_from_bytes(
     }
 }
 
+pub fn is_scene_linear_tiff(path: &str) -> bool {
+    let is_tiff = std::path::Path::new(path)
+        .extension()
+        .and_then(|s| s.to_str())
+        .map_or(false, |s| {
+            s.eq_ignore_ascii_case("tif") || s.eq_ignore_ascii_case("tiff")
+        });
+    if !is_tiff {
+        return false;
+    }
+    let Ok(file) = std::fs::File::open(path) else {
+        return false;
+    };
+    tiff::decoder::Decoder::new(std::io::BufReader::new(file))
+        .and_then(|mut decoder| decoder.get_tag_ascii_string(tiff::tags::Tag::ImageDescription))
+        .map_or(false, |description| {
+            description.trim_end_matches('\0') == SCENE_LINEAR_DESCRIPTION
+        })
+}
+
+// Stores scene-linear data as-is; the description tag makes the loader treat it like RAW data.
+pub fn save_scene_linear_tiff(image: &image::Rgb32FImage, path: &std::path::Path) -> Result<()> {
+    let file = std::fs::File::create(path)?;
+    let mut encoder = tiff::encoder::TiffEncoder::new(std::io::BufWriter::new(file))?;
+    let mut tiff_image =
+        encoder.new_image::<tiff::encoder::colortype::RGB32Float>(image.width(), image.height())?;
+    tiff_image
+        .encoder()
+        .write_tag(tiff::tags::Tag::ImageDescription, SCENE_LINEAR_DESCRIPTION)?;
+    tiff_image.write_data(image.as_raw())?;
+    Ok(())
+}
+
+// RAW files and scene-linear TIFFs both get the linear (RAW) rendering path.
+pub fn is_linear_source(path: &str) -> bool {
+    is_raw_file(path) || is_scene_linear_tiff(path)
+}
+
 pub fn load_image_with_orientation(bytes: &[u8]) -> Result<DynamicImage> {
     let cursor = Cursor::new(bytes);
     let mut reader = ImageReader::new(cursor.clone())
@@ -10,7 +10,9 @@ use serde_json::Value;
 use std::f32::consts::PI;
 use std::sync::Arc;
 
-pub use crate::gpu_processing::{get_or_init_gpu_context, process_and_get_dynamic_image};
+pub use crate::gpu_processing::{
+    get_or_init_gpu_context, process_and_get_dynamic_image, process_and_get_float_image,
+};
 use crate::{load_settings, mask_generation::MaskDefinition, AppState};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -122,6 +124,25 @@ pub fn apply_cpu_default_raw_processing(image: &mut DynamicImage) {
     *image = DynamicImage::ImageRgb32F(f32_image);
 }
 
+pub fn display_to_linear(image: &DynamicImage, is_raw_source: bool) -> Rgb32FImage {
+    let mut f32_image = image.to_rgb32f();
+
+    const GAMMA: f32 = 2.2;
+    const CONTRAST: f32 = 1.15;
+
+    f32_image.par_iter_mut().for_each(|v| {
+        *v = if is_raw_source {
+            ((*v - 0.5) / CONTRAST + 0.5).max(0.0).powf(GAMMA)
+        } else if *v <= 0.04045 {
+            *v / 12.92
+        } else {
+            ((*v + 0.055) / 1.055).powf(2.4)
+        };
+    });
+
+    f32_image
+}
+
 pub fn apply_orientation(image: DynamicImage, orientation: Orientation) -> DynamicImage {
     match orientation {
         Orientation::Normal | Orientation::Unknown => image,
@@ -229,6 +250,27 @@ pub struct HslColor {
     _pad: f32,
 }
 
+pub const MAX_COLOR_CURVE_POINTS: usize = 32;
+
+// Each curve packs two (x, y) points per vec4 to stay compact in the uniform.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Pod, Zeroable, Default)]
+#[repr(C)]
+pub struct ColorCurves {
+    pub hue_hue: [[f32; 4]; 16],
+    pub hue_sat: [[f32; 4]; 16],
+    pub hue_lum: [[f32; 4]; 16],
+    pub lum_sat: [[f32; 4]; 16],
+    pub sat_sat: [[f32; 4]; 16],
+    pub hue_hue_count: u32,
+    pub hue_sat_count: u32,
+    pub hue_lum_count: u32,
+    pub lum_sat_count: u32,
+    pub sat_sat_count: u32,
+    _pad1: u32,
+    _pad2: u32,
+    _pad3: u32,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, Pod, Zeroable, Default)]
 #[repr(C)]
 pub struct ColorGradeSettings {
@@ -251,6 +293,26 @@ pub struct ColorCalibrationSettings {
     _pad1: f32,
 }
 
+// Mixer rows hold the input weights for each output channel. The gray mix
+// weights follow the HSL bands, four per vec4.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Pod, Zeroable, Default)]
+#[repr(C)]
+pub struct ChannelMixerSettings {
+    pub red: [f32; 4],
+    pub green: [f32; 4],
+    pub blue: [f32; 4],
+    pub gray_mix_low: [f32; 4],
+    pub gray_mix_high: [f32; 4],
+    pub toning_hue: f32,
+    pub toning_saturation: f32,
+    pub enabled: u32,
+    pub monochrome: u32,
+    pub preserve_luminosity: u32,
+    _pad1: f32,
+    _pad2: f32,
+    _pad3: f32,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, Pod, Zeroable)]
 #[repr(C)]
 pub struct GpuMat3 {
@@ -351,8 +413,18 @@ pub struct GlobalAdjustments {
     pub red_curve_count: u32,
     pub green_curve_count: u32,
     pub blue_curve_count: u32,
-    _pad_end1: f32,
-    _pad_end2: f32,
+    pub parametric_shadows: f32,
+    pub parametric_darks: f32,
+    pub parametric_lights: f32,
+    pub parametric_highlights: f32,
+    pub parametric_shadow_split: f32,
+    pub parametric_midtone_split: f32,
+    pub parametric_highlight_split: f32,
+    _pad_parametric: f32,
+    pub color_curves: ColorCurves,
+    pub channel_mixer: ChannelMixerSettings,
+    pub hdr_headroom: f32,
+    pub float_output: u32,
     _pad_end3: f32,
     _pad_end4: f32,
 }
@@ -404,6 +476,7 @@ pub struct MaskAdjustments {
     pub red_curve_count: u32,
     pub green_curve_count: u32,
     pub blue_curve_count: u32,
+    pub color_curves: ColorCurves,
     _pad_end4: f32,
     _pad_end5: f32,
     _pad_end6: f32,
@@ -509,6 +582,21 @@ const SCALES: AdjustmentScales = AdjustmentScales {
     color_calibration_saturation: 120.0,
 };
 
+// Inverse of apply_white_balance in the shader: the temperature and tint
+// slider values that turn a linear RGB sample neutral. Red and blue share the
+// tint factor, so temperature alone balances them and tint then matches green.
+pub fn white_balance_for_neutral(linear_rgb: [f32; 3]) -> (f32, f32) {
+    let [r, g, b] = linear_rgb;
+    let temp = 5.0 * (b - r) / (b + r);
+    let red_blue = r * (1.0 + 0.2 * temp);
+    let green = g * (1.0 + 0.05 * temp);
+    let tint = 4.0 * (green - red_blue) / (green + red_blue);
+    (
+        (temp * SCALES.temperature).clamp(-100.0, 100.0),
+        (tint * SCALES.tint).clamp(-100.0, 100.0),
+    )
+}
+
 fn parse_hsl_adjustments(js_hsl: &serde_json::Value) -> [HslColor; 8] {
     let mut hsl_array = [HslColor::default(); 8];
     if let Some(hsl_map) = js_hsl.as_object() {
@@ -539,6 +627,98 @@ fn parse_hsl_adjustments(js_hsl: &serde_json::Value) -> [HslColor; 8] {
     hsl_array
 }
 
+// Points are x, y in 0-1 where y = 0.5 leaves the colour unchanged.
+fn pack_color_curve(points: &serde_json::Value) -> ([[f32; 4]; 16], u32) {
+    let mut sorted: Vec<(f32, f32)> = points
+        .as_array()
+        .map(|array| {
+            array
+                .iter()
+                .filter_map(|p| Some((p["x"].as_f64()? as f32, p["y"].as_f64()? as f32)))
+                .map(|(x, y)| (x.clamp(0.0, 1.0), y.clamp(0.0, 1.0)))
+                .collect()
+        })
+        .unwrap_or_default();
+    sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+    sorted.truncate(MAX_COLOR_CURVE_POINTS);
+
+    let mut packed = [[0.0; 4]; 16];
+    for (i, (x, y)) in sorted.iter().enumerate() {
+        packed[i / 2][(i % 2) * 2] = *x;
+        packed[i / 2][(i % 2) * 2 + 1] = *y;
+    }
+    (packed, sorted.len() as u32)
+}
+
+fn parse_color_curves(js_curves: &serde_json::Value) -> ColorCurves {
+    if js_curves.is_null() {
+        return ColorCurves::default();
+    }
+    let (hue_hue, hue_hue_count) = pack_color_curve(&js_curves["hueHue"]);
+    let (hue_sat, hue_sat_count) = pack_color_curve(&js_curves["hueSat"]);
+    let (hue_lum, hue_lum_count) = pack_color_curve(&js_curves["hueLum"]);
+    let (lum_sat, lum_sat_count) = pack_color_curve(&js_curves["lumSat"]);
+    let (sat_sat, sat_sat_count) = pack_color_curve(&js_curves["satSat"]);
+    ColorCurves {
+        hue_hue,
+        hue_sat,
+        hue_lum,
+        lum_sat,
+        sat_sat,
+        hue_hue_count,
+        hue_sat_count,
+        hue_lum_count,
+        lum_sat_count,
+        sat_sat_count,
+        _pad1: 0,
+        _pad2: 0,
+        _pad3: 0,
+    }
+}
+
+fn parse_channel_mixer(js_adjustments: &serde_json::Value) -> ChannelMixerSettings {
+    let mixer = &js_adjustments["channelMixer"];
+    let row = |output: &str| -> [f32; 4] {
+        let weight = |input: &str| {
+            let default = if input == output { 100.0 } else { 0.0 };
+            mixer[output][input].as_f64().unwrap_or(default) as f32 / 100.0
+        };
+        [weight("red"), weight("green"), weight("blue"), 0.0]
+    };
+    let (red, green, blue) = (row("red"), row("green"), row("blue"));
+    let is_identity = red == [1.0, 0.0, 0.0, 0.0]
+        && green == [0.0, 1.0, 0.0, 0.0]
+        && blue == [0.0, 0.0, 1.0, 0.0];
+
+    let bw = &js_adjustments["blackAndWhite"];
+    let gray = |band: &str| bw[band].as_f64().unwrap_or(0.0) as f32 / 100.0;
+    ChannelMixerSettings {
+        red,
+        green,
+        blue,
+        gray_mix_low: [
+            gray("reds"),
+            gray("oranges"),
+            gray("yellows"),
+            gray("greens"),
+        ],
+        gray_mix_high: [
+            gray("aquas"),
+            gray("blues"),
+            gray("purples"),
+            gray("magentas"),
+        ],
+        toning_hue: bw["toningHue"].as_f64().unwrap_or(0.0) as f32,
+        toning_saturation: bw["toningSaturation"].as_f64().unwrap_or(0.0) as f32 / 100.0,
+        enabled: !is_identity as u32,
+        monochrome: bw["enabled"].as_bool().unwrap_or(false) as u32,
+        preserve_luminosity: mixer["preserveLuminosity"].as_bool().unwrap_or(true) as u32,
+        _pad1: 0.0,
+        _pad2: 0.0,
+        _pad3: 0.0,
+    }
+}
+
 fn parse_color_grade_settings(js_cg: &serde_json::Value) -> ColorGradeSettings {
     if js_cg.is_null() {
         return ColorGradeSettings::default();
@@ -713,6 +893,13 @@ fn get_global_adjustments_from_json(
         Vec::new()
     };
 
+    let shadow_split =
+        get_val("curves", "parametricShadowSplit", 100.0, Some(25.0)).clamp(0.05, 0.85);
+    let midtone_split = get_val("curves", "parametricMidtoneSplit", 100.0, Some(50.0))
+        .clamp(shadow_split + 0.05, 0.9);
+    let highlight_split = get_val("curves", "parametricHighlightSplit", 100.0, Some(75.0))
+        .clamp(midtone_split + 0.05, 0.95);
+
     let cg_obj = js_adjustments
         .get("colorGrading")
         .cloned()
@@ -756,6 +943,10 @@ fn get_global_adjustments_from_json(
     let tone_mapper = js_adjustments["toneMapper"].as_str().unwrap_or("basic");
     let (pipe_to_rendering, rendering_to_pipe) = calculate_agx_matrices();
 
+    // An absolute white balance is already applied to RAW files in the sensor domain, so the
+    // relative sliders only act on images without one.
+    let relative_white_balance = !(is_raw && js_adjustments["whiteBalanceKelvin"].is_number());
+
     GlobalAdjustments {
         exposure: get_val("basic", "exposure", SCALES.exposure, None),
         brightness: get_val("basic", "brightness", SCALES.brightness, None),
@@ -766,8 +957,16 @@ fn get_global_adjustments_from_json(
         blacks: get_val("basic", "blacks", SCALES.blacks, None),
 
         saturation: get_val("color", "saturation", SCALES.saturation, None),
-        temperature: get_val("color", "temperature", SCALES.temperature, None),
-        tint: get_val("color", "tint", SCALES.tint, None),
+        temperature: if relative_white_balance {
+            get_val("color", "temperature", SCALES.temperature, None)
+        } else {
+            0.0
+        },
+        tint: if relative_white_balance {
+            get_val("color", "tint", SCALES.tint, None)
+        } else {
+            0.0
+        },
         vibrance: get_val("color", "vibrance", SCALES.vibrance, None),
 
         sharpness: get_val("details", "sharpness", SCALES.sharpness, None),
@@ -917,8 +1116,26 @@ fn get_global_adjustments_from_json(
         red_curve_count: red_points.len() as u32,
         green_curve_count: green_points.len() as u32,
         blue_curve_count: blue_points.len() as u32,
-        _pad_end1: 0.0,
-        _pad_end2: 0.0,
+        parametric_shadows: get_val("curves", "parametricShadows", 100.0, None),
+        parametric_darks: get_val("curves", "parametricDarks", 100.0, None),
+        parametric_lights: get_val("curves", "parametricLights", 100.0, None),
+        parametric_highlights: get_val("curves", "parametricHighlights", 100.0, None),
+        parametric_shadow_split: shadow_split,
+        parametric_midtone_split: midtone_split,
+        parametric_highlight_split: highlight_split,
+        _pad_parametric: 0.0,
+        color_curves: if is_visible("curves") {
+            parse_color_curves(&js_adjustments["colorCurves"])
+        } else {
+            ColorCurves::default()
+        },
+        channel_mixer: if is_visible("color") {
+            parse_channel_mixer(js_adjustments)
+        } else {
+            ChannelMixerSettings::default()
+        },
+        hdr_headroom: 0.0,
+        float_output: 0,
         _pad_end3: 0.0,
         _pad_end4: 0.0,
     }
@@ -1043,6 +1260,11 @@ fn get_mask_adjustments_from_json(adj: &serde_json::Value) -> MaskAdjustments {
         red_curve_count: red_points.len() as u32,
         green_curve_count: green_points.len() as u32,
         blue_curve_count: blue_points.len() as u32,
+        color_curves: if is_visible("curves") {
+            parse_color_curves(&adj["colorCurves"])
+        } else {
+            ColorCurves::default()
+        },
         _pad_end4: 0.0,
         _pad_end5: 0.0,
         _pad_end6: 0.0,
@@ -1534,4 +1756,32 @@ pub fn calculate_auto_adjustments(
     let results = perform_auto_analysis(&original_image);
 
     Ok(auto_results_to_json(&results))
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Same multipliers as apply_white_balance in shader.wgsl.
+    fn shader_white_balance(rgb: [f32; 3], temperature: f32, tint: f32) -> [f32; 3] {
+        let temp = temperature / SCALES.temperature;
+        let tnt = tint / SCALES.tint;
+        let temp_mult = [1.0 + temp * 0.2, 1.0 + temp * 0.05, 1.0 - temp * 0.2];
+        let tint_mult = [1.0 + tnt * 0.25, 1.0 - tnt * 0.25, 1.0 + tnt * 0.25];
+        std::array::from_fn(|c| rgb[c] * temp_mult[c] * tint_mult[c])
+    }
+
+    #[test]
+    fn sampled_white_balance_neutralizes_the_sample() {
+        for sample in [[0.3, 0.5, 0.6], [0.6, 0.45, 0.3], [0.4, 0.3, 0.42]] {
+            let (temperature, tint) = white_balance_for_neutral(sample);
+            let [r, g, b] = shader_white_balance(sample, temperature, tint);
+            assert!(
+                (r - g).abs() < 1e-4 && (b - g).abs() < 1e-4,
+                "{:?} became {:?}",
+                sample,
+                [r, g, b]
+            );
+        }
+    }
+}
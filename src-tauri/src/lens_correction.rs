@@ -0,0 +1,821 @@
+use image::{DynamicImage, ImageBuffer, Rgb, Rgba};
+use once_cell::sync::{Lazy, OnceCell};
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+static DATABASE_DIRS: OnceCell<Vec<PathBuf>> = OnceCell::new();
+static DATABASE: OnceCell<LensDatabase> = OnceCell::new();
+static ATTRIBUTE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"([A-Za-z_][\w:-]*)\s*=\s*"([^"]*)""#).unwrap());
+
+const MIN_MATCH_SCORE: f32 = 0.75;
+const AUTO_SCALE_ITERATIONS: usize = 20;
+const BORDER_SAMPLES: usize = 16;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LensSelection {
+    pub lens_maker: String,
+    pub lens_model: String,
+    pub camera_crop_factor: f32,
+    pub focal_length: f32,
+    pub aperture: f32,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LensSummary {
+    pub maker: String,
+    pub model: String,
+    pub crop_factor: f32,
+    pub has_distortion: bool,
+    pub has_vignetting: bool,
+    pub has_tca: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+struct CameraEntry {
+    maker: String,
+    model: String,
+    crop_factor: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct DistortionCalibration {
+    focal: f32,
+    // Rd = Ru * (c0 + c1*Ru + c2*Ru^2 + c3*Ru^3 + c4*Ru^4)
+    coefficients: [f32; 5],
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TcaCalibration {
+    focal: f32,
+    // Rd = Ru * (v + c*Ru + b*Ru^2), per red and blue channel
+    red: [f32; 3],
+    blue: [f32; 3],
+}
+
+#[derive(Debug, Clone, Copy)]
+struct VignettingCalibration {
+    focal: f32,
+    aperture: f32,
+    distance: f32,
+    k: [f32; 3],
+}
+
+#[derive(Debug, Clone, Default)]
+struct LensEntry {
+    maker: String,
+    model: String,
+    crop_factor: f32,
+    distortion: Vec<DistortionCalibration>,
+    tca: Vec<TcaCalibration>,
+    vignetting: Vec<VignettingCalibration>,
+}
+
+#[derive(Default)]
+struct LensDatabase {
+    cameras: Vec<CameraEntry>,
+    lenses: Vec<LensEntry>,
+}
+
+pub fn set_database_dirs(dirs: Vec<PathBuf>) {
+    let _ = DATABASE_DIRS.set(dirs);
+}
+
+fn database() -> &'static LensDatabase {
+    DATABASE.get_or_init(|| {
+        let mut db = LensDatabase::default();
+        for dir in DATABASE_DIRS.get().into_iter().flatten() {
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().and_then(|e| e.to_str()) != Some("xml") {
+                    continue;
+                }
+                match fs::read_to_string(&path) {
+                    Ok(content) => parse_database(&content, &mut db),
+                    Err(e) => log::warn!("Failed to read lens database {:?}: {}", path, e),
+                }
+            }
+        }
+        log::info!(
+            "Loaded lens database with {} cameras and {} lenses",
+            db.cameras.len(),
+            db.lenses.len()
+        );
+        db
+    })
+}
+
+enum XmlEvent<'a> {
+    Start(&'a str, HashMap<String, String>, bool),
+    End(&'a str),
+    Text(String),
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn tokenize_xml(content: &str) -> Vec<XmlEvent<'_>> {
+    let mut events = Vec::new();
+    let mut rest = content;
+    while let Some(start) = rest.find('<') {
+        let text = rest[..start].trim();
+        if !text.is_empty() {
+            events.push(XmlEvent::Text(decode_entities(text)));
+        }
+        rest = &rest[start..];
+
+        let (skip_end, terminator) = if rest.starts_with("<!--") {
+            (true, "-->")
+        } else if rest.starts_with("<?") {
+            (true, "?>")
+        } else if rest.starts_with("<!") {
+            (true, ">")
+        } else {
+            (false, ">")
+        };
+        let Some(end) = rest.find(terminator) else {
+            break;
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + terminator.len()..];
+        if skip_end {
+            continue;
+        }
+
+        if let Some(name) = tag.strip_prefix('/') {
+            events.push(XmlEvent::End(name.trim()));
+            continue;
+        }
+        let self_closing = tag.ends_with('/');
+        let tag = tag.trim_end_matches('/');
+        let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+        let attributes = ATTRIBUTE_REGEX
+            .captures_iter(&tag[name_end..])
+            .map(|c| (c[1].to_string(), decode_entities(&c[2])))
+            .collect();
+        events.push(XmlEvent::Start(&tag[..name_end], attributes, self_closing));
+    }
+    events
+}
+
+fn attr_or(attributes: &HashMap<String, String>, key: &str, default: f32) -> f32 {
+    attributes
+        .get(key)
+        .and_then(|v| v.trim().parse::<f32>().ok())
+        .unwrap_or(default)
+}
+
+fn attr(attributes: &HashMap<String, String>, key: &str) -> f32 {
+    attr_or(attributes, key, 0.0)
+}
+
+fn parse_calibration(name: &str, attributes: &HashMap<String, String>, lens: &mut LensEntry) {
+    let model = attributes.get("model").map(String::as_str).unwrap_or("");
+    let focal = attr(attributes, "focal");
+    match (name, model) {
+        ("distortion", "poly3") => {
+            let k1 = attr(attributes, "k1");
+            lens.distortion.push(DistortionCalibration {
+                focal,
+                coefficients: [1.0 - k1, 0.0, k1, 0.0, 0.0],
+            });
+        }
+        ("distortion", "poly5") => lens.distortion.push(DistortionCalibration {
+            focal,
+            coefficients: [
+                1.0,
+                0.0,
+                attr(attributes, "k1"),
+                0.0,
+                attr(attributes, "k2"),
+            ],
+        }),
+        ("distortion", "ptlens") => {
+            let (a, b, c) = (
+                attr(attributes, "a"),
+                attr(attributes, "b"),
+                attr(attributes, "c"),
+            );
+            lens.distortion.push(DistortionCalibration {
+                focal,
+                coefficients: [1.0 - a - b - c, c, b, a, 0.0],
+            });
+        }
+        ("tca", "linear") => lens.tca.push(TcaCalibration {
+            focal,
+            red: [attr(attributes, "kr"), 0.0, 0.0],
+            blue: [attr(attributes, "kb"), 0.0, 0.0],
+        }),
+        ("tca", "poly3") => lens.tca.push(TcaCalibration {
+            focal,
+            red: [
+                attr_or(attributes, "vr", 1.0),
+                attr(attributes, "cr"),
+                attr(attributes, "br"),
+            ],
+            blue: [
+                attr_or(attributes, "vb", 1.0),
+                attr(attributes, "cb"),
+                attr(attributes, "bb"),
+            ],
+        }),
+        ("vignetting", "pa") => lens.vignetting.push(VignettingCalibration {
+            focal,
+            aperture: attr(attributes, "aperture"),
+            distance: attr(attributes, "distance"),
+            k: [
+                attr(attributes, "k1"),
+                attr(attributes, "k2"),
+                attr(attributes, "k3"),
+            ],
+        }),
+        _ => {}
+    }
+}
+
+fn parse_database(content: &str, db: &mut LensDatabase) {
+    let mut stack: Vec<(&str, bool)> = Vec::new();
+    let mut camera: Option<CameraEntry> = None;
+    let mut lens: Option<LensEntry> = None;
+
+    for event in tokenize_xml(content) {
+        match event {
+            XmlEvent::Start(name, attributes, self_closing) => {
+                match name {
+                    "camera" => camera = Some(CameraEntry::default()),
+                    "lens" => lens = Some(LensEntry::default()),
+                    "distortion" | "tca" | "vignetting" => {
+                        if let Some(lens) = lens.as_mut() {
+                            parse_calibration(name, &attributes, lens);
+                        }
+                    }
+                    _ => {}
+                }
+                if !self_closing {
+                    stack.push((name, attributes.contains_key("lang")));
+                }
+            }
+            XmlEvent::End(name) => {
+                stack.pop();
+                match name {
+                    "camera" => {
+                        if let Some(entry) = camera.take() {
+                            db.cameras.push(entry);
+                        }
+                    }
+                    "lens" => {
+                        if let Some(mut entry) = lens.take() {
+                            entry.distortion.sort_by(|a, b| a.focal.total_cmp(&b.focal));
+                            entry.tca.sort_by(|a, b| a.focal.total_cmp(&b.focal));
+                            if entry.crop_factor <= 0.0 {
+                                entry.crop_factor = 1.0;
+                            }
+                            db.lenses.push(entry);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            XmlEvent::Text(text) => {
+                let Some(&(element, localized)) = stack.last() else {
+                    continue;
+                };
+                if localized {
+                    continue;
+                }
+                if let Some(entry) = lens.as_mut() {
+                    match element {
+                        "maker" => entry.maker = text,
+                        "model" => entry.model = text,
+                        "cropfactor" => entry.crop_factor = text.parse().unwrap_or(1.0),
+                        _ => {}
+                    }
+                } else if let Some(entry) = camera.as_mut() {
+                    match element {
+                        "maker" => entry.maker = text,
+                        "model" => entry.model = text,
+                        "cropfactor" => entry.crop_factor = text.parse().unwrap_or(1.0),
+                        _ => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn tokens(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !(c.is_alphanumeric() || c == '.'))
+        .map(|t| t.trim_matches('.').to_string())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+fn match_score(candidate: &str, query: &[String]) -> f32 {
+    let candidate_tokens = tokens(candidate);
+    if candidate_tokens.is_empty() || query.is_empty() {
+        return 0.0;
+    }
+    let matched = candidate_tokens
+        .iter()
+        .filter(|t| query.contains(t))
+        .count();
+    let coverage = matched as f32 / candidate_tokens.len() as f32;
+    let precision = matched as f32 / query.len() as f32;
+    0.8 * coverage + 0.2 * precision
+}
+
+fn exif_string(exif: &HashMap<String, String>, key: &str) -> Option<String> {
+    let value = exif.get(key)?.trim().trim_matches('"').trim().to_string();
+    (!value.is_empty()).then_some(value)
+}
+
+pub(crate) fn exif_number(exif: &HashMap<String, String>, key: &str) -> Option<f32> {
+    let value = exif_string(exif, key)?;
+    let value = value.trim_start_matches("f/");
+    let number: String = value
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    number.parse().ok().filter(|v: &f32| *v > 0.0)
+}
+
+fn find_camera<'a>(db: &'a LensDatabase, make: &str, model: &str) -> Option<&'a CameraEntry> {
+    let query = tokens(&format!("{} {}", make, model));
+    db.cameras
+        .iter()
+        .map(|camera| {
+            let score = match_score(&format!("{} {}", camera.maker, camera.model), &query);
+            (camera, score)
+        })
+        .filter(|(_, score)| *score >= MIN_MATCH_SCORE)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(camera, _)| camera)
+}
+
+pub fn detect_lens(exif: &HashMap<String, String>) -> Option<LensSelection> {
+    let db = database();
+    let lens_model = exif_string(exif, "LensModel")?;
+    let lens_make = exif_string(exif, "LensMake").unwrap_or_default();
+    let query = tokens(&format!("{} {}", lens_make, lens_model));
+
+    let (lens, _) = db
+        .lenses
+        .iter()
+        .map(|lens| {
+            let with_maker = match_score(&format!("{} {}", lens.maker, lens.model), &query);
+            (lens, with_maker.max(match_score(&lens.model, &query)))
+        })
+        .filter(|(_, score)| *score >= MIN_MATCH_SCORE)
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    let camera_crop_factor = match (exif_string(exif, "Make"), exif_string(exif, "Model")) {
+        (Some(make), Some(model)) => find_camera(db, &make, &model).map(|c| c.crop_factor),
+        _ => None,
+    }
+    .filter(|c| *c > 0.0)
+    .unwrap_or(lens.crop_factor);
+
+    Some(LensSelection {
+        lens_maker: lens.maker.clone(),
+        lens_model: lens.model.clone(),
+        camera_crop_factor,
+        focal_length: exif_number(exif, "FocalLength").unwrap_or(0.0),
+        aperture: exif_number(exif, "FNumber").unwrap_or(0.0),
+    })
+}
+
+pub fn list_lenses() -> Vec<LensSummary> {
+    let mut lenses: Vec<LensSummary> = database()
+        .lenses
+        .iter()
+        .map(|lens| LensSummary {
+            maker: lens.maker.clone(),
+            model: lens.model.clone(),
+            crop_factor: lens.crop_factor,
+            has_distortion: !lens.distortion.is_empty(),
+            has_vignetting: !lens.vignetting.is_empty(),
+            has_tca: !lens.tca.is_empty(),
+        })
+        .collect();
+    lenses.sort_by(|a, b| (&a.maker, &a.model).cmp(&(&b.maker, &b.model)));
+    lenses
+}
+
+fn interpolate_by_focal<T: Copy>(
+    entries: &[T],
+    focal: f32,
+    focal_of: impl Fn(&T) -> f32,
+    lerp: impl Fn(&T, &T, f32) -> T,
+) -> Option<T> {
+    let first = entries.first()?;
+    let last = entries.last()?;
+    if focal <= focal_of(first) || entries.len() == 1 {
+        return Some(*first);
+    }
+    if focal >= focal_of(last) {
+        return Some(*last);
+    }
+    entries.windows(2).find_map(|pair| {
+        let (f0, f1) = (focal_of(&pair[0]), focal_of(&pair[1]));
+        (focal >= f0 && focal <= f1).then(|| {
+            let t = if f1 > f0 {
+                (focal - f0) / (f1 - f0)
+            } else {
+                0.0
+            };
+            lerp(&pair[0], &pair[1], t)
+        })
+    })
+}
+
+fn lerp_array<const N: usize>(a: &[f32; N], b: &[f32; N], t: f32) -> [f32; N] {
+    std::array::from_fn(|i| a[i] + (b[i] - a[i]) * t)
+}
+
+fn blend_with_identity<const N: usize>(
+    coefficients: &[f32; N],
+    identity: &[f32; N],
+    amount: f32,
+) -> [f32; N] {
+    std::array::from_fn(|i| identity[i] + (coefficients[i] - identity[i]) * amount)
+}
+
+struct LensCorrection {
+    distortion: Option<[f32; 5]>,
+    tca: Option<([f32; 3], [f32; 3])>,
+    vignetting: Option<[f32; 3]>,
+    // Converts image-normalized radii into the calibration's normalized radii.
+    crop_ratio: f32,
+}
+
+fn amount(adjustments: &Value, key: &str) -> f32 {
+    adjustments
+        .get(key)
+        .and_then(|v| v.as_f64())
+        .unwrap_or(100.0) as f32
+        / 100.0
+}
+
+impl LensCorrection {
+    fn from_adjustments(adjustments: &Value) -> Option<Self> {
+        let selection: LensSelection =
+            serde_json::from_value(adjustments.get("lensProfile")?.clone()).ok()?;
+        let lens = database()
+            .lenses
+            .iter()
+            .find(|l| l.maker == selection.lens_maker && l.model == selection.lens_model)?;
+
+        let distortion_amount = amount(adjustments, "lensDistortionAmount");
+        let tca_amount = amount(adjustments, "lensTcaAmount");
+        let vignetting_amount = amount(adjustments, "lensVignettingAmount");
+        let focal = selection.focal_length;
+
+        let distortion = interpolate_by_focal(
+            &lens.distortion,
+            focal,
+            |c| c.focal,
+            |a, b, t| DistortionCalibration {
+                focal,
+                coefficients: lerp_array(&a.coefficients, &b.coefficients, t),
+            },
+        )
+        .filter(|_| distortion_amount != 0.0)
+        .map(|c| {
+            blend_with_identity(
+                &c.coefficients,
+                &[1.0, 0.0, 0.0, 0.0, 0.0],
+                distortion_amount,
+            )
+        });
+
+        let tca = interpolate_by_focal(
+            &lens.tca,
+            focal,
+            |c| c.focal,
+            |a, b, t| TcaCalibration {
+                focal,
+                red: lerp_array(&a.red, &b.red, t),
+                blue: lerp_array(&a.blue, &b.blue, t),
+            },
+        )
+        .filter(|_| tca_amount != 0.0)
+        .map(|c| {
+            (
+                blend_with_identity(&c.red, &[1.0, 0.0, 0.0], tca_amount),
+                blend_with_identity(&c.blue, &[1.0, 0.0, 0.0], tca_amount),
+            )
+        });
+
+        let vignetting = Self::vignetting_for(lens, focal, selection.aperture)
+            .filter(|_| vignetting_amount != 0.0)
+            .map(|k| k.map(|v| v * vignetting_amount));
+
+        if distortion.is_none() && tca.is_none() && vignetting.is_none() {
+            return None;
+        }
+
+        let crop_ratio = if selection.camera_crop_factor > 0.0 && lens.crop_factor > 0.0 {
+            lens.crop_factor / selection.camera_crop_factor
+        } else {
+            1.0
+        };
+
+        Some(Self {
+            distortion,
+            tca,
+            vignetting,
+            crop_ratio,
+        })
+    }
+
+    fn vignetting_for(lens: &LensEntry, focal: f32, aperture: f32) -> Option<[f32; 3]> {
+        let log_aperture = |a: f32| a.max(0.1).log2();
+        let target = log_aperture(if aperture > 0.0 { aperture } else { 8.0 });
+        let nearest = lens
+            .vignetting
+            .iter()
+            .map(|v| (log_aperture(v.aperture) - target).abs())
+            .min_by(|a, b| a.total_cmp(b))?;
+
+        let mut candidates: Vec<VignettingCalibration> = lens
+            .vignetting
+            .iter()
+            .filter(|v| ((log_aperture(v.aperture) - target).abs() - nearest).abs() < 1e-3)
+            .copied()
+            .collect();
+        let farthest = candidates.iter().map(|v| v.distance).fold(0.0f32, f32::max);
+        candidates.retain(|v| v.distance == farthest);
+        candidates.sort_by(|a, b| a.focal.total_cmp(&b.focal));
+
+        interpolate_by_focal(
+            &candidates,
+            focal,
+            |c| c.focal,
+            |a, b, t| VignettingCalibration {
+                focal,
+                aperture: a.aperture,
+                distance: a.distance,
+                k: lerp_array(&a.k, &b.k, t),
+            },
+        )
+        .map(|c| c.k)
+    }
+
+    fn distortion_factor(&self, r: f32) -> f32 {
+        match &self.distortion {
+            Some(c) => {
+                let r = r * self.crop_ratio;
+                c[0] + r * (c[1] + r * (c[2] + r * (c[3] + r * c[4])))
+            }
+            None => 1.0,
+        }
+    }
+
+    fn tca_factors(&self, r: f32) -> (f32, f32) {
+        match &self.tca {
+            Some((red, blue)) => {
+                let r = r * self.crop_ratio;
+                (
+                    red[0] + r * (red[1] + r * red[2]),
+                    blue[0] + r * (blue[1] + r * blue[2]),
+                )
+            }
+            None => (1.0, 1.0),
+        }
+    }
+
+    fn vignetting_gain(&self, r: f32) -> f32 {
+        match &self.vignetting {
+            Some(k) => {
+                let r2 = (r * self.crop_ratio).powi(2);
+                let falloff = 1.0 + r2 * (k[0] + r2 * (k[1] + r2 * k[2]));
+                if falloff > 1e-3 { 1.0 / falloff } else { 1.0 }
+            }
+            None => 1.0,
+        }
+    }
+
+    // Scale factors from an output radius to the source radius for red, green and blue.
+    fn source_scales(&self, r: f32) -> [f32; 3] {
+        let green = self.distortion_factor(r);
+        let (red, blue) = self.tca_factors(r * green);
+        [green * red, green, green * blue]
+    }
+}
+
+struct Geometry {
+    center_x: f32,
+    center_y: f32,
+    norm: f32,
+    diagonal_norm: f32,
+    scale: f32,
+}
+
+impl Geometry {
+    fn new(correction: &LensCorrection, width: usize, height: usize) -> Self {
+        let mut geometry = Geometry {
+            center_x: (width - 1) as f32 * 0.5,
+            center_y: (height - 1) as f32 * 0.5,
+            norm: width.min(height) as f32 * 0.5,
+            diagonal_norm: ((width * width + height * height) as f32).sqrt() * 0.5,
+            scale: 1.0,
+        };
+        if correction.distortion.is_some() || correction.tca.is_some() {
+            auto_scale(correction, &mut geometry, width as f32, height as f32);
+        }
+        geometry
+    }
+}
+
+fn fits_inside(correction: &LensCorrection, geometry: &Geometry, width: f32, height: f32) -> bool {
+    let (max_x, max_y) = (width - 1.0, height - 1.0);
+    (0..=BORDER_SAMPLES).all(|i| {
+        let t = i as f32 / BORDER_SAMPLES as f32;
+        [
+            (t * max_x, 0.0),
+            (t * max_x, max_y),
+            (0.0, t * max_y),
+            (max_x, t * max_y),
+        ]
+        .iter()
+        .all(|&(x, y)| {
+            let dx = (x - geometry.center_x) * geometry.scale;
+            let dy = (y - geometry.center_y) * geometry.scale;
+            let r = (dx * dx + dy * dy).sqrt() / geometry.norm;
+            correction.source_scales(r).iter().all(|&s| {
+                let sx = geometry.center_x + dx * s;
+                let sy = geometry.center_y + dy * s;
+                sx >= -0.5 && sy >= -0.5 && sx <= max_x + 0.5 && sy <= max_y + 0.5
+            })
+        })
+    })
+}
+
+fn auto_scale(correction: &LensCorrection, geometry: &mut Geometry, width: f32, height: f32) {
+    if fits_inside(correction, geometry, width, height) {
+        return;
+    }
+    let (mut low, mut high) = (0.5f32, 1.0f32);
+    for _ in 0..AUTO_SCALE_ITERATIONS {
+        geometry.scale = 0.5 * (low + high);
+        if fits_inside(correction, geometry, width, height) {
+            low = geometry.scale;
+        } else {
+            high = geometry.scale;
+        }
+    }
+    geometry.scale = low;
+}
+
+fn sample_channel(
+    data: &[f32],
+    width: usize,
+    height: usize,
+    channels: usize,
+    channel: usize,
+    x: f32,
+    y: f32,
+) -> f32 {
+    let x = x.clamp(0.0, (width - 1) as f32);
+    let y = y.clamp(0.0, (height - 1) as f32);
+    let x0 = x.floor() as usize;
+    let y0 = y.floor() as usize;
+    let x1 = (x0 + 1).min(width - 1);
+    let y1 = (y0 + 1).min(height - 1);
+    let fx = x - x0 as f32;
+    let fy = y - y0 as f32;
+    let at = |px: usize, py: usize| data[(py * width + px) * channels + channel];
+    let top = at(x0, y0) + (at(x1, y0) - at(x0, y0)) * fx;
+    let bottom = at(x0, y1) + (at(x1, y1) - at(x0, y1)) * fx;
+    top + (bottom - top) * fy
+}
+
+fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+pub fn has_lens_correction(adjustments: &Value) -> bool {
+    adjustments
+        .get("lensProfile")
+        .is_some_and(|profile| !profile.is_null())
+}
+
+// Maps a pixel of the corrected image to the position it samples in the uncorrected one,
+// using the green channel's distortion.
+pub fn source_point(adjustments: &Value, width: u32, height: u32, point: (f64, f64)) -> (f64, f64) {
+    if !has_lens_correction(adjustments) || width < 2 || height < 2 {
+        return point;
+    }
+    let Some(correction) = LensCorrection::from_adjustments(adjustments) else {
+        return point;
+    };
+    let geometry = Geometry::new(&correction, width as usize, height as usize);
+    let dx = (point.0 as f32 - geometry.center_x) * geometry.scale;
+    let dy = (point.1 as f32 - geometry.center_y) * geometry.scale;
+    let r = (dx * dx + dy * dy).sqrt() / geometry.norm;
+    let scale = correction.source_scales(r)[1];
+    (
+        (geometry.center_x + dx * scale) as f64,
+        (geometry.center_y + dy * scale) as f64,
+    )
+}
+
+pub fn apply_lens_correction(
+    image: DynamicImage,
+    adjustments: &Value,
+    is_raw: bool,
+) -> DynamicImage {
+    if !has_lens_correction(adjustments) {
+        return image;
+    }
+    let Some(correction) = LensCorrection::from_adjustments(adjustments) else {
+        return image;
+    };
+
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    if width < 2 || height < 2 {
+        return image;
+    }
+    let has_alpha = image.color().has_alpha();
+    let channels = if has_alpha { 4 } else { 3 };
+    let data: Vec<f32> = if has_alpha {
+        image.to_rgba32f().into_raw()
+    } else {
+        image.to_rgb32f().into_raw()
+    };
+
+    let geometry = Geometry::new(&correction, width, height);
+
+    let mut output = vec![0.0f32; data.len()];
+    output
+        .par_chunks_mut(width * channels)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for x in 0..width {
+                let dx = (x as f32 - geometry.center_x) * geometry.scale;
+                let dy = (y as f32 - geometry.center_y) * geometry.scale;
+                let r = (dx * dx + dy * dy).sqrt() / geometry.norm;
+                let scales = correction.source_scales(r);
+
+                let source_r = r * scales[1] * geometry.norm / geometry.diagonal_norm;
+                let gain = correction.vignetting_gain(source_r);
+
+                let pixel = &mut row[x * channels..(x + 1) * channels];
+                for c in 0..3 {
+                    let sx = geometry.center_x + dx * scales[c];
+                    let sy = geometry.center_y + dy * scales[c];
+                    let value = sample_channel(&data, width, height, channels, c, sx, sy);
+                    pixel[c] = if gain == 1.0 {
+                        value
+                    } else if is_raw {
+                        value * gain
+                    } else {
+                        linear_to_srgb(srgb_to_linear(value) * gain)
+                    };
+                }
+                if has_alpha {
+                    let sx = geometry.center_x + dx * scales[1];
+                    let sy = geometry.center_y + dy * scales[1];
+                    pixel[3] = sample_channel(&data, width, height, channels, 3, sx, sy);
+                }
+            }
+        });
+
+    if has_alpha {
+        ImageBuffer::<Rgba<f32>, _>::from_raw(width as u32, height as u32, output)
+            .map(DynamicImage::ImageRgba32F)
+            .unwrap_or(image)
+    } else {
+        ImageBuffer::<Rgb<f32>, _>::from_raw(width as u32, height as u32, output)
+            .map(DynamicImage::ImageRgb32F)
+            .unwrap_or(image)
+    }
+}
@@ -0,0 +1,373 @@
+use base64::{Engine as _, engine::general_purpose};
+use image::{DynamicImage, Rgba32FImage};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::perspective;
+
+const BASE_CELLS_LONG_EDGE: usize = 64;
+const MAX_CELLS_LONG_EDGE: usize = 1024;
+const NODES_PER_RADIUS: f64 = 4.0;
+const DAB_SPACING: f64 = 0.2;
+const BLOAT_RATE: f64 = 0.08;
+const RECONSTRUCT_RATE: f64 = 0.25;
+const DISPLACEMENT_PRECISION: f32 = 100_000.0;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LiquifyTool {
+    Forward,
+    Bloat,
+    Pucker,
+    Reconstruct,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct StrokePoint {
+    pub x: f64,
+    pub y: f64,
+}
+
+// Points are in pixels of the transformed image before cropping; size is the
+// brush diameter in the same units and strength ranges from 0 to 100.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LiquifyStroke {
+    pub tool: LiquifyTool,
+    pub points: Vec<StrokePoint>,
+    pub size: f64,
+    pub strength: f64,
+}
+
+// Backward displacement per mesh node, interleaved as dx, dy and measured in
+// fractions of the long image edge so the mesh is independent of resolution.
+// The grid starts coarse and is refined when a smaller brush needs it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(try_from = "PackedMesh", into = "PackedMesh")]
+pub struct LiquifyMesh {
+    pub columns: usize,
+    pub rows: usize,
+    pub displacements: Vec<f32>,
+}
+
+// Sidecar and undo history form of the mesh. Only runs of displaced nodes are
+// kept, as little-endian (first node u32, node count u32, then i32 dx, dy per
+// node in units of 1 / DISPLACEMENT_PRECISION), encoded as base64.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PackedMesh {
+    columns: usize,
+    rows: usize,
+    nodes: String,
+}
+
+impl From<LiquifyMesh> for PackedMesh {
+    fn from(mesh: LiquifyMesh) -> Self {
+        let quantized: Vec<i32> = mesh
+            .displacements
+            .iter()
+            .map(|d| (d * DISPLACEMENT_PRECISION).round() as i32)
+            .collect();
+        let displaced = |node: usize| quantized[node * 2] != 0 || quantized[node * 2 + 1] != 0;
+
+        let mut bytes = Vec::new();
+        let node_count = mesh.columns * mesh.rows;
+        let mut node = 0;
+        while node < node_count {
+            if !displaced(node) {
+                node += 1;
+                continue;
+            }
+            let start = node;
+            while node < node_count && displaced(node) {
+                node += 1;
+            }
+            bytes.extend_from_slice(&(start as u32).to_le_bytes());
+            bytes.extend_from_slice(&((node - start) as u32).to_le_bytes());
+            for value in &quantized[start * 2..node * 2] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+
+        Self {
+            columns: mesh.columns,
+            rows: mesh.rows,
+            nodes: general_purpose::STANDARD.encode(bytes),
+        }
+    }
+}
+
+impl TryFrom<PackedMesh> for LiquifyMesh {
+    type Error = String;
+
+    fn try_from(packed: PackedMesh) -> Result<Self, Self::Error> {
+        if packed.columns < 2
+            || packed.rows < 2
+            || packed.columns.max(packed.rows) > MAX_CELLS_LONG_EDGE + 1
+        {
+            return Err("Invalid liquify mesh size".to_string());
+        }
+        let bytes = general_purpose::STANDARD
+            .decode(&packed.nodes)
+            .map_err(|e| e.to_string())?;
+
+        let node_count = packed.columns * packed.rows;
+        let mut displacements = vec![0.0; node_count * 2];
+        let read_u32 = |offset: usize| {
+            bytes
+                .get(offset..offset + 4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .ok_or_else(|| "Truncated liquify mesh".to_string())
+        };
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let start = read_u32(offset)? as usize;
+            let length = read_u32(offset + 4)? as usize;
+            offset += 8;
+            if start + length > node_count {
+                return Err("Liquify mesh run out of range".to_string());
+            }
+            for value in &mut displacements[start * 2..(start + length) * 2] {
+                *value = read_u32(offset)? as i32 as f32 / DISPLACEMENT_PRECISION;
+                offset += 4;
+            }
+        }
+
+        Ok(Self {
+            columns: packed.columns,
+            rows: packed.rows,
+            displacements,
+        })
+    }
+}
+
+impl LiquifyMesh {
+    pub fn new(width: u32, height: u32) -> Self {
+        let (long, short) = (width.max(height) as f64, width.min(height).max(1) as f64);
+        let short_cells = ((BASE_CELLS_LONG_EDGE as f64 * short / long).round() as usize).max(1);
+        let (columns, rows) = if width >= height {
+            (BASE_CELLS_LONG_EDGE + 1, short_cells + 1)
+        } else {
+            (short_cells + 1, BASE_CELLS_LONG_EDGE + 1)
+        };
+        Self {
+            columns,
+            rows,
+            displacements: vec![0.0; columns * rows * 2],
+        }
+    }
+
+    pub fn from_adjustments(adjustments: &Value) -> Option<Self> {
+        serde_json::from_value(adjustments["liquify"].clone()).ok()
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.displacements.iter().all(|d| *d == 0.0)
+    }
+
+    fn node(&self, column: usize, row: usize) -> (f64, f64) {
+        let index = (row * self.columns + column) * 2;
+        (
+            self.displacements[index] as f64,
+            self.displacements[index + 1] as f64,
+        )
+    }
+
+    // Bilinear displacement at normalized image coordinates.
+    fn displacement_at(&self, u: f64, v: f64) -> (f64, f64) {
+        let gx = (u * (self.columns - 1) as f64).clamp(0.0, (self.columns - 1) as f64);
+        let gy = (v * (self.rows - 1) as f64).clamp(0.0, (self.rows - 1) as f64);
+        let (c0, r0) = (
+            (gx.floor() as usize).min(self.columns - 2),
+            (gy.floor() as usize).min(self.rows - 2),
+        );
+        let (fx, fy) = (gx - c0 as f64, gy - r0 as f64);
+
+        let (d00, d10) = (self.node(c0, r0), self.node(c0 + 1, r0));
+        let (d01, d11) = (self.node(c0, r0 + 1), self.node(c0 + 1, r0 + 1));
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+        (
+            lerp(lerp(d00.0, d10.0, fx), lerp(d01.0, d11.0, fx), fy),
+            lerp(lerp(d00.1, d10.1, fx), lerp(d01.1, d11.1, fx), fy),
+        )
+    }
+
+    // The point of the unwarped image that a pixel of a width x height
+    // liquified image samples from.
+    pub fn source_point(&self, width: f64, height: f64, x: f64, y: f64) -> (f64, f64) {
+        let long_edge = width.max(height);
+        let (dx, dy) = self.displacement_at(x / width, y / height);
+        (x + dx * long_edge, y + dy * long_edge)
+    }
+
+    // Subdivides every cell so node spacing is at most `spacing` pixels of a
+    // long edge of `long_edge`. Subdividing by whole factors keeps the warp
+    // exactly as it was, since the old bilinear surface is sampled at nodes
+    // that lie on the old grid lines.
+    fn refined(self, long_edge: f64, spacing: f64) -> Self {
+        let cells = self.columns.max(self.rows) - 1;
+        let mut factor = 1;
+        while cells * factor * 2 <= MAX_CELLS_LONG_EDGE
+            && long_edge / (cells * factor) as f64 > spacing
+        {
+            factor *= 2;
+        }
+        if factor == 1 {
+            return self;
+        }
+
+        let columns = (self.columns - 1) * factor + 1;
+        let rows = (self.rows - 1) * factor + 1;
+        let mut displacements = Vec::with_capacity(columns * rows * 2);
+        for row in 0..rows {
+            for column in 0..columns {
+                let (dx, dy) = self.displacement_at(
+                    column as f64 / (columns - 1) as f64,
+                    row as f64 / (rows - 1) as f64,
+                );
+                displacements.extend_from_slice(&[dx as f32, dy as f32]);
+            }
+        }
+        Self {
+            columns,
+            rows,
+            displacements,
+        }
+    }
+}
+
+pub fn is_liquify_active(adjustments: &Value) -> bool {
+    LiquifyMesh::from_adjustments(adjustments).is_some_and(|mesh| !mesh.is_identity())
+}
+
+// Resamples the stroke so dabs are spaced by a fraction of the brush radius.
+fn stroke_dabs(points: &[StrokePoint], radius: f64) -> Vec<(f64, f64)> {
+    let spacing = (radius * DAB_SPACING).max(1.0);
+    let mut dabs: Vec<(f64, f64)> = Vec::new();
+    for point in points {
+        let Some(&last) = dabs.last() else {
+            dabs.push((point.x, point.y));
+            continue;
+        };
+        let (dx, dy) = (point.x - last.0, point.y - last.1);
+        let steps = ((dx * dx + dy * dy).sqrt() / spacing).floor() as usize;
+        for step in 1..=steps {
+            let t = step as f64 * spacing / (dx * dx + dy * dy).sqrt();
+            dabs.push((last.0 + dx * t, last.1 + dy * t));
+        }
+    }
+    dabs
+}
+
+// Each dab moves where a node samples from, then composes that with the
+// existing warp so strokes accumulate instead of overwriting each other.
+pub fn apply_stroke(
+    mesh: Option<LiquifyMesh>,
+    stroke: &LiquifyStroke,
+    width: u32,
+    height: u32,
+) -> LiquifyMesh {
+    let mesh = mesh.unwrap_or_else(|| LiquifyMesh::new(width, height));
+
+    let (w, h) = (width.max(1) as f64, height.max(1) as f64);
+    let long_edge = w.max(h);
+    let radius = stroke.size / 2.0;
+    let strength = (stroke.strength / 100.0).clamp(0.0, 1.0);
+    if radius < 1.0 || strength <= 0.0 {
+        return mesh;
+    }
+
+    let mut mesh = mesh.refined(long_edge, radius / NODES_PER_RADIUS);
+    let (step_x, step_y) = (w / (mesh.columns - 1) as f64, h / (mesh.rows - 1) as f64);
+    let node_range = |center: f64, step: f64, count: usize| {
+        let first = ((center - radius) / step).ceil().max(0.0) as usize;
+        let last = (((center + radius) / step).floor().max(0.0) as usize).min(count - 1);
+        first..=last
+    };
+
+    let dabs = stroke_dabs(&stroke.points, radius);
+    let mut previous = dabs.first().copied();
+    let mut updates = Vec::new();
+    for &center in &dabs {
+        let delta = previous.map_or((0.0, 0.0), |p| (center.0 - p.0, center.1 - p.1));
+        previous = Some(center);
+        if stroke.tool == LiquifyTool::Forward && delta == (0.0, 0.0) {
+            continue;
+        }
+
+        // All nodes of a dab are computed from the mesh as it was before the
+        // dab, then written back together.
+        updates.clear();
+        for row in node_range(center.1, step_y, mesh.rows) {
+            for column in node_range(center.0, step_x, mesh.columns) {
+                let p = (column as f64 * step_x, row as f64 * step_y);
+                let (ox, oy) = (p.0 - center.0, p.1 - center.1);
+                let distance = (ox * ox + oy * oy).sqrt();
+                if distance >= radius {
+                    continue;
+                }
+                let falloff = 1.0 - (distance / radius).powi(2);
+                let weight = falloff * falloff * strength;
+
+                let (dx, dy) = match stroke.tool {
+                    LiquifyTool::Reconstruct => {
+                        let (dx, dy) = mesh.node(column, row);
+                        let keep = 1.0 - weight * RECONSTRUCT_RATE;
+                        (dx * keep, dy * keep)
+                    }
+                    tool => {
+                        let q = match tool {
+                            LiquifyTool::Forward => {
+                                (p.0 - weight * delta.0, p.1 - weight * delta.1)
+                            }
+                            LiquifyTool::Bloat => {
+                                let factor = 1.0 - weight * BLOAT_RATE;
+                                (center.0 + ox * factor, center.1 + oy * factor)
+                            }
+                            _ => {
+                                let factor = 1.0 + weight * BLOAT_RATE;
+                                (center.0 + ox * factor, center.1 + oy * factor)
+                            }
+                        };
+                        let (dx, dy) = mesh.displacement_at(q.0 / w, q.1 / h);
+                        (dx + (q.0 - p.0) / long_edge, dy + (q.1 - p.1) / long_edge)
+                    }
+                };
+                updates.push(((row * mesh.columns + column) * 2, dx as f32, dy as f32));
+            }
+        }
+        for &(index, dx, dy) in &updates {
+            mesh.displacements[index] = dx;
+            mesh.displacements[index + 1] = dy;
+        }
+    }
+
+    for value in mesh.displacements.iter_mut() {
+        *value = (*value * DISPLACEMENT_PRECISION).round() / DISPLACEMENT_PRECISION;
+    }
+    mesh
+}
+
+pub fn apply_liquify(image: DynamicImage, adjustments: &Value) -> DynamicImage {
+    let Some(mesh) = LiquifyMesh::from_adjustments(adjustments).filter(|m| !m.is_identity()) else {
+        return image;
+    };
+
+    let (width, height) = (image.width(), image.height());
+    let (w, h) = (width as f64, height as f64);
+    let source = image.to_rgba32f();
+    let mut output = Rgba32FImage::new(width, height);
+    output
+        .par_chunks_mut(width as usize * 4)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for x in 0..width as usize {
+                let (sx, sy) = mesh.source_point(w, h, x as f64, y as f64);
+                let pixel = perspective::sample_bilinear(&source, sx, sy);
+                row[x * 4..x * 4 + 4].copy_from_slice(&pixel.0);
+            }
+        });
+
+    DynamicImage::ImageRgba32F(output)
+}
@@ -5,23 +5,36 @@ use mimalloc::MiMalloc;
 static GLOBAL: MiMalloc = MiMalloc;
 
 mod ai_processing;
+mod color_management;
 mod comfyui_connector;
+mod crop_constraint;
 mod culling;
+mod dcp_processing;
 mod denoising;
 mod file_management;
+mod focus_stacking;
 mod formats;
 mod gpu_processing;
+mod hdr_export;
+mod hdr_merge;
 mod image_loader;
 mod image_processing;
 mod inpainting;
+mod lens_correction;
+mod liquify;
 mod lut_processing;
 mod mask_generation;
 mod panorama_stitching;
 mod panorama_utils;
+mod perspective;
 mod preset_converter;
+mod projection;
 mod raw_processing;
+mod raw_utils;
+mod soft_proofing;
 mod tagging;
 mod tagging_utils;
+mod upright;
 
 use log;
 use std::collections::{HashMap, hash_map::DefaultHasher};
@@ -38,10 +51,13 @@ use std::sync::Mutex;
 
 use base64::{Engine as _, engine::general_purpose};
 use chrono::{DateTime, Utc};
+use image::codecs::avif::AvifEncoder;
 use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::codecs::tiff::TiffEncoder;
 use image::{
-    DynamicImage, GenericImageView, GrayImage, ImageBuffer, ImageFormat, Luma, Rgb, RgbImage, Rgba,
-    RgbaImage, imageops,
+    DynamicImage, GenericImageView, GrayImage, ImageBuffer, ImageEncoder, ImageFormat, Luma, Rgb,
+    Rgb32FImage, RgbImage, Rgba, Rgba32FImage, RgbaImage, imageops,
 };
 use little_exif::exif_tag::ExifTag;
 use little_exif::filetype::FileExtension;
@@ -61,21 +77,25 @@ use crate::ai_processing::{
     generate_image_embeddings, get_or_init_ai_models, run_sam_decoder, run_sky_seg_model,
     run_u2netp_model,
 };
+use crate::color_management::{OutputColorSpace, RenderingIntent};
 use crate::file_management::{
     AppSettings, load_settings, parse_virtual_path,
     read_file_mapped,
 };
 use crate::formats::is_raw_file;
+use crate::hdr_export::HdrExportSettings;
 use crate::image_loader::{
-    composite_patches_on_image, load_and_composite, load_base_image_from_bytes,
+    composite_patches_on_image, is_linear_source, load_and_composite, load_base_image_from_bytes,
 };
 use crate::image_processing::{
     Crop, GpuContext, ImageMetadata, apply_coarse_rotation, apply_crop, apply_flip, apply_rotation,
     get_all_adjustments_from_json, get_or_init_gpu_context, process_and_get_dynamic_image,
-    downscale_f32_image, apply_cpu_default_raw_processing,
+    process_and_get_float_image, downscale_f32_image, apply_cpu_default_raw_processing,
 };
 use crate::lut_processing::Lut;
 use crate::mask_generation::{AiPatchDefinition, MaskDefinition, generate_mask_bitmap};
+use crate::raw_processing::{RawWhiteBalance, calculate_raw_develop_hash};
+use crate::soft_proofing::{SoftProof, SoftProofSettings};
 use tagging_utils::{candidates, hierarchy};
 
 #[derive(Clone)]
@@ -83,6 +103,7 @@ pub struct LoadedImage {
     path: String,
     image: DynamicImage,
     is_raw: bool,
+    raw_develop_hash: u64,
 }
 
 #[derive(Clone)]
@@ -110,11 +131,16 @@ pub struct AppState {
     ai_init_lock: TokioMutex<()>,
     export_task_handle: Mutex<Option<JoinHandle<()>>>,
     panorama_result: Arc<Mutex<Option<DynamicImage>>>,
+    hdr_result: Arc<Mutex<Option<DynamicImage>>>,
+    hdr_cancellation_token: Arc<AtomicBool>,
+    focus_stack_result: Arc<Mutex<Option<DynamicImage>>>,
+    focus_stack_cancellation_token: Arc<AtomicBool>,
     denoise_result: Arc<Mutex<Option<DynamicImage>>>,
     indexing_task_handle: Mutex<Option<JoinHandle<()>>>,
     pub lut_cache: Mutex<HashMap<String, Arc<Lut>>>,
     initial_file_path: Mutex<Option<String>>,
     thumbnail_cancellation_token: Arc<AtomicBool>,
+    soft_proof: Mutex<Option<Arc<SoftProof>>>,
 }
 
 #[derive(serde::Serialize)]
@@ -124,6 +150,9 @@ struct LoadImageResult {
     metadata: ImageMetadata,
     exif: HashMap<String, String>,
     is_raw: bool,
+    as_shot_white_balance: Option<RawWhiteBalance>,
+    detected_lens: Option<lens_correction::LensSelection>,
+    lens_optics: Option<projection::LensOptics>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -152,6 +181,24 @@ struct ExportSettings {
     strip_gps: bool,
     filename_template: Option<String>,
     watermark: Option<WatermarkSettings>,
+    #[serde(default)]
+    color_space: OutputColorSpace,
+    #[serde(default)]
+    rendering_intent: RenderingIntent,
+    #[serde(default)]
+    hdr: Option<HdrExportSettings>,
+}
+
+impl ExportSettings {
+    // HDR exports are built from the sRGB render; the AVIF path moves it to
+    // Rec.2020 itself and Ultra HDR keeps an sRGB base image.
+    fn output_color_space(&self) -> OutputColorSpace {
+        if self.hdr.is_some() {
+            OutputColorSpace::Srgb
+        } else {
+            self.color_space
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -166,6 +213,16 @@ struct LutParseResult {
     size: u32,
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DcpParseResult {
+    name: String,
+    camera_model: String,
+    dual_illuminant: bool,
+    has_look_table: bool,
+    has_tone_curve: bool,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum WatermarkAnchor {
@@ -199,6 +256,7 @@ struct ImageDimensions {
 fn apply_all_transformations(
     image: &DynamicImage,
     adjustments: &serde_json::Value,
+    is_raw: bool,
 ) -> (DynamicImage, (f32, f32)) {
     let start_time = std::time::Instant::now();
 
@@ -207,13 +265,24 @@ fn apply_all_transformations(
     let flip_horizontal = adjustments["flipHorizontal"].as_bool().unwrap_or(false);
     let flip_vertical = adjustments["flipVertical"].as_bool().unwrap_or(false);
 
-    let coarse_rotated_image = apply_coarse_rotation(image.clone(), orientation_steps);
+    let lens_corrected_image =
+        lens_correction::apply_lens_correction(image.clone(), adjustments, is_raw);
+    let coarse_rotated_image = apply_coarse_rotation(lens_corrected_image, orientation_steps);
     let flipped_image = apply_flip(coarse_rotated_image, flip_horizontal, flip_vertical);
-    let rotated_image = apply_rotation(&flipped_image, rotation_degrees);
+    let projected_image = projection::apply_projection(flipped_image, adjustments);
+    let rotated_image = apply_rotation(&projected_image, rotation_degrees);
+    let perspective_image = perspective::apply_perspective(rotated_image, adjustments);
+    let liquified_image = liquify::apply_liquify(perspective_image, adjustments);
 
     let crop_data: Option<Crop> = serde_json::from_value(adjustments["crop"].clone()).ok();
-    let crop_json = serde_json::to_value(crop_data.clone()).unwrap_or(serde_json::Value::Null);
-    let cropped_image = apply_crop(rotated_image, &crop_json);
+    let crop_data = crop_constraint::constrain_crop(
+        liquified_image.width(),
+        liquified_image.height(),
+        adjustments,
+        crop_data,
+    );
+    let crop_json = serde_json::to_value(crop_data).unwrap_or(serde_json::Value::Null);
+    let cropped_image = apply_crop(liquified_image, &crop_json);
 
     let unscaled_crop_offset = crop_data.map_or((0.0, 0.0), |c| (c.x as f32, c.y as f32));
 
@@ -222,9 +291,61 @@ fn apply_all_transformations(
     (cropped_image, unscaled_crop_offset)
 }
 
+// Maps a point of the transformed, uncropped image back into the developed image by undoing
+// the stages of apply_all_transformations in reverse order.
+fn transformed_to_source_point(
+    adjustments: &serde_json::Value,
+    width: u32,
+    height: u32,
+    point: (f64, f64),
+) -> (f64, f64) {
+    let orientation_steps = adjustments["orientationSteps"].as_u64().unwrap_or(0) as u8;
+    let rotation_degrees = adjustments["rotation"].as_f64().unwrap_or(0.0);
+    let flip_horizontal = adjustments["flipHorizontal"].as_bool().unwrap_or(false);
+    let flip_vertical = adjustments["flipVertical"].as_bool().unwrap_or(false);
+
+    let (w, h) = (width as f64, height as f64);
+    let (rotated_w, rotated_h) = if orientation_steps % 2 == 1 {
+        (h, w)
+    } else {
+        (w, h)
+    };
+
+    let point = liquify::LiquifyMesh::from_adjustments(adjustments).map_or(point, |mesh| {
+        mesh.source_point(rotated_w, rotated_h, point.0, point.1)
+    });
+
+    let perspective = perspective::PerspectiveParams::from_adjustments(adjustments);
+    let (x, y) = perspective::unwarp_point(&perspective, rotated_w as u32, rotated_h as u32, point);
+
+    let (sin_a, cos_a) = rotation_degrees.to_radians().sin_cos();
+    let (cx, cy) = (rotated_w / 2.0, rotated_h / 2.0);
+    let (px, py) = (x - cx, y - cy);
+    let (x, y) = (px * cos_a + py * sin_a + cx, -px * sin_a + py * cos_a + cy);
+
+    let (x, y) = projection::ProjectionParams::from_adjustments(adjustments)
+        .mapper(rotated_w as u32, rotated_h as u32)
+        .and_then(|mapper| mapper.source_point(x, y))
+        .unwrap_or((x, y));
+
+    let x = if flip_horizontal { rotated_w - x } else { x };
+    let y = if flip_vertical { rotated_h - y } else { y };
+
+    let (x, y) = match orientation_steps {
+        1 => (y, h - x),
+        2 => (w - x, h - y),
+        3 => (w - y, x),
+        _ => (x, y),
+    };
+
+    lens_correction::source_point(adjustments, width, height, (x, y))
+}
+
 fn calculate_transform_hash(adjustments: &serde_json::Value) -> u64 {
     let mut hasher = DefaultHasher::new();
 
+    calculate_raw_develop_hash(adjustments).hash(&mut hasher);
+
     let orientation_steps = adjustments["orientationSteps"].as_u64().unwrap_or(0);
     orientation_steps.hash(&mut hasher);
 
@@ -237,12 +358,58 @@ fn calculate_transform_hash(adjustments: &serde_json::Value) -> u64 {
     let flip_v = adjustments["flipVertical"].as_bool().unwrap_or(false);
     flip_v.hash(&mut hasher);
 
+    let perspective = perspective::PerspectiveParams::from_adjustments(adjustments);
+    if !perspective.is_identity() {
+        for value in [
+            perspective.perspective_vertical,
+            perspective.perspective_horizontal,
+            perspective.perspective_rotate,
+            perspective.perspective_aspect,
+            perspective.perspective_scale,
+            perspective.perspective_x_offset,
+            perspective.perspective_y_offset,
+        ] {
+            value.to_bits().hash(&mut hasher);
+        }
+    }
+
     if let Some(crop_val) = adjustments.get("crop") {
         if !crop_val.is_null() {
             crop_val.to_string().hash(&mut hasher);
         }
     }
 
+    crop_constraint::is_constrain_crop_enabled(adjustments).hash(&mut hasher);
+
+    let projection = projection::ProjectionParams::from_adjustments(adjustments);
+    if !projection.is_identity() {
+        projection.projection_source.hash(&mut hasher);
+        projection.projection_target.hash(&mut hasher);
+        for value in [
+            projection.projection_focal_length,
+            projection.projection_crop_factor,
+            projection.projection_panini_distance,
+            projection.projection_scale,
+        ] {
+            value.to_bits().hash(&mut hasher);
+        }
+    }
+
+    if liquify::is_liquify_active(adjustments) {
+        adjustments["liquify"].to_string().hash(&mut hasher);
+    }
+
+    if lens_correction::has_lens_correction(adjustments) {
+        adjustments["lensProfile"].to_string().hash(&mut hasher);
+        for key in [
+            "lensDistortionAmount",
+            "lensVignettingAmount",
+            "lensTcaAmount",
+        ] {
+            adjustments[key].as_f64().unwrap_or(100.0).to_bits().hash(&mut hasher);
+        }
+    }
+
     if let Some(patches_val) = adjustments.get("aiPatches") {
         if let Some(patches_arr) = patches_val.as_array() {
             patches_arr.len().hash(&mut hasher);
@@ -313,7 +480,7 @@ fn generate_transformed_preview(
         .map_err(|e| format!("Failed to composite AI patches: {}", e))?;
 
     let (transformed_full_res, unscaled_crop_offset) =
-        apply_all_transformations(&patched_original_image, adjustments);
+        apply_all_transformations(&patched_original_image, adjustments, loaded_image.is_raw);
 
     let settings = load_settings(app_handle.clone()).unwrap_or_default();
     let final_preview_dim = settings.editor_preview_resolution.unwrap_or(1920);
@@ -394,15 +561,36 @@ async fn load_image(
     let highlight_compression = settings.raw_highlight_compression.unwrap_or(2.5);
 
     let path_clone = source_path_str.clone();
-    let (pristine_img, exif_data) = tokio::task::spawn_blocking(move || {
-        let result: Result<(DynamicImage, HashMap<String, String>), String> = (|| {
+    let adjustments_clone = metadata.adjustments.clone();
+    let use_embedded_preview = is_raw_file(&source_path_str)
+        && !settings.force_full_raw_development.unwrap_or(false);
+    let preview_dim = settings.editor_preview_resolution.unwrap_or(1920);
+    let app_handle_clone = app_handle.clone();
+    let (pristine_img, exif_data, white_balance) = tokio::task::spawn_blocking(move || {
+        let result: Result<
+            (
+                DynamicImage,
+                HashMap<String, String>,
+                Option<RawWhiteBalance>,
+            ),
+            String,
+        > = (|| {
             match read_file_mapped(Path::new(&path_clone)) {
                 Ok(mmap) => {
-                    let img =
-                        load_base_image_from_bytes(&mmap, &path_clone, false, highlight_compression)
-                            .map_err(|e| e.to_string())?;
+                    if use_embedded_preview {
+                        emit_embedded_preview(&mmap, &app_handle_clone, preview_dim);
+                    }
+                    let img = load_base_image_from_bytes(
+                        &mmap,
+                        &path_clone,
+                        false,
+                        highlight_compression,
+                        &adjustments_clone,
+                    )
+                    .map_err(|e| e.to_string())?;
                     let exif = read_exif_data(&mmap);
-                    Ok((img, exif))
+                    let white_balance = read_white_balance(&mmap, &path_clone, &adjustments_clone);
+                    Ok((img, exif, white_balance))
                 }
                 Err(e) => {
                     log::warn!(
@@ -413,15 +601,21 @@ async fn load_image(
                     let bytes = fs::read(&path_clone).map_err(|io_err| {
                         format!("Fallback read failed for {}: {}", path_clone, io_err)
                     })?;
+                    if use_embedded_preview {
+                        emit_embedded_preview(&bytes, &app_handle_clone, preview_dim);
+                    }
                     let img = load_base_image_from_bytes(
                         &bytes,
                         &path_clone,
                         false,
                         highlight_compression,
+                        &adjustments_clone,
                     )
                     .map_err(|e| e.to_string())?;
                     let exif = read_exif_data(&bytes);
-                    Ok((img, exif))
+                    let white_balance =
+                        read_white_balance(&bytes, &path_clone, &adjustments_clone);
+                    Ok((img, exif, white_balance))
                 }
             }
         })();
@@ -431,7 +625,7 @@ async fn load_image(
     .map_err(|e| e.to_string())??;
 
     let (orig_width, orig_height) = pristine_img.dimensions();
-    let is_raw = is_raw_file(&source_path_str);
+    let is_raw = is_linear_source(&source_path_str);
 
     *state.cached_preview.lock().unwrap() = None;
     *state.gpu_image_cache.lock().unwrap() = None;
@@ -439,17 +633,300 @@ async fn load_image(
         path: source_path_str.clone(),
         image: pristine_img,
         is_raw,
+        raw_develop_hash: calculate_raw_develop_hash(&metadata.adjustments),
     });
 
     Ok(LoadImageResult {
         width: orig_width,
         height: orig_height,
         metadata,
+        detected_lens: lens_correction::detect_lens(&exif_data),
+        lens_optics: projection::detect_optics(&exif_data),
         exif: exif_data,
         is_raw,
+        as_shot_white_balance: white_balance,
     })
 }
 
+fn emit_embedded_preview(bytes: &[u8], app_handle: &tauri::AppHandle, max_dim: u32) {
+    let Ok(preview) = raw_processing::extract_embedded_preview(bytes, max_dim) else {
+        return;
+    };
+    let preview = if preview.width() > max_dim || preview.height() > max_dim {
+        preview.thumbnail(max_dim, max_dim)
+    } else {
+        preview
+    };
+    let preview = apply_soft_proof(&app_handle.state::<AppState>(), preview);
+
+    let mut buf = Cursor::new(Vec::new());
+    if preview
+        .to_rgb8()
+        .write_with_encoder(JpegEncoder::new_with_quality(&mut buf, 85))
+        .is_ok()
+    {
+        let _ = app_handle.emit("preview-update-embedded", buf.get_ref());
+    }
+}
+
+fn read_white_balance(bytes: &[u8], path: &str, adjustments: &Value) -> Option<RawWhiteBalance> {
+    if !is_raw_file(path) {
+        return None;
+    }
+    match raw_processing::read_raw_white_balance(bytes, adjustments) {
+        Ok(white_balance) => Some(white_balance),
+        Err(e) => {
+            log::warn!("Could not read as-shot white balance for {}: {}", path, e);
+            None
+        }
+    }
+}
+
+#[tauri::command]
+async fn build_defect_map(paths: Vec<String>) -> Result<usize, String> {
+    tokio::task::spawn_blocking(move || raw_utils::defects::build_defect_map(&paths))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn list_lens_profiles() -> Result<Vec<lens_correction::LensSummary>, String> {
+    tokio::task::spawn_blocking(lens_correction::list_lenses)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Size of the canvas the crop is expressed in, i.e. the loaded image after coarse rotation.
+fn crop_canvas_dimensions(
+    js_adjustments: &Value,
+    state: &tauri::State<AppState>,
+) -> Result<(u32, u32), String> {
+    let (width, height) = state
+        .original_image
+        .lock()
+        .unwrap()
+        .as_ref()
+        .ok_or("No image loaded")?
+        .image
+        .dimensions();
+    let orientation_steps = js_adjustments["orientationSteps"].as_u64().unwrap_or(0);
+    Ok(if orientation_steps % 2 == 1 {
+        (height, width)
+    } else {
+        (width, height)
+    })
+}
+
+#[tauri::command]
+fn calculate_max_crop(
+    js_adjustments: Value,
+    aspect_ratio: Option<f64>,
+    state: tauri::State<AppState>,
+) -> Result<Crop, String> {
+    let (width, height) = crop_canvas_dimensions(&js_adjustments, &state)?;
+
+    let aspect = aspect_ratio
+        .filter(|a| *a > 0.0)
+        .unwrap_or_else(|| crop_constraint::crop_aspect(width, height, &js_adjustments, None));
+    let region = crop_constraint::valid_region(width, height, &js_adjustments);
+    crop_constraint::largest_crop(&region, aspect, None)
+        .ok_or_else(|| "The transformed image is too small to crop.".to_string())
+}
+
+// The crop the renderer will actually use, so the UI can store it instead of
+// having the constraint silently applied at render time.
+#[tauri::command]
+fn constrain_crop(
+    js_adjustments: Value,
+    state: tauri::State<AppState>,
+) -> Result<Option<Crop>, String> {
+    let (width, height) = crop_canvas_dimensions(&js_adjustments, &state)?;
+    let crop: Option<Crop> = serde_json::from_value(js_adjustments["crop"].clone()).ok();
+    Ok(crop_constraint::constrain_crop(width, height, &js_adjustments, crop))
+}
+
+#[tauri::command]
+fn apply_liquify_stroke(
+    js_adjustments: Value,
+    stroke: liquify::LiquifyStroke,
+    state: tauri::State<AppState>,
+) -> Result<liquify::LiquifyMesh, String> {
+    let (width, height) = state
+        .original_image
+        .lock()
+        .unwrap()
+        .as_ref()
+        .ok_or("No image loaded")?
+        .image
+        .dimensions();
+    let orientation_steps = js_adjustments["orientationSteps"].as_u64().unwrap_or(0);
+    let (width, height) = if orientation_steps % 2 == 1 {
+        (height, width)
+    } else {
+        (width, height)
+    };
+
+    let mesh = liquify::LiquifyMesh::from_adjustments(&js_adjustments);
+    Ok(liquify::apply_stroke(mesh, &stroke, width, height))
+}
+
+#[tauri::command]
+async fn calculate_upright(
+    js_adjustments: Value,
+    mode: upright::UprightMode,
+    guides: Option<Vec<upright::UprightGuide>>,
+    state: tauri::State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<Value, String> {
+    const UPRIGHT_PREVIEW_DIM: u32 = 1024;
+
+    let loaded_image = get_developed_image(&state, &js_adjustments, &app_handle)?;
+    let is_raw = loaded_image.is_raw;
+
+    tokio::task::spawn_blocking(move || {
+        let orientation_steps = js_adjustments["orientationSteps"].as_u64().unwrap_or(0) as u8;
+        let (full_w, full_h) = loaded_image.image.dimensions();
+        let full_size = if orientation_steps % 2 == 1 {
+            (full_h, full_w)
+        } else {
+            (full_w, full_h)
+        };
+
+        let preview = downscale_f32_image(
+            &loaded_image.image,
+            UPRIGHT_PREVIEW_DIM,
+            UPRIGHT_PREVIEW_DIM,
+        );
+        let preview = lens_correction::apply_lens_correction(preview, &js_adjustments, is_raw);
+        let preview = apply_coarse_rotation(preview, orientation_steps);
+        let preview = apply_flip(
+            preview,
+            js_adjustments["flipHorizontal"].as_bool().unwrap_or(false),
+            js_adjustments["flipVertical"].as_bool().unwrap_or(false),
+        );
+        let preview = projection::apply_projection(preview, &js_adjustments);
+        let preview = apply_rotation(
+            &preview,
+            js_adjustments["rotation"].as_f64().unwrap_or(0.0) as f32,
+        );
+
+        let current = perspective::PerspectiveParams::from_adjustments(&js_adjustments);
+        let result = upright::calculate_upright(
+            &preview,
+            is_raw,
+            current,
+            mode,
+            &guides.unwrap_or_default(),
+            full_size,
+        )?;
+
+        Ok(serde_json::json!({
+            "perspectiveVertical": result.perspective_vertical,
+            "perspectiveHorizontal": result.perspective_horizontal,
+            "perspectiveRotate": result.perspective_rotate,
+        }))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn calculate_auto_straighten(
+    js_adjustments: Value,
+    state: tauri::State<'_, AppState>,
+) -> Result<upright::StraightenEstimate, String> {
+    let loaded_image = state
+        .original_image
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or("No image loaded for auto straighten")?;
+    let sky_models = state
+        .ai_state
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|ai_state| ai_state.models.clone());
+
+    tokio::task::spawn_blocking(move || {
+        let preview =
+            upright::straighten_preview(&loaded_image.image, &js_adjustments, loaded_image.is_raw);
+        let sky_mask = sky_models.and_then(|models| {
+            run_sky_seg_model(&preview, &models.sky_seg)
+                .map_err(|e| log::warn!("Sky segmentation for auto straighten failed: {}", e))
+                .ok()
+        });
+        upright::estimate_straighten(&preview, loaded_image.is_raw, sky_mask.as_ref())
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+// The sample point is in pixels of the transformed image before cropping, like mask and
+// liquify coordinates.
+#[tauri::command]
+fn sample_white_balance(
+    x: f64,
+    y: f64,
+    radius: Option<u32>,
+    js_adjustments: Value,
+    state: tauri::State<AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<Value, String> {
+    let loaded_image = get_developed_image(&state, &js_adjustments, &app_handle)?;
+
+    let image = loaded_image.image.to_rgb32f();
+    let (width, height) = image.dimensions();
+    let (source_x, source_y) = transformed_to_source_point(&js_adjustments, width, height, (x, y));
+    if source_x < 0.0 || source_y < 0.0 || source_x >= width as f64 || source_y >= height as f64 {
+        return Err("Sample point is outside of the image".to_string());
+    }
+    let (x, y) = (source_x as u32, source_y as u32);
+
+    let radius = radius.unwrap_or(5);
+    let mut sum = [0.0f64; 3];
+    let mut count = 0u32;
+    for sy in y.saturating_sub(radius)..=(y + radius).min(height - 1) {
+        for sx in x.saturating_sub(radius)..=(x + radius).min(width - 1) {
+            let p = image.get_pixel(sx, sy);
+            for c in 0..3 {
+                sum[c] += p[c] as f64;
+            }
+            count += 1;
+        }
+    }
+    let mean: [f32; 3] = std::array::from_fn(|c| (sum[c] / count as f64) as f32);
+    if mean.iter().any(|&c| c <= 1e-6) {
+        return Err("Sampled region is too dark to use as a neutral reference".to_string());
+    }
+
+    if loaded_image.is_raw {
+        let bytes = fs::read(&loaded_image.path).map_err(|e| e.to_string())?;
+        let white_balance = raw_processing::read_raw_white_balance(&bytes, &js_adjustments)
+            .map_err(|e| e.to_string())?;
+        let camera =
+            white_balance.srgb_to_camera * nalgebra::Vector3::new(mean[0], mean[1], mean[2]);
+        let (kelvin, tint) = white_balance
+            .kelvin_tint_for_neutral([camera.x, camera.y, camera.z])
+            .ok_or("Could not derive a white balance from the sampled region")?;
+        return Ok(serde_json::json!({
+            "whiteBalanceKelvin": kelvin.round(),
+            "whiteBalanceTint": tint.round(),
+            "temperature": 0.0,
+            "tint": 0.0,
+        }));
+    }
+
+    // The shader white balances after decoding non-RAW images to linear.
+    let (temperature, tint) =
+        image_processing::white_balance_for_neutral(mean.map(color_management::srgb_to_linear));
+    Ok(serde_json::json!({
+        "temperature": temperature,
+        "tint": tint,
+    }))
+}
+
 #[tauri::command]
 fn get_image_dimensions(path: String) -> Result<ImageDimensions, String> {
     let (source_path, _) = parse_virtual_path(&path);
@@ -466,23 +943,43 @@ fn cancel_thumbnail_generation(state: tauri::State<AppState>) -> Result<(), Stri
     Ok(())
 }
 
-fn apply_watermark(
-    base_image: &mut DynamicImage,
+struct PlacedWatermark {
+    image: RgbaImage,
+    x: i64,
+    y: i64,
+}
+
+impl PlacedWatermark {
+    // Visits every watermark pixel that lands inside a base image of the given
+    // size, with its base coordinates and opacity-scaled alpha.
+    fn for_each_covered(&self, base_w: u32, base_h: u32, mut f: impl FnMut(u32, u32, &Rgba<u8>)) {
+        for (wm_x, wm_y, pixel) in self.image.enumerate_pixels() {
+            let x = self.x + wm_x as i64;
+            let y = self.y + wm_y as i64;
+            if x >= 0 && y >= 0 && x < base_w as i64 && y < base_h as i64 {
+                f(x as u32, y as u32, pixel);
+            }
+        }
+    }
+}
+
+fn place_watermark(
     watermark_settings: &WatermarkSettings,
-) -> Result<(), String> {
+    base_w: u32,
+    base_h: u32,
+) -> Result<Option<PlacedWatermark>, String> {
     let watermark_img = image::open(&watermark_settings.path)
         .map_err(|e| format!("Failed to open watermark image: {}", e))?;
 
-    let (base_w, base_h) = base_image.dimensions();
     let base_min_dim = base_w.min(base_h) as f32;
 
-    let watermark_scale_factor = (base_min_dim * (watermark_settings.scale / 100.0))
-        / watermark_img.width().max(1) as f32;
+    let watermark_scale_factor =
+        (base_min_dim * (watermark_settings.scale / 100.0)) / watermark_img.width().max(1) as f32;
     let new_wm_w = (watermark_img.width() as f32 * watermark_scale_factor).round() as u32;
     let new_wm_h = (watermark_img.height() as f32 * watermark_scale_factor).round() as u32;
 
     if new_wm_w == 0 || new_wm_h == 0 {
-        return Ok(());
+        return Ok(None);
     }
 
     let scaled_watermark =
@@ -493,10 +990,9 @@ fn apply_watermark(
     for pixel in scaled_watermark_rgba.pixels_mut() {
         pixel[3] = (pixel[3] as f32 * opacity_factor) as u8;
     }
-    let final_watermark = DynamicImage::ImageRgba8(scaled_watermark_rgba);
 
     let spacing_pixels = (base_min_dim * (watermark_settings.spacing / 100.0)) as i64;
-    let (wm_w, wm_h) = final_watermark.dimensions();
+    let (wm_w, wm_h) = scaled_watermark_rgba.dimensions();
 
     let x = match watermark_settings.anchor {
         WatermarkAnchor::TopLeft | WatermarkAnchor::CenterLeft | WatermarkAnchor::BottomLeft => {
@@ -517,14 +1013,57 @@ fn apply_watermark(
         WatermarkAnchor::CenterLeft | WatermarkAnchor::Center | WatermarkAnchor::CenterRight => {
             (base_h as i64 - wm_h as i64) / 2
         }
-        WatermarkAnchor::BottomLeft | WatermarkAnchor::BottomCenter | WatermarkAnchor::BottomRight => {
-            base_h as i64 - wm_h as i64 - spacing_pixels
-        }
+        WatermarkAnchor::BottomLeft
+        | WatermarkAnchor::BottomCenter
+        | WatermarkAnchor::BottomRight => base_h as i64 - wm_h as i64 - spacing_pixels,
     };
 
-    image::imageops::overlay(base_image, &final_watermark, x, y);
+    Ok(Some(PlacedWatermark {
+        image: scaled_watermark_rgba,
+        x,
+        y,
+    }))
+}
 
-    Ok(())
+// Float renders are blended in float so nothing is clipped or quantized; set
+// `linear` when the image holds linear light rather than sRGB-encoded values.
+fn apply_watermark(base_image: &mut DynamicImage, watermark: &PlacedWatermark, linear: bool) {
+    let (base_w, base_h) = base_image.dimensions();
+    match base_image {
+        DynamicImage::ImageRgba32F(float_image) => {
+            watermark.for_each_covered(base_w, base_h, |x, y, wm_pixel| {
+                let alpha = wm_pixel[3] as f32 / 255.0;
+                let base_pixel = float_image.get_pixel_mut(x, y);
+                for c in 0..3 {
+                    let encoded = wm_pixel[c] as f32 / 255.0;
+                    let value = if linear {
+                        color_management::srgb_to_linear(encoded)
+                    } else {
+                        encoded
+                    };
+                    base_pixel[c] = value * alpha + base_pixel[c] * (1.0 - alpha);
+                }
+                base_pixel[3] = alpha + base_pixel[3] * (1.0 - alpha);
+            });
+        }
+        _ => image::imageops::overlay(
+            base_image,
+            &DynamicImage::ImageRgba8(watermark.image.clone()),
+            watermark.x,
+            watermark.y,
+        ),
+    }
+}
+
+// Pulls the gain toward 1.0 under the watermark so it shows at the same
+// brightness in the HDR rendition as in the SDR one.
+fn apply_watermark_to_gain_map(gain_map: &mut GrayImage, watermark: &PlacedWatermark) {
+    let (base_w, base_h) = gain_map.dimensions();
+    watermark.for_each_covered(base_w, base_h, |x, y, wm_pixel| {
+        let alpha = wm_pixel[3] as f32 / 255.0;
+        let gain = gain_map.get_pixel_mut(x, y);
+        gain[0] = (gain[0] as f32 * (1.0 - alpha)).round() as u8;
+    });
 }
 
 #[tauri::command]
@@ -535,12 +1074,7 @@ fn apply_adjustments(
 ) -> Result<(), String> {
     let context = get_or_init_gpu_context(&state)?;
     let adjustments_clone = js_adjustments.clone();
-    let loaded_image = state
-        .original_image
-        .lock()
-        .unwrap()
-        .clone()
-        .ok_or("No original image loaded")?;
+    let loaded_image = get_developed_image(&state, &js_adjustments, &app_handle)?;
     let new_transform_hash = calculate_transform_hash(&adjustments_clone);
 
     let mut cached_preview_lock = state.cached_preview.lock().unwrap();
@@ -634,6 +1168,8 @@ fn apply_adjustments(
                 let _ = app_handle.emit("waveform-update", waveform_data);
             }
 
+            let final_processed_image = apply_soft_proof(&state, final_processed_image);
+
             let mut buf = Cursor::new(Vec::new());
             if final_processed_image
                 .to_rgb8()
@@ -648,6 +1184,37 @@ fn apply_adjustments(
     Ok(())
 }
 
+// Simulates the active soft-proof target on an editor preview. The proof is
+// cloned out of the lock so concurrent previews don't wait on each other.
+fn apply_soft_proof(state: &AppState, image: DynamicImage) -> DynamicImage {
+    let soft_proof = state.soft_proof.lock().unwrap().clone();
+    match soft_proof {
+        Some(soft_proof) => soft_proof.apply(&image),
+        None => image,
+    }
+}
+
+#[tauri::command]
+fn set_soft_proof(
+    settings: Option<SoftProofSettings>,
+    state: tauri::State<AppState>,
+) -> Result<(), String> {
+    if let (Some(current), Some(new_settings)) = (&*state.soft_proof.lock().unwrap(), &settings) {
+        if current.settings == *new_settings {
+            return Ok(());
+        }
+    }
+
+    let soft_proof = match settings {
+        Some(settings) => Some(Arc::new(
+            SoftProof::new(settings).map_err(|e| e.to_string())?,
+        )),
+        None => None,
+    };
+    *state.soft_proof.lock().unwrap() = soft_proof;
+    Ok(())
+}
+
 #[tauri::command]
 fn generate_uncropped_preview(
     js_adjustments: serde_json::Value,
@@ -656,12 +1223,7 @@ fn generate_uncropped_preview(
 ) -> Result<(), String> {
     let context = get_or_init_gpu_context(&state)?;
     let adjustments_clone = js_adjustments.clone();
-    let loaded_image = state
-        .original_image
-        .lock()
-        .unwrap()
-        .clone()
-        .ok_or("No original image loaded")?;
+    let loaded_image = get_developed_image(&state, &js_adjustments, &app_handle)?;
 
     thread::spawn(move || {
         let state = app_handle.state::<AppState>();
@@ -678,26 +1240,51 @@ fn generate_uncropped_preview(
             };
 
         let orientation_steps = adjustments_clone["orientationSteps"].as_u64().unwrap_or(0) as u8;
-        let coarse_rotated_image = apply_coarse_rotation(patched_image, orientation_steps);
 
         let settings = load_settings(app_handle.clone()).unwrap_or_default();
         let preview_dim = settings.editor_preview_resolution.unwrap_or(1920);
 
-        let (rotated_w, rotated_h) = coarse_rotated_image.dimensions();
+        let (original_w, original_h) = patched_image.dimensions();
 
-        let (processing_base, scale_for_gpu) = if rotated_w > preview_dim || rotated_h > preview_dim
-        {
-            let base = downscale_f32_image(&coarse_rotated_image, preview_dim, preview_dim);
-            let scale = if rotated_w > 0 {
-                base.width() as f32 / rotated_w as f32
+        let (processing_base, scale_for_gpu) =
+            if original_w > preview_dim || original_h > preview_dim {
+                let base = downscale_f32_image(&patched_image, preview_dim, preview_dim);
+                let scale = if original_w > 0 {
+                    base.width() as f32 / original_w as f32
+                } else {
+                    1.0
+                };
+                (base, scale)
             } else {
-                1.0
+                (patched_image, 1.0)
             };
-            (base, scale)
+
+        // Same order as apply_all_transformations: lens profiles are defined in sensor
+        // orientation, so the correction runs before the coarse rotation.
+        let processing_base =
+            lens_correction::apply_lens_correction(processing_base, &adjustments_clone, is_raw);
+        let processing_base = apply_coarse_rotation(processing_base, orientation_steps);
+        let processing_base = projection::apply_projection(processing_base, &adjustments_clone);
+
+        // The crop view applies flip and fine rotation with CSS, which cannot be
+        // combined with a projective or liquify warp, so bake them in when either is set.
+        let processing_base = if perspective::is_perspective_active(&adjustments_clone)
+            || liquify::is_liquify_active(&adjustments_clone)
+        {
+            let flipped = apply_flip(
+                processing_base,
+                adjustments_clone["flipHorizontal"].as_bool().unwrap_or(false),
+                adjustments_clone["flipVertical"].as_bool().unwrap_or(false),
+            );
+            let rotated = apply_rotation(
+                &flipped,
+                adjustments_clone["rotation"].as_f64().unwrap_or(0.0) as f32,
+            );
+            let warped = perspective::apply_perspective(rotated, &adjustments_clone);
+            liquify::apply_liquify(warped, &adjustments_clone)
         } else {
-            (coarse_rotated_image.clone(), 1.0)
+            processing_base
         };
-
         let (preview_width, preview_height) = processing_base.dimensions();
 
         let mask_definitions: Vec<MaskDefinition> = js_adjustments
@@ -732,6 +1319,7 @@ fn generate_uncropped_preview(
             lut,
             "generate_uncropped_preview",
         ) {
+            let processed_image = apply_soft_proof(&state, processed_image);
             let mut buf = Cursor::new(Vec::new());
             if processed_image
                 .to_rgb8()
@@ -752,12 +1340,7 @@ fn generate_original_transformed_preview(
     state: tauri::State<AppState>,
     app_handle: tauri::AppHandle,
 ) -> Result<Response, String> {
-    let loaded_image = state
-        .original_image
-        .lock()
-        .unwrap()
-        .clone()
-        .ok_or("No original image loaded")?;
+    let loaded_image = get_developed_image(&state, &js_adjustments, &app_handle)?;
 
     let mut image_for_preview = loaded_image.image.clone();
     if loaded_image.is_raw {
@@ -765,7 +1348,7 @@ fn generate_original_transformed_preview(
     }
 
     let (transformed_full_res, _unscaled_crop_offset) =
-        apply_all_transformations(&image_for_preview, &js_adjustments);
+        apply_all_transformations(&image_for_preview, &js_adjustments, false);
 
     let settings = load_settings(app_handle).unwrap_or_default();
     let preview_dim = settings.editor_preview_resolution.unwrap_or(1920);
@@ -796,27 +1379,83 @@ fn get_full_image_for_processing(
     Ok((loaded_image.image.clone(), loaded_image.is_raw))
 }
 
+fn get_developed_image(
+    state: &tauri::State<AppState>,
+    adjustments: &Value,
+    app_handle: &tauri::AppHandle,
+) -> Result<LoadedImage, String> {
+    let loaded_image = state
+        .original_image
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or("No original image loaded")?;
+
+    let raw_develop_hash = calculate_raw_develop_hash(adjustments);
+    if !loaded_image.is_raw || loaded_image.raw_develop_hash == raw_develop_hash {
+        return Ok(loaded_image);
+    }
+
+    let settings = load_settings(app_handle.clone()).unwrap_or_default();
+    let highlight_compression = settings.raw_highlight_compression.unwrap_or(2.5);
+
+    let image = match read_file_mapped(Path::new(&loaded_image.path)) {
+        Ok(mmap) => load_base_image_from_bytes(
+            &mmap,
+            &loaded_image.path,
+            false,
+            highlight_compression,
+            adjustments,
+        ),
+        Err(e) => {
+            log::warn!(
+                "Failed to memory-map file '{}': {}. Falling back to standard read.",
+                loaded_image.path,
+                e
+            );
+            let bytes = fs::read(&loaded_image.path).map_err(|e| e.to_string())?;
+            load_base_image_from_bytes(
+                &bytes,
+                &loaded_image.path,
+                false,
+                highlight_compression,
+                adjustments,
+            )
+        }
+    }
+    .map_err(|e| e.to_string())?;
+
+    let redeveloped = LoadedImage {
+        path: loaded_image.path,
+        image,
+        is_raw: true,
+        raw_develop_hash,
+    };
+
+    *state.cached_preview.lock().unwrap() = None;
+    *state.gpu_image_cache.lock().unwrap() = None;
+    *state.original_image.lock().unwrap() = Some(redeveloped.clone());
+
+    Ok(redeveloped)
+}
+
 #[tauri::command]
 fn generate_fullscreen_preview(
     js_adjustments: serde_json::Value,
     state: tauri::State<AppState>,
+    app_handle: tauri::AppHandle,
 ) -> Result<Response, String> {
     let context = get_or_init_gpu_context(&state)?;
-    let (original_image, is_raw) = get_full_image_for_processing(&state)?;
-    let path = state
-        .original_image
-        .lock()
-        .unwrap()
-        .as_ref()
-        .ok_or("Original image path not found")?
-        .path
-        .clone();
+    let loaded_image = get_developed_image(&state, &js_adjustments, &app_handle)?;
+    let original_image = loaded_image.image;
+    let is_raw = loaded_image.is_raw;
+    let path = loaded_image.path;
     let unique_hash = calculate_full_job_hash(&path, &js_adjustments);
     let base_image = composite_patches_on_image(&original_image, &js_adjustments)
         .map_err(|e| format!("Failed to composite AI patches for fullscreen: {}", e))?;
 
     let (transformed_image, unscaled_crop_offset) =
-        apply_all_transformations(&base_image, &js_adjustments);
+        apply_all_transformations(&base_image, &js_adjustments, is_raw);
     let (img_w, img_h) = transformed_image.dimensions();
 
     let mask_definitions: Vec<MaskDefinition> = js_adjustments
@@ -843,6 +1482,7 @@ fn generate_fullscreen_preview(
         lut,
         "generate_fullscreen_preview",
     )?;
+    let final_image = apply_soft_proof(&state, final_image);
 
     let mut buf = Cursor::new(Vec::new());
     final_image
@@ -853,17 +1493,85 @@ fn generate_fullscreen_preview(
     Ok(Response::new(buf.into_inner()))
 }
 
+fn resize_for_export(image: DynamicImage, resize_opts: &ResizeOptions) -> DynamicImage {
+    let (current_w, current_h) = image.dimensions();
+    let should_resize = if resize_opts.dont_enlarge {
+        match resize_opts.mode {
+            ResizeMode::LongEdge => current_w.max(current_h) > resize_opts.value,
+            ResizeMode::ShortEdge => current_w.min(current_h) > resize_opts.value,
+            ResizeMode::Width => current_w > resize_opts.value,
+            ResizeMode::Height => current_h > resize_opts.value,
+        }
+    } else {
+        true
+    };
+
+    if !should_resize {
+        return image;
+    }
+
+    match resize_opts.mode {
+        ResizeMode::LongEdge => {
+            let (w, h) = if current_w > current_h {
+                (
+                    resize_opts.value,
+                    (resize_opts.value as f32 * (current_h as f32 / current_w as f32)).round()
+                        as u32,
+                )
+            } else {
+                (
+                    (resize_opts.value as f32 * (current_w as f32 / current_h as f32)).round()
+                        as u32,
+                    resize_opts.value,
+                )
+            };
+            image.resize(w, h, imageops::FilterType::Lanczos3)
+        }
+        ResizeMode::ShortEdge => {
+            let (w, h) = if current_w < current_h {
+                (
+                    resize_opts.value,
+                    (resize_opts.value as f32 * (current_h as f32 / current_w as f32)).round()
+                        as u32,
+                )
+            } else {
+                (
+                    (resize_opts.value as f32 * (current_w as f32 / current_h as f32)).round()
+                        as u32,
+                    resize_opts.value,
+                )
+            };
+            image.resize(w, h, imageops::FilterType::Lanczos3)
+        }
+        ResizeMode::Width => {
+            image.resize(resize_opts.value, u32::MAX, imageops::FilterType::Lanczos3)
+        }
+        ResizeMode::Height => {
+            image.resize(u32::MAX, resize_opts.value, imageops::FilterType::Lanczos3)
+        }
+    }
+}
+// What an export render hands to the encoder.
+enum ExportRender {
+    Standard(DynamicImage),
+    // SDR base plus the log2 gain map for an Ultra HDR JPEG.
+    UltraHdr(DynamicImage, GrayImage, HdrExportSettings),
+    // Linear HDR rendition relative to SDR white, for an HDR AVIF.
+    HdrAvif(Rgba32FImage, HdrExportSettings),
+}
+
 fn process_image_for_export(
     path: &str,
     base_image: &DynamicImage,
     js_adjustments: &Value,
     export_settings: &ExportSettings,
+    output_format: &str,
     context: &GpuContext,
     state: &tauri::State<AppState>,
     is_raw: bool,
-) -> Result<DynamicImage, String> {
+) -> Result<ExportRender, String> {
     let (transformed_image, unscaled_crop_offset) =
-        apply_all_transformations(&base_image, &js_adjustments);
+        apply_all_transformations(&base_image, &js_adjustments, is_raw);
     let (img_w, img_h) = transformed_image.dimensions();
 
     let mask_definitions: Vec<MaskDefinition> = js_adjustments
@@ -884,85 +1592,141 @@ fn process_image_for_export(
 
     let unique_hash = calculate_full_job_hash(path, js_adjustments);
 
-    let mut final_image = process_and_get_dynamic_image(
+    if export_settings.hdr.is_some() && export_settings.color_space != OutputColorSpace::Srgb {
+        log::warn!(
+            "HDR export ignores the {:?} output color space and writes sRGB-based output.",
+            export_settings.color_space
+        );
+    }
+
+    let place_export_watermark = |image: &DynamicImage| match &export_settings.watermark {
+        Some(watermark_settings) => {
+            let (width, height) = image.dimensions();
+            place_watermark(watermark_settings, width, height)
+        }
+        None => Ok(None),
+    };
+
+    // HDR AVIF is written straight from a float render of the HDR rendition so
+    // nothing above SDR white goes through 8 bits.
+    if let Some(hdr_settings) = &export_settings.hdr {
+        if output_format.eq_ignore_ascii_case("avif") {
+            let mut hdr_adjustments = all_adjustments;
+            hdr_adjustments.global.hdr_headroom = hdr_settings.headroom();
+            let mut hdr_image = process_and_get_float_image(
+                &context,
+                &state,
+                &transformed_image,
+                unique_hash,
+                hdr_adjustments,
+                &mask_bitmaps,
+                lut,
+                "process_image_for_export_hdr",
+            )?;
+
+            // The resampler clamps float samples to 0..1, so resize the
+            // rendition normalized to its headroom.
+            if let Some(resize_opts) = &export_settings.resize {
+                let headroom = hdr_settings.headroom();
+                let scale_rgb = |image: DynamicImage, factor: f32| {
+                    let mut image = image.into_rgba32f();
+                    for pixel in image.pixels_mut() {
+                        for c in 0..3 {
+                            pixel[c] *= factor;
+                        }
+                    }
+                    DynamicImage::ImageRgba32F(image)
+                };
+                hdr_image = scale_rgb(hdr_image, 1.0 / headroom);
+                hdr_image = scale_rgb(resize_for_export(hdr_image, resize_opts), headroom);
+            }
+            if let Some(watermark) = place_export_watermark(&hdr_image)? {
+                apply_watermark(&mut hdr_image, &watermark, true);
+            }
+            return Ok(ExportRender::HdrAvif(hdr_image.to_rgba32f(), *hdr_settings));
+        }
+    }
+
+    // Wide-gamut output is converted from an undithered float render so nothing
+    // is quantized before the output transfer curve is applied.
+    let color_space = export_settings.output_color_space();
+    let render = if color_space == OutputColorSpace::Srgb {
+        process_and_get_dynamic_image
+    } else {
+        process_and_get_float_image
+    };
+    let mut final_image = render(
         &context,
         &state,
         &transformed_image,
         unique_hash,
         all_adjustments,
         &mask_bitmaps,
-        lut,
+        lut.clone(),
         "process_image_for_export",
     )?;
 
+    let mut hdr_gain_map = match &export_settings.hdr {
+        Some(hdr_settings) => {
+            let mut hdr_adjustments = all_adjustments;
+            hdr_adjustments.global.hdr_headroom = hdr_settings.headroom();
+            Some(
+                process_and_get_dynamic_image(
+                    &context,
+                    &state,
+                    &transformed_image,
+                    unique_hash,
+                    hdr_adjustments,
+                    &mask_bitmaps,
+                    lut,
+                    "process_image_for_export_hdr",
+                )?
+                .to_luma8(),
+            )
+        }
+        None => None,
+    };
+
     if let Some(resize_opts) = &export_settings.resize {
-        let (current_w, current_h) = final_image.dimensions();
-        let should_resize = if resize_opts.dont_enlarge {
-            match resize_opts.mode {
-                ResizeMode::LongEdge => current_w.max(current_h) > resize_opts.value,
-                ResizeMode::ShortEdge => current_w.min(current_h) > resize_opts.value,
-                ResizeMode::Width => current_w > resize_opts.value,
-                ResizeMode::Height => current_h > resize_opts.value,
-            }
-        } else {
-            true
-        };
+        final_image = resize_for_export(final_image, resize_opts);
+        hdr_gain_map = hdr_gain_map.map(|gain_map| {
+            resize_for_export(DynamicImage::ImageLuma8(gain_map), resize_opts).to_luma8()
+        });
+    }
 
-        if should_resize {
-            final_image = match resize_opts.mode {
-                ResizeMode::LongEdge => {
-                    let (w, h) = if current_w > current_h {
-                        (
-                            resize_opts.value,
-                            (resize_opts.value as f32 * (current_h as f32 / current_w as f32))
-                                .round() as u32,
-                        )
-                    } else {
-                        (
-                            (resize_opts.value as f32 * (current_w as f32 / current_h as f32))
-                                .round() as u32,
-                            resize_opts.value,
-                        )
-                    };
-                    final_image.resize(w, h, imageops::FilterType::Lanczos3)
-                }
-                ResizeMode::ShortEdge => {
-                    let (w, h) = if current_w < current_h {
-                        (
-                            resize_opts.value,
-                            (resize_opts.value as f32 * (current_h as f32 / current_w as f32))
-                                .round() as u32,
-                        )
-                    } else {
-                        (
-                            (resize_opts.value as f32 * (current_w as f32 / current_h as f32))
-                                .round() as u32,
-                            resize_opts.value,
-                        )
-                    };
-                    final_image.resize(w, h, imageops::FilterType::Lanczos3)
-                }
-                ResizeMode::Width => {
-                    final_image.resize(resize_opts.value, u32::MAX, imageops::FilterType::Lanczos3)
-                }
-                ResizeMode::Height => {
-                    final_image.resize(u32::MAX, resize_opts.value, imageops::FilterType::Lanczos3)
-                }
-            };
+    if let Some(watermark) = place_export_watermark(&final_image)? {
+        apply_watermark(&mut final_image, &watermark, false);
+        if let Some(gain_map) = hdr_gain_map.as_mut() {
+            apply_watermark_to_gain_map(gain_map, &watermark);
         }
     }
 
-    if let Some(watermark_settings) = &export_settings.watermark {
-        apply_watermark(&mut final_image, watermark_settings)?;
-    }
+    let final_image = color_management::convert_to_output_space(
+        final_image,
+        color_space,
+        export_settings.rendering_intent,
+    );
+    Ok(match (hdr_gain_map, export_settings.hdr) {
+        (Some(gain_map), Some(hdr_settings)) => {
+            ExportRender::UltraHdr(final_image, gain_map, hdr_settings)
+        }
+        _ => ExportRender::Standard(final_image),
+    })
+}
 
-    Ok(final_image)
+fn attach_icc_profile<E: ImageEncoder>(encoder: &mut E, icc_profile: Option<&[u8]>) {
+    if let Some(profile) = icc_profile {
+        if let Err(e) = encoder.set_icc_profile(profile.to_vec()) {
+            log::warn!("Failed to embed ICC profile: {}", e);
+        }
+    }
 }
 
 fn encode_image_to_bytes(
     image: &DynamicImage,
     output_format: &str,
     jpeg_quality: u8,
+    icc_profile: Option<&[u8]>,
 ) -> Result<Vec<u8>, String> {
     let mut image_bytes = Vec::new();
     let mut cursor = Cursor::new(&mut image_bytes);
@@ -970,7 +1734,8 @@ fn encode_image_to_bytes(
     match output_format.to_lowercase().as_str() {
         "jpg" | "jpeg" => {
             let rgb_image = image.to_rgb8();
-            let encoder = JpegEncoder::new_with_quality(&mut cursor, jpeg_quality);
+            let mut encoder = JpegEncoder::new_with_quality(&mut cursor, jpeg_quality);
+            attach_icc_profile(&mut encoder, icc_profile);
             rgb_image
                 .write_with_encoder(encoder)
                 .map_err(|e| e.to_string())?;
@@ -982,13 +1747,24 @@ fn encode_image_to_bytes(
                 image.clone()
             };
 
+            let mut encoder = PngEncoder::new(&mut cursor);
+            attach_icc_profile(&mut encoder, icc_profile);
             image_to_encode
-                .write_to(&mut cursor, image::ImageFormat::Png)
+                .write_with_encoder(encoder)
                 .map_err(|e| e.to_string())?;
         }
         "tiff" => {
+            let mut encoder = TiffEncoder::new(&mut cursor);
+            attach_icc_profile(&mut encoder, icc_profile);
+            image
+                .write_with_encoder(encoder)
+                .map_err(|e| e.to_string())?;
+        }
+        "avif" => {
+            let encoder = AvifEncoder::new_with_speed_quality(&mut cursor, 6, jpeg_quality);
             image
-                .write_to(&mut cursor, image::ImageFormat::Tiff)
+                .to_rgba8()
+                .write_with_encoder(encoder)
                 .map_err(|e| e.to_string())?;
         }
         _ => return Err(format!("Unsupported file format: {}", output_format)),
@@ -996,6 +1772,73 @@ fn encode_image_to_bytes(
     Ok(image_bytes)
 }
 
+fn encode_export_image(
+    render: &ExportRender,
+    source_path_str: &str,
+    output_format: &str,
+    export_settings: &ExportSettings,
+) -> Result<Vec<u8>, String> {
+    let color_space = export_settings.output_color_space();
+    let icc_profile = color_management::icc_profile(color_space, export_settings.rendering_intent)
+        .map_err(|e| e.to_string())?;
+    let format = output_format.to_lowercase();
+
+    let final_image = match render {
+        ExportRender::HdrAvif(hdr_image, hdr_settings) => {
+            return hdr_export::encode_hdr_avif(
+                hdr_image,
+                hdr_settings,
+                export_settings.jpeg_quality,
+            )
+            .map_err(|e| e.to_string());
+        }
+        ExportRender::UltraHdr(final_image, gain_map, hdr_settings)
+            if format == "jpg" || format == "jpeg" =>
+        {
+            let mut primary_bytes = encode_image_to_bytes(
+                final_image,
+                &format,
+                export_settings.jpeg_quality,
+                Some(&icc_profile),
+            )?;
+            write_image_with_metadata(
+                &mut primary_bytes,
+                source_path_str,
+                &format,
+                export_settings.keep_metadata,
+                export_settings.strip_gps,
+                color_space,
+            )?;
+            return hdr_export::encode_ultra_hdr_jpeg(
+                &primary_bytes,
+                gain_map,
+                hdr_settings,
+                export_settings.jpeg_quality,
+            )
+            .map_err(|e| e.to_string());
+        }
+        ExportRender::UltraHdr(final_image, ..) | ExportRender::Standard(final_image) => {
+            final_image
+        }
+    };
+
+    let mut image_bytes = encode_image_to_bytes(
+        final_image,
+        &format,
+        export_settings.jpeg_quality,
+        Some(&icc_profile),
+    )?;
+    write_image_with_metadata(
+        &mut image_bytes,
+        source_path_str,
+        &format,
+        export_settings.keep_metadata,
+        export_settings.strip_gps,
+        color_space,
+    )?;
+    Ok(image_bytes)
+}
+
 #[tauri::command]
 async fn export_image(
     original_path: String,
@@ -1010,7 +1853,9 @@ async fn export_image(
     }
 
     let context = get_or_init_gpu_context(&state)?;
-    let (original_image_data, is_raw) = get_full_image_for_processing(&state)?;
+    let loaded_image = get_developed_image(&state, &js_adjustments, &app_handle)?;
+    let original_image_data = loaded_image.image;
+    let is_raw = loaded_image.is_raw;
     let context = Arc::new(context);
 
     let task = tokio::spawn(async move {
@@ -1022,16 +1867,6 @@ async fn export_image(
             let base_image = composite_patches_on_image(&original_image_data, &js_adjustments)
                 .map_err(|e| format!("Failed to composite AI patches for export: {}", e))?;
 
-            let final_image = process_image_for_export(
-                &source_path_str,
-                &base_image,
-                &js_adjustments,
-                &export_settings,
-                &context,
-                &state,
-                is_raw,
-            )?;
-
             let output_path_obj = std::path::Path::new(&output_path);
             let extension = output_path_obj
                 .extension()
@@ -1039,17 +1874,20 @@ async fn export_image(
                 .unwrap_or("")
                 .to_lowercase();
 
-            let mut image_bytes =
-                encode_image_to_bytes(&final_image, &extension, export_settings.jpeg_quality)?;
-
-            write_image_with_metadata(
-                &mut image_bytes,
+            let render = process_image_for_export(
                 &source_path_str,
+                &base_image,
+                &js_adjustments,
+                &export_settings,
                 &extension,
-                export_settings.keep_metadata,
-                export_settings.strip_gps,
+                &context,
+                &state,
+                is_raw,
             )?;
 
+            let image_bytes =
+                encode_export_image(&render, &source_path_str, &extension, &export_settings)?;
+
             fs::write(&output_path, image_bytes).map_err(|e| e.to_string())?;
 
             Ok(())
@@ -1150,7 +1988,7 @@ async fn batch_export_images(
                             ImageMetadata::default()
                         };
                         let js_adjustments = metadata.adjustments;
-                        let is_raw = is_raw_file(&source_path_str);
+                        let is_raw = is_linear_source(&source_path_str);
 
                         let base_image = match read_file_mapped(Path::new(&source_path_str)) {
                             Ok(mmap) => load_and_composite(
@@ -1181,11 +2019,12 @@ async fn batch_export_images(
                             }
                         };
 
-                        let final_image = process_image_for_export(
+                        let render = process_image_for_export(
                             &source_path_str,
                             &base_image,
                             &js_adjustments,
                             &export_settings,
+                            &output_format,
                             &context,
                             &state,
                             is_raw,
@@ -1231,18 +2070,11 @@ async fn batch_export_images(
                         let new_filename = format!("{}.{}", new_stem, output_format);
                         let output_path = output_folder_path.join(new_filename);
 
-                        let mut image_bytes = encode_image_to_bytes(
-                            &final_image,
-                            &output_format,
-                            export_settings.jpeg_quality,
-                        )?;
-
-                        write_image_with_metadata(
-                            &mut image_bytes,
+                        let image_bytes = encode_export_image(
+                            &render,
                             &source_path_str,
                             &output_format,
-                            export_settings.keep_metadata,
-                            export_settings.strip_gps,
+                            &export_settings,
                         )?;
 
                         fs::write(&output_path, image_bytes)
@@ -1312,12 +2144,7 @@ async fn estimate_export_size(
     app_handle: tauri::AppHandle,
 ) -> Result<usize, String> {
     let context = get_or_init_gpu_context(&state)?;
-    let loaded_image = state
-        .original_image
-        .lock()
-        .unwrap()
-        .clone()
-        .ok_or("No original image loaded")?;
+    let loaded_image = get_developed_image(&state, &js_adjustments, &app_handle)?;
     let is_raw = loaded_image.is_raw;
 
     let new_transform_hash = calculate_transform_hash(&js_adjustments);
@@ -1379,11 +2206,12 @@ async fn estimate_export_size(
         &processed_preview,
         &output_format,
         export_settings.jpeg_quality,
+        None,
     )?;
     let preview_byte_size = preview_bytes.len();
 
     let (transformed_full_res, _unscaled_crop_offset) =
-        apply_all_transformations(&loaded_image.image, &js_adjustments);
+        apply_all_transformations(&loaded_image.image, &js_adjustments, is_raw);
     let (mut final_full_w, mut final_full_h) = transformed_full_res.dimensions();
 
     if let Some(resize_opts) = &export_settings.resize {
@@ -1471,7 +2299,7 @@ async fn estimate_batch_export_size(
     let first_path = &paths[0];
     let (source_path, sidecar_path) = parse_virtual_path(first_path);
     let source_path_str = source_path.to_string_lossy().to_string();
-    let is_raw = is_raw_file(&source_path_str);
+    let is_raw = is_linear_source(&source_path_str);
 
     let metadata: ImageMetadata = if sidecar_path.exists() {
         let file_content = fs::read_to_string(sidecar_path).map_err(|e| e.to_string())?;
@@ -1487,8 +2315,14 @@ async fn estimate_batch_export_size(
     const ESTIMATE_DIM: u32 = 1280;
 
     let original_image = match read_file_mapped(Path::new(&source_path_str)) {
-        Ok(mmap) => load_base_image_from_bytes(&mmap, &source_path_str, true, highlight_compression)
-            .map_err(|e| e.to_string())?,
+        Ok(mmap) => load_base_image_from_bytes(
+            &mmap,
+            &source_path_str,
+            true,
+            highlight_compression,
+            &js_adjustments,
+        )
+        .map_err(|e| e.to_string())?,
         Err(e) => {
             log::warn!(
                 "Failed to memory-map file '{}': {}. Falling back to standard read.",
@@ -1496,15 +2330,21 @@ async fn estimate_batch_export_size(
                 e
             );
             let bytes = fs::read(&source_path_str).map_err(|io_err| io_err.to_string())?;
-            load_base_image_from_bytes(&bytes, &source_path_str, true, highlight_compression)
-                .map_err(|e| e.to_string())?
+            load_base_image_from_bytes(
+                &bytes,
+                &source_path_str,
+                true,
+                highlight_compression,
+                &js_adjustments,
+            )
+            .map_err(|e| e.to_string())?
         }
     };
 
     let base_image_preview = downscale_f32_image(&original_image, ESTIMATE_DIM, ESTIMATE_DIM);
 
     let (transformed_preview, unscaled_crop_offset) =
-        apply_all_transformations(&base_image_preview, &js_adjustments);
+        apply_all_transformations(&base_image_preview, &js_adjustments, is_raw);
     let (preview_w, preview_h) = transformed_preview.dimensions();
 
     let mask_definitions: Vec<MaskDefinition> = js_adjustments
@@ -1542,11 +2382,12 @@ async fn estimate_batch_export_size(
         &processed_preview,
         &output_format,
         export_settings.jpeg_quality,
+        None,
     )?;
     let single_image_estimated_size = preview_bytes.len();
 
     let (transformed_full_res, _unscaled_crop_offset) =
-        apply_all_transformations(&original_image, &js_adjustments);
+        apply_all_transformations(&original_image, &js_adjustments, is_raw);
     let (mut final_full_w, mut final_full_h) = transformed_full_res.dimensions();
 
     if let Some(resize_opts) = &export_settings.resize {
@@ -1626,6 +2467,7 @@ fn write_image_with_metadata(
     output_format: &str,
     keep_metadata: bool,
     strip_gps: bool,
+    color_space: OutputColorSpace,
 ) -> Result<(), String> {
     if !keep_metadata || output_format.to_lowercase() == "tiff" {
         // FIXME: temporary solution until I find a way to write metadata to TIFF
@@ -1808,7 +2650,7 @@ fn write_image_with_metadata(
 
     metadata.set_tag(ExifTag::Software("RapidRAW".to_string()));
     metadata.set_tag(ExifTag::Orientation(vec![1u16]));
-    metadata.set_tag(ExifTag::ColorSpace(vec![1u16]));
+    metadata.set_tag(ExifTag::ColorSpace(vec![color_space.exif_color_space()]));
 
     // little_exif has a bug where writing a Metadata object causes a panic, even if you do everything else right - see https://github.com/TechnikTobi/little_exif/issues/76
     let write_result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
@@ -1864,6 +2706,9 @@ async fn generate_ai_foreground_mask(
     flip_horizontal: bool,
     flip_vertical: bool,
     orientation_steps: u8,
+    perspective: Option<perspective::PerspectiveParams>,
+    projection: Option<projection::ProjectionParams>,
+    liquify: Option<liquify::LiquifyMesh>,
     state: tauri::State<'_, AppState>,
     app_handle: tauri::AppHandle,
 ) -> Result<AiForegroundMaskParameters, String> {
@@ -1882,6 +2727,9 @@ async fn generate_ai_foreground_mask(
         flip_horizontal: Some(flip_horizontal),
         flip_vertical: Some(flip_vertical),
         orientation_steps: Some(orientation_steps),
+        perspective,
+        projection,
+        liquify,
     })
 }
 
@@ -1891,6 +2739,9 @@ async fn generate_ai_sky_mask(
     flip_horizontal: bool,
     flip_vertical: bool,
     orientation_steps: u8,
+    perspective: Option<perspective::PerspectiveParams>,
+    projection: Option<projection::ProjectionParams>,
+    liquify: Option<liquify::LiquifyMesh>,
     state: tauri::State<'_, AppState>,
     app_handle: tauri::AppHandle,
 ) -> Result<AiSkyMaskParameters, String> {
@@ -1909,6 +2760,9 @@ async fn generate_ai_sky_mask(
         flip_horizontal: Some(flip_horizontal),
         flip_vertical: Some(flip_vertical),
         orientation_steps: Some(orientation_steps),
+        perspective,
+        projection,
+        liquify,
     })
 }
 
@@ -1921,6 +2775,9 @@ async fn generate_ai_subject_mask(
     flip_horizontal: bool,
     flip_vertical: bool,
     orientation_steps: u8,
+    perspective: Option<perspective::PerspectiveParams>,
+    projection: Option<projection::ProjectionParams>,
+    liquify: Option<liquify::LiquifyMesh>,
     state: tauri::State<'_, AppState>,
     app_handle: tauri::AppHandle,
 ) -> Result<AiSubjectMaskParameters, String> {
@@ -1968,10 +2825,30 @@ async fn generate_ai_subject_mask(
 
     let center = (coarse_rotated_w / 2.0, coarse_rotated_h / 2.0);
 
-    let p1 = start_point;
-    let p2 = (start_point.0, end_point.1);
-    let p3 = end_point;
-    let p4 = (end_point.0, start_point.1);
+    let unliquify = |p: (f64, f64)| match &liquify {
+        Some(mesh) => mesh.source_point(coarse_rotated_w, coarse_rotated_h, p.0, p.1),
+        None => p,
+    };
+
+    let p1 = unliquify(start_point);
+    let p2 = unliquify((start_point.0, end_point.1));
+    let p3 = unliquify(end_point);
+    let p4 = unliquify((end_point.0, start_point.1));
+
+    let unwarp = |p: (f64, f64)| match &perspective {
+        Some(params) => perspective::unwarp_point(
+            params,
+            coarse_rotated_w as u32,
+            coarse_rotated_h as u32,
+            p,
+        ),
+        None => p,
+    };
+
+    let p1 = unwarp(p1);
+    let p2 = unwarp(p2);
+    let p3 = unwarp(p3);
+    let p4 = unwarp(p4);
 
     let angle_rad = (rotation as f64).to_radians();
     let cos_a = angle_rad.cos();
@@ -1985,10 +2862,20 @@ async fn generate_ai_subject_mask(
         (new_px, new_py)
     };
 
-    let up1 = unrotate(p1);
-    let up2 = unrotate(p2);
-    let up3 = unrotate(p3);
-    let up4 = unrotate(p4);
+    let projection_mapper = projection
+        .as_ref()
+        .and_then(|params| params.mapper(coarse_rotated_w as u32, coarse_rotated_h as u32));
+    let unproject = |p: (f64, f64)| {
+        projection_mapper
+            .as_ref()
+            .and_then(|mapper| mapper.source_point(p.0, p.1))
+            .unwrap_or(p)
+    };
+
+    let up1 = unproject(unrotate(p1));
+    let up2 = unproject(unrotate(p2));
+    let up3 = unproject(unrotate(p3));
+    let up4 = unproject(unrotate(p4));
 
     let unflip = |p: (f64, f64)| {
         let mut new_px = p.0;
@@ -2049,6 +2936,9 @@ async fn generate_ai_subject_mask(
         flip_horizontal: Some(flip_horizontal),
         flip_vertical: Some(flip_vertical),
         orientation_steps: Some(orientation_steps),
+        perspective,
+        projection,
+        liquify,
     })
 }
 
@@ -2074,7 +2964,7 @@ fn generate_preset_preview(
     let preview_base = downscale_f32_image(&original_image, PRESET_PREVIEW_DIM, PRESET_PREVIEW_DIM);
 
     let (transformed_image, unscaled_crop_offset) =
-        apply_all_transformations(&preview_base, &js_adjustments);
+        apply_all_transformations(&preview_base, &js_adjustments, is_raw);
     let (img_w, img_h) = transformed_image.dimensions();
 
     let mask_definitions: Vec<MaskDefinition> = js_adjustments
@@ -2372,10 +3262,15 @@ async fn generate_all_community_previews(
         let (source_path, _) = parse_virtual_path(image_path);
         let source_path_str = source_path.to_string_lossy().to_string();
         let image_bytes = fs::read(&source_path).map_err(|e| e.to_string())?;
-        let original_image =
-            crate::image_loader::load_base_image_from_bytes(&image_bytes, &source_path_str, true, highlight_compression )
-                .map_err(|e| e.to_string())?;
-        let is_raw = is_raw_file(&source_path_str);
+        let original_image = crate::image_loader::load_base_image_from_bytes(
+            &image_bytes,
+            &source_path_str,
+            true,
+            highlight_compression,
+            &Value::Null,
+        )
+        .map_err(|e| e.to_string())?;
+        let is_raw = is_linear_source(&source_path_str);
         base_thumbnails.push((
             downscale_f32_image(&original_image, PROCESSING_DIM, PROCESSING_DIM),
             is_raw,
@@ -2392,7 +3287,7 @@ async fn generate_all_community_previews(
 
         for (i, (base_image, is_raw)) in base_thumbnails.iter().enumerate() {
             let (transformed_image, unscaled_crop_offset) =
-                crate::apply_all_transformations(&base_image, &js_adjustments);
+                crate::apply_all_transformations(&base_image, &js_adjustments, *is_raw);
             let (img_w, img_h) = transformed_image.dimensions();
 
             let mask_definitions: Vec<MaskDefinition> = js_adjustments
@@ -2507,6 +3402,8 @@ async fn stitch_panorama(
 
     let panorama_result_handle = state.panorama_result.clone();
 
+    let is_linear = is_linear_source(&source_paths[0]);
+
     let task = tokio::task::spawn_blocking(move || {
         let panorama_result = panorama_stitching::stitch_images(source_paths, app_handle.clone());
 
@@ -2521,11 +3418,14 @@ async fn stitch_panorama(
                     ((800.0 * w as f32 / h as f32).round() as u32, 800)
                 };
 
-                let preview_f32 = crate::image_processing::downscale_f32_image(
+                let mut preview_f32 = crate::image_processing::downscale_f32_image(
                     &panorama_image,
                     new_w,
                     new_h
                 );
+                if is_linear {
+                    apply_cpu_default_raw_processing(&mut preview_f32);
+                }
 
                 let preview_u8 = preview_f32.to_rgb8();
 
@@ -2562,9 +3462,30 @@ async fn stitch_panorama(
     }
 }
 
+fn save_as_linear_dng(
+    image: &DynamicImage,
+    output_path: &Path,
+    source_path: &Path,
+    is_raw_source: bool,
+) -> Result<(), String> {
+    let linear = crate::image_processing::display_to_linear(image, is_raw_source);
+    save_scene_linear_dng(&linear, output_path, source_path)
+}
+
+fn save_scene_linear_dng(
+    linear: &Rgb32FImage,
+    output_path: &Path,
+    source_path: &Path,
+) -> Result<(), String> {
+    let source_bytes = fs::read(source_path).ok();
+    raw_utils::dng_writer::write_linear_dng(output_path, linear, source_bytes.as_deref())
+        .map_err(|e| format!("Failed to write DNG: {}", e))
+}
+
 #[tauri::command]
 async fn save_panorama(
     first_path_str: String,
+    as_dng: Option<bool>,
     state: tauri::State<'_, AppState>,
 ) -> Result<String, String> {
     let panorama_image = state
@@ -2586,6 +3507,27 @@ async fn save_panorama(
         .and_then(|s| s.to_str())
         .unwrap_or("panorama");
 
+    // Panoramas from linear sources are stitched from the unclamped linear data.
+    let linear_panorama = panorama_image
+        .as_rgb32f()
+        .filter(|_| is_linear_source(&first_path.to_string_lossy()));
+
+    if as_dng.unwrap_or(false) {
+        let output_path = parent_dir.join(format!("{}_Pano.dng", stem));
+        match linear_panorama {
+            Some(linear) => save_scene_linear_dng(linear, &output_path, &first_path)?,
+            None => save_as_linear_dng(&panorama_image, &output_path, &first_path, false)?,
+        }
+        return Ok(output_path.to_string_lossy().to_string());
+    }
+
+    if let Some(linear) = linear_panorama {
+        let output_path = parent_dir.join(format!("{}_Pano.tiff", stem));
+        crate::image_loader::save_scene_linear_tiff(linear, &output_path)
+            .map_err(|e| format!("Failed to save panorama image: {}", e))?;
+        return Ok(output_path.to_string_lossy().to_string());
+    }
+
     let (output_filename, image_to_save): (String, DynamicImage) = if panorama_image.color().has_alpha() {
         (format!("{}_Pano.png", stem), DynamicImage::ImageRgba8(panorama_image.to_rgba8()))
     } else if panorama_image.as_rgb32f().is_some() {
@@ -2603,6 +3545,255 @@ async fn save_panorama(
     Ok(output_path.to_string_lossy().to_string())
 }
 
+#[tauri::command]
+async fn merge_hdr(
+    paths: Vec<String>,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), String> {
+    if paths.len() < 2 {
+        return Err("Please select at least two bracketed images to merge.".to_string());
+    }
+
+    let source_paths: Vec<String> = paths
+        .iter()
+        .map(|p| parse_virtual_path(p).0.to_string_lossy().into_owned())
+        .collect();
+
+    let hdr_result_handle = state.hdr_result.clone();
+    let cancel_token = state.hdr_cancellation_token.clone();
+    cancel_token.store(false, Ordering::SeqCst);
+
+    let task = tokio::task::spawn_blocking(move || {
+        let hdr_result = hdr_merge::merge_hdr(source_paths, app_handle.clone(), cancel_token);
+
+        match hdr_result {
+            Ok(hdr_image) => {
+                let _ = app_handle.emit("hdr-progress", "Creating preview...");
+
+                let (w, h) = hdr_image.dimensions();
+                let (new_w, new_h) = if w > h {
+                    (800, (800.0 * h as f32 / w as f32).round() as u32)
+                } else {
+                    ((800.0 * w as f32 / h as f32).round() as u32, 800)
+                };
+
+                let mut preview_f32 =
+                    crate::image_processing::downscale_f32_image(&hdr_image, new_w, new_h);
+                apply_cpu_default_raw_processing(&mut preview_f32);
+                let preview_u8 = preview_f32.to_rgb8();
+
+                let mut buf = Cursor::new(Vec::new());
+                if let Err(e) = preview_u8.write_to(&mut buf, ImageFormat::Png) {
+                    return Err(format!("Failed to encode HDR preview: {}", e));
+                }
+
+                let base64_str = general_purpose::STANDARD.encode(buf.get_ref());
+                let final_base64 = format!("data:image/png;base64,{}", base64_str);
+
+                *hdr_result_handle.lock().unwrap() = Some(hdr_image);
+
+                let _ = app_handle.emit(
+                    "hdr-complete",
+                    serde_json::json!({
+                        "base64": final_base64,
+                    }),
+                );
+                Ok(())
+            }
+            Err(e) => {
+                let _ = app_handle.emit("hdr-error", e.clone());
+                Err(e)
+            }
+        }
+    });
+
+    match task.await {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(join_err) => Err(format!("HDR merge task failed: {}", join_err)),
+    }
+}
+
+#[tauri::command]
+fn cancel_hdr_merge(state: tauri::State<AppState>) -> Result<(), String> {
+    state.hdr_cancellation_token.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+#[tauri::command]
+async fn save_hdr(
+    first_path_str: String,
+    as_dng: Option<bool>,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    let hdr_image = state.hdr_result.lock().unwrap().take().ok_or_else(|| {
+        "No HDR image found in memory to save. It might have already been saved.".to_string()
+    })?;
+
+    let (first_path, _) = parse_virtual_path(&first_path_str);
+    let parent_dir = first_path
+        .parent()
+        .ok_or_else(|| "Could not determine parent directory of the first image.".to_string())?;
+    let stem = first_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("hdr");
+
+    let linear = hdr_image
+        .as_rgb32f()
+        .ok_or_else(|| "HDR result is not a float image.".to_string())?;
+
+    if as_dng.unwrap_or(false) {
+        let output_path = parent_dir.join(format!("{}_HDR.dng", stem));
+        save_scene_linear_dng(linear, &output_path, &first_path)?;
+        return Ok(output_path.to_string_lossy().to_string());
+    }
+
+    let output_path = parent_dir.join(format!("{}_HDR.tiff", stem));
+    crate::image_loader::save_scene_linear_tiff(linear, &output_path)
+        .map_err(|e| format!("Failed to save HDR image: {}", e))?;
+
+    Ok(output_path.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+async fn focus_stack_images(
+    paths: Vec<String>,
+    app_handle: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), String> {
+    if paths.len() < 2 {
+        return Err("Please select at least two images to stack.".to_string());
+    }
+
+    let source_paths: Vec<String> = paths
+        .iter()
+        .map(|p| parse_virtual_path(p).0.to_string_lossy().into_owned())
+        .collect();
+
+    let focus_stack_result_handle = state.focus_stack_result.clone();
+    let cancel_token = state.focus_stack_cancellation_token.clone();
+    cancel_token.store(false, Ordering::SeqCst);
+
+    let task = tokio::task::spawn_blocking(move || {
+        let stack_result =
+            focus_stacking::stack_images(source_paths, app_handle.clone(), cancel_token);
+
+        match stack_result {
+            Ok((stacked_image, depth_map)) => {
+                let _ = app_handle.emit("focus-stack-progress", "Creating preview...");
+
+                let (w, h) = stacked_image.dimensions();
+                let (new_w, new_h) = if w > h {
+                    (800, (800.0 * h as f32 / w as f32).round() as u32)
+                } else {
+                    ((800.0 * w as f32 / h as f32).round() as u32, 800)
+                };
+
+                let preview_u8 =
+                    crate::image_processing::downscale_f32_image(&stacked_image, new_w, new_h)
+                        .to_rgb8();
+                let depth_preview = image::imageops::resize(
+                    &depth_map,
+                    new_w,
+                    new_h,
+                    image::imageops::FilterType::Triangle,
+                );
+
+                let mut buf = Cursor::new(Vec::new());
+                if let Err(e) = preview_u8.write_to(&mut buf, ImageFormat::Png) {
+                    return Err(format!("Failed to encode focus stack preview: {}", e));
+                }
+                let mut depth_buf = Cursor::new(Vec::new());
+                if let Err(e) = depth_preview.write_to(&mut depth_buf, ImageFormat::Png) {
+                    return Err(format!("Failed to encode depth map preview: {}", e));
+                }
+
+                let final_base64 = format!(
+                    "data:image/png;base64,{}",
+                    general_purpose::STANDARD.encode(buf.get_ref())
+                );
+                let depth_base64 = format!(
+                    "data:image/png;base64,{}",
+                    general_purpose::STANDARD.encode(depth_buf.get_ref())
+                );
+
+                *focus_stack_result_handle.lock().unwrap() = Some(stacked_image);
+
+                let _ = app_handle.emit(
+                    "focus-stack-complete",
+                    serde_json::json!({
+                        "base64": final_base64,
+                        "depthMapBase64": depth_base64,
+                    }),
+                );
+                Ok(())
+            }
+            Err(e) => {
+                let _ = app_handle.emit("focus-stack-error", e.clone());
+                Err(e)
+            }
+        }
+    });
+
+    match task.await {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e),
+        Err(join_err) => Err(format!("Focus stack task failed: {}", join_err)),
+    }
+}
+
+#[tauri::command]
+fn cancel_focus_stack(state: tauri::State<AppState>) -> Result<(), String> {
+    state.focus_stack_cancellation_token.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+#[tauri::command]
+async fn save_focus_stack(
+    first_path_str: String,
+    as_dng: Option<bool>,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    let stacked_image = state
+        .focus_stack_result
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or_else(|| {
+            "No focus stack found in memory to save. It might have already been saved."
+                .to_string()
+        })?;
+
+    let (first_path, _) = parse_virtual_path(&first_path_str);
+    let parent_dir = first_path
+        .parent()
+        .ok_or_else(|| "Could not determine parent directory of the first image.".to_string())?;
+    let stem = first_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("focus_stack");
+
+    if as_dng.unwrap_or(false) {
+        let output_path = parent_dir.join(format!("{}_Stack.dng", stem));
+        save_as_linear_dng(
+            &stacked_image,
+            &output_path,
+            &first_path,
+            is_linear_source(&first_path.to_string_lossy()),
+        )?;
+        return Ok(output_path.to_string_lossy().to_string());
+    }
+
+    let output_path = parent_dir.join(format!("{}_Stack.tiff", stem));
+    DynamicImage::ImageRgb32F(stacked_image.to_rgb32f())
+        .save(&output_path)
+        .map_err(|e| format!("Failed to save focus stack: {}", e))?;
+
+    Ok(output_path.to_string_lossy().to_string())
+}
+
 #[tauri::command]
 async fn apply_denoising(
     path: String,
@@ -2610,13 +3801,25 @@ async fn apply_denoising(
     app_handle: tauri::AppHandle,
     state: tauri::State<'_, AppState>,
 ) -> Result<(), String> {
-    let (source_path, _) = parse_virtual_path(&path);
+    let (source_path, sidecar_path) = parse_virtual_path(&path);
     let path_str = source_path.to_string_lossy().to_string();
 
+    let metadata: ImageMetadata = if sidecar_path.exists() {
+        let file_content = fs::read_to_string(sidecar_path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&file_content).unwrap_or_default()
+    } else {
+        ImageMetadata::default()
+    };
+
     let denoise_result_handle = state.denoise_result.clone();
 
     tokio::task::spawn_blocking(move || {
-        match denoising::denoise_image(path_str, intensity, app_handle.clone()) {
+        match denoising::denoise_image(
+            path_str,
+            intensity,
+            &metadata.adjustments,
+            app_handle.clone(),
+        ) {
             Ok((image, _base64_ignored_in_this_handler_logic)) => {
                 *denoise_result_handle.lock().unwrap() = Some(image);
             }
@@ -2632,6 +3835,7 @@ async fn apply_denoising(
 #[tauri::command]
 async fn save_denoised_image(
     original_path_str: String,
+    as_dng: Option<bool>,
     state: tauri::State<'_, AppState>,
 ) -> Result<String, String> {
     let denoised_image = state
@@ -2644,7 +3848,7 @@ async fn save_denoised_image(
                 .to_string()
         })?;
 
-    let is_raw = crate::formats::is_raw_file(&original_path_str);
+    let is_raw = is_linear_source(&original_path_str);
 
     let (first_path, _) = parse_virtual_path(&original_path_str);
     let parent_dir = first_path
@@ -2655,6 +3859,12 @@ async fn save_denoised_image(
         .and_then(|s| s.to_str())
         .unwrap_or("denoised");
 
+    if as_dng.unwrap_or(false) {
+        let output_path = parent_dir.join(format!("{}_Denoised.dng", stem));
+        save_as_linear_dng(&denoised_image, &output_path, &first_path, is_raw)?;
+        return Ok(output_path.to_string_lossy().to_string());
+    }
+
     let (output_filename, image_to_save): (String, DynamicImage) = if is_raw {
         let filename = format!("{}_Denoised.tiff", stem);
         (filename, denoised_image) 
@@ -2712,7 +3922,7 @@ fn generate_preview_for_path(
     let context = get_or_init_gpu_context(&state)?;
     let (source_path, _) = parse_virtual_path(&path);
     let source_path_str = source_path.to_string_lossy().to_string();
-    let is_raw = is_raw_file(&source_path_str);
+    let is_raw = is_linear_source(&source_path_str);
     let settings = load_settings(app_handle.clone()).unwrap_or_default();
     let highlight_compression = settings.raw_highlight_compression.unwrap_or(2.5);
 
@@ -2744,7 +3954,7 @@ fn generate_preview_for_path(
     };
 
     let (transformed_image, unscaled_crop_offset) =
-        apply_all_transformations(&base_image, &js_adjustments);
+        apply_all_transformations(&base_image, &js_adjustments, is_raw);
     let (img_w, img_h) = transformed_image.dimensions();
     let mask_definitions: Vec<MaskDefinition> = js_adjustments
         .get("masks")
@@ -2791,6 +4001,20 @@ async fn load_and_parse_lut(
     Ok(LutParseResult { size: lut_size })
 }
 
+#[tauri::command]
+async fn load_and_parse_dcp(path: String) -> Result<DcpParseResult, String> {
+    let profile = dcp_processing::parse_dcp_file(&path).map_err(|e| e.to_string())?;
+    let profile = dcp_processing::cache_dcp(&path, profile);
+
+    Ok(DcpParseResult {
+        name: profile.name.clone(),
+        camera_model: profile.camera_model.clone(),
+        dual_illuminant: profile.is_dual_illuminant(),
+        has_look_table: profile.has_look_table(),
+        has_tone_curve: profile.has_tone_curve(),
+    })
+}
+
 fn apply_window_effect(theme: String, window: impl raw_window_handle::HasWindowHandle) {
     #[cfg(target_os = "windows")]
     {
@@ -3005,6 +4229,25 @@ fn main() {
 
             setup_logging(&app_handle);
 
+            if let Ok(cache_dir) = app_handle.path().app_cache_dir() {
+                raw_utils::calibration::set_cache_dir(cache_dir);
+            }
+            if let Ok(data_dir) = app_handle.path().app_data_dir() {
+                raw_utils::defects::set_data_dir(data_dir);
+            }
+
+            let mut lens_database_dirs = Vec::new();
+            if let Ok(resource_dir) = app_handle
+                .path()
+                .resolve("resources/lensfun", tauri::path::BaseDirectory::Resource)
+            {
+                lens_database_dirs.push(resource_dir);
+            }
+            if let Ok(data_dir) = app_handle.path().app_data_dir() {
+                lens_database_dirs.push(data_dir.join("lensfun"));
+            }
+            lens_correction::set_database_dirs(lens_database_dirs);
+
             if let Some(backend) = &settings.processing_backend {
                 if backend != "auto" {
                     log::info!("Applied processing backend setting: {}", backend);
@@ -3044,15 +4287,21 @@ fn main() {
             ai_init_lock: TokioMutex::new(()),
             export_task_handle: Mutex::new(None),
             panorama_result: Arc::new(Mutex::new(None)),
+            hdr_result: Arc::new(Mutex::new(None)),
+            hdr_cancellation_token: Arc::new(AtomicBool::new(false)),
+            focus_stack_result: Arc::new(Mutex::new(None)),
+            focus_stack_cancellation_token: Arc::new(AtomicBool::new(false)),
             denoise_result: Arc::new(Mutex::new(None)),
             indexing_task_handle: Mutex::new(None),
             lut_cache: Mutex::new(HashMap::new()),
             initial_file_path: Mutex::new(None),
             thumbnail_cancellation_token: Arc::new(AtomicBool::new(false)),
+            soft_proof: Mutex::new(None),
         })
         .invoke_handler(tauri::generate_handler![
             load_image,
             apply_adjustments,
+            set_soft_proof,
             export_image,
             batch_export_images,
             cancel_export,
@@ -3076,9 +4325,24 @@ fn main() {
             save_collage,
             stitch_panorama,
             save_panorama,
+            merge_hdr,
+            cancel_hdr_merge,
+            save_hdr,
+            focus_stack_images,
+            cancel_focus_stack,
+            save_focus_stack,
             apply_denoising,
             save_denoised_image,
             load_and_parse_lut,
+            load_and_parse_dcp,
+            sample_white_balance,
+            build_defect_map,
+            list_lens_profiles,
+            calculate_max_crop,
+            constrain_crop,
+            apply_liquify_stroke,
+            calculate_upright,
+            calculate_auto_straighten,
             fetch_community_presets,
             generate_all_community_previews,
             save_temp_file,
@@ -3089,6 +4353,8 @@ fn main() {
             image_processing::calculate_auto_adjustments,
             file_management::read_exif_for_paths,
             file_management::list_images_in_dir,
+            file_management::get_folder_calibration,
+            file_management::set_folder_calibration,
             file_management::list_images_recursive,
             file_management::get_folder_tree,
             file_management::get_pinned_folder_trees,
@@ -1,6 +1,9 @@
 use crate::ai_processing::{
     AiForegroundMaskParameters, AiSkyMaskParameters, AiSubjectMaskParameters,
 };
+use crate::liquify::LiquifyMesh;
+use crate::perspective::{self, PerspectiveParams};
+use crate::projection::ProjectionParams;
 use base64::{Engine as _, engine::general_purpose};
 use image::{GrayImage, Luma};
 use imageproc::distance_transform::Norm as DilationNorm;
@@ -421,6 +424,9 @@ fn generate_ai_bitmap_from_full_mask(
     flip_horizontal: bool,
     flip_vertical: bool,
     orientation_steps: u8,
+    perspective: Option<&PerspectiveParams>,
+    projection: Option<&ProjectionParams>,
+    liquify: Option<&LiquifyMesh>,
     width: u32,
     height: u32,
     scale: f32,
@@ -444,10 +450,47 @@ fn generate_ai_bitmap_from_full_mask(
     let center_x = scaled_coarse_rotated_w / 2.0;
     let center_y = scaled_coarse_rotated_h / 2.0;
 
+    let perspective_inverse = perspective
+        .filter(|p| !p.is_identity())
+        .and_then(|p| {
+            p.matrix(
+                scaled_coarse_rotated_w.round() as u32,
+                scaled_coarse_rotated_h.round() as u32,
+            )
+            .try_inverse()
+        });
+    let projection_mapper = projection.and_then(|p| {
+        p.mapper(
+            scaled_coarse_rotated_w.round() as u32,
+            scaled_coarse_rotated_h.round() as u32,
+        )
+    });
+
     for y_out in 0..height {
         for x_out in 0..width {
-            let x_uncrop = x_out as f32 + crop_offset.0;
-            let y_uncrop = y_out as f32 + crop_offset.1;
+            let mut x_uncrop = x_out as f32 + crop_offset.0;
+            let mut y_uncrop = y_out as f32 + crop_offset.1;
+
+            if let Some(mesh) = liquify {
+                let (x, y) = mesh.source_point(
+                    scaled_coarse_rotated_w as f64,
+                    scaled_coarse_rotated_h as f64,
+                    x_uncrop as f64,
+                    y_uncrop as f64,
+                );
+                x_uncrop = x as f32;
+                y_uncrop = y as f32;
+            }
+
+            if let Some(inverse) = &perspective_inverse {
+                match perspective::project(inverse, x_uncrop as f64, y_uncrop as f64) {
+                    Some((x, y)) => {
+                        x_uncrop = x as f32;
+                        y_uncrop = y as f32;
+                    }
+                    None => continue,
+                }
+            }
 
             let x_unflipped = if flip_horizontal {
                 scaled_coarse_rotated_w - x_uncrop
@@ -465,8 +508,18 @@ fn generate_ai_bitmap_from_full_mask(
 
             let x_rot = x_centered * cos_a + y_centered * sin_a;
             let y_rot = -x_centered * sin_a + y_centered * cos_a;
-            let x_unrotated_fine = x_rot + center_x;
-            let y_unrotated_fine = y_rot + center_y;
+            let mut x_unrotated_fine = x_rot + center_x;
+            let mut y_unrotated_fine = y_rot + center_y;
+
+            if let Some(mapper) = &projection_mapper {
+                match mapper.source_point(x_unrotated_fine as f64, y_unrotated_fine as f64) {
+                    Some((x, y)) => {
+                        x_unrotated_fine = x as f32;
+                        y_unrotated_fine = y as f32;
+                    }
+                    None => continue,
+                }
+            }
 
             let (x_unrotated_coarse, y_unrotated_coarse) = match orientation_steps {
                 0 => (x_unrotated_fine, y_unrotated_fine),
@@ -502,6 +555,9 @@ fn generate_ai_bitmap_from_base64(
     flip_horizontal: bool,
     flip_vertical: bool,
     orientation_steps: u8,
+    perspective: Option<&PerspectiveParams>,
+    projection: Option<&ProjectionParams>,
+    liquify: Option<&LiquifyMesh>,
     width: u32,
     height: u32,
     scale: f32,
@@ -522,6 +578,9 @@ fn generate_ai_bitmap_from_base64(
         flip_horizontal,
         flip_vertical,
         orientation_steps,
+        perspective,
+        projection,
+        liquify,
         width,
         height,
         scale,
@@ -547,6 +606,9 @@ fn generate_ai_sky_bitmap(
         params.flip_horizontal.unwrap_or(false),
         params.flip_vertical.unwrap_or(false),
         params.orientation_steps.unwrap_or(0),
+        params.perspective.as_ref(),
+        params.projection.as_ref(),
+        params.liquify.as_ref(),
         width,
         height,
         scale,
@@ -582,6 +644,9 @@ fn generate_ai_foreground_bitmap(
         params.flip_horizontal.unwrap_or(false),
         params.flip_vertical.unwrap_or(false),
         params.orientation_steps.unwrap_or(0),
+        params.perspective.as_ref(),
+        params.projection.as_ref(),
+        params.liquify.as_ref(),
         width,
         height,
         scale,
@@ -617,6 +682,9 @@ fn generate_ai_subject_bitmap(
         params.flip_horizontal.unwrap_or(false),
         params.flip_vertical.unwrap_or(false),
         params.orientation_steps.unwrap_or(0),
+        params.perspective.as_ref(),
+        params.projection.as_ref(),
+        params.liquify.as_ref(),
         width,
         height,
         scale,
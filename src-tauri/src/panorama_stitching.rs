@@ -7,7 +7,7 @@ use std::path::Path;
 use std::time::Instant;
 use tauri::{AppHandle, Emitter};
 
-use crate::formats::is_raw_file;
+use crate::image_loader::is_linear_source;
 use crate::image_processing::apply_cpu_default_raw_processing;
 use crate::panorama_utils::{processing, stitching};
 
@@ -81,16 +81,23 @@ pub fn stitch_images(image_paths: Vec<String>, app_handle: AppHandle) -> Result<
             let file_bytes = fs::read(filename)
                 .map_err(|e| format!("Failed to read image {}: {}", filename, e))?;
 
-            let mut dynamic_image =
-                crate::image_loader::load_base_image_from_bytes(&file_bytes, filename, false, 2.5)
-                    .map_err(|e| format!("Failed to load image {}: {}", filename, e))?;
+            let mut dynamic_image = crate::image_loader::load_base_image_from_bytes(
+                &file_bytes,
+                filename,
+                false,
+                2.5,
+                &serde_json::Value::Null,
+            )
+            .map_err(|e| format!("Failed to load image {}: {}", filename, e))?;
+
+            // Features come from the rendered look, but stitching keeps the unclamped linear data
+            // so the panorama can be saved with its full highlight headroom.
+            let image_f32 = dynamic_image.to_rgb32f();
 
-            if is_raw_file(filename) {
+            if is_linear_source(filename) {
                 apply_cpu_default_raw_processing(&mut dynamic_image);
             }
 
-            let image_f32 = dynamic_image.to_rgb32f();
-
             let color_full_u8 = dynamic_image.to_rgb8();
             let gray_full = image::imageops::colorops::grayscale(&color_full_u8);
 
@@ -1,8 +1,8 @@
 use crate::panorama_stitching::{BRIEF_DESCRIPTOR_SIZE, Descriptor, Feature, KeyPoint, Match};
-use image::{GrayImage, ImageBuffer, Luma};
+use image::{GrayImage, ImageBuffer, Luma, Rgb32FImage};
 use imageproc::corners::{Corner, corners_fast9};
 use imageproc::filter::gaussian_blur_f32;
-use nalgebra::{Matrix3, Point2, SVD};
+use nalgebra::{Matrix3, Point2, SVD, Vector3};
 use rand::prelude::*;
 use rand::rng;
 use rayon::prelude::*;
@@ -402,3 +402,82 @@ pub fn generate_low_detail_mask(gray_full: &GrayImage) -> GrayImage {
         });
     mask
 }
+
+pub fn align_to_reference(
+    features1: &[Feature],
+    s1: f64,
+    features2: &[Feature],
+    s2: f64,
+) -> Option<Matrix3<f64>> {
+    let initial_matches = match_features(features1, features2);
+    if initial_matches.len() < MIN_INLIERS_FOR_CONNECTION {
+        return None;
+    }
+
+    let keypoints1: Vec<KeyPoint> = features1.iter().map(|f| f.keypoint).collect();
+    let keypoints2: Vec<KeyPoint> = features2.iter().map(|f| f.keypoint).collect();
+    let (_h_small, inliers) =
+        find_homography_ransac(&initial_matches, &keypoints1, &keypoints2)?;
+    if inliers.len() < MIN_INLIERS_FOR_CONNECTION {
+        return None;
+    }
+
+    let inlier_points: Vec<(Point2<f64>, Point2<f64>)> = inliers
+        .iter()
+        .map(|m| {
+            let p1 = keypoints1[m.index1];
+            let p2 = keypoints2[m.index2];
+            (
+                Point2::new(p1.x as f64, p1.y as f64),
+                Point2::new(p2.x as f64, p2.y as f64),
+            )
+        })
+        .collect();
+    let h_refined = compute_homography(&inlier_points)?;
+
+    let scale_ref_inv = Matrix3::new(1.0 / s1, 0.0, 0.0, 0.0, 1.0 / s1, 0.0, 0.0, 0.0, 1.0);
+    let scale_frame = Matrix3::new(s2, 0.0, 0.0, 0.0, s2, 0.0, 0.0, 0.0, 1.0);
+    Some(scale_frame * h_refined * scale_ref_inv)
+}
+
+fn sample_bilinear(image: &Rgb32FImage, x: f64, y: f64) -> Option<[f32; 3]> {
+    let (w, h) = image.dimensions();
+    if x < 0.0 || y < 0.0 || x > (w - 1) as f64 || y > (h - 1) as f64 {
+        return None;
+    }
+    let x0 = x.floor() as u32;
+    let y0 = y.floor() as u32;
+    let x1 = (x0 + 1).min(w - 1);
+    let y1 = (y0 + 1).min(h - 1);
+    let fx = (x - x0 as f64) as f32;
+    let fy = (y - y0 as f64) as f32;
+
+    let p00 = image.get_pixel(x0, y0).0;
+    let p10 = image.get_pixel(x1, y0).0;
+    let p01 = image.get_pixel(x0, y1).0;
+    let p11 = image.get_pixel(x1, y1).0;
+    Some(std::array::from_fn(|c| {
+        let top = p00[c] + (p10[c] - p00[c]) * fx;
+        let bottom = p01[c] + (p11[c] - p01[c]) * fx;
+        top + (bottom - top) * fy
+    }))
+}
+
+pub fn warp_to_reference(
+    frame: &Rgb32FImage,
+    homography: &Matrix3<f64>,
+    width: u32,
+    height: u32,
+) -> Vec<Option<[f32; 3]>> {
+    (0..width * height)
+        .into_par_iter()
+        .map(|i| {
+            let (x, y) = ((i % width) as f64, (i / width) as f64);
+            let p = homography * Vector3::new(x, y, 1.0);
+            if p.z.abs() < 1e-12 {
+                return None;
+            }
+            sample_bilinear(frame, p.x / p.z, p.y / p.z)
+        })
+        .collect()
+}
@@ -0,0 +1,218 @@
+use image::{DynamicImage, Rgba, Rgba32FImage};
+use nalgebra::{Matrix3, Vector3};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+const MAX_TILT_DEGREES: f64 = 30.0;
+const MAX_ASPECT_STOPS: f64 = 0.5;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PerspectiveParams {
+    pub perspective_vertical: f64,
+    pub perspective_horizontal: f64,
+    pub perspective_rotate: f64,
+    pub perspective_aspect: f64,
+    pub perspective_scale: f64,
+    pub perspective_x_offset: f64,
+    pub perspective_y_offset: f64,
+}
+
+impl Default for PerspectiveParams {
+    fn default() -> Self {
+        Self {
+            perspective_vertical: 0.0,
+            perspective_horizontal: 0.0,
+            perspective_rotate: 0.0,
+            perspective_aspect: 0.0,
+            perspective_scale: 100.0,
+            perspective_x_offset: 0.0,
+            perspective_y_offset: 0.0,
+        }
+    }
+}
+
+impl PerspectiveParams {
+    pub fn from_adjustments(adjustments: &Value) -> Self {
+        let get = |key: &str, default: f64| adjustments[key].as_f64().unwrap_or(default);
+        Self {
+            perspective_vertical: get("perspectiveVertical", 0.0),
+            perspective_horizontal: get("perspectiveHorizontal", 0.0),
+            perspective_rotate: get("perspectiveRotate", 0.0),
+            perspective_aspect: get("perspectiveAspect", 0.0),
+            perspective_scale: get("perspectiveScale", 100.0),
+            perspective_x_offset: get("perspectiveXOffset", 0.0),
+            perspective_y_offset: get("perspectiveYOffset", 0.0),
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    // Maps pixel coordinates of the rotated image to pixel coordinates of the
+    // corrected image. Vertical and horizontal tilt the virtual camera around
+    // the image centre, so the centre stays put before scale and offset.
+    pub fn matrix(&self, width: u32, height: u32) -> Matrix3<f64> {
+        let (cx, cy) = (width as f64 / 2.0, height as f64 / 2.0);
+        let focal = width.max(height) as f64;
+
+        let center = Matrix3::new(1.0, 0.0, -cx, 0.0, 1.0, -cy, 0.0, 0.0, 1.0);
+        let k = Matrix3::new(focal, 0.0, 0.0, 0.0, focal, 0.0, 0.0, 0.0, 1.0);
+        let k_inv = Matrix3::new(1.0 / focal, 0.0, 0.0, 0.0, 1.0 / focal, 0.0, 0.0, 0.0, 1.0);
+
+        let tilt_x = (self.perspective_vertical / 100.0 * MAX_TILT_DEGREES).to_radians();
+        let tilt_y = (self.perspective_horizontal / 100.0 * MAX_TILT_DEGREES).to_radians();
+        let rot_x = Matrix3::new(
+            1.0,
+            0.0,
+            0.0,
+            0.0,
+            tilt_x.cos(),
+            -tilt_x.sin(),
+            0.0,
+            tilt_x.sin(),
+            tilt_x.cos(),
+        );
+        let rot_y = Matrix3::new(
+            tilt_y.cos(),
+            0.0,
+            tilt_y.sin(),
+            0.0,
+            1.0,
+            0.0,
+            -tilt_y.sin(),
+            0.0,
+            tilt_y.cos(),
+        );
+        let mut tilt = k * rot_y * rot_x * k_inv;
+        let shifted_center = tilt * Vector3::new(0.0, 0.0, 1.0);
+        if shifted_center.z.abs() > 1e-9 {
+            let (sx, sy) = (
+                shifted_center.x / shifted_center.z,
+                shifted_center.y / shifted_center.z,
+            );
+            tilt = Matrix3::new(1.0, 0.0, -sx, 0.0, 1.0, -sy, 0.0, 0.0, 1.0) * tilt;
+        }
+
+        let angle = self.perspective_rotate.to_radians();
+        let rotate = Matrix3::new(
+            angle.cos(),
+            -angle.sin(),
+            0.0,
+            angle.sin(),
+            angle.cos(),
+            0.0,
+            0.0,
+            0.0,
+            1.0,
+        );
+
+        let aspect = 2f64.powf(self.perspective_aspect / 100.0 * MAX_ASPECT_STOPS);
+        let scale = (self.perspective_scale / 100.0).max(0.01);
+        let scale_aspect = Matrix3::new(
+            scale * aspect,
+            0.0,
+            0.0,
+            0.0,
+            scale / aspect,
+            0.0,
+            0.0,
+            0.0,
+            1.0,
+        );
+
+        let uncenter = Matrix3::new(
+            1.0,
+            0.0,
+            cx + self.perspective_x_offset / 100.0 * cx,
+            0.0,
+            1.0,
+            cy + self.perspective_y_offset / 100.0 * cy,
+            0.0,
+            0.0,
+            1.0,
+        );
+
+        uncenter * scale_aspect * rotate * tilt * center
+    }
+}
+
+pub fn is_perspective_active(adjustments: &Value) -> bool {
+    !PerspectiveParams::from_adjustments(adjustments).is_identity()
+}
+
+pub fn project(h: &Matrix3<f64>, x: f64, y: f64) -> Option<(f64, f64)> {
+    let p = h * Vector3::new(x, y, 1.0);
+    (p.z > 1e-9).then(|| (p.x / p.z, p.y / p.z))
+}
+
+// Maps a point of the corrected image back into the rotated image, for mask
+// coordinates that were picked on the corrected view.
+pub fn unwarp_point(
+    params: &PerspectiveParams,
+    width: u32,
+    height: u32,
+    point: (f64, f64),
+) -> (f64, f64) {
+    if params.is_identity() {
+        return point;
+    }
+    params
+        .matrix(width, height)
+        .try_inverse()
+        .and_then(|inverse| project(&inverse, point.0, point.1))
+        .unwrap_or(point)
+}
+
+pub(crate) fn sample_bilinear(image: &Rgba32FImage, x: f64, y: f64) -> Rgba<f32> {
+    let (width, height) = image.dimensions();
+    if x < -0.5 || y < -0.5 || x > width as f64 - 0.5 || y > height as f64 - 0.5 {
+        return Rgba([0.0, 0.0, 0.0, 0.0]);
+    }
+    let x = x.clamp(0.0, (width - 1) as f64);
+    let y = y.clamp(0.0, (height - 1) as f64);
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (fx, fy) = ((x - x0 as f64) as f32, (y - y0 as f64) as f32);
+
+    let p00 = image.get_pixel(x0, y0).0;
+    let p10 = image.get_pixel(x1, y0).0;
+    let p01 = image.get_pixel(x0, y1).0;
+    let p11 = image.get_pixel(x1, y1).0;
+    Rgba(std::array::from_fn(|c| {
+        let top = p00[c] + (p10[c] - p00[c]) * fx;
+        let bottom = p01[c] + (p11[c] - p01[c]) * fx;
+        top + (bottom - top) * fy
+    }))
+}
+
+pub fn apply_perspective(image: DynamicImage, adjustments: &Value) -> DynamicImage {
+    let params = PerspectiveParams::from_adjustments(adjustments);
+    if params.is_identity() {
+        return image;
+    }
+
+    let (width, height) = (image.width(), image.height());
+    let Some(inverse) = params.matrix(width, height).try_inverse() else {
+        return image;
+    };
+
+    let source = image.to_rgba32f();
+    let mut output = Rgba32FImage::new(width, height);
+    output
+        .par_chunks_mut(width as usize * 4)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for x in 0..width as usize {
+                let pixel = match project(&inverse, x as f64, y as f64) {
+                    Some((sx, sy)) => sample_bilinear(&source, sx, sy),
+                    None => Rgba([0.0, 0.0, 0.0, 0.0]),
+                };
+                row[x * 4..x * 4 + 4].copy_from_slice(&pixel.0);
+            }
+        });
+
+    DynamicImage::ImageRgba32F(output)
+}
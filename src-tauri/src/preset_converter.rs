@@ -137,6 +137,13 @@ pub fn convert_xmp_to_preset(xmp_content: &str) -> Result<Preset, String> {
         ("GrainSize", "grainSize"),
         ("GrainFrequency", "grainRoughness"),
         ("ColorGradeBlending", "blending"),
+        ("ParametricShadows", "parametricShadows"),
+        ("ParametricDarks", "parametricDarks"),
+        ("ParametricLights", "parametricLights"),
+        ("ParametricHighlights", "parametricHighlights"),
+        ("ParametricShadowSplit", "parametricShadowSplit"),
+        ("ParametricMidtoneSplit", "parametricMidtoneSplit"),
+        ("ParametricHighlightSplit", "parametricHighlightSplit"),
     ];
 
     for (xmp_key, rr_key) in mappings {
@@ -166,23 +173,42 @@ pub fn convert_xmp_to_preset(xmp_content: &str) -> Result<Preset, String> {
         );
     }
 
-    if let Some(adjusted_k) = get_attr_as_f64(&attrs, "Temperature") {
+    // RAW files use the absolute Kelvin/tint; the relative sliders carry the same shift for
+    // everything else and are ignored on RAW files once an absolute white balance is set.
+    let absolute_temperature = get_attr_as_f64(&attrs, "Temperature").filter(|&k| k >= 1000.0);
+    if let Some(adjusted_k) = absolute_temperature {
         const AS_SHOT_DEFAULT: f64 = 5500.0;
         const MAX_MIRED_SHIFT: f64 = 150.0;
+        adjustments.insert("whiteBalanceKelvin".to_string(), json!(adjusted_k));
         let as_shot_k = get_attr_as_f64(&attrs, "AsShotTemperature").unwrap_or(AS_SHOT_DEFAULT);
-        let mired_adjusted = 1_000_000.0 / adjusted_k;
-        let mired_as_shot = 1_000_000.0 / as_shot_k;
-        let mired_delta = mired_adjusted - mired_as_shot;
+        let mired_delta = 1_000_000.0 / adjusted_k - 1_000_000.0 / as_shot_k;
         let temp_value = (-mired_delta / MAX_MIRED_SHIFT) * 100.0;
         adjustments.insert(
             "temperature".to_string(),
             json!(temp_value.clamp(-100.0, 100.0)),
         );
-    }
 
-    if let Some(tint_val) = get_attr_as_f64(&attrs, "Tint") {
-        let scaled_tint = (tint_val / 150.0) * 100.0;
-        adjustments.insert("tint".to_string(), json!(scaled_tint.clamp(-100.0, 100.0)));
+        if let Some(tint_val) = get_attr_as_f64(&attrs, "Tint") {
+            adjustments.insert("whiteBalanceTint".to_string(), json!(tint_val));
+            let as_shot_tint = get_attr_as_f64(&attrs, "AsShotTint").unwrap_or(0.0);
+            let scaled_tint = ((tint_val - as_shot_tint) / 150.0) * 100.0;
+            adjustments.insert("tint".to_string(), json!(scaled_tint.clamp(-100.0, 100.0)));
+        }
+    } else {
+        let relative_temperature = get_attr_as_f64(&attrs, "IncrementalTemperature")
+            .or_else(|| get_attr_as_f64(&attrs, "Temperature"));
+        if let Some(temp_val) = relative_temperature {
+            adjustments.insert(
+                "temperature".to_string(),
+                json!(temp_val.clamp(-100.0, 100.0)),
+            );
+        }
+
+        let relative_tint =
+            get_attr_as_f64(&attrs, "IncrementalTint").or_else(|| get_attr_as_f64(&attrs, "Tint"));
+        if let Some(tint_val) = relative_tint {
+            adjustments.insert("tint".to_string(), json!(tint_val.clamp(-100.0, 100.0)));
+        }
     }
 
     let colors = [
@@ -229,6 +255,29 @@ pub fn convert_xmp_to_preset(xmp_content: &str) -> Result<Preset, String> {
         adjustments.insert("hsl".to_string(), Value::Object(hsl_map));
     }
 
+    let mut black_and_white_map = Map::new();
+    for (src, dst) in colors {
+        if let Some(raw) = attrs.get(&format!("GrayMixer{}", src)) {
+            if let Some(num) = parse_num(raw.trim_start_matches('+')) {
+                if let Some(json_val) = num_to_json(num) {
+                    black_and_white_map.insert(dst.to_string(), json_val);
+                }
+            }
+        }
+    }
+    if attrs
+        .get("ConvertToGrayscale")
+        .is_some_and(|v| v.eq_ignore_ascii_case("true"))
+    {
+        black_and_white_map.insert("enabled".to_string(), json!(true));
+    }
+    if !black_and_white_map.is_empty() {
+        adjustments.insert(
+            "blackAndWhite".to_string(),
+            Value::Object(black_and_white_map),
+        );
+    }
+
     let mut shadows_map = Map::new();
     let mut midtones_map = Map::new();
     let mut highlights_map = Map::new();
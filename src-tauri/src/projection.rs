@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+use std::f64::consts::FRAC_PI_2;
+
+use image::{DynamicImage, Rgba, Rgba32FImage};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::lens_correction;
+use crate::perspective;
+
+const FULL_FRAME_DIAGONAL_MM: f64 = 43.2666;
+const MAX_FIT_ANGLE: f64 = 80.0 * std::f64::consts::PI / 180.0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Projection {
+    #[default]
+    Rectilinear,
+    Equisolid,
+    Equidistant,
+    Orthographic,
+    Stereographic,
+    Panini,
+    Mercator,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ProjectionParams {
+    pub projection_source: Projection,
+    pub projection_target: Projection,
+    pub projection_focal_length: f64,
+    pub projection_crop_factor: f64,
+    pub projection_panini_distance: f64,
+    pub projection_scale: f64,
+}
+
+impl Default for ProjectionParams {
+    fn default() -> Self {
+        Self {
+            projection_source: Projection::Rectilinear,
+            projection_target: Projection::Rectilinear,
+            projection_focal_length: 0.0,
+            projection_crop_factor: 1.0,
+            projection_panini_distance: 100.0,
+            projection_scale: 100.0,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct LensOptics {
+    pub focal_length: f64,
+    pub crop_factor: f64,
+}
+
+// Focal length in mm and the crop factor derived from the 35mm equivalent,
+// which is what the projection stage needs to know the field of view.
+pub fn detect_optics(exif: &HashMap<String, String>) -> Option<LensOptics> {
+    let focal_length = lens_correction::exif_number(exif, "FocalLength")? as f64;
+    let crop_factor = lens_correction::exif_number(exif, "FocalLengthIn35mmFilm")
+        .map(|equivalent| equivalent as f64 / focal_length)
+        .filter(|crop| (0.5..=10.0).contains(crop))
+        .unwrap_or(1.0);
+    Some(LensOptics {
+        focal_length,
+        crop_factor: (crop_factor * 100.0).round() / 100.0,
+    })
+}
+
+// Maps a unit-focal image plane point to a viewing ray.
+fn ray_from_plane(projection: Projection, x: f64, y: f64, panini_d: f64) -> Option<[f64; 3]> {
+    match projection {
+        Projection::Panini => {
+            let k = x * x / ((panini_d + 1.0) * (panini_d + 1.0));
+            let discriminant =
+                k * k * panini_d * panini_d - (k + 1.0) * (k * panini_d * panini_d - 1.0);
+            if discriminant < 0.0 {
+                return None;
+            }
+            let cos_lon = (-k * panini_d + discriminant.sqrt()) / (k + 1.0);
+            let s = (panini_d + 1.0) / (panini_d + cos_lon);
+            let lon = x.atan2(s * cos_lon);
+            let lat = (y / s).atan();
+            Some(ray_from_angles(lon, lat))
+        }
+        Projection::Mercator => Some(ray_from_angles(x, y.sinh().atan())),
+        _ => {
+            let r = (x * x + y * y).sqrt();
+            let theta = match projection {
+                Projection::Rectilinear => r.atan(),
+                Projection::Equidistant => r,
+                Projection::Equisolid => 2.0 * (r / 2.0).min(1.0).asin(),
+                Projection::Orthographic => {
+                    if r > 1.0 {
+                        return None;
+                    }
+                    r.asin()
+                }
+                _ => 2.0 * (r / 2.0).atan(),
+            };
+            if r < 1e-12 {
+                return Some([0.0, 0.0, 1.0]);
+            }
+            let sin_theta = theta.sin();
+            Some([sin_theta * x / r, sin_theta * y / r, theta.cos()])
+        }
+    }
+}
+
+fn ray_from_angles(lon: f64, lat: f64) -> [f64; 3] {
+    [lat.cos() * lon.sin(), lat.sin(), lat.cos() * lon.cos()]
+}
+
+// Maps a viewing ray to a unit-focal image plane point.
+fn plane_from_ray(projection: Projection, ray: [f64; 3], panini_d: f64) -> Option<(f64, f64)> {
+    let [x, y, z] = ray;
+    match projection {
+        Projection::Panini => {
+            let lon = x.atan2(z);
+            let lat = y.atan2((x * x + z * z).sqrt());
+            let denominator = panini_d + lon.cos();
+            if denominator <= 1e-9 || lat.abs() >= FRAC_PI_2 {
+                return None;
+            }
+            let s = (panini_d + 1.0) / denominator;
+            Some((s * lon.sin(), s * lat.tan()))
+        }
+        Projection::Mercator => {
+            let lat = y.atan2((x * x + z * z).sqrt());
+            if lat.abs() >= FRAC_PI_2 - 1e-6 {
+                return None;
+            }
+            Some((x.atan2(z), lat.tan().asinh()))
+        }
+        _ => {
+            let planar = (x * x + y * y).sqrt();
+            let theta = planar.atan2(z);
+            let r = match projection {
+                Projection::Rectilinear => {
+                    if theta >= FRAC_PI_2 {
+                        return None;
+                    }
+                    theta.tan()
+                }
+                Projection::Equidistant => theta,
+                Projection::Equisolid => 2.0 * (theta / 2.0).sin(),
+                Projection::Orthographic => {
+                    if theta > FRAC_PI_2 {
+                        return None;
+                    }
+                    theta.sin()
+                }
+                _ => 2.0 * (theta / 2.0).tan(),
+            };
+            if planar < 1e-12 {
+                return Some((0.0, 0.0));
+            }
+            Some((r * x / planar, r * y / planar))
+        }
+    }
+}
+
+pub struct ProjectionMapper {
+    source: Projection,
+    target: Projection,
+    panini_d: f64,
+    source_focal: f64,
+    target_focal: f64,
+    center: (f64, f64),
+}
+
+impl ProjectionMapper {
+    // Maps an output pixel to the pixel of the unprojected image it samples.
+    pub fn source_point(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let ray = ray_from_plane(
+            self.target,
+            (x - self.center.0) / self.target_focal,
+            (y - self.center.1) / self.target_focal,
+            self.panini_d,
+        )?;
+        let (px, py) = plane_from_ray(self.source, ray, self.panini_d)?;
+        Some((
+            self.center.0 + px * self.source_focal,
+            self.center.1 + py * self.source_focal,
+        ))
+    }
+}
+
+impl ProjectionParams {
+    pub fn from_adjustments(adjustments: &Value) -> Self {
+        let projection =
+            |key: &str| serde_json::from_value(adjustments[key].clone()).unwrap_or_default();
+        let get = |key: &str, default: f64| adjustments[key].as_f64().unwrap_or(default);
+        Self {
+            projection_source: projection("projectionSource"),
+            projection_target: projection("projectionTarget"),
+            projection_focal_length: get("projectionFocalLength", 0.0),
+            projection_crop_factor: get("projectionCropFactor", 1.0),
+            projection_panini_distance: get("projectionPaniniDistance", 100.0),
+            projection_scale: get("projectionScale", 100.0),
+        }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.projection_source == self.projection_target || self.projection_focal_length <= 0.0
+    }
+
+    // The output focal length is picked so the middle of the long edge stays
+    // in place, then scaled by the user.
+    pub fn mapper(&self, width: u32, height: u32) -> Option<ProjectionMapper> {
+        if self.is_identity() || width == 0 || height == 0 {
+            return None;
+        }
+
+        let (w, h) = (width as f64, height as f64);
+        let crop_factor = if self.projection_crop_factor > 0.0 {
+            self.projection_crop_factor
+        } else {
+            1.0
+        };
+        let diagonal = (w * w + h * h).sqrt();
+        let source_focal =
+            self.projection_focal_length * crop_factor * diagonal / FULL_FRAME_DIAGONAL_MM;
+        let panini_d = (self.projection_panini_distance / 100.0).clamp(0.0, 1.0);
+
+        let half_long_edge = w.max(h) / 2.0;
+        let edge_ray = ray_from_plane(
+            self.projection_source,
+            half_long_edge / source_focal,
+            0.0,
+            panini_d,
+        )?;
+        let edge_angle = edge_ray[0].atan2(edge_ray[2]).min(MAX_FIT_ANGLE);
+        let fit_ray = [edge_angle.sin(), 0.0, edge_angle.cos()];
+        let fit_focal = plane_from_ray(self.projection_target, fit_ray, panini_d)
+            .map(|(x, _)| x)
+            .filter(|x| *x > 1e-6)
+            .map_or(source_focal, |x| half_long_edge / x);
+
+        Some(ProjectionMapper {
+            source: self.projection_source,
+            target: self.projection_target,
+            panini_d,
+            source_focal,
+            target_focal: fit_focal * (self.projection_scale / 100.0).max(0.1),
+            center: (w / 2.0, h / 2.0),
+        })
+    }
+}
+
+pub fn is_projection_active(adjustments: &Value) -> bool {
+    !ProjectionParams::from_adjustments(adjustments).is_identity()
+}
+
+pub fn apply_projection(image: DynamicImage, adjustments: &Value) -> DynamicImage {
+    let params = ProjectionParams::from_adjustments(adjustments);
+    let Some(mapper) = params.mapper(image.width(), image.height()) else {
+        return image;
+    };
+
+    let width = image.width();
+    let source = image.to_rgba32f();
+    let mut output = Rgba32FImage::new(width, image.height());
+    output
+        .par_chunks_mut(width as usize * 4)
+        .enumerate()
+        .for_each(|(y, row)| {
+            for x in 0..width as usize {
+                let pixel = match mapper.source_point(x as f64, y as f64) {
+                    Some((sx, sy)) => perspective::sample_bilinear(&source, sx, sy),
+                    None => Rgba([0.0, 0.0, 0.0, 0.0]),
+                };
+                row[x * 4..x * 4 + 4].copy_from_slice(&pixel.0);
+            }
+        });
+
+    DynamicImage::ImageRgba32F(output)
+}
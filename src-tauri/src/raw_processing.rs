@@ -1,27 +1,363 @@
+use crate::dcp_processing::{self, DcpProfile};
 use crate::image_processing::apply_orientation;
-use anyhow::Result;
+use crate::raw_utils::calibration::{self, CalibrationFrames};
+use crate::raw_utils::defects;
+use crate::raw_utils::demosaic::{self, CfaPattern};
+use crate::raw_utils::dng_writer;
+use crate::raw_utils::highlights;
+use crate::raw_utils::levels;
+use crate::raw_utils::white_balance;
+use anyhow::{Result, anyhow};
 use image::{DynamicImage, ImageBuffer, Rgba};
+use nalgebra::{Matrix3, SMatrix, Vector3, Vector4};
 use rawler::{
-    decoders::{Orientation, RawDecodeParams},
+    decoders::{Decoder, Orientation, RawDecodeParams},
     imgop::develop::{DemosaicAlgorithm, Intermediate, ProcessingStep, RawDevelop},
-    rawimage::RawImage,
+    imgop::xyz::Illuminant,
+    rawimage::{RawImage, RawImageData, RawPhotometricInterpretation},
     rawsource::RawSource,
 };
+use rayon::prelude::*;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+pub const RAW_DEVELOP_ADJUSTMENT_KEYS: &[&str] = &[
+    "demosaicAlgorithm",
+    "highlightReconstruction",
+    "dcpProfilePath",
+    "dcpApplyToneCurve",
+    "whiteBalanceKelvin",
+    "whiteBalanceTint",
+    "darkFramePaths",
+    "flatFieldPaths",
+    "hotPixelStrength",
+    "hotPixelUseDefectMap",
+];
+
+const SRGB_TO_XYZ_D65: [f32; 9] = [
+    0.4124564, 0.3575761, 0.1804375, 0.2126729, 0.7151522, 0.0721750, 0.0193339, 0.1191920,
+    0.9503041,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DemosaicMethod {
+    Default,
+    Fast,
+    Rcd,
+    Lmmse,
+    GradientWeighted,
+}
+
+impl DemosaicMethod {
+    pub fn from_adjustments(adjustments: &Value) -> Self {
+        match adjustments
+            .get("demosaicAlgorithm")
+            .and_then(|v| v.as_str())
+            .unwrap_or("default")
+            .to_lowercase()
+            .as_str()
+        {
+            "fast" | "speed" => DemosaicMethod::Fast,
+            "rcd" => DemosaicMethod::Rcd,
+            "lmmse" => DemosaicMethod::Lmmse,
+            "gradientweighted" => DemosaicMethod::GradientWeighted,
+            _ => DemosaicMethod::Default,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HighlightReconstruction {
+    Clip,
+    Blend,
+    InpaintOpposed,
+    Compress,
+}
+
+impl HighlightReconstruction {
+    pub fn from_adjustments(adjustments: &Value) -> Self {
+        match adjustments
+            .get("highlightReconstruction")
+            .and_then(|v| v.as_str())
+            .unwrap_or("compress")
+            .to_lowercase()
+            .as_str()
+        {
+            "clip" => HighlightReconstruction::Clip,
+            "blend" | "unclip" => HighlightReconstruction::Blend,
+            "inpaintopposed" | "inpaint_opposed" | "inpaint-opposed" => {
+                HighlightReconstruction::InpaintOpposed
+            }
+            _ => HighlightReconstruction::Compress,
+        }
+    }
+
+    fn apply(self, pixels: &mut [[f32; 3]], clip_levels: [f32; 3], highlight_compression: f32) {
+        match self {
+            HighlightReconstruction::Clip => highlights::clip(pixels, clip_levels),
+            HighlightReconstruction::Blend => highlights::blend(pixels, clip_levels),
+            HighlightReconstruction::InpaintOpposed => {
+                highlights::inpaint_opposed(pixels, clip_levels)
+            }
+            HighlightReconstruction::Compress => {
+                highlights::compress(pixels, clip_levels, highlight_compression)
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct RawWhiteBalance {
+    pub as_shot_kelvin: f32,
+    pub as_shot_tint: f32,
+    #[serde(skip)]
+    pub multipliers: [f32; 3],
+    #[serde(skip)]
+    pub xyz_to_cam: Matrix3<f32>,
+    // Maps the developed sRGB render back to white-balanced camera RGB, through
+    // the selected DCP when there is one.
+    #[serde(skip)]
+    pub srgb_to_camera: Matrix3<f32>,
+}
+
+impl RawWhiteBalance {
+    pub fn kelvin_tint_for_neutral(&self, camera_rgb: [f32; 3]) -> Option<(f32, f32)> {
+        if camera_rgb.iter().any(|&c| !c.is_finite() || c <= 1e-6) {
+            return None;
+        }
+        let corrected: [f32; 3] =
+            std::array::from_fn(|c| self.multipliers[c] * camera_rgb[1] / camera_rgb[c]);
+        white_balance::kelvin_tint_for_multipliers(&self.xyz_to_cam, corrected)
+    }
+}
+
+pub fn read_raw_white_balance(file_bytes: &[u8], adjustments: &Value) -> Result<RawWhiteBalance> {
+    let source = RawSource::new_from_slice(file_bytes);
+    let decoder = rawler::get_decoder(&source)?;
+    let raw_image = decoder.raw_image(&source, &RawDecodeParams::default(), true)?;
+
+    let xyz_to_cam =
+        xyz_to_camera_matrix(&raw_image).ok_or_else(|| anyhow!("no camera color matrix"))?;
+    let as_shot = white_balance_multipliers(&raw_image);
+    let (as_shot_kelvin, as_shot_tint) =
+        white_balance::kelvin_tint_for_multipliers(&xyz_to_cam, as_shot)
+            .ok_or_else(|| anyhow!("invalid as-shot white balance"))?;
+
+    let multipliers = user_white_balance_multipliers(&xyz_to_cam, adjustments).unwrap_or(as_shot);
+    let camera_to_srgb = match dcp_profile_from_adjustments(adjustments, &raw_image) {
+        Some(profile) => Some(profile.camera_to_srgb(multipliers.map(|m| 1.0 / m))),
+        None => camera_to_srgb_from_xyz_to_cam(&xyz_to_cam),
+    };
+
+    Ok(RawWhiteBalance {
+        as_shot_kelvin,
+        as_shot_tint,
+        multipliers,
+        xyz_to_cam,
+        srgb_to_camera: camera_to_srgb
+            .and_then(|m| m.try_inverse())
+            .unwrap_or_else(Matrix3::identity),
+    })
+}
+
+fn user_kelvin_tint(adjustments: &Value) -> Option<(f32, f32)> {
+    let kelvin = adjustments.get("whiteBalanceKelvin")?.as_f64()? as f32;
+    let tint = adjustments
+        .get("whiteBalanceTint")
+        .and_then(|v| v.as_f64())
+        .unwrap_or(0.0) as f32;
+    Some((kelvin, tint))
+}
+
+fn user_white_balance_multipliers(
+    xyz_to_cam: &Matrix3<f32>,
+    adjustments: &Value,
+) -> Option<[f32; 3]> {
+    let (kelvin, tint) = user_kelvin_tint(adjustments)?;
+    white_balance::multipliers_for_kelvin_tint(xyz_to_cam, kelvin, tint)
+}
+
+// Three-color CFAs only use the fourth slot for a second green, so it follows the green
+// multiplier; four-color sensors get it from their fourth color matrix row.
+fn user_fourth_multiplier(
+    raw_image: &RawImage,
+    xyz_to_cam: &Matrix3<f32>,
+    adjustments: &Value,
+) -> f32 {
+    let current = raw_image.wb_coeffs[3];
+    if color_channel_count(raw_image) != 4 {
+        return if current.is_finite() { 1.0 } else { current };
+    }
+    let fourth_row = raw_image
+        .color_matrix
+        .get(&Illuminant::D65)
+        .or_else(|| raw_image.color_matrix.values().next())
+        .filter(|m| m.len() >= 12)
+        .map(|m| Vector3::new(m[9] as f32, m[10] as f32, m[11] as f32));
+    fourth_row
+        .zip(user_kelvin_tint(adjustments))
+        .and_then(|(row, (kelvin, tint))| {
+            white_balance::channel_multiplier_for_kelvin_tint(xyz_to_cam, &row, kelvin, tint)
+        })
+        .unwrap_or(current)
+}
+
+fn dcp_profile_from_adjustments(
+    adjustments: &Value,
+    raw_image: &RawImage,
+) -> Option<Arc<DcpProfile>> {
+    let path = adjustments
+        .get("dcpProfilePath")
+        .and_then(|v| v.as_str())
+        .filter(|p| !p.is_empty())?;
+    match dcp_processing::get_or_load_dcp(path) {
+        Ok(profile) if profile.matches_camera(&raw_image.camera.make, &raw_image.camera.model) => {
+            Some(profile)
+        }
+        Ok(profile) => {
+            log::warn!(
+                "Ignoring DCP profile {} made for {}, image is from {} {}",
+                path,
+                profile.camera_model,
+                raw_image.camera.make,
+                raw_image.camera.model
+            );
+            None
+        }
+        Err(e) => {
+            log::warn!("Failed to load DCP profile {}: {}", path, e);
+            None
+        }
+    }
+}
+
+fn white_balance_multipliers(raw_image: &RawImage) -> [f32; 3] {
+    let wb = raw_image.wb_coeffs;
+    let green_coeff = if wb[1].is_finite() && wb[1] > 0.0 { wb[1] } else { 1.0 };
+    std::array::from_fn(|c| {
+        if wb[c].is_finite() && wb[c] > 0.0 {
+            wb[c] / green_coeff
+        } else {
+            1.0
+        }
+    })
+}
+
+pub fn calculate_raw_develop_hash(adjustments: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for key in RAW_DEVELOP_ADJUSTMENT_KEYS {
+        if let Some(value) = adjustments.get(*key) {
+            if !value.is_null() {
+                key.hash(&mut hasher);
+                value.to_string().hash(&mut hasher);
+            }
+        }
+    }
+    let calibration_frames = CalibrationFrames::from_adjustments(adjustments);
+    if !calibration_frames.is_empty() {
+        calibration::frames_fingerprint(&calibration_frames).hash(&mut hasher);
+    }
+    if adjustments
+        .get("hotPixelUseDefectMap")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+    {
+        defects::defect_maps_fingerprint().hash(&mut hasher);
+    }
+    hasher.finish()
+}
 
 pub fn develop_raw_image(
     file_bytes: &[u8],
     fast_demosaic: bool,
     highlight_compression: f32,
+    adjustments: &Value,
 ) -> Result<DynamicImage> {
     let (developed_image, orientation) =
-        develop_internal(file_bytes, fast_demosaic, highlight_compression)?;
+        develop_internal(file_bytes, fast_demosaic, highlight_compression, adjustments)?;
     Ok(apply_orientation(developed_image, orientation))
 }
 
+pub fn extract_embedded_preview(file_bytes: &[u8], min_dimension: u32) -> Result<DynamicImage> {
+    let source = RawSource::new_from_slice(file_bytes);
+    let decoder = rawler::get_decoder(&source)?;
+    let params = RawDecodeParams::default();
+
+    // Candidates go from smallest to largest so only the first one that is big enough
+    // gets decoded.
+    let mut largest: Option<(u32, u32)> = None;
+    let mut preview = None;
+    for candidate in 0..3 {
+        let image = match candidate {
+            0 => decoder.thumbnail_image(&source, &params),
+            1 => decoder.preview_image(&source, &params),
+            _ => decoder.full_image(&source, &params),
+        };
+        let Some(image) = image.ok().flatten() else {
+            continue;
+        };
+        if image.width().max(image.height()) >= min_dimension {
+            preview = Some(image);
+            break;
+        }
+        let area = image.width() as u64 * image.height() as u64;
+        if largest.map_or(true, |(w, h)| area > w as u64 * h as u64) {
+            largest = Some((image.width(), image.height()));
+        }
+    }
+    let preview = preview.ok_or_else(|| match largest {
+        Some((width, height)) => anyhow!("Embedded preview is too small ({}x{})", width, height),
+        None => anyhow!("No embedded preview found"),
+    })?;
+
+    let orientation = raw_orientation(decoder.as_ref(), &source);
+    Ok(apply_orientation(preview, orientation))
+}
+
+fn raw_orientation(decoder: &dyn Decoder, source: &RawSource) -> Orientation {
+    decoder
+        .raw_metadata(source, &RawDecodeParams::default())
+        .ok()
+        .and_then(|metadata| metadata.exif.orientation)
+        .map(Orientation::from_u16)
+        .unwrap_or(Orientation::Normal)
+}
+
+// Size of the developed image, read from the sensor metadata without decoding any pixels.
+pub fn read_raw_dimensions(file_bytes: &[u8]) -> Result<(u32, u32)> {
+    let source = RawSource::new_from_slice(file_bytes);
+    let decoder = rawler::get_decoder(&source)?;
+    let raw_image = decoder.raw_image(&source, &RawDecodeParams::default(), true)?;
+    let (width, height) = raw_image
+        .crop_area
+        .as_ref()
+        .or(raw_image.active_area.as_ref())
+        .map(|rect| (rect.d.w, rect.d.h))
+        .unwrap_or((raw_image.width, raw_image.height));
+
+    let swapped = matches!(
+        raw_orientation(decoder.as_ref(), &source),
+        Orientation::Transpose
+            | Orientation::Rotate90
+            | Orientation::Transverse
+            | Orientation::Rotate270
+    );
+    if swapped {
+        Ok((height as u32, width as u32))
+    } else {
+        Ok((width as u32, height as u32))
+    }
+}
+
 fn develop_internal(
     file_bytes: &[u8],
     fast_demosaic: bool,
     highlight_compression: f32,
+    adjustments: &Value,
 ) -> Result<(DynamicImage, Orientation)> {
     let source = RawSource::new_from_slice(file_bytes);
     let decoder = rawler::get_decoder(&source)?;
@@ -34,18 +370,84 @@ fn develop_internal(
         .map(Orientation::from_u16)
         .unwrap_or(Orientation::Normal);
 
-    let original_white_level = raw_image
-        .whitelevel
-        .0
-        .get(0)
-        .cloned()
-        .unwrap_or(u16::MAX as u32) as f32;
-    let original_black_level = raw_image
-        .blacklevel
-        .levels
-        .get(0)
-        .map(|r| r.as_f32())
-        .unwrap_or(0.0);
+    let (original_black_level, original_white_level) = levels::equalize_levels(&mut raw_image);
+
+    let calibration_frames = CalibrationFrames::from_adjustments(adjustments);
+    if let Err(e) =
+        calibration::apply_calibration(&mut raw_image, &calibration_frames, original_black_level)
+    {
+        log::warn!("Skipping dark/flat calibration: {}", e);
+    }
+
+    let defect_strength = adjustments
+        .get("hotPixelStrength")
+        .and_then(|v| v.as_f64())
+        .unwrap_or(0.0) as f32;
+    let use_defect_map = adjustments
+        .get("hotPixelUseDefectMap")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    if defect_strength > 0.0 || use_defect_map {
+        let remapped = defects::correct_defects(
+            &mut raw_image,
+            defect_strength,
+            use_defect_map,
+            original_black_level,
+            original_white_level,
+        );
+        if remapped > 0 {
+            log::info!("Remapped {} defective sensor pixels", remapped);
+        }
+    }
+
+    if let Some(xyz_to_cam) = xyz_to_camera_matrix(&raw_image) {
+        if let Some(multipliers) = user_white_balance_multipliers(&xyz_to_cam, adjustments) {
+            let fourth = user_fourth_multiplier(&raw_image, &xyz_to_cam, adjustments);
+            raw_image.wb_coeffs = [multipliers[0], 1.0, multipliers[2], fourth];
+        }
+    }
+
+    let highlight_mode = HighlightReconstruction::from_adjustments(adjustments);
+    let dcp_profile = dcp_profile_from_adjustments(adjustments, &raw_image);
+    // The pipeline tone-maps the linear result later, so the profile's own
+    // curve is opt-in to avoid applying two tone curves.
+    let dcp_tone_curve = adjustments
+        .get("dcpApplyToneCurve")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let demosaic_method = DemosaicMethod::from_adjustments(adjustments);
+    if !matches!(demosaic_method, DemosaicMethod::Default | DemosaicMethod::Fast) {
+        match develop_with_custom_demosaic(
+            &raw_image,
+            demosaic_method,
+            original_black_level,
+            original_white_level,
+        ) {
+            Ok((mut pixels, width, height)) => {
+                let clip_levels = white_balance_multipliers(&raw_image);
+                highlight_mode.apply(&mut pixels, clip_levels, highlight_compression);
+                camera_rgb_to_output(
+                    &mut pixels,
+                    &raw_image,
+                    dcp_profile.as_deref(),
+                    dcp_tone_curve,
+                );
+                let buffer = ImageBuffer::<Rgba<f32>, _>::from_fn(width, height, |x, y| {
+                    let p = pixels[(y * width + x) as usize];
+                    Rgba([p[0], p[1], p[2], 1.0])
+                });
+                return Ok((DynamicImage::ImageRgba32F(buffer), orientation));
+            }
+            Err(e) => {
+                log::warn!(
+                    "{:?} demosaic unavailable for this image, using default: {}",
+                    demosaic_method,
+                    e
+                );
+            }
+        }
+    }
 
     let headroom_white_level = u32::MAX as f32;
     for level in raw_image.whitelevel.0.iter_mut() {
@@ -53,17 +455,28 @@ fn develop_internal(
     }
 
     let mut developer = RawDevelop::default();
-    if fast_demosaic {
+    if demosaic_method == DemosaicMethod::Fast
+        || (fast_demosaic && demosaic_method == DemosaicMethod::Default)
+    {
         developer.demosaic_algorithm = DemosaicAlgorithm::Speed;
     }
-    developer.steps.retain(|&step| step != ProcessingStep::SRgb);
+    // Highlight reconstruction needs white-balanced camera RGB, so the color matrix (or DCP)
+    // is applied here after it instead of by rawler's Calibrate step.
+    developer
+        .steps
+        .retain(|&step| step != ProcessingStep::SRgb && step != ProcessingStep::Calibrate);
 
     let mut developed_intermediate = developer.develop_intermediate(&raw_image)?;
 
     let denominator = (original_white_level - original_black_level).max(1.0);
-    let rescale_factor = (headroom_white_level - original_black_level) / denominator;
-
-    let safe_highlight_compression = highlight_compression.max(1.01);
+    let baseline_gain = match raw_image.photometric {
+        RawPhotometricInterpretation::LinearRaw => dng_writer::read_headroom_exposure(file_bytes)
+            .map_or(1.0, |ev| 2.0f32.powf(ev)),
+        _ => 1.0,
+    };
+    let rescale_factor =
+        (headroom_white_level - original_black_level) / denominator * baseline_gain;
+    let clip_levels = white_balance_multipliers(&raw_image).map(|m| m * baseline_gain);
 
     match &mut developed_intermediate {
         Intermediate::Monochrome(pixels) => {
@@ -73,48 +486,25 @@ fn develop_internal(
             });
         }
         Intermediate::ThreeColor(pixels) => {
-            pixels.data.iter_mut().for_each(|p| {
-                let r = (p[0] * rescale_factor).max(0.0);
-                let g = (p[1] * rescale_factor).max(0.0);
-                let b = (p[2] * rescale_factor).max(0.0);
-
-                let max_c = r.max(g).max(b);
-
-                let (final_r, final_g, final_b) = if max_c > 1.0 {
-                    let min_c = r.min(g).min(b);
-                    let compression_factor = (1.0
-                        - (max_c - 1.0) / (safe_highlight_compression - 1.0))
-                        .max(0.0)
-                        .min(1.0);
-                    let compressed_r = min_c + (r - min_c) * compression_factor;
-                    let compressed_g = min_c + (g - min_c) * compression_factor;
-                    let compressed_b = min_c + (b - min_c) * compression_factor;
-                    let compressed_max = compressed_r.max(compressed_g).max(compressed_b);
-
-                    if compressed_max > 1e-6 {
-                        let rescale = max_c / compressed_max;
-                        (
-                            compressed_r * rescale,
-                            compressed_g * rescale,
-                            compressed_b * rescale,
-                        )
-                    } else {
-                        (max_c, max_c, max_c)
-                    }
-                } else {
-                    (r, g, b)
-                };
-
-                p[0] = final_r;
-                p[1] = final_g;
-                p[2] = final_b;
+            pixels.data.par_iter_mut().for_each(|p| {
+                *p = p.map(|c| (c * rescale_factor).max(0.0));
             });
+            highlight_mode.apply(&mut pixels.data, clip_levels, highlight_compression);
+            camera_rgb_to_output(
+                &mut pixels.data,
+                &raw_image,
+                dcp_profile.as_deref(),
+                dcp_tone_curve,
+            );
         }
         Intermediate::FourColor(pixels) => {
-            pixels.data.iter_mut().for_each(|p| {
+            // The reconstruction modes are three-channel, so four-color sensors are clipped at
+            // the lowest saturation level in camera space before the matrix mixes channels.
+            let clip_level = four_color_clip_level(&raw_image) * baseline_gain;
+            pixels.data.par_iter_mut().for_each(|p| {
                 p.iter_mut().for_each(|c| {
                     let linear_val = *c * rescale_factor;
-                    *c = linear_val;
+                    *c = linear_val.min(clip_level);
                 });
             });
         }
@@ -139,12 +529,210 @@ fn develop_internal(
             });
             DynamicImage::ImageRgba32F(buffer)
         }
-        _ => {
-            return Err(anyhow::anyhow!(
-                "Unsupported intermediate format for f32 conversion"
-            ));
+        Intermediate::FourColor(pixels) => {
+            let cam_to_srgb = four_color_to_srgb_matrix(&raw_image)
+                .ok_or_else(|| anyhow!("No usable color matrix for four-color sensor"))?;
+            let rgb: Vec<[f32; 3]> = pixels
+                .data
+                .par_iter()
+                .map(|p| {
+                    let v = cam_to_srgb * Vector4::new(p[0], p[1], p[2], p[3]);
+                    [v.x.max(0.0), v.y.max(0.0), v.z.max(0.0)]
+                })
+                .collect();
+            let buffer = ImageBuffer::<Rgba<f32>, _>::from_fn(width, height, |x, y| {
+                let p = rgb[(y * width + x) as usize];
+                Rgba([p[0], p[1], p[2], 1.0])
+            });
+            DynamicImage::ImageRgba32F(buffer)
         }
     };
 
     Ok((dynamic_image, orientation))
-}
\ No newline at end of file
+}
+
+// Many decoders pad three-color cameras with a 4x3 color matrix, so the channel count has to
+// come from the CFA itself rather than from the matrix size.
+fn color_channel_count(raw_image: &RawImage) -> usize {
+    match &raw_image.photometric {
+        RawPhotometricInterpretation::Cfa(config) => {
+            let cfa = &config.cfa;
+            let mut seen = [false; 4];
+            for row in 0..cfa.height {
+                for col in 0..cfa.width {
+                    if let Some(slot) = seen.get_mut(cfa.color_at(row, col)) {
+                        *slot = true;
+                    }
+                }
+            }
+            seen.iter().filter(|&&s| s).count()
+        }
+        _ => raw_image.cpp,
+    }
+}
+
+fn four_color_clip_level(raw_image: &RawImage) -> f32 {
+    let wb = raw_image.wb_coeffs;
+    let green_coeff = if wb[1].is_finite() && wb[1] > 0.0 {
+        wb[1]
+    } else {
+        1.0
+    };
+    wb.iter()
+        .filter(|c| c.is_finite() && **c > 0.0)
+        .map(|c| c / green_coeff)
+        .fold(f32::INFINITY, f32::min)
+        .min(1.0)
+}
+
+fn camera_rgb_to_output(
+    pixels: &mut [[f32; 3]],
+    raw_image: &RawImage,
+    dcp_profile: Option<&DcpProfile>,
+    dcp_tone_curve: bool,
+) {
+    if let Some(profile) = dcp_profile {
+        let camera_neutral = white_balance_multipliers(raw_image).map(|m| 1.0 / m);
+        profile.apply(pixels, camera_neutral, dcp_tone_curve);
+        return;
+    }
+
+    let Some(cam_to_srgb) = camera_to_srgb_matrix(raw_image) else {
+        log::warn!("No usable color matrix, leaving camera RGB untouched");
+        return;
+    };
+    pixels.par_iter_mut().for_each(|p| {
+        let rgb = cam_to_srgb * Vector3::new(p[0], p[1], p[2]);
+        *p = [rgb.x.max(0.0), rgb.y.max(0.0), rgb.z.max(0.0)];
+    });
+}
+
+fn xyz_to_camera_matrix(raw_image: &RawImage) -> Option<Matrix3<f32>> {
+    let xyz_to_cam = raw_image
+        .color_matrix
+        .get(&Illuminant::D65)
+        .or_else(|| raw_image.color_matrix.values().next())?;
+    if xyz_to_cam.len() < 9 {
+        return None;
+    }
+    Some(Matrix3::from_row_iterator(
+        xyz_to_cam.iter().take(9).map(|&v| v as f32),
+    ))
+}
+
+fn camera_to_srgb_matrix(raw_image: &RawImage) -> Option<Matrix3<f32>> {
+    camera_to_srgb_from_xyz_to_cam(&xyz_to_camera_matrix(raw_image)?)
+}
+
+fn four_color_to_srgb_matrix(raw_image: &RawImage) -> Option<SMatrix<f32, 3, 4>> {
+    if color_channel_count(raw_image) != 4 {
+        return None;
+    }
+    let xyz_to_cam = raw_image
+        .color_matrix
+        .get(&Illuminant::D65)
+        .or_else(|| raw_image.color_matrix.values().next())?;
+    if xyz_to_cam.len() < 12 {
+        return None;
+    }
+
+    let xyz_to_cam =
+        SMatrix::<f32, 4, 3>::from_row_iterator(xyz_to_cam.iter().take(12).map(|&v| v as f32));
+    let mut srgb_to_cam = xyz_to_cam * Matrix3::from_row_slice(&SRGB_TO_XYZ_D65);
+    for mut row in srgb_to_cam.row_iter_mut() {
+        let sum: f32 = row.iter().sum();
+        if sum.abs() > 1e-6 {
+            row /= sum;
+        }
+    }
+    let transposed = srgb_to_cam.transpose();
+    (transposed * srgb_to_cam)
+        .try_inverse()
+        .map(|inverse| inverse * transposed)
+}
+
+fn camera_to_srgb_from_xyz_to_cam(xyz_to_cam: &Matrix3<f32>) -> Option<Matrix3<f32>> {
+    let srgb_to_xyz = Matrix3::from_row_slice(&SRGB_TO_XYZ_D65);
+    let mut srgb_to_cam = xyz_to_cam * srgb_to_xyz;
+    for mut row in srgb_to_cam.row_iter_mut() {
+        let sum: f32 = row.iter().sum();
+        if sum.abs() > 1e-6 {
+            row /= sum;
+        }
+    }
+    srgb_to_cam.try_inverse()
+}
+
+fn develop_with_custom_demosaic(
+    raw_image: &RawImage,
+    method: DemosaicMethod,
+    black_level: f32,
+    white_level: f32,
+) -> Result<(Vec<[f32; 3]>, u32, u32)> {
+    let cfa = match &raw_image.photometric {
+        RawPhotometricInterpretation::Cfa(config) => &config.cfa,
+        _ => return Err(anyhow!("image has no color filter array")),
+    };
+    if raw_image.cpp != 1 {
+        return Err(anyhow!("expected one sample per pixel, got {}", raw_image.cpp));
+    }
+
+    let pattern = CfaPattern::from_fn(cfa.width, cfa.height, |row, col| cfa.color_at(row, col));
+    if !pattern.is_three_color() {
+        return Err(anyhow!("unsupported CFA pattern"));
+    }
+
+    let width = raw_image.width;
+    let height = raw_image.height;
+
+    let wb_multipliers = white_balance_multipliers(raw_image);
+
+    let range = (white_level - black_level).max(1.0);
+    let normalize = |i: usize, v: f32| {
+        let c = pattern.color_at(i / width, i % width);
+        ((v - black_level) / range).max(0.0) * wb_multipliers[c]
+    };
+    let plane: Vec<f32> = match &raw_image.data {
+        RawImageData::Integer(data) => data
+            .par_iter()
+            .enumerate()
+            .map(|(i, &v)| normalize(i, v as f32))
+            .collect(),
+        RawImageData::Float(data) => data
+            .par_iter()
+            .enumerate()
+            .map(|(i, &v)| normalize(i, v))
+            .collect(),
+    };
+
+    let demosaiced = match method {
+        DemosaicMethod::Rcd if pattern.is_bayer() => demosaic::rcd(&plane, width, height, &pattern),
+        DemosaicMethod::Lmmse if pattern.is_bayer() => {
+            demosaic::lmmse(&plane, width, height, &pattern)
+        }
+        _ => demosaic::gradient_weighted(&plane, width, height, &pattern),
+    };
+    drop(plane);
+
+    let (crop_x, crop_y, crop_w, crop_h) = raw_image
+        .crop_area
+        .as_ref()
+        .or(raw_image.active_area.as_ref())
+        .map(|rect| (rect.p.x, rect.p.y, rect.d.w, rect.d.h))
+        .unwrap_or((0, 0, width, height));
+    let crop_x = crop_x.min(width.saturating_sub(1));
+    let crop_y = crop_y.min(height.saturating_sub(1));
+    let crop_w = crop_w.min(width - crop_x).max(1);
+    let crop_h = crop_h.min(height - crop_y).max(1);
+
+    let mut output = vec![[0.0f32; 3]; crop_w * crop_h];
+    output
+        .par_chunks_mut(crop_w)
+        .enumerate()
+        .for_each(|(row, out_row)| {
+            let src_start = (row + crop_y) * width + crop_x;
+            out_row.copy_from_slice(&demosaiced[src_start..src_start + crop_w]);
+        });
+
+    Ok((output, crop_w as u32, crop_h as u32))
+}
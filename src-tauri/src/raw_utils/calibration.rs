@@ -0,0 +1,304 @@
+use crate::raw_utils::levels;
+use anyhow::{Result, anyhow};
+use once_cell::sync::{Lazy, OnceCell};
+use rawler::rawimage::{RawImage, RawImageData, RawPhotometricInterpretation};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+static CACHE_DIR: OnceCell<PathBuf> = OnceCell::new();
+static MASTER_FRAMES: Lazy<Mutex<HashMap<u64, Arc<MasterFrame>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CalibrationFrames {
+    #[serde(default)]
+    pub dark_frame_paths: Vec<String>,
+    #[serde(default)]
+    pub flat_field_paths: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum FrameKind {
+    Dark,
+    Flat,
+}
+
+pub struct MasterFrame {
+    width: usize,
+    height: usize,
+    data: Vec<f32>,
+}
+
+pub fn set_cache_dir(dir: PathBuf) {
+    let _ = CACHE_DIR.set(dir.join("calibration"));
+}
+
+fn paths_from_value(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::String(path)) if !path.is_empty() => vec![path.clone()],
+        Some(Value::Array(paths)) => paths
+            .iter()
+            .filter_map(|p| p.as_str())
+            .filter(|p| !p.is_empty())
+            .map(String::from)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+impl CalibrationFrames {
+    pub fn from_adjustments(adjustments: &Value) -> Self {
+        Self {
+            dark_frame_paths: paths_from_value(adjustments.get("darkFramePaths")),
+            flat_field_paths: paths_from_value(adjustments.get("flatFieldPaths")),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dark_frame_paths.is_empty() && self.flat_field_paths.is_empty()
+    }
+}
+
+fn hash_frame_files(paths: &[String], hasher: &mut DefaultHasher) {
+    let mut sorted: Vec<&String> = paths.iter().collect();
+    sorted.sort();
+    for path in sorted {
+        path.hash(hasher);
+        if let Ok(meta) = fs::metadata(path) {
+            meta.len().hash(hasher);
+            if let Ok(modified) = meta.modified() {
+                modified.hash(hasher);
+            }
+        }
+    }
+}
+
+fn cache_key(kind: FrameKind, paths: &[String]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    kind.hash(&mut hasher);
+    hash_frame_files(paths, &mut hasher);
+    hasher.finish()
+}
+
+// Changes whenever one of the referenced frame files is replaced on disk.
+pub fn frames_fingerprint(frames: &CalibrationFrames) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hash_frame_files(&frames.dark_frame_paths, &mut hasher);
+    hash_frame_files(&frames.flat_field_paths, &mut hasher);
+    hasher.finish()
+}
+
+fn read_cached_frame(path: &Path) -> Option<MasterFrame> {
+    let bytes = fs::read(path).ok()?;
+    if bytes.len() < 8 {
+        return None;
+    }
+    let width = u32::from_le_bytes(bytes[0..4].try_into().ok()?) as usize;
+    let height = u32::from_le_bytes(bytes[4..8].try_into().ok()?) as usize;
+    let data: Vec<f32> = bytes[8..]
+        .chunks_exact(4)
+        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect();
+    if data.len() != width * height {
+        return None;
+    }
+    Some(MasterFrame {
+        width,
+        height,
+        data,
+    })
+}
+
+fn write_cached_frame(path: &Path, frame: &MasterFrame) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut bytes = Vec::with_capacity(8 + frame.data.len() * 4);
+    bytes.extend_from_slice(&(frame.width as u32).to_le_bytes());
+    bytes.extend_from_slice(&(frame.height as u32).to_le_bytes());
+    for v in &frame.data {
+        bytes.extend_from_slice(&v.to_le_bytes());
+    }
+    fs::write(path, bytes)?;
+    Ok(())
+}
+
+fn frame_values(raw_image: &RawImage) -> Vec<f32> {
+    match &raw_image.data {
+        RawImageData::Integer(data) => data.iter().map(|&v| v as f32).collect(),
+        RawImageData::Float(data) => data.clone(),
+    }
+}
+
+fn cfa_dims(raw_image: &RawImage) -> (usize, usize) {
+    match &raw_image.photometric {
+        RawPhotometricInterpretation::Cfa(config) => {
+            (config.cfa.width.max(1), config.cfa.height.max(1))
+        }
+        _ => (1, 1),
+    }
+}
+
+fn build_master_frame(kind: FrameKind, paths: &[String]) -> Result<MasterFrame> {
+    let mut frames = Vec::with_capacity(paths.len());
+    let mut dims = None;
+    let mut pattern = (1, 1);
+    let mut black = 0.0;
+    for path in paths {
+        let (raw_image, frame_black, _) = levels::decode_equalized(path)?;
+        if raw_image.cpp != 1 {
+            return Err(anyhow!("calibration frame {} is not CFA data", path));
+        }
+        let frame_dims = (raw_image.width, raw_image.height);
+        if *dims.get_or_insert(frame_dims) != frame_dims {
+            return Err(anyhow!(
+                "calibration frame {} has mismatched dimensions",
+                path
+            ));
+        }
+        pattern = cfa_dims(&raw_image);
+        black = frame_black;
+        frames.push(frame_values(&raw_image));
+    }
+    let (width, height) = dims.ok_or_else(|| anyhow!("no calibration frames given"))?;
+
+    let mut data: Vec<f32> = (0..width * height)
+        .into_par_iter()
+        .map_init(
+            || Vec::with_capacity(frames.len()),
+            |samples, i| {
+                samples.clear();
+                samples.extend(frames.iter().map(|f| f[i]));
+                samples.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+                let mid = samples.len() / 2;
+                if samples.len() % 2 == 0 {
+                    0.5 * (samples[mid - 1] + samples[mid])
+                } else {
+                    samples[mid]
+                }
+            },
+        )
+        .collect();
+    drop(frames);
+
+    if kind == FrameKind::Flat {
+        let (pattern_w, pattern_h) = pattern;
+        let mut sums = vec![0.0f64; pattern_w * pattern_h];
+        let mut counts = vec![0u64; pattern_w * pattern_h];
+        for (i, v) in data.iter_mut().enumerate() {
+            *v = (*v - black).max(0.0);
+            let cell = ((i / width) % pattern_h) * pattern_w + (i % width) % pattern_w;
+            sums[cell] += *v as f64;
+            counts[cell] += 1;
+        }
+        let means: Vec<f32> = sums
+            .iter()
+            .zip(&counts)
+            .map(|(&s, &c)| if c > 0 { (s / c as f64) as f32 } else { 1.0 })
+            .collect();
+        data.par_iter_mut().enumerate().for_each(|(i, v)| {
+            let cell = ((i / width) % pattern_h) * pattern_w + (i % width) % pattern_w;
+            let mean = means[cell];
+            *v = if mean > 0.0 { *v / mean } else { 1.0 };
+        });
+    }
+
+    Ok(MasterFrame {
+        width,
+        height,
+        data,
+    })
+}
+
+fn get_master_frame(kind: FrameKind, paths: &[String]) -> Result<Arc<MasterFrame>> {
+    let key = cache_key(kind, paths);
+    if let Some(frame) = MASTER_FRAMES.lock().unwrap().get(&key) {
+        return Ok(frame.clone());
+    }
+
+    let cache_file = CACHE_DIR
+        .get()
+        .map(|dir| dir.join(format!("{:016x}.bin", key)));
+    let frame = match cache_file.as_deref().and_then(read_cached_frame) {
+        Some(frame) => frame,
+        None => {
+            let frame = build_master_frame(kind, paths)?;
+            if let Some(path) = &cache_file {
+                if let Err(e) = write_cached_frame(path, &frame) {
+                    log::warn!("Failed to cache master calibration frame: {}", e);
+                }
+            }
+            frame
+        }
+    };
+
+    let frame = Arc::new(frame);
+    MASTER_FRAMES.lock().unwrap().insert(key, frame.clone());
+    Ok(frame)
+}
+
+pub fn apply_calibration(
+    raw_image: &mut RawImage,
+    frames: &CalibrationFrames,
+    black_level: f32,
+) -> Result<()> {
+    if frames.is_empty() {
+        return Ok(());
+    }
+    if raw_image.cpp != 1 {
+        return Err(anyhow!("calibration frames require CFA data"));
+    }
+
+    let (width, height) = (raw_image.width, raw_image.height);
+    let load = |kind, paths: &[String]| -> Result<Option<Arc<MasterFrame>>> {
+        if paths.is_empty() {
+            return Ok(None);
+        }
+        let frame = get_master_frame(kind, paths)?;
+        if frame.width != width || frame.height != height {
+            return Err(anyhow!(
+                "calibration frame is {}x{}, image is {}x{}",
+                frame.width,
+                frame.height,
+                width,
+                height
+            ));
+        }
+        Ok(Some(frame))
+    };
+    let dark = load(FrameKind::Dark, &frames.dark_frame_paths)?;
+    let flat = load(FrameKind::Flat, &frames.flat_field_paths)?;
+
+    let correct = |i: usize, v: f32| {
+        let mut v = match &dark {
+            Some(dark) => v - dark.data[i] + black_level,
+            None => v,
+        };
+        if let Some(flat) = &flat {
+            let gain = flat.data[i];
+            if gain > 1e-3 {
+                v = black_level + (v - black_level) / gain;
+            }
+        }
+        v
+    };
+
+    match &mut raw_image.data {
+        RawImageData::Integer(data) => data.par_iter_mut().enumerate().for_each(|(i, v)| {
+            *v = correct(i, *v as f32).round().clamp(0.0, u16::MAX as f32) as u16;
+        }),
+        RawImageData::Float(data) => data
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(i, v)| *v = correct(i, *v).max(0.0)),
+    }
+    Ok(())
+}
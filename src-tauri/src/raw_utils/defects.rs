@@ -0,0 +1,323 @@
+use crate::raw_utils::demosaic::CfaPattern;
+use crate::raw_utils::levels;
+use anyhow::{Result, anyhow};
+use once_cell::sync::OnceCell;
+use rawler::rawimage::{RawImage, RawImageData, RawPhotometricInterpretation};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+const MIN_NEIGHBOURS: usize = 4;
+const NOISE_FLOOR: f32 = 0.01;
+const DEAD_MIN_MEDIAN: f32 = 0.05;
+const DEFECT_MAP_STRENGTH: f32 = 50.0;
+
+static DEFECT_MAP_DIR: OnceCell<PathBuf> = OnceCell::new();
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DefectMap {
+    pub make: String,
+    pub model: String,
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<(usize, usize)>,
+}
+
+pub fn set_data_dir(dir: PathBuf) {
+    let _ = DEFECT_MAP_DIR.set(dir.join("defect_maps"));
+}
+
+fn camera_key(raw_image: &RawImage) -> String {
+    format!("{}_{}", raw_image.camera.make, raw_image.camera.model)
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+fn defect_map_path(raw_image: &RawImage) -> Option<PathBuf> {
+    DEFECT_MAP_DIR
+        .get()
+        .map(|dir| dir.join(format!("{}.json", camera_key(raw_image))))
+}
+
+// The camera is unknown before decoding, so every stored map contributes to the fingerprint.
+pub fn defect_maps_fingerprint() -> u64 {
+    let Some(entries) = DEFECT_MAP_DIR.get().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return 0;
+    };
+    let mut hasher = DefaultHasher::new();
+    let mut files: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let meta = entry.metadata().ok()?;
+            Some((entry.file_name(), meta.len(), meta.modified().ok()))
+        })
+        .collect();
+    files.sort();
+    files.hash(&mut hasher);
+    hasher.finish()
+}
+
+pub fn load_defect_map(raw_image: &RawImage) -> Option<DefectMap> {
+    let content = fs::read_to_string(defect_map_path(raw_image)?).ok()?;
+    let map: DefectMap = serde_json::from_str(&content).ok()?;
+    if map.width != raw_image.width || map.height != raw_image.height {
+        return None;
+    }
+    Some(map)
+}
+
+fn save_defect_map(raw_image: &RawImage, pixels: Vec<(usize, usize)>) -> Result<()> {
+    let path = defect_map_path(raw_image).ok_or_else(|| anyhow!("defect map directory not set"))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let map = DefectMap {
+        make: raw_image.camera.make.clone(),
+        model: raw_image.camera.model.clone(),
+        width: raw_image.width,
+        height: raw_image.height,
+        pixels,
+    };
+    fs::write(path, serde_json::to_string(&map)?)?;
+    Ok(())
+}
+
+fn cfa_pattern(raw_image: &RawImage) -> Option<CfaPattern> {
+    if raw_image.cpp != 1 {
+        return None;
+    }
+    match &raw_image.photometric {
+        RawPhotometricInterpretation::Cfa(config) => {
+            let cfa = &config.cfa;
+            Some(CfaPattern::from_fn(cfa.width, cfa.height, |row, col| {
+                cfa.color_at(row, col)
+            }))
+        }
+        _ => None,
+    }
+}
+
+fn neighbour_offsets(pattern: &CfaPattern) -> Vec<Vec<(isize, isize)>> {
+    let (width, height) = pattern.dims();
+    let mut offsets = Vec::with_capacity(width * height);
+    for row in 0..height {
+        for col in 0..width {
+            let color = pattern.color_at(row, col);
+            let mut cell = Vec::new();
+            for radius in 2..=3isize {
+                cell.clear();
+                for dy in -radius..=radius {
+                    for dx in -radius..=radius {
+                        if dy == 0 && dx == 0 {
+                            continue;
+                        }
+                        let r = (row as isize + dy).rem_euclid(height as isize) as usize;
+                        let c = (col as isize + dx).rem_euclid(width as isize) as usize;
+                        if pattern.color_at(r, c) == color {
+                            cell.push((dy, dx));
+                        }
+                    }
+                }
+                if cell.len() >= MIN_NEIGHBOURS {
+                    break;
+                }
+            }
+            offsets.push(cell);
+        }
+    }
+    offsets
+}
+
+fn normalized_plane(raw_image: &RawImage, black_level: f32, white_level: f32) -> Vec<f32> {
+    let range = (white_level - black_level).max(1.0);
+    match &raw_image.data {
+        RawImageData::Integer(data) => data
+            .par_iter()
+            .map(|&v| (v as f32 - black_level) / range)
+            .collect(),
+        RawImageData::Float(data) => data
+            .par_iter()
+            .map(|&v| (v - black_level) / range)
+            .collect(),
+    }
+}
+
+fn detect(
+    plane: &[f32],
+    width: usize,
+    height: usize,
+    pattern: &CfaPattern,
+    strength: f32,
+) -> Vec<(usize, usize)> {
+    let offsets = neighbour_offsets(pattern);
+    let (pattern_w, pattern_h) = pattern.dims();
+    let sensitivity = 3.0 - 2.5 * (strength / 100.0).clamp(0.0, 1.0);
+
+    (0..height)
+        .into_par_iter()
+        .flat_map_iter(|row| {
+            let offsets = &offsets;
+            let mut neighbours: Vec<f32> = Vec::new();
+            (0..width).filter_map(move |col| {
+                let cell = &offsets[(row % pattern_h) * pattern_w + col % pattern_w];
+                neighbours.clear();
+                neighbours.extend(cell.iter().filter_map(|&(dy, dx)| {
+                    let r = row as isize + dy;
+                    let c = col as isize + dx;
+                    if r < 0 || c < 0 || r >= height as isize || c >= width as isize {
+                        None
+                    } else {
+                        Some(plane[r as usize * width + c as usize])
+                    }
+                }));
+                if neighbours.len() < MIN_NEIGHBOURS {
+                    return None;
+                }
+                neighbours.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+                let value = plane[row * width + col];
+                let min = neighbours[0];
+                let max = neighbours[neighbours.len() - 1];
+                let median = neighbours[neighbours.len() / 2];
+                let spread = (max - min).max(0.0) + NOISE_FLOOR;
+
+                let hot = value > max && value - median > sensitivity * spread;
+                let dead = value < min
+                    && median > DEAD_MIN_MEDIAN
+                    && median - value > sensitivity * spread;
+                (hot || dead).then_some((row, col))
+            })
+        })
+        .collect()
+}
+
+fn remap(raw_image: &mut RawImage, pattern: &CfaPattern, pixels: &HashSet<(usize, usize)>) {
+    let (width, height) = (raw_image.width, raw_image.height);
+    let offsets = neighbour_offsets(pattern);
+    let (pattern_w, pattern_h) = pattern.dims();
+
+    let sample = |data: &RawImageData, idx: usize| match data {
+        RawImageData::Integer(values) => values[idx] as f32,
+        RawImageData::Float(values) => values[idx],
+    };
+
+    let replacements: Vec<(usize, f32)> = pixels
+        .iter()
+        .filter(|&&(row, col)| row < height && col < width)
+        .filter_map(|&(row, col)| {
+            let cell = &offsets[(row % pattern_h) * pattern_w + col % pattern_w];
+            let mut neighbours: Vec<f32> = cell
+                .iter()
+                .filter_map(|&(dy, dx)| {
+                    let r = row as isize + dy;
+                    let c = col as isize + dx;
+                    if r < 0 || c < 0 || r >= height as isize || c >= width as isize {
+                        return None;
+                    }
+                    let (r, c) = (r as usize, c as usize);
+                    if pixels.contains(&(r, c)) {
+                        return None;
+                    }
+                    Some(sample(&raw_image.data, r * width + c))
+                })
+                .collect();
+            if neighbours.is_empty() {
+                return None;
+            }
+            neighbours.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+            Some((row * width + col, neighbours[neighbours.len() / 2]))
+        })
+        .collect();
+
+    match &mut raw_image.data {
+        RawImageData::Integer(data) => {
+            for (idx, value) in replacements {
+                data[idx] = value.round().clamp(0.0, u16::MAX as f32) as u16;
+            }
+        }
+        RawImageData::Float(data) => {
+            for (idx, value) in replacements {
+                data[idx] = value;
+            }
+        }
+    }
+}
+
+pub fn correct_defects(
+    raw_image: &mut RawImage,
+    strength: f32,
+    use_defect_map: bool,
+    black_level: f32,
+    white_level: f32,
+) -> usize {
+    let Some(pattern) = cfa_pattern(raw_image) else {
+        return 0;
+    };
+
+    let mut pixels: HashSet<(usize, usize)> = HashSet::new();
+    if use_defect_map {
+        if let Some(map) = load_defect_map(raw_image) {
+            pixels.extend(map.pixels);
+        }
+    }
+    if strength > 0.0 {
+        let plane = normalized_plane(raw_image, black_level, white_level);
+        pixels.extend(detect(
+            &plane,
+            raw_image.width,
+            raw_image.height,
+            &pattern,
+            strength,
+        ));
+    }
+
+    if !pixels.is_empty() {
+        remap(raw_image, &pattern, &pixels);
+    }
+    pixels.len()
+}
+
+pub fn build_defect_map(paths: &[String]) -> Result<usize> {
+    let mut persistent: Option<HashSet<(usize, usize)>> = None;
+    let mut reference: Option<RawImage> = None;
+
+    for path in paths {
+        let (raw_image, black_level, white_level) = levels::decode_equalized(path)?;
+        let pattern =
+            cfa_pattern(&raw_image).ok_or_else(|| anyhow!("{} does not contain CFA data", path))?;
+        if let Some(reference) = &reference {
+            if reference.width != raw_image.width || reference.height != raw_image.height {
+                return Err(anyhow!("{} has mismatched dimensions", path));
+            }
+        }
+        let plane = normalized_plane(&raw_image, black_level, white_level);
+        let detected: HashSet<(usize, usize)> = detect(
+            &plane,
+            raw_image.width,
+            raw_image.height,
+            &pattern,
+            DEFECT_MAP_STRENGTH,
+        )
+        .into_iter()
+        .collect();
+
+        persistent = Some(match persistent {
+            Some(previous) => previous.intersection(&detected).copied().collect(),
+            None => detected,
+        });
+        reference = Some(raw_image);
+    }
+
+    let reference = reference.ok_or_else(|| anyhow!("no frames given"))?;
+    let mut pixels: Vec<(usize, usize)> = persistent.unwrap_or_default().into_iter().collect();
+    pixels.sort_unstable();
+    let count = pixels.len();
+    save_defect_map(&reference, pixels)?;
+    Ok(count)
+}
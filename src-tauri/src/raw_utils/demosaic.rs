@@ -0,0 +1,825 @@
+use rayon::prelude::*;
+
+const EPS: f32 = 1e-5;
+const EPS_SQ: f32 = 1e-10;
+const WEIGHT_EPS: f32 = 1e-3;
+const RCD_BORDER: usize = 4;
+const LMMSE_BORDER: usize = 10;
+const LMMSE_RADIUS: isize = 4;
+const LMMSE_SIGMA: f32 = 2.0;
+const GREEN_DIRECTIONS: [(isize, isize); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+
+pub struct CfaPattern {
+    width: usize,
+    height: usize,
+    colors: Vec<usize>,
+}
+
+impl CfaPattern {
+    pub fn from_fn(width: usize, height: usize, color_at: impl Fn(usize, usize) -> usize) -> Self {
+        let width = width.max(1);
+        let height = height.max(1);
+        let colors = (0..width * height)
+            .map(|i| color_at(i / width, i % width))
+            .collect();
+        Self {
+            width,
+            height,
+            colors,
+        }
+    }
+
+    pub fn dims(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    #[inline]
+    pub fn color_at(&self, row: usize, col: usize) -> usize {
+        self.colors[(row % self.height) * self.width + (col % self.width)]
+    }
+
+    pub fn is_three_color(&self) -> bool {
+        let mut seen = [false; 3];
+        for &c in &self.colors {
+            if c > 2 {
+                return false;
+            }
+            seen[c] = true;
+        }
+        seen.iter().all(|&s| s)
+    }
+
+    pub fn is_bayer(&self) -> bool {
+        if self.width % 2 != 0 || self.height % 2 != 0 || !self.is_three_color() {
+            return false;
+        }
+        let repeats = (0..self.height).all(|row| {
+            (0..self.width).all(|col| self.color_at(row, col) == self.color_at(row % 2, col % 2))
+        });
+        let mut counts = [0; 3];
+        for row in 0..2 {
+            for col in 0..2 {
+                counts[self.color_at(row, col)] += 1;
+            }
+        }
+        let greens_on_diagonal = (self.color_at(0, 0) == 1) == (self.color_at(1, 1) == 1);
+        repeats && counts == [1, 2, 1] && greens_on_diagonal
+    }
+}
+
+#[inline]
+fn sqr(v: f32) -> f32 {
+    v * v
+}
+
+#[inline]
+fn discriminate(central: f32, neighbourhood: f32) -> f32 {
+    if (0.5 - central).abs() < (0.5 - neighbourhood).abs() {
+        neighbourhood
+    } else {
+        central
+    }
+}
+
+fn split_planes(cfa: &[f32], width: usize, pattern: &CfaPattern) -> [Vec<f32>; 3] {
+    let mut planes = [
+        vec![0.0f32; cfa.len()],
+        vec![0.0f32; cfa.len()],
+        vec![0.0f32; cfa.len()],
+    ];
+    for (i, &v) in cfa.iter().enumerate() {
+        planes[pattern.color_at(i / width, i % width)][i] = v;
+    }
+    planes
+}
+
+fn interleave(planes: &[Vec<f32>; 3]) -> Vec<[f32; 3]> {
+    planes[0]
+        .par_iter()
+        .zip(planes[1].par_iter())
+        .zip(planes[2].par_iter())
+        .map(|((&r, &g), &b)| [r, g, b])
+        .collect()
+}
+
+fn interpolate_average(
+    cfa: &[f32],
+    width: usize,
+    height: usize,
+    pattern: &CfaPattern,
+    row: usize,
+    col: usize,
+) -> [f32; 3] {
+    let own = pattern.color_at(row, col);
+    let mut result = [0.0f32; 3];
+    result[own] = cfa[row * width + col];
+
+    let mut sums = [0.0f32; 3];
+    let mut counts = [0u32; 3];
+    for radius in 1..=2isize {
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                if dx.abs() != radius && dy.abs() != radius {
+                    continue;
+                }
+                let y = row as isize + dy;
+                let x = col as isize + dx;
+                if y < 0 || x < 0 || y >= height as isize || x >= width as isize {
+                    continue;
+                }
+                let c = pattern.color_at(y as usize, x as usize);
+                sums[c] += cfa[y as usize * width + x as usize];
+                counts[c] += 1;
+            }
+        }
+        if (0..3).all(|c| c == own || counts[c] > 0) {
+            break;
+        }
+    }
+
+    for c in 0..3 {
+        if c != own && counts[c] > 0 {
+            result[c] = sums[c] / counts[c] as f32;
+        }
+    }
+    result
+}
+
+fn fill_border(
+    planes: &mut [Vec<f32>; 3],
+    cfa: &[f32],
+    width: usize,
+    height: usize,
+    pattern: &CfaPattern,
+    border: usize,
+) {
+    let mut fill = |row: usize, col: usize| {
+        let p = interpolate_average(cfa, width, height, pattern, row, col);
+        let i = row * width + col;
+        planes[0][i] = p[0];
+        planes[1][i] = p[1];
+        planes[2][i] = p[2];
+    };
+
+    for row in 0..height {
+        if row < border || row + border >= height {
+            for col in 0..width {
+                fill(row, col);
+            }
+        } else {
+            for col in (0..border.min(width)).chain(width.saturating_sub(border).max(border)..width)
+            {
+                fill(row, col);
+            }
+        }
+    }
+}
+
+pub fn rcd(cfa: &[f32], width: usize, height: usize, pattern: &CfaPattern) -> Vec<[f32; 3]> {
+    let size = width * height;
+    let mut planes = split_planes(cfa, width, pattern);
+    if width < 2 * RCD_BORDER + 1 || height < 2 * RCD_BORDER + 1 {
+        fill_border(&mut planes, cfa, width, height, pattern, width.max(height));
+        return interleave(&planes);
+    }
+
+    let (w1, w2, w3, w4) = (width, 2 * width, 3 * width, 4 * width);
+    let border_rows = |row: usize, border: usize| row >= border && row < height - border;
+
+    let mut hpf_v = vec![0.0f32; size];
+    let mut hpf_h = vec![0.0f32; size];
+    hpf_v
+        .par_chunks_mut(width)
+        .zip(hpf_h.par_chunks_mut(width))
+        .enumerate()
+        .filter(|(row, _)| border_rows(*row, 3))
+        .for_each(|(row, (v_row, h_row))| {
+            for col in 3..width - 3 {
+                let i = row * width + col;
+                v_row[col] = sqr((cfa[i - w3] - cfa[i - w1] - cfa[i + w1] + cfa[i + w3])
+                    - 3.0 * (cfa[i - w2] + cfa[i + w2])
+                    + 6.0 * cfa[i]);
+                h_row[col] = sqr((cfa[i - 3] - cfa[i - 1] - cfa[i + 1] + cfa[i + 3])
+                    - 3.0 * (cfa[i - 2] + cfa[i + 2])
+                    + 6.0 * cfa[i]);
+            }
+        });
+
+    let mut vh_dir = vec![0.5f32; size];
+    vh_dir
+        .par_chunks_mut(width)
+        .enumerate()
+        .filter(|(row, _)| border_rows(*row, RCD_BORDER))
+        .for_each(|(row, dir_row)| {
+            for col in RCD_BORDER..width - RCD_BORDER {
+                let i = row * width + col;
+                let v_stat = (hpf_v[i - w1] + hpf_v[i] + hpf_v[i + w1]).max(EPS_SQ);
+                let h_stat = (hpf_h[i - 1] + hpf_h[i] + hpf_h[i + 1]).max(EPS_SQ);
+                dir_row[col] = v_stat / (v_stat + h_stat);
+            }
+        });
+    drop(hpf_v);
+    drop(hpf_h);
+
+    let mut lpf = vec![0.0f32; size];
+    lpf.par_chunks_mut(width)
+        .enumerate()
+        .filter(|(row, _)| border_rows(*row, 2))
+        .for_each(|(row, lpf_row)| {
+            for col in 2..width - 2 {
+                if pattern.color_at(row, col) == 1 {
+                    continue;
+                }
+                let i = row * width + col;
+                lpf_row[col] = 0.25 * cfa[i]
+                    + 0.125 * (cfa[i - w1] + cfa[i + w1] + cfa[i - 1] + cfa[i + 1])
+                    + 0.0625
+                        * (cfa[i - w1 - 1] + cfa[i - w1 + 1] + cfa[i + w1 - 1] + cfa[i + w1 + 1]);
+            }
+        });
+
+    planes[1]
+        .par_chunks_mut(width)
+        .enumerate()
+        .filter(|(row, _)| border_rows(*row, RCD_BORDER))
+        .for_each(|(row, green_row)| {
+            for col in RCD_BORDER..width - RCD_BORDER {
+                if pattern.color_at(row, col) == 1 {
+                    continue;
+                }
+                let i = row * width + col;
+
+                let n_grad = EPS
+                    + (cfa[i - w1] - cfa[i + w1]).abs()
+                    + (cfa[i] - cfa[i - w2]).abs()
+                    + (cfa[i - w1] - cfa[i - w3]).abs()
+                    + (cfa[i - w2] - cfa[i - w4]).abs();
+                let s_grad = EPS
+                    + (cfa[i - w1] - cfa[i + w1]).abs()
+                    + (cfa[i] - cfa[i + w2]).abs()
+                    + (cfa[i + w1] - cfa[i + w3]).abs()
+                    + (cfa[i + w2] - cfa[i + w4]).abs();
+                let w_grad = EPS
+                    + (cfa[i - 1] - cfa[i + 1]).abs()
+                    + (cfa[i] - cfa[i - 2]).abs()
+                    + (cfa[i - 1] - cfa[i - 3]).abs()
+                    + (cfa[i - 2] - cfa[i - 4]).abs();
+                let e_grad = EPS
+                    + (cfa[i - 1] - cfa[i + 1]).abs()
+                    + (cfa[i] - cfa[i + 2]).abs()
+                    + (cfa[i + 1] - cfa[i + 3]).abs()
+                    + (cfa[i + 2] - cfa[i + 4]).abs();
+
+                let ratio =
+                    |other: usize| 1.0 + (lpf[i] - lpf[other]) / (EPS + lpf[i] + lpf[other]);
+                let n_est = cfa[i - w1] * ratio(i - w2);
+                let s_est = cfa[i + w1] * ratio(i + w2);
+                let w_est = cfa[i - 1] * ratio(i - 2);
+                let e_est = cfa[i + 1] * ratio(i + 2);
+
+                let v_est = (s_grad * n_est + n_grad * s_est) / (n_grad + s_grad);
+                let h_est = (w_grad * e_est + e_grad * w_est) / (e_grad + w_grad);
+
+                let vh_disc = discriminate(
+                    vh_dir[i],
+                    0.25 * (vh_dir[i - w1 - 1]
+                        + vh_dir[i - w1 + 1]
+                        + vh_dir[i + w1 - 1]
+                        + vh_dir[i + w1 + 1]),
+                );
+                green_row[col] = (vh_disc * h_est + (1.0 - vh_disc) * v_est).max(0.0);
+            }
+        });
+    drop(lpf);
+
+    fill_border(&mut planes, cfa, width, height, pattern, RCD_BORDER);
+
+    let mut hpf_p = vec![0.0f32; size];
+    let mut hpf_q = vec![0.0f32; size];
+    hpf_p
+        .par_chunks_mut(width)
+        .zip(hpf_q.par_chunks_mut(width))
+        .enumerate()
+        .filter(|(row, _)| border_rows(*row, 3))
+        .for_each(|(row, (p_row, q_row))| {
+            for col in 3..width - 3 {
+                let i = row * width + col;
+                p_row[col] = sqr((cfa[i - w3 - 3] - cfa[i - w1 - 1] - cfa[i + w1 + 1]
+                    + cfa[i + w3 + 3])
+                    - 3.0 * (cfa[i - w2 - 2] + cfa[i + w2 + 2])
+                    + 6.0 * cfa[i]);
+                q_row[col] = sqr((cfa[i - w3 + 3] - cfa[i - w1 + 1] - cfa[i + w1 - 1]
+                    + cfa[i + w3 - 3])
+                    - 3.0 * (cfa[i - w2 + 2] + cfa[i + w2 - 2])
+                    + 6.0 * cfa[i]);
+            }
+        });
+
+    let mut pq_dir = vec![0.5f32; size];
+    pq_dir
+        .par_chunks_mut(width)
+        .enumerate()
+        .filter(|(row, _)| border_rows(*row, RCD_BORDER))
+        .for_each(|(row, dir_row)| {
+            for col in RCD_BORDER..width - RCD_BORDER {
+                if pattern.color_at(row, col) == 1 {
+                    continue;
+                }
+                let i = row * width + col;
+                let p_stat = (hpf_p[i - w1 - 1] + hpf_p[i] + hpf_p[i + w1 + 1]).max(EPS_SQ);
+                let q_stat = (hpf_q[i - w1 + 1] + hpf_q[i] + hpf_q[i + w1 - 1]).max(EPS_SQ);
+                dir_row[col] = p_stat / (p_stat + q_stat);
+            }
+        });
+    drop(hpf_p);
+    drop(hpf_q);
+
+    let [red, green, blue] = planes;
+    let mut new_red = red.clone();
+    let mut new_blue = blue.clone();
+    new_red
+        .par_chunks_mut(width)
+        .zip(new_blue.par_chunks_mut(width))
+        .enumerate()
+        .filter(|(row, _)| border_rows(*row, RCD_BORDER))
+        .for_each(|(row, (r_row, b_row))| {
+            for col in RCD_BORDER..width - RCD_BORDER {
+                let own = pattern.color_at(row, col);
+                if own == 1 {
+                    continue;
+                }
+                let i = row * width + col;
+                let (c, out_row) = if own == 0 {
+                    (&blue, &mut *b_row)
+                } else {
+                    (&red, &mut *r_row)
+                };
+
+                let pq_disc = discriminate(
+                    pq_dir[i],
+                    0.25 * (pq_dir[i - w1 - 1]
+                        + pq_dir[i - w1 + 1]
+                        + pq_dir[i + w1 - 1]
+                        + pq_dir[i + w1 + 1]),
+                );
+
+                let nw_grad = EPS
+                    + (c[i - w1 - 1] - c[i + w1 + 1]).abs()
+                    + (c[i - w1 - 1] - c[i - w3 - 3]).abs()
+                    + (green[i] - green[i - w2 - 2]).abs();
+                let ne_grad = EPS
+                    + (c[i - w1 + 1] - c[i + w1 - 1]).abs()
+                    + (c[i - w1 + 1] - c[i - w3 + 3]).abs()
+                    + (green[i] - green[i - w2 + 2]).abs();
+                let sw_grad = EPS
+                    + (c[i + w1 - 1] - c[i - w1 + 1]).abs()
+                    + (c[i + w1 - 1] - c[i + w3 - 3]).abs()
+                    + (green[i] - green[i + w2 - 2]).abs();
+                let se_grad = EPS
+                    + (c[i + w1 + 1] - c[i - w1 - 1]).abs()
+                    + (c[i + w1 + 1] - c[i + w3 + 3]).abs()
+                    + (green[i] - green[i + w2 + 2]).abs();
+
+                let nw_est = c[i - w1 - 1] - green[i - w1 - 1];
+                let ne_est = c[i - w1 + 1] - green[i - w1 + 1];
+                let sw_est = c[i + w1 - 1] - green[i + w1 - 1];
+                let se_est = c[i + w1 + 1] - green[i + w1 + 1];
+
+                let p_est = (nw_grad * se_est + se_grad * nw_est) / (nw_grad + se_grad);
+                let q_est = (ne_grad * sw_est + sw_grad * ne_est) / (ne_grad + sw_grad);
+
+                out_row[col] = (green[i] + (1.0 - pq_disc) * p_est + pq_disc * q_est).max(0.0);
+            }
+        });
+    drop(pq_dir);
+
+    let red = new_red;
+    let blue = new_blue;
+    let mut new_red = red.clone();
+    let mut new_blue = blue.clone();
+    new_red
+        .par_chunks_mut(width)
+        .zip(new_blue.par_chunks_mut(width))
+        .enumerate()
+        .filter(|(row, _)| border_rows(*row, RCD_BORDER))
+        .for_each(|(row, (r_row, b_row))| {
+            for col in RCD_BORDER..width - RCD_BORDER {
+                if pattern.color_at(row, col) != 1 {
+                    continue;
+                }
+                let i = row * width + col;
+                let vh_disc = discriminate(
+                    vh_dir[i],
+                    0.25 * (vh_dir[i - w1 - 1]
+                        + vh_dir[i - w1 + 1]
+                        + vh_dir[i + w1 - 1]
+                        + vh_dir[i + w1 + 1]),
+                );
+
+                let estimate = |c: &[f32]| {
+                    let n_grad = EPS
+                        + (green[i] - green[i - w2]).abs()
+                        + (c[i - w1] - c[i + w1]).abs()
+                        + (c[i - w1] - c[i - w3]).abs();
+                    let s_grad = EPS
+                        + (green[i] - green[i + w2]).abs()
+                        + (c[i + w1] - c[i - w1]).abs()
+                        + (c[i + w1] - c[i + w3]).abs();
+                    let w_grad = EPS
+                        + (green[i] - green[i - 2]).abs()
+                        + (c[i - 1] - c[i + 1]).abs()
+                        + (c[i - 1] - c[i - 3]).abs();
+                    let e_grad = EPS
+                        + (green[i] - green[i + 2]).abs()
+                        + (c[i + 1] - c[i - 1]).abs()
+                        + (c[i + 1] - c[i + 3]).abs();
+
+                    let n_est = c[i - w1] - green[i - w1];
+                    let s_est = c[i + w1] - green[i + w1];
+                    let w_est = c[i - 1] - green[i - 1];
+                    let e_est = c[i + 1] - green[i + 1];
+
+                    let v_est = (s_grad * n_est + n_grad * s_est) / (n_grad + s_grad);
+                    let h_est = (w_grad * e_est + e_grad * w_est) / (e_grad + w_grad);
+
+                    (green[i] + (1.0 - vh_disc) * v_est + vh_disc * h_est).max(0.0)
+                };
+
+                r_row[col] = estimate(&red);
+                b_row[col] = estimate(&blue);
+            }
+        });
+
+    interleave(&[new_red, green, new_blue])
+}
+
+fn gaussian_kernel() -> [f32; (2 * LMMSE_RADIUS + 1) as usize] {
+    let mut kernel = [0.0f32; (2 * LMMSE_RADIUS + 1) as usize];
+    for k in -LMMSE_RADIUS..=LMMSE_RADIUS {
+        kernel[(k + LMMSE_RADIUS) as usize] =
+            (-((k * k) as f32) / (2.0 * LMMSE_SIGMA * LMMSE_SIGMA)).exp();
+    }
+    let sum: f32 = kernel.iter().sum();
+    kernel.iter_mut().for_each(|k| *k /= sum);
+    kernel
+}
+
+fn lmmse_estimate(signal: impl Fn(isize) -> f32, noisy: impl Fn(isize) -> f32) -> (f32, f32) {
+    let n = (2 * LMMSE_RADIUS + 1) as f32;
+    let mean = (-LMMSE_RADIUS..=LMMSE_RADIUS).map(&signal).sum::<f32>() / n;
+    let var_signal = (-LMMSE_RADIUS..=LMMSE_RADIUS)
+        .map(|k| sqr(signal(k) - mean))
+        .sum::<f32>()
+        / n
+        + EPS_SQ;
+    let var_noise = (-LMMSE_RADIUS..=LMMSE_RADIUS)
+        .map(|k| sqr(signal(k) - noisy(k)))
+        .sum::<f32>()
+        / n
+        + EPS_SQ;
+
+    let gain = var_signal / (var_signal + var_noise);
+    let estimate = mean + gain * (noisy(0) - mean);
+    let error = var_signal * var_noise / (var_signal + var_noise);
+    (estimate, error)
+}
+
+pub fn lmmse(cfa: &[f32], width: usize, height: usize, pattern: &CfaPattern) -> Vec<[f32; 3]> {
+    let size = width * height;
+    let mut planes = split_planes(cfa, width, pattern);
+    if width < 2 * LMMSE_BORDER + 1 || height < 2 * LMMSE_BORDER + 1 {
+        fill_border(&mut planes, cfa, width, height, pattern, width.max(height));
+        return interleave(&planes);
+    }
+
+    let (w1, w2) = (width, 2 * width);
+    let border_rows = |row: usize, border: usize| row >= border && row < height - border;
+
+    let mut diff_h = vec![0.0f32; size];
+    let mut diff_v = vec![0.0f32; size];
+    diff_h
+        .par_chunks_mut(width)
+        .zip(diff_v.par_chunks_mut(width))
+        .enumerate()
+        .filter(|(row, _)| border_rows(*row, 2))
+        .for_each(|(row, (h_row, v_row))| {
+            for col in 2..width - 2 {
+                let i = row * width + col;
+                let est_h = 0.5 * (cfa[i - 1] + cfa[i + 1])
+                    + 0.25 * (2.0 * cfa[i] - cfa[i - 2] - cfa[i + 2]);
+                let est_v = 0.5 * (cfa[i - w1] + cfa[i + w1])
+                    + 0.25 * (2.0 * cfa[i] - cfa[i - w2] - cfa[i + w2]);
+                if pattern.color_at(row, col) == 1 {
+                    h_row[col] = cfa[i] - est_h;
+                    v_row[col] = cfa[i] - est_v;
+                } else {
+                    h_row[col] = est_h - cfa[i];
+                    v_row[col] = est_v - cfa[i];
+                }
+            }
+        });
+
+    let kernel = gaussian_kernel();
+    let radius = LMMSE_RADIUS as usize;
+    let mut smooth_h = vec![0.0f32; size];
+    let mut smooth_v = vec![0.0f32; size];
+    smooth_h
+        .par_chunks_mut(width)
+        .zip(smooth_v.par_chunks_mut(width))
+        .enumerate()
+        .filter(|(row, _)| border_rows(*row, radius + 2))
+        .for_each(|(row, (h_row, v_row))| {
+            for col in radius + 2..width - radius - 2 {
+                let i = row * width + col;
+                let mut sum_h = 0.0;
+                let mut sum_v = 0.0;
+                for (k, weight) in kernel.iter().enumerate() {
+                    sum_h += weight * diff_h[i + k - radius];
+                    sum_v += weight * diff_v[i + k * width - radius * width];
+                }
+                h_row[col] = sum_h;
+                v_row[col] = sum_v;
+            }
+        });
+
+    planes[1]
+        .par_chunks_mut(width)
+        .enumerate()
+        .filter(|(row, _)| border_rows(*row, LMMSE_BORDER))
+        .for_each(|(row, green_row)| {
+            for col in LMMSE_BORDER..width - LMMSE_BORDER {
+                if pattern.color_at(row, col) == 1 {
+                    continue;
+                }
+                let i = row * width + col;
+                let offset_h = |k: isize| (i as isize + k) as usize;
+                let offset_v = |k: isize| (i as isize + k * width as isize) as usize;
+
+                let (est_h, err_h) =
+                    lmmse_estimate(|k| smooth_h[offset_h(k)], |k| diff_h[offset_h(k)]);
+                let (est_v, err_v) =
+                    lmmse_estimate(|k| smooth_v[offset_v(k)], |k| diff_v[offset_v(k)]);
+
+                let diff = (err_v * est_h + err_h * est_v) / (err_h + err_v);
+                green_row[col] = (cfa[i] + diff).max(0.0);
+            }
+        });
+    drop(diff_h);
+    drop(diff_v);
+    drop(smooth_h);
+    drop(smooth_v);
+
+    fill_border(&mut planes, cfa, width, height, pattern, LMMSE_BORDER);
+
+    let [red, green, blue] = planes;
+    let mut new_red = red.clone();
+    let mut new_blue = blue.clone();
+    new_red
+        .par_chunks_mut(width)
+        .zip(new_blue.par_chunks_mut(width))
+        .enumerate()
+        .filter(|(row, _)| border_rows(*row, LMMSE_BORDER))
+        .for_each(|(row, (r_row, b_row))| {
+            for col in LMMSE_BORDER..width - LMMSE_BORDER {
+                let own = pattern.color_at(row, col);
+                if own == 1 {
+                    continue;
+                }
+                let i = row * width + col;
+                let (c, out_row) = if own == 0 {
+                    (&blue, &mut *b_row)
+                } else {
+                    (&red, &mut *r_row)
+                };
+                let diff = 0.25
+                    * ((green[i - w1 - 1] - c[i - w1 - 1])
+                        + (green[i - w1 + 1] - c[i - w1 + 1])
+                        + (green[i + w1 - 1] - c[i + w1 - 1])
+                        + (green[i + w1 + 1] - c[i + w1 + 1]));
+                out_row[col] = (green[i] - diff).max(0.0);
+            }
+        });
+
+    let red = new_red;
+    let blue = new_blue;
+    let mut new_red = red.clone();
+    let mut new_blue = blue.clone();
+    new_red
+        .par_chunks_mut(width)
+        .zip(new_blue.par_chunks_mut(width))
+        .enumerate()
+        .filter(|(row, _)| border_rows(*row, LMMSE_BORDER))
+        .for_each(|(row, (r_row, b_row))| {
+            for col in LMMSE_BORDER..width - LMMSE_BORDER {
+                if pattern.color_at(row, col) != 1 {
+                    continue;
+                }
+                let i = row * width + col;
+                let estimate = |c: &[f32]| {
+                    let diff = 0.25
+                        * ((green[i - w1] - c[i - w1])
+                            + (green[i + w1] - c[i + w1])
+                            + (green[i - 1] - c[i - 1])
+                            + (green[i + 1] - c[i + 1]));
+                    (green[i] - diff).max(0.0)
+                };
+                r_row[col] = estimate(&red);
+                b_row[col] = estimate(&blue);
+            }
+        });
+
+    interleave(&[new_red, green, new_blue])
+}
+
+fn find_green_along(
+    cfa: &[f32],
+    width: usize,
+    height: usize,
+    pattern: &CfaPattern,
+    row: usize,
+    col: usize,
+    (dx, dy): (isize, isize),
+) -> Option<(f32, f32)> {
+    for step in 1..=2isize {
+        let x = col as isize + dx * step;
+        let y = row as isize + dy * step;
+        if x < 0 || y < 0 || x >= width as isize || y >= height as isize {
+            return None;
+        }
+        if pattern.color_at(y as usize, x as usize) == 1 {
+            return Some((cfa[y as usize * width + x as usize], step as f32));
+        }
+    }
+    None
+}
+
+// Works on any three-color CFA, X-Trans included: green is interpolated along
+// the smoothest of four directions, then red and blue from color differences
+// weighted by green similarity and distance.
+pub fn gradient_weighted(
+    cfa: &[f32],
+    width: usize,
+    height: usize,
+    pattern: &CfaPattern,
+) -> Vec<[f32; 3]> {
+    let mut green = vec![0.0f32; width * height];
+    green
+        .par_chunks_mut(width)
+        .enumerate()
+        .for_each(|(row, green_row)| {
+            for col in 0..width {
+                let i = row * width + col;
+                if pattern.color_at(row, col) == 1 {
+                    green_row[col] = cfa[i];
+                    continue;
+                }
+
+                let mut weighted_sum = 0.0f32;
+                let mut weight_total = 0.0f32;
+                for &(dx, dy) in GREEN_DIRECTIONS.iter() {
+                    let forward = find_green_along(cfa, width, height, pattern, row, col, (dx, dy));
+                    let backward =
+                        find_green_along(cfa, width, height, pattern, row, col, (-dx, -dy));
+                    if let (Some((g_f, d_f)), Some((g_b, d_b))) = (forward, backward) {
+                        let estimate = (g_f * d_b + g_b * d_f) / (d_f + d_b);
+                        let gradient = (g_f - g_b).abs() / (d_f + d_b);
+                        let weight = 1.0 / sqr(gradient + WEIGHT_EPS);
+                        weighted_sum += estimate * weight;
+                        weight_total += weight;
+                    }
+                }
+
+                green_row[col] = if weight_total > 0.0 {
+                    weighted_sum / weight_total
+                } else {
+                    interpolate_average(cfa, width, height, pattern, row, col)[1]
+                };
+            }
+        });
+
+    let mut output = vec![[0.0f32; 3]; width * height];
+    output
+        .par_chunks_mut(width)
+        .enumerate()
+        .for_each(|(row, out_row)| {
+            for col in 0..width {
+                let i = row * width + col;
+                let own = pattern.color_at(row, col);
+                let g = green[i];
+                let mut pixel = [0.0f32; 3];
+                pixel[1] = g;
+                pixel[own] = cfa[i];
+
+                for c in [0usize, 2] {
+                    if c == own {
+                        continue;
+                    }
+                    let mut weighted_diff = 0.0f32;
+                    let mut weight_total = 0.0f32;
+                    for dy in -2isize..=2 {
+                        for dx in -2isize..=2 {
+                            let y = row as isize + dy;
+                            let x = col as isize + dx;
+                            if y < 0 || x < 0 || y >= height as isize || x >= width as isize {
+                                continue;
+                            }
+                            if pattern.color_at(y as usize, x as usize) != c {
+                                continue;
+                            }
+                            let j = y as usize * width + x as usize;
+                            let distance = ((dx * dx + dy * dy) as f32).sqrt();
+                            let weight = 1.0 / (((g - green[j]).abs() + WEIGHT_EPS) * distance);
+                            weighted_diff += (cfa[j] - green[j]) * weight;
+                            weight_total += weight;
+                        }
+                    }
+                    pixel[c] = if weight_total > 0.0 {
+                        (g + weighted_diff / weight_total).max(0.0)
+                    } else {
+                        interpolate_average(cfa, width, height, pattern, row, col)[c]
+                    };
+                }
+
+                out_row[col] = pixel;
+            }
+        });
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: usize = 32;
+    const TOLERANCE: f32 = 1e-3;
+
+    fn rggb() -> CfaPattern {
+        CfaPattern::from_fn(2, 2, |row, col| match (row % 2, col % 2) {
+            (0, 0) => 0,
+            (1, 1) => 2,
+            _ => 1,
+        })
+    }
+
+    fn xtrans() -> CfaPattern {
+        const LAYOUT: [&[u8; 6]; 6] = [
+            b"GGRGGB", b"GGBGGR", b"BRGRBG", b"GGBGGR", b"GGRGGB", b"RBGBRG",
+        ];
+        CfaPattern::from_fn(6, 6, |row, col| match LAYOUT[row][col] {
+            b'R' => 0,
+            b'G' => 1,
+            _ => 2,
+        })
+    }
+
+    // A grey scene puts the same value on every CFA site, whatever its color.
+    fn grey_scene(value: impl Fn(usize, usize) -> f32) -> Vec<f32> {
+        (0..SIZE * SIZE)
+            .map(|i| value(i / SIZE, i % SIZE))
+            .collect()
+    }
+
+    fn assert_grey(output: &[[f32; 3]], margin: usize, expected: impl Fn(usize, usize) -> f32) {
+        for row in margin..SIZE - margin {
+            for col in margin..SIZE - margin {
+                let want = expected(row, col);
+                for (c, &got) in output[row * SIZE + col].iter().enumerate() {
+                    assert!(
+                        (got - want).abs() < TOLERANCE,
+                        "channel {} at ({}, {}): got {}, expected {}",
+                        c,
+                        row,
+                        col,
+                        got,
+                        want
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn flat_field_stays_flat() {
+        let cfa = grey_scene(|_, _| 0.5);
+        let bayer = rggb();
+        let xtrans = xtrans();
+
+        assert_grey(&rcd(&cfa, SIZE, SIZE, &bayer), 0, |_, _| 0.5);
+        assert_grey(&lmmse(&cfa, SIZE, SIZE, &bayer), 0, |_, _| 0.5);
+        assert_grey(&gradient_weighted(&cfa, SIZE, SIZE, &bayer), 0, |_, _| 0.5);
+        assert_grey(&gradient_weighted(&cfa, SIZE, SIZE, &xtrans), 0, |_, _| 0.5);
+    }
+
+    // Pixels next to the border are averaged across the edge, so the check
+    // starts two pixels inside the interpolated area.
+    #[test]
+    fn vertical_edge_has_no_zipper() {
+        let edge = |_: usize, col: usize| if col < SIZE / 2 { 0.2 } else { 0.8 };
+        let cfa = grey_scene(edge);
+        let bayer = rggb();
+
+        assert_grey(&rcd(&cfa, SIZE, SIZE, &bayer), RCD_BORDER + 2, edge);
+        assert_grey(&lmmse(&cfa, SIZE, SIZE, &bayer), LMMSE_BORDER + 2, edge);
+    }
+}
@@ -0,0 +1,418 @@
+use anyhow::{Result, anyhow};
+use exif::{In, Reader as ExifReader, Tag};
+use image::{Rgb32FImage, imageops};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use crate::dcp_processing;
+
+const PREVIEW_MAX_DIMENSION: u32 = 1024;
+const ILLUMINANT_D65: u16 = 21;
+const PHOTOMETRIC_LINEAR_RAW: u16 = 34892;
+const TAG_BASELINE_EXPOSURE: u16 = 50730;
+const TAG_DNG_PRIVATE_DATA: u16 = 50740;
+// Marks files from this writer, whose BaselineExposure restores the normalized headroom.
+const PRIVATE_DATA_MARKER: &str = "RapidRAW linear headroom";
+
+const XYZ_D65_TO_SRGB: [f32; 9] = [
+    3.2404542, -1.5371385, -0.4985314, -0.9692660, 1.8760108, 0.0415560, 0.0556434, -0.2040259,
+    1.0572252,
+];
+
+const COPIED_PRIMARY_TAGS: &[Tag] = &[Tag::Make, Tag::Model, Tag::Artist, Tag::Copyright];
+const COPIED_EXIF_TAGS: &[Tag] = &[
+    Tag::ExposureTime,
+    Tag::FNumber,
+    Tag::ExposureProgram,
+    Tag::PhotographicSensitivity,
+    Tag::DateTimeOriginal,
+    Tag::DateTimeDigitized,
+    Tag::ExposureBiasValue,
+    Tag::MeteringMode,
+    Tag::Flash,
+    Tag::FocalLength,
+    Tag::FocalLengthIn35mmFilm,
+    Tag::LensMake,
+    Tag::LensModel,
+];
+
+enum TagValue {
+    Byte(Vec<u8>),
+    Ascii(Vec<u8>),
+    Short(Vec<u16>),
+    Long(Vec<u32>),
+    Rational(Vec<(u32, u32)>),
+    SRational(Vec<(i32, i32)>),
+}
+
+impl TagValue {
+    fn ascii(value: &str) -> Self {
+        let mut bytes = value.as_bytes().to_vec();
+        bytes.push(0);
+        TagValue::Ascii(bytes)
+    }
+
+    fn field_type(&self) -> u16 {
+        match self {
+            TagValue::Byte(_) => 1,
+            TagValue::Ascii(_) => 2,
+            TagValue::Short(_) => 3,
+            TagValue::Long(_) => 4,
+            TagValue::Rational(_) => 5,
+            TagValue::SRational(_) => 10,
+        }
+    }
+
+    fn count(&self) -> u32 {
+        (match self {
+            TagValue::Byte(v) | TagValue::Ascii(v) => v.len(),
+            TagValue::Short(v) => v.len(),
+            TagValue::Long(v) => v.len(),
+            TagValue::Rational(v) => v.len(),
+            TagValue::SRational(v) => v.len(),
+        }) as u32
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            TagValue::Byte(v) | TagValue::Ascii(v) => v.clone(),
+            TagValue::Short(v) => v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+            TagValue::Long(v) => v.iter().flat_map(|x| x.to_le_bytes()).collect(),
+            TagValue::Rational(v) => v
+                .iter()
+                .flat_map(|(n, d)| n.to_le_bytes().into_iter().chain(d.to_le_bytes()))
+                .collect(),
+            TagValue::SRational(v) => v
+                .iter()
+                .flat_map(|(n, d)| n.to_le_bytes().into_iter().chain(d.to_le_bytes()))
+                .collect(),
+        }
+    }
+
+    fn from_exif(value: &exif::Value) -> Option<Self> {
+        match value {
+            exif::Value::Byte(v) => Some(TagValue::Byte(v.clone())),
+            exif::Value::Ascii(v) => v
+                .first()
+                .map(|s| TagValue::ascii(&String::from_utf8_lossy(s))),
+            exif::Value::Short(v) => Some(TagValue::Short(v.clone())),
+            exif::Value::Long(v) => Some(TagValue::Long(v.clone())),
+            exif::Value::Rational(v) => Some(TagValue::Rational(
+                v.iter().map(|r| (r.num, r.denom)).collect(),
+            )),
+            exif::Value::SRational(v) => Some(TagValue::SRational(
+                v.iter().map(|r| (r.num, r.denom)).collect(),
+            )),
+            _ => None,
+        }
+    }
+}
+
+fn srational(value: f32) -> (i32, i32) {
+    ((value * 10000.0).round() as i32, 10000)
+}
+
+fn rational(value: f32) -> (u32, u32) {
+    ((value.max(0.0) * 10000.0).round() as u32, 10000)
+}
+
+struct TiffWriter {
+    buffer: Vec<u8>,
+}
+
+impl TiffWriter {
+    fn new() -> Self {
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(b"II");
+        buffer.extend_from_slice(&42u16.to_le_bytes());
+        buffer.extend_from_slice(&0u32.to_le_bytes());
+        Self { buffer }
+    }
+
+    fn align(&mut self) {
+        if self.buffer.len() % 2 != 0 {
+            self.buffer.push(0);
+        }
+    }
+
+    fn offset(&self) -> Result<u32> {
+        u32::try_from(self.buffer.len()).map_err(|_| anyhow!("DNG exceeds the 4 GB TIFF limit"))
+    }
+
+    fn write_data(&mut self, data: &[u8]) -> Result<u32> {
+        self.align();
+        let offset = self.offset()?;
+        self.buffer.extend_from_slice(data);
+        Ok(offset)
+    }
+
+    fn write_ifd(&mut self, entries: &BTreeMap<u16, TagValue>) -> Result<u32> {
+        self.align();
+        let ifd_offset = self.offset()?;
+        let mut extra_offset = ifd_offset as usize + 2 + entries.len() * 12 + 4;
+        let mut table = Vec::with_capacity(entries.len() * 12 + 6);
+        let mut extra = Vec::new();
+
+        table.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        for (tag, value) in entries {
+            let bytes = value.to_bytes();
+            table.extend_from_slice(&tag.to_le_bytes());
+            table.extend_from_slice(&value.field_type().to_le_bytes());
+            table.extend_from_slice(&value.count().to_le_bytes());
+            if bytes.len() <= 4 {
+                let mut inline = [0u8; 4];
+                inline[..bytes.len()].copy_from_slice(&bytes);
+                table.extend_from_slice(&inline);
+            } else {
+                let offset = u32::try_from(extra_offset)
+                    .map_err(|_| anyhow!("DNG exceeds the 4 GB TIFF limit"))?;
+                table.extend_from_slice(&offset.to_le_bytes());
+                extra.extend_from_slice(&bytes);
+                if bytes.len() % 2 != 0 {
+                    extra.push(0);
+                }
+                extra_offset += bytes.len() + bytes.len() % 2;
+            }
+        }
+        table.extend_from_slice(&0u32.to_le_bytes());
+
+        self.buffer.extend_from_slice(&table);
+        self.buffer.extend_from_slice(&extra);
+        Ok(ifd_offset)
+    }
+
+    fn finish(mut self, first_ifd: u32) -> Vec<u8> {
+        self.buffer[4..8].copy_from_slice(&first_ifd.to_le_bytes());
+        self.buffer
+    }
+}
+
+fn linear_to_srgb_u8(v: f32) -> u8 {
+    let v = v.clamp(0.0, 1.0);
+    let encoded = if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    };
+    (encoded * 255.0).round() as u8
+}
+
+fn strip_entries(
+    entries: &mut BTreeMap<u16, TagValue>,
+    width: u32,
+    height: u32,
+    offset: u32,
+    byte_count: u32,
+) {
+    entries.insert(256, TagValue::Long(vec![width]));
+    entries.insert(257, TagValue::Long(vec![height]));
+    entries.insert(259, TagValue::Short(vec![1]));
+    entries.insert(273, TagValue::Long(vec![offset]));
+    entries.insert(277, TagValue::Short(vec![3]));
+    entries.insert(278, TagValue::Long(vec![height]));
+    entries.insert(279, TagValue::Long(vec![byte_count]));
+    entries.insert(284, TagValue::Short(vec![1]));
+}
+
+// Writes scene-linear sRGB/D65 data as a floating point LinearRaw DNG. Values
+// above 1.0 are normalized into range and restored through BaselineExposure.
+pub fn write_linear_dng(
+    path: &Path,
+    image: &Rgb32FImage,
+    source_bytes: Option<&[u8]>,
+) -> Result<()> {
+    let (width, height) = image.dimensions();
+    let peak = image
+        .as_raw()
+        .iter()
+        .copied()
+        .filter(|v| v.is_finite())
+        .fold(1.0f32, f32::max);
+    let baseline_exposure = peak.log2();
+
+    let source_exif = source_bytes.and_then(|bytes| {
+        ExifReader::new()
+            .read_from_container(&mut Cursor::new(bytes))
+            .ok()
+    });
+    let copy_tags = |tags: &[Tag], entries: &mut BTreeMap<u16, TagValue>| {
+        let Some(exif) = &source_exif else { return };
+        for tag in tags {
+            if let Some(value) = exif
+                .get_field(*tag, In::PRIMARY)
+                .and_then(|field| TagValue::from_exif(&field.value))
+            {
+                entries.insert(tag.number(), value);
+            }
+        }
+    };
+
+    let mut writer = TiffWriter::new();
+
+    let raw_bytes: Vec<u8> = image
+        .as_raw()
+        .iter()
+        .flat_map(|v| (v.max(0.0) / peak).to_le_bytes())
+        .collect();
+    let raw_byte_count = u32::try_from(raw_bytes.len())
+        .map_err(|_| anyhow!("Image is too large to be written as a single DNG strip"))?;
+    let raw_offset = writer.write_data(&raw_bytes)?;
+    drop(raw_bytes);
+
+    let preview = imageops::thumbnail(
+        image,
+        (width * PREVIEW_MAX_DIMENSION / width.max(height)).clamp(1, width),
+        (height * PREVIEW_MAX_DIMENSION / width.max(height)).clamp(1, height),
+    );
+    let (preview_width, preview_height) = preview.dimensions();
+    let preview_bytes: Vec<u8> = preview
+        .as_raw()
+        .iter()
+        .map(|&v| linear_to_srgb_u8(v))
+        .collect();
+    let preview_offset = writer.write_data(&preview_bytes)?;
+
+    let mut raw_ifd = BTreeMap::new();
+    strip_entries(&mut raw_ifd, width, height, raw_offset, raw_byte_count);
+    raw_ifd.insert(254, TagValue::Long(vec![0]));
+    raw_ifd.insert(258, TagValue::Short(vec![32, 32, 32]));
+    raw_ifd.insert(262, TagValue::Short(vec![PHOTOMETRIC_LINEAR_RAW]));
+    raw_ifd.insert(339, TagValue::Short(vec![3, 3, 3]));
+    raw_ifd.insert(50714, TagValue::Long(vec![0]));
+    raw_ifd.insert(50717, TagValue::Long(vec![1, 1, 1]));
+    let raw_ifd_offset = writer.write_ifd(&raw_ifd)?;
+
+    let mut exif_ifd = BTreeMap::new();
+    copy_tags(COPIED_EXIF_TAGS, &mut exif_ifd);
+    let exif_ifd_offset = if exif_ifd.is_empty() {
+        None
+    } else {
+        Some(writer.write_ifd(&exif_ifd)?)
+    };
+
+    let mut main_ifd = BTreeMap::new();
+    copy_tags(COPIED_PRIMARY_TAGS, &mut main_ifd);
+    let camera_name = match (main_ifd.get(&271), main_ifd.get(&272)) {
+        (Some(TagValue::Ascii(make)), Some(TagValue::Ascii(model))) => format!(
+            "{} {}",
+            String::from_utf8_lossy(make).trim_end_matches('\0'),
+            String::from_utf8_lossy(model).trim_end_matches('\0')
+        ),
+        _ => "RapidRAW Linear".to_string(),
+    };
+    strip_entries(
+        &mut main_ifd,
+        preview_width,
+        preview_height,
+        preview_offset,
+        preview_bytes.len() as u32,
+    );
+    main_ifd.insert(254, TagValue::Long(vec![1]));
+    main_ifd.insert(258, TagValue::Short(vec![8, 8, 8]));
+    main_ifd.insert(262, TagValue::Short(vec![2]));
+    main_ifd.insert(274, TagValue::Short(vec![1]));
+    main_ifd.insert(305, TagValue::ascii("RapidRAW"));
+    main_ifd.insert(330, TagValue::Long(vec![raw_ifd_offset]));
+    if let Some(offset) = exif_ifd_offset {
+        main_ifd.insert(34665, TagValue::Long(vec![offset]));
+    }
+    main_ifd.insert(50706, TagValue::Byte(vec![1, 4, 0, 0]));
+    main_ifd.insert(50707, TagValue::Byte(vec![1, 4, 0, 0]));
+    main_ifd.insert(50708, TagValue::ascii(&camera_name));
+    main_ifd.insert(
+        50721,
+        TagValue::SRational(XYZ_D65_TO_SRGB.iter().map(|&v| srational(v)).collect()),
+    );
+    main_ifd.insert(50728, TagValue::Rational(vec![rational(1.0); 3]));
+    main_ifd.insert(
+        TAG_BASELINE_EXPOSURE,
+        TagValue::SRational(vec![srational(baseline_exposure)]),
+    );
+    main_ifd.insert(
+        TAG_DNG_PRIVATE_DATA,
+        TagValue::Byte(TagValue::ascii(PRIVATE_DATA_MARKER).to_bytes()),
+    );
+    main_ifd.insert(50778, TagValue::Short(vec![ILLUMINANT_D65]));
+    let main_ifd_offset = writer.write_ifd(&main_ifd)?;
+
+    fs::write(path, writer.finish(main_ifd_offset))?;
+    Ok(())
+}
+
+// BaselineExposure in other LinearRaw DNGs is a rendering hint, so only our own files
+// have it turned into a data gain.
+pub fn read_headroom_exposure(file_bytes: &[u8]) -> Option<f32> {
+    if dcp_processing::read_tiff_string(file_bytes, TAG_DNG_PRIVATE_DATA).as_deref()
+        != Some(PRIVATE_DATA_MARKER)
+    {
+        return None;
+    }
+    dcp_processing::read_tiff_number(file_bytes, TAG_BASELINE_EXPOSURE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raw_processing::develop_raw_image;
+    use image::Rgb;
+
+    #[test]
+    fn linear_dng_round_trips_through_raw_development() {
+        let (width, height) = (64, 48);
+        let source = Rgb32FImage::from_fn(width, height, |x, y| {
+            let base = 0.05 + 0.9 * x as f32 / width as f32;
+            if y < height / 4 {
+                Rgb([base * 4.0, base * 3.0, base * 2.0])
+            } else {
+                Rgb([base, base * 0.5, base * 0.25])
+            }
+        });
+        let peak = source.as_raw().iter().copied().fold(1.0f32, f32::max);
+
+        let file = tempfile::Builder::new().suffix(".dng").tempfile().unwrap();
+        write_linear_dng(file.path(), &source, None).unwrap();
+        let bytes = fs::read(file.path()).unwrap();
+
+        let headroom = read_headroom_exposure(&bytes).expect("writer marker missing");
+        assert!((headroom - peak.log2()).abs() < 1e-3);
+
+        let adjustments = serde_json::json!({ "highlightReconstruction": "clip" });
+        let developed = develop_raw_image(&bytes, false, 2.5, &adjustments)
+            .unwrap()
+            .to_rgb32f();
+        assert_eq!(developed.dimensions(), source.dimensions());
+        for (x, y, expected) in source.enumerate_pixels() {
+            let actual = developed.get_pixel(x, y);
+            for c in 0..3 {
+                let tolerance = 0.02 * expected[c].max(0.05);
+                assert!(
+                    (expected[c] - actual[c]).abs() <= tolerance,
+                    "pixel ({}, {}) channel {}: expected {}, got {}",
+                    x,
+                    y,
+                    c,
+                    expected[c],
+                    actual[c]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn baseline_exposure_of_foreign_dngs_is_ignored() {
+        let source = Rgb32FImage::from_pixel(8, 8, Rgb([2.0, 2.0, 2.0]));
+        let file = tempfile::Builder::new().suffix(".dng").tempfile().unwrap();
+        write_linear_dng(file.path(), &source, None).unwrap();
+        let mut bytes = fs::read(file.path()).unwrap();
+
+        let marker = PRIVATE_DATA_MARKER.as_bytes();
+        let position = bytes
+            .windows(marker.len())
+            .position(|window| window == marker)
+            .unwrap();
+        bytes[position] = b'X';
+        assert_eq!(read_headroom_exposure(&bytes), None);
+    }
+}
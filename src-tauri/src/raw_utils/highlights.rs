@@ -0,0 +1,155 @@
+use rayon::prelude::*;
+
+const NEAR_CLIP_LEVEL: f32 = 0.85;
+
+const BLEND_FORWARD: [[f32; 3]; 3] = [
+    [1.0, 1.0, 1.0],
+    [1.732_050_8, -1.732_050_8, 0.0],
+    [-1.0, -1.0, 2.0],
+];
+const BLEND_INVERSE: [[f32; 3]; 3] = [
+    [1.0, 0.866_025_4, -0.5],
+    [1.0, -0.866_025_4, -0.5],
+    [1.0, 0.0, 1.0],
+];
+
+#[inline]
+fn mul(m: &[[f32; 3]; 3], p: [f32; 3]) -> [f32; 3] {
+    [
+        m[0][0] * p[0] + m[0][1] * p[1] + m[0][2] * p[2],
+        m[1][0] * p[0] + m[1][1] * p[1] + m[1][2] * p[2],
+        m[2][0] * p[0] + m[2][1] * p[1] + m[2][2] * p[2],
+    ]
+}
+
+#[inline]
+fn max_channel(p: &[f32; 3]) -> f32 {
+    p[0].max(p[1]).max(p[2])
+}
+
+#[inline]
+fn common_clip_level(clip_levels: [f32; 3]) -> f32 {
+    clip_levels[0]
+        .min(clip_levels[1])
+        .min(clip_levels[2])
+        .max(1e-6)
+}
+
+#[inline]
+fn opposed_mean(p: &[f32; 3], c: usize) -> f32 {
+    0.5 * (p[(c + 1) % 3] + p[(c + 2) % 3])
+}
+
+// Pixels are white-balanced camera RGB; `clip_levels` holds the value at which each
+// channel saturated on the sensor (white level times the channel's WB multiplier).
+pub fn clip(pixels: &mut [[f32; 3]], clip_levels: [f32; 3]) {
+    let clip_level = common_clip_level(clip_levels);
+    pixels.par_iter_mut().for_each(|p| {
+        p.iter_mut().for_each(|c| *c = c.min(clip_level));
+    });
+}
+
+pub fn blend(pixels: &mut [[f32; 3]], clip_levels: [f32; 3]) {
+    let clip_level = common_clip_level(clip_levels);
+    pixels.par_iter_mut().for_each(|p| {
+        if max_channel(p) <= clip_level {
+            return;
+        }
+
+        let clipped = p.map(|c| c.min(clip_level));
+        let mut unclipped_lab = mul(&BLEND_FORWARD, *p);
+        let clipped_lab = mul(&BLEND_FORWARD, clipped);
+
+        let unclipped_chroma =
+            unclipped_lab[1] * unclipped_lab[1] + unclipped_lab[2] * unclipped_lab[2];
+        let clipped_chroma = clipped_lab[1] * clipped_lab[1] + clipped_lab[2] * clipped_lab[2];
+        let ratio = if unclipped_chroma > 1e-12 {
+            (clipped_chroma / unclipped_chroma).sqrt()
+        } else {
+            0.0
+        };
+        unclipped_lab[1] *= ratio;
+        unclipped_lab[2] *= ratio;
+
+        let blended = mul(&BLEND_INVERSE, unclipped_lab);
+        *p = blended.map(|c| (c / 3.0).max(0.0));
+    });
+}
+
+pub fn inpaint_opposed(pixels: &mut [[f32; 3]], clip_levels: [f32; 3]) {
+    let (sums, counts) = pixels
+        .par_iter()
+        .fold(
+            || ([0.0f64; 3], [0u64; 3]),
+            |(mut sums, mut counts), p| {
+                for c in 0..3 {
+                    if p[c] >= NEAR_CLIP_LEVEL * clip_levels[c] && p[c] < clip_levels[c] {
+                        sums[c] += (p[c] - opposed_mean(p, c)) as f64;
+                        counts[c] += 1;
+                    }
+                }
+                (sums, counts)
+            },
+        )
+        .reduce(
+            || ([0.0f64; 3], [0u64; 3]),
+            |(a_sums, a_counts), (b_sums, b_counts)| {
+                (
+                    std::array::from_fn(|c| a_sums[c] + b_sums[c]),
+                    std::array::from_fn(|c| a_counts[c] + b_counts[c]),
+                )
+            },
+        );
+
+    let chroma: [f32; 3] = std::array::from_fn(|c| {
+        if counts[c] > 0 {
+            (sums[c] / counts[c] as f64) as f32
+        } else {
+            0.0
+        }
+    });
+
+    pixels.par_iter_mut().for_each(|p| {
+        if (0..3).all(|c| p[c] < clip_levels[c]) {
+            return;
+        }
+        let original = *p;
+        for c in 0..3 {
+            if original[c] >= clip_levels[c] {
+                p[c] = original[c].max(opposed_mean(&original, c) + chroma[c]);
+            }
+        }
+    });
+}
+
+pub fn compress(pixels: &mut [[f32; 3]], clip_levels: [f32; 3], highlight_compression: f32) {
+    let highlight_compression = highlight_compression.max(1.01);
+    let clip_level = common_clip_level(clip_levels);
+    pixels.par_iter_mut().for_each(|p| {
+        let [r, g, b] = p.map(|c| c / clip_level);
+        let max_c = r.max(g).max(b);
+        if max_c <= 1.0 {
+            return;
+        }
+
+        let min_c = r.min(g).min(b);
+        let compression_factor =
+            (1.0 - (max_c - 1.0) / (highlight_compression - 1.0)).clamp(0.0, 1.0);
+        let compressed_r = min_c + (r - min_c) * compression_factor;
+        let compressed_g = min_c + (g - min_c) * compression_factor;
+        let compressed_b = min_c + (b - min_c) * compression_factor;
+        let compressed_max = compressed_r.max(compressed_g).max(compressed_b);
+
+        let compressed = if compressed_max > 1e-6 {
+            let rescale = max_c / compressed_max;
+            [
+                compressed_r * rescale,
+                compressed_g * rescale,
+                compressed_b * rescale,
+            ]
+        } else {
+            [max_c, max_c, max_c]
+        };
+        *p = compressed.map(|c| c * clip_level);
+    });
+}
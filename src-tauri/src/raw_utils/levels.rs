@@ -0,0 +1,306 @@
+use anyhow::Result;
+use rawler::{
+    decoders::RawDecodeParams,
+    rawimage::{RawImage, RawImageData, RawPhotometricInterpretation},
+    rawsource::RawSource,
+};
+use rayon::prelude::*;
+use std::fs;
+
+pub struct SensorLevels {
+    width: usize,
+    height: usize,
+    cpp: usize,
+    black: Vec<f32>,
+    white: Vec<f32>,
+}
+
+impl SensorLevels {
+    pub fn from_raw_image(raw_image: &RawImage) -> Self {
+        let cpp = raw_image.cpp.max(1);
+        let white_levels = &raw_image.whitelevel.0;
+        let fallback_white = white_levels.first().copied().unwrap_or(u16::MAX as u32) as f32;
+        let white = (0..cpp)
+            .map(|s| white_levels.get(s).map_or(fallback_white, |&w| w as f32))
+            .collect();
+
+        if let Some((width, height, black)) = measure_black_areas(raw_image) {
+            return Self {
+                width,
+                height,
+                cpp,
+                black,
+                white,
+            };
+        }
+
+        let bl = &raw_image.blacklevel;
+        let (bl_width, bl_height, bl_cpp) = (bl.width.max(1), bl.height.max(1), bl.cpp.max(1));
+        let fallback_black = bl.levels.first().map_or(0.0, |l| l.as_f32());
+        let complete = bl.levels.len() >= bl_width * bl_height * bl_cpp;
+
+        let mut black = Vec::with_capacity(bl_width * bl_height * cpp);
+        for row in 0..bl_height {
+            for col in 0..bl_width {
+                for s in 0..cpp {
+                    let idx = (row * bl_width + col) * bl_cpp + s % bl_cpp;
+                    black.push(if complete {
+                        bl.levels[idx].as_f32()
+                    } else {
+                        fallback_black
+                    });
+                }
+            }
+        }
+
+        Self {
+            width: bl_width,
+            height: bl_height,
+            cpp,
+            black,
+            white,
+        }
+    }
+
+    pub fn black_at(&self, row: usize, col: usize, sample: usize) -> f32 {
+        self.black[((row % self.height) * self.width + col % self.width) * self.cpp + sample]
+    }
+
+    pub fn white_at(&self, sample: usize) -> f32 {
+        self.white[sample]
+    }
+
+    pub fn is_uniform(&self, black: f32, white: f32) -> bool {
+        self.black.iter().all(|&b| b == black) && self.white.iter().all(|&w| w == white)
+    }
+}
+
+fn sample_value(data: &RawImageData, idx: usize) -> f32 {
+    match data {
+        RawImageData::Integer(values) => values[idx] as f32,
+        RawImageData::Float(values) => values[idx],
+    }
+}
+
+fn measure_black_areas(raw_image: &RawImage) -> Option<(usize, usize, Vec<f32>)> {
+    if raw_image.blackareas.is_empty() || raw_image.cpp != 1 {
+        return None;
+    }
+    let cfa = match &raw_image.photometric {
+        RawPhotometricInterpretation::Cfa(config) => &config.cfa,
+        _ => return None,
+    };
+    let (pattern_w, pattern_h) = (cfa.width.max(1), cfa.height.max(1));
+    let (width, height) = (raw_image.width, raw_image.height);
+
+    let mut sums = vec![0.0f64; pattern_w * pattern_h];
+    let mut counts = vec![0u64; pattern_w * pattern_h];
+    for area in &raw_image.blackareas {
+        let x_end = (area.p.x + area.d.w).min(width);
+        let y_end = (area.p.y + area.d.h).min(height);
+        for row in area.p.y..y_end {
+            for col in area.p.x..x_end {
+                let cell = (row % pattern_h) * pattern_w + col % pattern_w;
+                sums[cell] += sample_value(&raw_image.data, row * width + col) as f64;
+                counts[cell] += 1;
+            }
+        }
+    }
+
+    if counts.iter().any(|&c| c == 0) {
+        return None;
+    }
+    let black = sums
+        .iter()
+        .zip(&counts)
+        .map(|(&sum, &count)| (sum / count as f64) as f32)
+        .collect();
+    Some((pattern_w, pattern_h, black))
+}
+
+pub fn equalize_levels(raw_image: &mut RawImage) -> (f32, f32) {
+    let levels = SensorLevels::from_raw_image(raw_image);
+    let ref_black = raw_image
+        .blacklevel
+        .levels
+        .first()
+        .map_or(0.0, |l| l.as_f32());
+    let ref_white = levels.white_at(0);
+    if levels.is_uniform(ref_black, ref_white) {
+        return (ref_black, ref_white);
+    }
+
+    let width = raw_image.width;
+    let cpp = levels.cpp;
+    let ref_range = (ref_white - ref_black).max(1.0);
+    let remap = |idx: usize, v: f32| {
+        let pixel = idx / cpp;
+        let sample = idx % cpp;
+        let black = levels.black_at(pixel / width, pixel % width, sample);
+        let range = (levels.white_at(sample) - black).max(1.0);
+        ref_black + (v - black) / range * ref_range
+    };
+
+    match &mut raw_image.data {
+        RawImageData::Integer(data) => data.par_iter_mut().enumerate().for_each(|(i, v)| {
+            *v = remap(i, *v as f32).round().clamp(0.0, u16::MAX as f32) as u16;
+        }),
+        RawImageData::Float(data) => data
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(i, v)| *v = remap(i, *v).max(0.0)),
+    }
+
+    if let Some(first) = raw_image.blacklevel.levels.first().cloned() {
+        raw_image
+            .blacklevel
+            .levels
+            .iter_mut()
+            .for_each(|l| *l = first.clone());
+    }
+    let first_white = raw_image.whitelevel.0.first().copied();
+    if let Some(first) = first_white {
+        raw_image.whitelevel.0.iter_mut().for_each(|w| *w = first);
+    }
+
+    (ref_black, ref_white)
+}
+
+// Decodes an auxiliary frame (dark, flat or defect reference) with equalized levels.
+pub fn decode_equalized(path: &str) -> Result<(RawImage, f32, f32)> {
+    let bytes = fs::read(path)?;
+    let source = RawSource::new_from_slice(&bytes);
+    let decoder = rawler::get_decoder(&source)?;
+    let mut raw_image = decoder.raw_image(&source, &RawDecodeParams::default(), false)?;
+    let (black_level, white_level) = equalize_levels(&mut raw_image);
+    Ok((raw_image, black_level, white_level))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rawler::{
+        cfa::CFA,
+        decoders::Camera,
+        imgop::{Dim2, Point, Rect},
+        rawimage::{BlackLevel, WhiteLevel},
+    };
+
+    const WIDTH: usize = 8;
+    const HEIGHT: usize = 8;
+
+    fn rggb_image(data: Vec<u16>, black: BlackLevel, white: u32) -> RawImage {
+        let camera = Camera {
+            cfa: CFA::new("RGGB"),
+            ..Camera::default()
+        };
+        RawImage::new(
+            camera,
+            WIDTH,
+            HEIGHT,
+            1,
+            [2.0, 1.0, 1.5, f32::NAN],
+            RawImageData::Integer(data),
+            Some(black),
+            Some(WhiteLevel(vec![white])),
+            false,
+        )
+    }
+
+    fn cfa_cell(index: usize) -> usize {
+        let (row, col) = (index / WIDTH, index % WIDTH);
+        (row % 2) * 2 + col % 2
+    }
+
+    fn integer_data(raw_image: &RawImage) -> &[u16] {
+        match &raw_image.data {
+            RawImageData::Integer(data) => data,
+            RawImageData::Float(_) => panic!("expected integer data"),
+        }
+    }
+
+    #[test]
+    fn per_cfa_black_levels_are_equalized() {
+        let blacks = [100u32, 200, 300, 400];
+        let white = 4100u32;
+        let data = (0..WIDTH * HEIGHT)
+            .map(|i| {
+                let black = blacks[cfa_cell(i)];
+                (black + (white - black) / 4) as u16
+            })
+            .collect();
+        let mut raw_image = rggb_image(data, BlackLevel::new(&blacks, 2, 2, 1), white);
+
+        let (black, white_level) = equalize_levels(&mut raw_image);
+
+        assert_eq!((black, white_level), (100.0, 4100.0));
+        for &v in integer_data(&raw_image) {
+            assert!((v as i32 - 1100).abs() <= 1, "got {}", v);
+        }
+        assert!(
+            raw_image
+                .blacklevel
+                .levels
+                .iter()
+                .all(|l| l.as_f32() == 100.0)
+        );
+    }
+
+    #[test]
+    fn per_sample_white_levels_are_equalized() {
+        let whites = [1000u32, 2000, 4000];
+        let data = (0..WIDTH * HEIGHT * 3)
+            .map(|i| (whites[i % 3] / 2) as f32)
+            .collect();
+        let mut raw_image = RawImage::new(
+            Camera::default(),
+            WIDTH,
+            HEIGHT,
+            3,
+            [1.0, 1.0, 1.0, f32::NAN],
+            RawImageData::Float(data),
+            Some(BlackLevel::new(&[0u32], 1, 1, 1)),
+            Some(WhiteLevel(whites.to_vec())),
+            false,
+        );
+        raw_image.photometric = RawPhotometricInterpretation::LinearRaw;
+
+        let (_, white_level) = equalize_levels(&mut raw_image);
+
+        assert_eq!(white_level, 1000.0);
+        let RawImageData::Float(data) = &raw_image.data else {
+            panic!("expected float data");
+        };
+        assert!(data.iter().all(|&v| (v - 500.0).abs() < 1e-3));
+        assert!(raw_image.whitelevel.0.iter().all(|&w| w == 1000));
+    }
+
+    #[test]
+    fn masked_black_areas_neutralize_channel_offsets() {
+        let measured_blacks = [510u32, 520, 530, 540];
+        let white = 4095u32;
+        let masked_rows = 2;
+        let data = (0..WIDTH * HEIGHT)
+            .map(|i| {
+                let black = measured_blacks[cfa_cell(i)];
+                if i / WIDTH < masked_rows {
+                    black as u16
+                } else {
+                    (black + (white - black) / 4) as u16
+                }
+            })
+            .collect();
+        let mut raw_image = rggb_image(data, BlackLevel::new(&[512u32], 1, 1, 1), white);
+        raw_image.blackareas = vec![Rect::new(Point::new(0, 0), Dim2::new(WIDTH, masked_rows))];
+
+        let (black, white_level) = equalize_levels(&mut raw_image);
+
+        assert_eq!((black, white_level), (512.0, 4095.0));
+        let active = &integer_data(&raw_image)[WIDTH * masked_rows..];
+        let (min, max) = active
+            .iter()
+            .fold((u16::MAX, 0), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+        assert!(max - min <= 1, "channels differ: {}..{}", min, max);
+        assert!((min as i32 - (512 + (4095 - 512) / 4) as i32).abs() <= 1);
+    }
+}
@@ -0,0 +1,7 @@
+pub mod calibration;
+pub mod defects;
+pub mod demosaic;
+pub mod dng_writer;
+pub mod highlights;
+pub mod levels;
+pub mod white_balance;
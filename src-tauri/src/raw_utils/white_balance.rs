@@ -0,0 +1,140 @@
+use nalgebra::{Matrix3, Vector3};
+
+pub const MIN_KELVIN: f32 = 1667.0;
+pub const MAX_KELVIN: f32 = 25000.0;
+const TINT_SCALE: f32 = 3000.0;
+
+fn planckian_xy(kelvin: f32) -> (f32, f32) {
+    let t = kelvin.clamp(MIN_KELVIN, MAX_KELVIN) as f64;
+    let (t2, t3) = (t * t, t * t * t);
+    let x = if t <= 4000.0 {
+        -0.2661239e9 / t3 - 0.2343589e6 / t2 + 0.8776956e3 / t + 0.179910
+    } else {
+        -3.0258469e9 / t3 + 2.1070379e6 / t2 + 0.2226347e3 / t + 0.240390
+    };
+    let (x2, x3) = (x * x, x * x * x);
+    let y = if t <= 2222.0 {
+        -1.1063814 * x3 - 1.34811020 * x2 + 2.18555832 * x - 0.20219683
+    } else if t <= 4000.0 {
+        -0.9549476 * x3 - 1.37418593 * x2 + 2.09137015 * x - 0.16748867
+    } else {
+        3.0817580 * x3 - 5.87338670 * x2 + 3.75112997 * x - 0.37001483
+    };
+    (x as f32, y as f32)
+}
+
+fn xy_to_uv(x: f32, y: f32) -> (f32, f32) {
+    let d = -2.0 * x + 12.0 * y + 3.0;
+    (4.0 * x / d, 6.0 * y / d)
+}
+
+fn uv_to_xy(u: f32, v: f32) -> (f32, f32) {
+    let d = 2.0 * u - 8.0 * v + 4.0;
+    (3.0 * u / d, 2.0 * v / d)
+}
+
+fn locus_uv(mired: f32) -> (f32, f32) {
+    let (x, y) = planckian_xy(1_000_000.0 / mired);
+    xy_to_uv(x, y)
+}
+
+// Unit normal pointing towards green; a positive tint describes a greenish
+// illuminant, which the multipliers then correct towards magenta.
+fn locus_normal(mired: f32) -> (f32, f32) {
+    let (u0, v0) = locus_uv(mired - 0.5);
+    let (u1, v1) = locus_uv(mired + 0.5);
+    let (du, dv) = (u1 - u0, v1 - v0);
+    let len = (du * du + dv * dv).sqrt().max(1e-12);
+    let (nu, nv) = (-dv / len, du / len);
+    if nv < 0.0 { (-nu, -nv) } else { (nu, nv) }
+}
+
+pub fn kelvin_tint_to_xy(kelvin: f32, tint: f32) -> (f32, f32) {
+    let mired = 1_000_000.0 / kelvin.clamp(MIN_KELVIN, MAX_KELVIN);
+    let (u, v) = locus_uv(mired);
+    let (nu, nv) = locus_normal(mired);
+    let offset = tint / TINT_SCALE;
+    uv_to_xy(u + nu * offset, v + nv * offset)
+}
+
+pub fn xy_to_kelvin_tint(x: f32, y: f32) -> (f32, f32) {
+    let (u, v) = xy_to_uv(x, y);
+    let distance = |mired: f32| {
+        let (lu, lv) = locus_uv(mired);
+        (u - lu).powi(2) + (v - lv).powi(2)
+    };
+
+    let mut lo = 1_000_000.0 / MAX_KELVIN;
+    let mut hi = 1_000_000.0 / MIN_KELVIN;
+    for _ in 0..80 {
+        let m1 = lo + (hi - lo) / 3.0;
+        let m2 = hi - (hi - lo) / 3.0;
+        if distance(m1) < distance(m2) {
+            hi = m2;
+        } else {
+            lo = m1;
+        }
+    }
+    let mired = 0.5 * (lo + hi);
+
+    let (lu, lv) = locus_uv(mired);
+    let (nu, nv) = locus_normal(mired);
+    let tint = ((u - lu) * nu + (v - lv) * nv) * TINT_SCALE;
+    (1_000_000.0 / mired, tint)
+}
+
+fn xy_to_xyz(x: f32, y: f32) -> Vector3<f32> {
+    let y = y.max(1e-6);
+    Vector3::new(x / y, 1.0, (1.0 - x - y) / y)
+}
+
+pub fn multipliers_for_kelvin_tint(
+    xyz_to_cam: &Matrix3<f32>,
+    kelvin: f32,
+    tint: f32,
+) -> Option<[f32; 3]> {
+    let (x, y) = kelvin_tint_to_xy(kelvin, tint);
+    let neutral = xyz_to_cam * xy_to_xyz(x, y);
+    if neutral.iter().any(|&c| !c.is_finite() || c <= 1e-6) {
+        return None;
+    }
+    Some([neutral.y / neutral.x, 1.0, neutral.y / neutral.z])
+}
+
+// Multiplier for an extra sensor channel (e.g. the E/G2 plane of four-color CFAs), solved
+// against the same illuminant so it lands on the green level like the other three.
+pub fn channel_multiplier_for_kelvin_tint(
+    xyz_to_cam: &Matrix3<f32>,
+    channel_row: &Vector3<f32>,
+    kelvin: f32,
+    tint: f32,
+) -> Option<f32> {
+    let (x, y) = kelvin_tint_to_xy(kelvin, tint);
+    let xyz = xy_to_xyz(x, y);
+    let green = (xyz_to_cam * xyz).y;
+    let channel = channel_row.dot(&xyz);
+    if !green.is_finite() || !channel.is_finite() || channel <= 1e-6 {
+        return None;
+    }
+    Some(green / channel)
+}
+
+pub fn kelvin_tint_for_multipliers(
+    xyz_to_cam: &Matrix3<f32>,
+    multipliers: [f32; 3],
+) -> Option<(f32, f32)> {
+    if multipliers.iter().any(|&m| !m.is_finite() || m <= 0.0) {
+        return None;
+    }
+    let neutral = Vector3::new(
+        1.0 / multipliers[0],
+        1.0 / multipliers[1],
+        1.0 / multipliers[2],
+    );
+    let xyz = xyz_to_cam.try_inverse()? * neutral;
+    let sum = xyz.x + xyz.y + xyz.z;
+    if sum.abs() <= 1e-6 {
+        return None;
+    }
+    Some(xy_to_kelvin_tint(xyz.x / sum, xyz.y / sum))
+}
@@ -12,6 +12,38 @@ struct HslColor {
     _pad: f32,
 }
 
+struct ColorCurves {
+    hue_hue: array<vec4<f32>, 16>,
+    hue_sat: array<vec4<f32>, 16>,
+    hue_lum: array<vec4<f32>, 16>,
+    lum_sat: array<vec4<f32>, 16>,
+    sat_sat: array<vec4<f32>, 16>,
+    hue_hue_count: u32,
+    hue_sat_count: u32,
+    hue_lum_count: u32,
+    lum_sat_count: u32,
+    sat_sat_count: u32,
+    _pad1: u32,
+    _pad2: u32,
+    _pad3: u32,
+}
+
+struct ChannelMixerSettings {
+    red: vec4<f32>,
+    green: vec4<f32>,
+    blue: vec4<f32>,
+    gray_mix_low: vec4<f32>,
+    gray_mix_high: vec4<f32>,
+    toning_hue: f32,
+    toning_saturation: f32,
+    enabled: u32,
+    monochrome: u32,
+    preserve_luminosity: u32,
+    _pad1: f32,
+    _pad2: f32,
+    _pad3: f32,
+}
+
 struct ColorGradeSettings {
     hue: f32,
     saturation: f32,
@@ -110,8 +142,18 @@ struct GlobalAdjustments {
     red_curve_count: u32,
     green_curve_count: u32,
     blue_curve_count: u32,
-    _pad_end1: f32,
-    _pad_end2: f32,
+    parametric_shadows: f32,
+    parametric_darks: f32,
+    parametric_lights: f32,
+    parametric_highlights: f32,
+    parametric_shadow_split: f32,
+    parametric_midtone_split: f32,
+    parametric_highlight_split: f32,
+    _pad_parametric: f32,
+    color_curves: ColorCurves,
+    channel_mixer: ChannelMixerSettings,
+    hdr_headroom: f32,
+    float_output: u32,
     _pad_end3: f32,
     _pad_end4: f32,
 }
@@ -161,6 +203,7 @@ struct MaskAdjustments {
     red_curve_count: u32,
     green_curve_count: u32,
     blue_curve_count: u32,
+    color_curves: ColorCurves,
     _pad_end4: f32,
     _pad_end5: f32,
     _pad_end6: f32,
@@ -524,6 +567,46 @@ fn apply_white_balance(color: vec3<f32>, temp: f32, tnt: f32) -> vec3<f32> {
     return rgb;
 }
 
+// The gray mix weights brighten or darken the luma of each hue band before
+// the image is collapsed to monochrome, like a colored filter on film.
+fn apply_channel_mixer(color: vec3<f32>, mixer: ChannelMixerSettings) -> vec3<f32> {
+    var rgb = color;
+    if (mixer.enabled != 0u) {
+        let mixed = vec3<f32>(dot(rgb, mixer.red.xyz), dot(rgb, mixer.green.xyz), dot(rgb, mixer.blue.xyz));
+        let mixed_luma = get_luma(mixed);
+        if (mixer.preserve_luminosity != 0u && mixed_luma > 0.0001) {
+            rgb = mixed * (get_luma(rgb) / mixed_luma);
+        } else {
+            rgb = mixed;
+        }
+        rgb = max(rgb, vec3<f32>(0.0));
+    }
+    if (mixer.monochrome == 0u) {
+        return rgb;
+    }
+
+    let hsv = rgb_to_hsv(max(rgb, vec3<f32>(0.0)));
+    var weights = array<f32, 8>(
+        mixer.gray_mix_low.x, mixer.gray_mix_low.y, mixer.gray_mix_low.z, mixer.gray_mix_low.w,
+        mixer.gray_mix_high.x, mixer.gray_mix_high.y, mixer.gray_mix_high.z, mixer.gray_mix_high.w
+    );
+    var total_influence = 0.0;
+    var weighted_mix = 0.0;
+    for (var i = 0u; i < 8u; i = i + 1u) {
+        let influence = get_raw_hsl_influence(hsv.x, HSL_RANGES[i].center, HSL_RANGES[i].width);
+        total_influence += influence;
+        weighted_mix += influence * weights[i];
+    }
+    let band_mix = weighted_mix / max(total_influence, 0.0001) * smoothstep(0.0, 0.5, hsv.y);
+    let gray = max(get_luma(rgb) * (1.0 + band_mix), 0.0);
+
+    if (mixer.toning_saturation <= 0.0) {
+        return vec3<f32>(gray);
+    }
+    let tone = mix(vec3<f32>(1.0), hsv_to_rgb(vec3<f32>(mixer.toning_hue, 1.0, 1.0)), mixer.toning_saturation * 0.5);
+    return gray * tone / get_luma(tone);
+}
+
 fn apply_creative_color(color: vec3<f32>, sat: f32, vib: f32) -> vec3<f32> {
     var processed = color;
     let luma = get_luma(processed);
@@ -614,6 +697,75 @@ fn apply_hsl_panel(color: vec3<f32>, hsl_adjustments: array<HslColor, 8>, coords
     return final_color;
 }
 
+fn color_curve_point(points: ptr<function, array<vec4<f32>, 16>>, count: u32, index: i32, periodic: bool) -> vec2<f32> {
+    let n = i32(count);
+    var i = index;
+    var offset = 0.0;
+    if (periodic) {
+        if (i < 0) { i += n; offset = -1.0; }
+        else if (i >= n) { i -= n; offset = 1.0; }
+    } else {
+        i = clamp(i, 0, n - 1);
+    }
+    let packed = (*points)[u32(i) / 2u];
+    let p = select(packed.zw, packed.xy, u32(i) % 2u == 0u);
+    return vec2<f32>(p.x + offset, p.y);
+}
+
+// Catmull-Rom through the control points. Hue indexed curves wrap around so
+// the red end joins the magenta end smoothly.
+fn eval_color_curve(curve: array<vec4<f32>, 16>, count: u32, x: f32, periodic: bool) -> f32 {
+    if (count == 0u) { return 0.5; }
+    var points = curve;
+    if (count == 1u) { return points[0].y; }
+
+    let n = i32(count);
+    var k = -1;
+    for (var i = 0; i < n; i = i + 1) {
+        if (color_curve_point(&points, count, i, periodic).x <= x) { k = i; }
+    }
+    if (!periodic) {
+        if (k < 0) { return points[0].y; }
+        if (k >= n - 1) { return color_curve_point(&points, count, n - 1, false).y; }
+    }
+
+    let p0 = color_curve_point(&points, count, k - 1, periodic);
+    let p1 = color_curve_point(&points, count, k, periodic);
+    let p2 = color_curve_point(&points, count, k + 1, periodic);
+    let p3 = color_curve_point(&points, count, k + 2, periodic);
+    let m1 = (p2.y - p0.y) / max(0.0001, p2.x - p0.x);
+    let m2 = (p3.y - p1.y) / max(0.0001, p3.x - p1.x);
+    return interpolate_cubic_hermite(x, Point(p1.x, p1.y, 0.0, 0.0), Point(p2.x, p2.y, 0.0, 0.0), m1, m2);
+}
+
+fn apply_color_curves(color: vec3<f32>, curves: ColorCurves) -> vec3<f32> {
+    if (curves.hue_hue_count + curves.hue_sat_count + curves.hue_lum_count + curves.lum_sat_count + curves.sat_sat_count == 0u) {
+        return color;
+    }
+    let hsv = rgb_to_hsv(max(color, vec3<f32>(0.0)));
+    let original_luma = get_luma(color);
+    let hue = hsv.x / 360.0;
+    let perceptual_luma = linear_to_srgb(vec3<f32>(clamp(original_luma, 0.0, 1.0))).x;
+    let hue_weight = smoothstep(0.02, 0.15, hsv.y);
+
+    let hue_shift = (eval_color_curve(curves.hue_hue, curves.hue_hue_count, hue, true) - 0.5) * 360.0 * hue_weight;
+    let hue_sat_scale = mix(1.0, 2.0 * eval_color_curve(curves.hue_sat, curves.hue_sat_count, hue, true), hue_weight);
+    let lum_sat_scale = 2.0 * eval_color_curve(curves.lum_sat, curves.lum_sat_count, perceptual_luma, false);
+    let sat_sat_scale = 2.0 * eval_color_curve(curves.sat_sat, curves.sat_sat_count, hsv.y, false);
+    let lum_scale = mix(1.0, 2.0 * eval_color_curve(curves.hue_lum, curves.hue_lum_count, hue, true), hue_weight);
+
+    var shifted = hsv;
+    shifted.x = (hsv.x + hue_shift + 360.0) % 360.0;
+    shifted.y = clamp(hsv.y * hue_sat_scale * lum_sat_scale * sat_sat_scale, 0.0, 1.0);
+    let shifted_rgb = hsv_to_rgb(shifted);
+    let new_luma = get_luma(shifted_rgb);
+    let target_luma = original_luma * lum_scale;
+    if (new_luma < 0.0001) {
+        return vec3<f32>(max(0.0, target_luma));
+    }
+    return shifted_rgb * (target_luma / new_luma);
+}
+
 fn apply_color_grading(color: vec3<f32>, shadows: ColorGradeSettings, midtones: ColorGradeSettings, highlights: ColorGradeSettings, blending: f32, balance: f32) -> vec3<f32> {
     let luma = get_luma(max(vec3(0.0), color));
     let base_shadow_crossover = 0.1;
@@ -930,6 +1082,36 @@ fn is_default_curve(points: array<Point, 16>, count: u32) -> bool {
     return abs(p0.y - 0.0) < 0.1 && abs(p1.y - 255.0) < 0.1;
 }
 
+// Lightroom style region curve: each region's centre is shifted by its amount
+// and the split points sit halfway between neighbours, kept monotonic.
+fn apply_parametric_curve(color: vec3<f32>, amounts: vec4<f32>, splits: vec3<f32>) -> vec3<f32> {
+    if (all(amounts == vec4<f32>(0.0))) {
+        return color;
+    }
+    var bounds = array<f32, 5>(0.0, splits.x, splits.y, splits.z, 1.0);
+    var offsets: array<f32, 4>;
+    for (var k = 0u; k < 4u; k = k + 1u) {
+        offsets[k] = amounts[k] * 0.5 * (bounds[k + 1u] - bounds[k]);
+    }
+
+    var points: array<Point, 16>;
+    points[0] = Point(0.0, 0.0, 0.0, 0.0);
+    var prev_y = 0.0;
+    for (var k = 0u; k < 4u; k = k + 1u) {
+        let center = (bounds[k] + bounds[k + 1u]) * 0.5;
+        prev_y = clamp(center + offsets[k], prev_y, 1.0);
+        points[2u * k + 1u] = Point(center * 255.0, prev_y * 255.0, 0.0, 0.0);
+        if (k < 3u) {
+            let split = bounds[k + 1u];
+            prev_y = clamp(split + (offsets[k] + offsets[k + 1u]) * 0.5, prev_y, 1.0);
+            points[2u * k + 2u] = Point(split * 255.0, prev_y * 255.0, 0.0, 0.0);
+        }
+    }
+    points[8] = Point(255.0, 255.0, 0.0, 0.0);
+
+    return vec3<f32>(apply_curve(color.r, points, 9u), apply_curve(color.g, points, 9u), apply_curve(color.b, points, 9u));
+}
+
 fn apply_all_curves(color: vec3<f32>, luma_curve: array<Point, 16>, luma_curve_count: u32, red_curve: array<Point, 16>, red_curve_count: u32, green_curve: array<Point, 16>, green_curve_count: u32, blue_curve: array<Point, 16>, blue_curve_count: u32) -> vec3<f32> {
     let red_is_default = is_default_curve(red_curve, red_curve_count);
     let green_is_default = is_default_curve(green_curve, green_curve_count);
@@ -957,12 +1139,14 @@ fn apply_all_adjustments(initial_rgb: vec3<f32>, adj: GlobalAdjustments, coords_
     processed_rgb = apply_dehaze(processed_rgb, adj.dehaze);
     processed_rgb = apply_centre_tonal_and_color(processed_rgb, adj.centre, coords_i);
     processed_rgb = apply_white_balance(processed_rgb, adj.temperature, adj.tint);
+    processed_rgb = apply_channel_mixer(processed_rgb, adj.channel_mixer);
     processed_rgb = apply_filmic_exposure(processed_rgb, adj.brightness);
     processed_rgb = apply_tonal_adjustments(processed_rgb, adj.contrast, adj.shadows, adj.whites, adj.blacks);
     processed_rgb = apply_highlights_adjustment(processed_rgb, adj.highlights);
 
     processed_rgb = apply_color_calibration(processed_rgb, adj.color_calibration);
     processed_rgb = apply_hsl_panel(processed_rgb, adj.hsl, coords_i);
+    processed_rgb = apply_color_curves(processed_rgb, adj.color_curves);
     processed_rgb = apply_color_grading(processed_rgb, adj.color_grading_shadows, adj.color_grading_midtones, adj.color_grading_highlights, adj.color_grading_blending, adj.color_grading_balance);
     processed_rgb = apply_creative_color(processed_rgb, adj.saturation, adj.vibrance);
 
@@ -980,6 +1164,7 @@ fn apply_all_mask_adjustments(initial_rgb: vec3<f32>, adj: MaskAdjustments, coor
     processed_rgb = apply_tonal_adjustments(processed_rgb, adj.contrast, adj.shadows, adj.whites, adj.blacks);
 
     processed_rgb = apply_hsl_panel(processed_rgb, adj.hsl, coords_i);
+    processed_rgb = apply_color_curves(processed_rgb, adj.color_curves);
     processed_rgb = apply_color_grading(processed_rgb, adj.color_grading_shadows, adj.color_grading_midtones, adj.color_grading_highlights, adj.color_grading_blending, adj.color_grading_balance);
     processed_rgb = apply_creative_color(processed_rgb, adj.saturation, adj.vibrance);
     
@@ -1073,6 +1258,41 @@ fn sample_lut_tetrahedral(uv: vec3<f32>) -> vec3<f32> {
     return res;
 }
 
+// Display-referred edits applied after tone mapping: parametric curve, curves,
+// mask curves and the LUT.
+fn apply_tone_edits(base_srgb: vec3<f32>, absolute_coord: vec2<u32>) -> vec3<f32> {
+    let parametric_srgb = apply_parametric_curve(base_srgb,
+        vec4<f32>(adjustments.global.parametric_shadows, adjustments.global.parametric_darks, adjustments.global.parametric_lights, adjustments.global.parametric_highlights),
+        vec3<f32>(adjustments.global.parametric_shadow_split, adjustments.global.parametric_midtone_split, adjustments.global.parametric_highlight_split)
+    );
+
+    var edited = apply_all_curves(parametric_srgb,
+        adjustments.global.luma_curve, adjustments.global.luma_curve_count,
+        adjustments.global.red_curve, adjustments.global.red_curve_count,
+        adjustments.global.green_curve, adjustments.global.green_curve_count,
+        adjustments.global.blue_curve, adjustments.global.blue_curve_count
+    );
+
+    for (var i = 0u; i < adjustments.mask_count; i = i + 1u) {
+        let influence = get_mask_influence(i, absolute_coord);
+        if (influence > 0.001) {
+            let mask_curved_srgb = apply_all_curves(edited,
+                adjustments.mask_adjustments[i].luma_curve, adjustments.mask_adjustments[i].luma_curve_count,
+                adjustments.mask_adjustments[i].red_curve, adjustments.mask_adjustments[i].red_curve_count,
+                adjustments.mask_adjustments[i].green_curve, adjustments.mask_adjustments[i].green_curve_count,
+                adjustments.mask_adjustments[i].blue_curve, adjustments.mask_adjustments[i].blue_curve_count
+            );
+            edited = mix(edited, mask_curved_srgb, influence);
+        }
+    }
+
+    if (adjustments.global.has_lut == 1u) {
+        let lut_color = sample_lut_tetrahedral(edited);
+        edited = mix(edited, lut_color, adjustments.global.lut_intensity);
+    }
+    return edited;
+}
+
 @compute @workgroup_size(8, 8, 1)
 fn main(@builtin(global_invocation_id) id: vec3<u32>) {
     let out_dims = vec2<u32>(textureDimensions(output_texture));
@@ -1155,31 +1375,29 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
     } else {
         base_srgb = linear_to_srgb(composite_rgb_linear);
     }
-
-    var final_rgb = apply_all_curves(base_srgb,
-        adjustments.global.luma_curve, adjustments.global.luma_curve_count,
-        adjustments.global.red_curve, adjustments.global.red_curve_count,
-        adjustments.global.green_curve, adjustments.global.green_curve_count,
-        adjustments.global.blue_curve, adjustments.global.blue_curve_count
-    );
-
-    for (var i = 0u; i < adjustments.mask_count; i = i + 1u) {
-        let influence = get_mask_influence(i, absolute_coord);
-        if (influence > 0.001) {
-            let mask_curved_srgb = apply_all_curves(final_rgb,
-                adjustments.mask_adjustments[i].luma_curve, adjustments.mask_adjustments[i].luma_curve_count,
-                adjustments.mask_adjustments[i].red_curve, adjustments.mask_adjustments[i].red_curve_count,
-                adjustments.mask_adjustments[i].green_curve, adjustments.mask_adjustments[i].green_curve_count,
-                adjustments.mask_adjustments[i].blue_curve, adjustments.mask_adjustments[i].blue_curve_count
-            );
-            final_rgb = mix(final_rgb, mask_curved_srgb, influence);
-        }
-    }
-
-    if (adjustments.global.has_lut == 1u) {
-        let lut_color = sample_lut_tetrahedral(final_rgb);
-        
-        final_rgb = mix(final_rgb, lut_color, adjustments.global.lut_intensity);
+    var final_rgb = apply_tone_edits(base_srgb, absolute_coord);
+
+    // HDR pass: restore the highlight range the tone mapper compressed or
+    // clipped, then run that brighter base through the same curves, mask
+    // curves and LUT as the SDR rendition. Range above SDR white follows the
+    // edits' response at white.
+    var hdr_gain = 1.0;
+    if (adjustments.global.hdr_headroom > 1.0) {
+        let headroom = adjustments.global.hdr_headroom;
+        let scene_luma = max(get_luma(composite_rgb_linear), 0.0);
+        let base_linear = srgb_to_linear(clamp(base_srgb, vec3<f32>(0.0), vec3<f32>(1.0)));
+        let tonemapped_luma = get_luma(base_linear);
+        let restore_ratio = scene_luma / max(tonemapped_luma, 0.0001);
+        let restore = clamp(mix(1.0, restore_ratio, smoothstep(0.5, 1.0, tonemapped_luma)), 1.0, headroom);
+
+        let hdr_base_linear = base_linear * restore;
+        let sdr_part = min(hdr_base_linear, vec3<f32>(1.0));
+        let excess = hdr_base_linear - sdr_part;
+        let edited_white = srgb_to_linear(clamp(apply_tone_edits(vec3<f32>(1.0), absolute_coord), vec3<f32>(0.0), vec3<f32>(1.0)));
+        let edited_part = srgb_to_linear(clamp(apply_tone_edits(linear_to_srgb(sdr_part), absolute_coord), vec3<f32>(0.0), vec3<f32>(1.0)));
+        let hdr_luma = get_luma(edited_part + excess * edited_white);
+        let sdr_luma = get_luma(srgb_to_linear(clamp(final_rgb, vec3<f32>(0.0), vec3<f32>(1.0))));
+        hdr_gain = clamp(hdr_luma / max(sdr_luma, 0.0001), 1.0, headroom);
     }
 
     if (adjustments.global.grain_amount > 0.0) {
@@ -1214,6 +1432,19 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
         if (v_amount < 0.0) { final_rgb *= (1.0 + v_amount * vignette_mask); } else { final_rgb = mix(final_rgb, vec3<f32>(1.0), v_amount * vignette_mask); }
     }
 
+    // Float targets receive the linear HDR rendition relative to SDR white;
+    // 8-bit targets receive the log2 gain map that rebuilds it from the SDR one.
+    if (adjustments.global.hdr_headroom > 1.0) {
+        if (adjustments.global.float_output == 1u) {
+            let sdr_linear = srgb_to_linear(clamp(final_rgb, vec3<f32>(0.0), vec3<f32>(1.0)));
+            textureStore(output_texture, id.xy, vec4<f32>(sdr_linear * hdr_gain, original_alpha));
+        } else {
+            let encoded_gain = log2(hdr_gain) / log2(adjustments.global.hdr_headroom);
+            textureStore(output_texture, id.xy, vec4<f32>(vec3<f32>(encoded_gain), 1.0));
+        }
+        return;
+    }
+
     if (adjustments.global.show_clipping == 1u) {
         let HIGHLIGHT_WARNING_COLOR = vec3<f32>(1.0, 0.0, 0.0);
         let SHADOW_WARNING_COLOR = vec3<f32>(0.0, 0.0, 1.0);
@@ -1226,8 +1457,11 @@ fn main(@builtin(global_invocation_id) id: vec3<u32>) {
         }
     }
 
-    let dither_amount = 1.0 / 255.0;
-    final_rgb += dither(id.xy) * dither_amount;
+    // Float targets keep full precision, so only 8-bit output needs dithering.
+    if (adjustments.global.float_output == 0u) {
+        let dither_amount = 1.0 / 255.0;
+        final_rgb += dither(id.xy) * dither_amount;
+    }
 
     textureStore(output_texture, id.xy, vec4<f32>(clamp(final_rgb, vec3<f32>(0.0), vec3<f32>(1.0)), original_alpha));
 }
\ No newline at end of file
@@ -0,0 +1,152 @@
+use anyhow::{Result, anyhow};
+use image::{DynamicImage, RgbImage};
+use lcms2::{DisallowCache, Flags, GlobalContext, Intent, PixelFormat, Profile, Transform};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::color_management::RenderingIntent;
+
+const GAMUT_WARNING_COLOR: [u8; 3] = [255, 0, 255];
+const GAMUT_DELTA_E_THRESHOLD: f32 = 3.0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SoftProofSettings {
+    pub profile_path: String,
+    #[serde(default)]
+    pub rendering_intent: RenderingIntent,
+    #[serde(default)]
+    pub black_point_compensation: bool,
+    #[serde(default)]
+    pub gamut_warning: bool,
+}
+
+// Transforms without the one-pixel cache are Sync, so one SoftProof can be
+// shared by every preview thread.
+type ProofTransform = Transform<[u8; 3], [u8; 3], GlobalContext, DisallowCache>;
+
+pub struct SoftProof {
+    pub settings: SoftProofSettings,
+    simulation: ProofTransform,
+    round_trip: ProofTransform,
+    srgb_to_linear: [f32; 256],
+}
+
+fn srgb_to_lab(rgb: [u8; 3], srgb_to_linear: &[f32; 256]) -> [f32; 3] {
+    let [r, g, b] = rgb.map(|c| srgb_to_linear[c as usize]);
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+    let f = |t: f32| {
+        if t > 0.008856 {
+            t.cbrt()
+        } else {
+            7.787 * t + 16.0 / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+// A color is out of gamut when a colorimetric round trip through the output
+// profile moves it further than a just noticeable difference.
+fn is_out_of_gamut(original: [u8; 3], round_trip: [u8; 3], srgb_to_linear: &[f32; 256]) -> bool {
+    let (a, b) = (
+        srgb_to_lab(original, srgb_to_linear),
+        srgb_to_lab(round_trip, srgb_to_linear),
+    );
+    let delta_e = ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt();
+    delta_e > GAMUT_DELTA_E_THRESHOLD
+}
+
+fn lcms_intent(intent: RenderingIntent) -> Intent {
+    match intent {
+        RenderingIntent::Perceptual => Intent::Perceptual,
+        RenderingIntent::RelativeColorimetric => Intent::RelativeColorimetric,
+    }
+}
+
+impl SoftProof {
+    pub fn new(settings: SoftProofSettings) -> Result<Self> {
+        let output_profile = Profile::new_file(&settings.profile_path)
+            .map_err(|e| anyhow!("Failed to load ICC profile: {}", e))?;
+        let srgb = Profile::new_srgb();
+
+        let mut flags = Flags::SOFT_PROOFING | Flags::NO_CACHE;
+        if settings.black_point_compensation {
+            flags = flags | Flags::BLACKPOINT_COMPENSATION;
+        }
+
+        // The display side stays relative colorimetric so paper white maps to
+        // monitor white; the chosen intent drives the trip into the printer space.
+        let simulation = Transform::new_proofing(
+            &srgb,
+            PixelFormat::RGB_8,
+            &srgb,
+            PixelFormat::RGB_8,
+            &output_profile,
+            lcms_intent(settings.rendering_intent),
+            Intent::RelativeColorimetric,
+            flags,
+        )
+        .map_err(|e| anyhow!("Failed to create proofing transform: {}", e))?;
+
+        let round_trip = Transform::new_proofing(
+            &srgb,
+            PixelFormat::RGB_8,
+            &srgb,
+            PixelFormat::RGB_8,
+            &output_profile,
+            Intent::RelativeColorimetric,
+            Intent::RelativeColorimetric,
+            Flags::SOFT_PROOFING | Flags::NO_CACHE,
+        )
+        .map_err(|e| anyhow!("Failed to create gamut check transform: {}", e))?;
+
+        let mut srgb_to_linear = [0.0; 256];
+        for (i, value) in srgb_to_linear.iter_mut().enumerate() {
+            let v = i as f32 / 255.0;
+            *value = if v <= 0.04045 {
+                v / 12.92
+            } else {
+                ((v + 0.055) / 1.055).powf(2.4)
+            };
+        }
+
+        Ok(Self {
+            settings,
+            simulation,
+            round_trip,
+            srgb_to_linear,
+        })
+    }
+
+    pub fn apply(&self, image: &DynamicImage) -> DynamicImage {
+        let source = image.to_rgb8();
+        let (width, height) = source.dimensions();
+        let pixels: &[[u8; 3]] = bytemuck::cast_slice(source.as_raw());
+
+        let mut simulated = vec![[0u8; 3]; pixels.len()];
+        self.simulation.transform_pixels(pixels, &mut simulated);
+
+        if self.settings.gamut_warning {
+            let mut round_trip = vec![[0u8; 3]; pixels.len()];
+            self.round_trip.transform_pixels(pixels, &mut round_trip);
+
+            let srgb_to_linear = &self.srgb_to_linear;
+            simulated
+                .par_iter_mut()
+                .zip(pixels.par_iter().zip(round_trip.par_iter()))
+                .for_each(|(dst, (original, trip))| {
+                    if is_out_of_gamut(*original, *trip, srgb_to_linear) {
+                        *dst = GAMUT_WARNING_COLOR;
+                    }
+                });
+        }
+
+        let raw: Vec<u8> = bytemuck::cast_slice(&simulated).to_vec();
+        RgbImage::from_raw(width, height, raw)
+            .map(DynamicImage::ImageRgb8)
+            .unwrap_or_else(|| image.clone())
+    }
+}
@@ -0,0 +1,660 @@
+use image::{DynamicImage, GrayImage, Luma};
+use imageproc::edges::canny;
+use imageproc::hough::{LineDetectionOptions, detect_lines};
+use nalgebra::{Matrix3, SymmetricEigen, Vector3};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::image_processing::{apply_coarse_rotation, apply_flip, downscale_f32_image};
+use crate::lens_correction;
+use crate::perspective::{self, PerspectiveParams};
+use crate::projection;
+
+const MAX_CLASSIFY_ANGLE_DEGREES: f64 = 30.0;
+const MAX_SEGMENTS: usize = 80;
+const INLIER_DISTANCE: f64 = 1.5;
+const ROBUST_SCALE: f64 = 0.05;
+const REGULARIZATION: f64 = 0.02;
+
+const STRAIGHTEN_PREVIEW_DIM: u32 = 1024;
+const MAX_STRAIGHTEN_DEGREES: f64 = 15.0;
+const STRAIGHTEN_TOLERANCE_DEGREES: f64 = 1.0;
+const VERTICAL_STRAIGHTEN_WEIGHT: f64 = 0.5;
+const SKY_HORIZON_WEIGHT: f64 = 2.0;
+const MIN_STRAIGHTEN_CONFIDENCE: f64 = 0.35;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum UprightMode {
+    Level,
+    Vertical,
+    Full,
+    Guided,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct GuidePoint {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct UprightGuide {
+    pub start: GuidePoint,
+    pub end: GuidePoint,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineOrientation {
+    Vertical,
+    Horizontal,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    start: (f64, f64),
+    end: (f64, f64),
+    weight: f64,
+    orientation: LineOrientation,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Parameter {
+    Rotate,
+    Vertical,
+    Horizontal,
+}
+
+fn classify(start: (f64, f64), end: (f64, f64)) -> Option<LineOrientation> {
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    let angle = dy.abs().atan2(dx.abs()).to_degrees();
+    if angle >= 90.0 - MAX_CLASSIFY_ANGLE_DEGREES {
+        Some(LineOrientation::Vertical)
+    } else if angle <= MAX_CLASSIFY_ANGLE_DEGREES {
+        Some(LineOrientation::Horizontal)
+    } else {
+        None
+    }
+}
+
+fn to_gray(image: &DynamicImage, is_linear: bool) -> GrayImage {
+    let rgb = image.to_rgb32f();
+    GrayImage::from_fn(rgb.width(), rgb.height(), |x, y| {
+        let p = rgb.get_pixel(x, y);
+        let luma = (0.2126 * p[0] + 0.7152 * p[1] + 0.0722 * p[2]).clamp(0.0, 1.0);
+        let encoded = if is_linear {
+            luma.powf(1.0 / 2.2)
+        } else {
+            luma
+        };
+        Luma([(encoded * 255.0).round() as u8])
+    })
+}
+
+// Hough lines are refined against the edge pixels that voted for them, which
+// recovers sub-degree angles and the extent of the actual segment.
+fn detect_segments(gray: &GrayImage) -> Vec<Segment> {
+    let (width, height) = gray.dimensions();
+    let edges = canny(gray, 20.0, 50.0);
+    let edge_points: Vec<(f64, f64)> = edges
+        .enumerate_pixels()
+        .filter(|(_, _, p)| p[0] > 0)
+        .map(|(x, y, _)| (x as f64, y as f64))
+        .collect();
+
+    let vote_threshold = (width.min(height) / 6).max(20);
+    let options = LineDetectionOptions {
+        vote_threshold,
+        suppression_radius: 8,
+    };
+
+    let mut segments: Vec<Segment> = detect_lines(&edges, options)
+        .into_iter()
+        .filter_map(|line| {
+            let theta = (line.angle_in_degrees as f64).to_radians();
+            let (nx, ny) = (theta.cos(), theta.sin());
+            let r = line.r as f64;
+            let inliers: Vec<(f64, f64)> = edge_points
+                .iter()
+                .copied()
+                .filter(|&(x, y)| (x * nx + y * ny - r).abs() <= INLIER_DISTANCE)
+                .collect();
+            if inliers.len() < vote_threshold as usize {
+                return None;
+            }
+
+            let n = inliers.len() as f64;
+            let (mx, my) = inliers
+                .iter()
+                .fold((0.0, 0.0), |acc, p| (acc.0 + p.0 / n, acc.1 + p.1 / n));
+            let (sxx, sxy, syy) = inliers.iter().fold((0.0, 0.0, 0.0), |acc, p| {
+                let (dx, dy) = (p.0 - mx, p.1 - my);
+                (acc.0 + dx * dx, acc.1 + dx * dy, acc.2 + dy * dy)
+            });
+            let direction_angle = 0.5 * (2.0 * sxy).atan2(sxx - syy);
+            let (dx, dy) = (direction_angle.cos(), direction_angle.sin());
+
+            let (t_min, t_max) = inliers.iter().fold((f64::MAX, f64::MIN), |acc, p| {
+                let t = (p.0 - mx) * dx + (p.1 - my) * dy;
+                (acc.0.min(t), acc.1.max(t))
+            });
+            let start = (mx + dx * t_min, my + dy * t_min);
+            let end = (mx + dx * t_max, my + dy * t_max);
+
+            classify(start, end).map(|orientation| Segment {
+                start,
+                end,
+                weight: n,
+                orientation,
+            })
+        })
+        .collect();
+
+    segments.sort_by(|a, b| b.weight.total_cmp(&a.weight));
+    segments.truncate(MAX_SEGMENTS);
+    segments
+}
+
+fn homogeneous_line(segment: &Segment, center: (f64, f64)) -> Vector3<f64> {
+    let p = Vector3::new(segment.start.0 - center.0, segment.start.1 - center.1, 1.0);
+    let q = Vector3::new(segment.end.0 - center.0, segment.end.1 - center.1, 1.0);
+    let line = p.cross(&q);
+    let norm = (line.x * line.x + line.y * line.y).sqrt();
+    if norm > 1e-9 { line / norm } else { line }
+}
+
+// Least-squares intersection of the lines, relative to the image centre.
+fn vanishing_point(
+    segments: &[Segment],
+    orientation: LineOrientation,
+    center: (f64, f64),
+) -> Option<Vector3<f64>> {
+    let mut scatter = Matrix3::<f64>::zeros();
+    let mut count = 0;
+    for segment in segments.iter().filter(|s| s.orientation == orientation) {
+        let line = homogeneous_line(segment, center);
+        scatter += line * line.transpose() * segment.weight;
+        count += 1;
+    }
+    if count < 2 {
+        return None;
+    }
+    let eigen = SymmetricEigen::new(scatter);
+    let (index, _) = eigen
+        .eigenvalues
+        .iter()
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(b.1))?;
+    Some(eigen.eigenvectors.column(index).into_owned())
+}
+
+// Tilt that sends the vanishing point to infinity, in slider units.
+fn tilt_from_vanishing_point(component: f64, w: f64, focal: f64, invert: bool) -> f64 {
+    if w.abs() < 1e-9 {
+        return 0.0;
+    }
+    let distance = component / w;
+    let angle = if invert {
+        (focal / distance).atan()
+    } else {
+        (-focal / distance).atan()
+    };
+    (angle.to_degrees() / 30.0 * 100.0).clamp(-100.0, 100.0)
+}
+
+fn set_parameter(params: &mut PerspectiveParams, parameter: Parameter, value: f64) {
+    match parameter {
+        Parameter::Rotate => params.perspective_rotate = value.clamp(-10.0, 10.0),
+        Parameter::Vertical => params.perspective_vertical = value.clamp(-100.0, 100.0),
+        Parameter::Horizontal => params.perspective_horizontal = value.clamp(-100.0, 100.0),
+    }
+}
+
+fn get_parameter(params: &PerspectiveParams, parameter: Parameter) -> f64 {
+    match parameter {
+        Parameter::Rotate => params.perspective_rotate,
+        Parameter::Vertical => params.perspective_vertical,
+        Parameter::Horizontal => params.perspective_horizontal,
+    }
+}
+
+fn cost(params: &PerspectiveParams, segments: &[Segment], width: u32, height: u32) -> f64 {
+    let matrix = params.matrix(width, height);
+    let residual: f64 = segments
+        .iter()
+        .map(|segment| {
+            let (Some(p), Some(q)) = (
+                perspective::project(&matrix, segment.start.0, segment.start.1),
+                perspective::project(&matrix, segment.end.0, segment.end.1),
+            ) else {
+                return segment.weight;
+            };
+            let (dx, dy) = (q.0 - p.0, q.1 - p.1);
+            let length = (dx * dx + dy * dy).sqrt().max(1e-9);
+            let error = match segment.orientation {
+                LineOrientation::Vertical => dx / length,
+                LineOrientation::Horizontal => dy / length,
+            };
+            segment.weight * (1.0 + (error / ROBUST_SCALE).powi(2)).ln()
+        })
+        .sum();
+    let total_weight: f64 = segments.iter().map(|s| s.weight).sum::<f64>().max(1e-9);
+    let regularization = REGULARIZATION
+        * ((params.perspective_vertical / 100.0).powi(2)
+            + (params.perspective_horizontal / 100.0).powi(2)
+            + (params.perspective_rotate / 10.0).powi(2));
+    residual / total_weight + regularization
+}
+
+fn nelder_mead(
+    f: impl Fn(&[f64]) -> f64,
+    start: Vec<f64>,
+    step: f64,
+    iterations: usize,
+) -> Vec<f64> {
+    let n = start.len();
+    let mut simplex: Vec<(Vec<f64>, f64)> = (0..=n)
+        .map(|i| {
+            let mut point = start.clone();
+            if i > 0 {
+                point[i - 1] += step;
+            }
+            let value = f(&point);
+            (point, value)
+        })
+        .collect();
+
+    for _ in 0..iterations {
+        simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let centroid: Vec<f64> = (0..n)
+            .map(|j| simplex[..n].iter().map(|(p, _)| p[j]).sum::<f64>() / n as f64)
+            .collect();
+        let towards = |t: f64| -> Vec<f64> {
+            (0..n)
+                .map(|j| centroid[j] + t * (simplex[n].0[j] - centroid[j]))
+                .collect()
+        };
+
+        let reflected = towards(-1.0);
+        let reflected_value = f(&reflected);
+        if reflected_value < simplex[0].1 {
+            let expanded = towards(-2.0);
+            let expanded_value = f(&expanded);
+            simplex[n] = if expanded_value < reflected_value {
+                (expanded, expanded_value)
+            } else {
+                (reflected, reflected_value)
+            };
+        } else if reflected_value < simplex[n - 1].1 {
+            simplex[n] = (reflected, reflected_value);
+        } else {
+            let contracted = towards(0.5);
+            let contracted_value = f(&contracted);
+            if contracted_value < simplex[n].1 {
+                simplex[n] = (contracted, contracted_value);
+            } else {
+                let best = simplex[0].0.clone();
+                for (point, value) in simplex.iter_mut().skip(1) {
+                    for j in 0..n {
+                        point[j] = best[j] + 0.5 * (point[j] - best[j]);
+                    }
+                    *value = f(point);
+                }
+            }
+        }
+    }
+    simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+    simplex.swap_remove(0).0
+}
+
+fn solve(
+    segments: &[Segment],
+    current: PerspectiveParams,
+    parameters: &[Parameter],
+    width: u32,
+    height: u32,
+) -> PerspectiveParams {
+    let center = (width as f64 / 2.0, height as f64 / 2.0);
+    let focal = width.max(height) as f64;
+
+    let mut initial = current;
+    for parameter in parameters {
+        match parameter {
+            Parameter::Vertical => {
+                if let Some(vp) = vanishing_point(segments, LineOrientation::Vertical, center) {
+                    log::info!("Vertical vanishing point (centred): {:?}", vp);
+                    initial.perspective_vertical =
+                        tilt_from_vanishing_point(vp.y, vp.z, focal, false);
+                }
+            }
+            Parameter::Horizontal => {
+                if let Some(vp) = vanishing_point(segments, LineOrientation::Horizontal, center) {
+                    log::info!("Horizontal vanishing point (centred): {:?}", vp);
+                    initial.perspective_horizontal =
+                        tilt_from_vanishing_point(vp.x, vp.z, focal, true);
+                }
+            }
+            Parameter::Rotate => {}
+        }
+    }
+
+    // The analytic guess assumes the sign convention of the tilt, so try both
+    // and the unchanged parameters before refining.
+    let mut candidates = vec![current, initial];
+    let mut flipped = initial;
+    flipped.perspective_vertical = -initial.perspective_vertical;
+    flipped.perspective_horizontal = -initial.perspective_horizontal;
+    candidates.push(flipped);
+    let start = candidates
+        .into_iter()
+        .min_by(|a, b| {
+            cost(a, segments, width, height).total_cmp(&cost(b, segments, width, height))
+        })
+        .unwrap_or(current);
+
+    let evaluate = |values: &[f64]| {
+        let mut params = current;
+        for (parameter, value) in parameters.iter().zip(values) {
+            set_parameter(&mut params, *parameter, *value);
+        }
+        cost(&params, segments, width, height)
+    };
+    let start_values: Vec<f64> = parameters
+        .iter()
+        .map(|p| get_parameter(&start, *p))
+        .collect();
+    let solution = nelder_mead(evaluate, start_values, 5.0, 200);
+
+    let mut result = current;
+    for (parameter, value) in parameters.iter().zip(solution) {
+        set_parameter(&mut result, *parameter, (value * 10.0).round() / 10.0);
+    }
+    result
+}
+
+fn guide_segments(
+    guides: &[UprightGuide],
+    current: &PerspectiveParams,
+    full_size: (u32, u32),
+    preview_scale: f64,
+) -> Vec<Segment> {
+    guides
+        .iter()
+        .filter_map(|guide| {
+            let unwarp = |p: GuidePoint| {
+                let (x, y) =
+                    perspective::unwarp_point(current, full_size.0, full_size.1, (p.x, p.y));
+                (x * preview_scale, y * preview_scale)
+            };
+            let (start, end) = (unwarp(guide.start), unwarp(guide.end));
+            let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+            let orientation = if dy.abs() >= dx.abs() {
+                LineOrientation::Vertical
+            } else {
+                LineOrientation::Horizontal
+            };
+            ((dx * dx + dy * dy).sqrt() > 1.0).then_some(Segment {
+                start,
+                end,
+                weight: 1.0,
+                orientation,
+            })
+        })
+        .collect()
+}
+
+fn guided_parameters(segments: &[Segment]) -> Vec<Parameter> {
+    let verticals = segments
+        .iter()
+        .filter(|s| s.orientation == LineOrientation::Vertical)
+        .count();
+    let horizontals = segments.len() - verticals;
+    match (segments.len(), verticals, horizontals) {
+        (0 | 1, _, _) => vec![Parameter::Rotate],
+        (2, 2, 0) | (2, 1, 1) => vec![Parameter::Rotate, Parameter::Vertical],
+        (2, 0, 2) => vec![Parameter::Rotate, Parameter::Horizontal],
+        _ => vec![
+            Parameter::Rotate,
+            Parameter::Vertical,
+            Parameter::Horizontal,
+        ],
+    }
+}
+
+// `image` is the preview after rotation but before the perspective stage, and
+// guides are given in full-resolution coordinates of the corrected image.
+pub fn calculate_upright(
+    image: &DynamicImage,
+    is_linear: bool,
+    current: PerspectiveParams,
+    mode: UprightMode,
+    guides: &[UprightGuide],
+    full_size: (u32, u32),
+) -> Result<PerspectiveParams, String> {
+    let (width, height) = (image.width(), image.height());
+    if width == 0 || height == 0 || full_size.0 == 0 {
+        return Err("Image is empty.".to_string());
+    }
+
+    let (segments, parameters) = match mode {
+        UprightMode::Guided => {
+            if !(2..=4).contains(&guides.len()) {
+                return Err("Guided upright needs between two and four guide lines.".to_string());
+            }
+            let preview_scale = width as f64 / full_size.0 as f64;
+            let segments = guide_segments(guides, &current, full_size, preview_scale);
+            let parameters = guided_parameters(&segments);
+            (segments, parameters)
+        }
+        _ => {
+            let segments = detect_segments(&to_gray(image, is_linear));
+            let parameters = match mode {
+                UprightMode::Level => vec![Parameter::Rotate],
+                UprightMode::Vertical => vec![Parameter::Rotate, Parameter::Vertical],
+                _ => vec![
+                    Parameter::Rotate,
+                    Parameter::Vertical,
+                    Parameter::Horizontal,
+                ],
+            };
+            (segments, parameters)
+        }
+    };
+
+    log::info!(
+        "Upright ({:?}) solving {:?} from {} line segments",
+        mode,
+        parameters,
+        segments.len()
+    );
+    if segments.is_empty() {
+        return Err("No usable lines were found in the image.".to_string());
+    }
+
+    let mut base = current;
+    for parameter in &parameters {
+        set_parameter(&mut base, *parameter, 0.0);
+    }
+    Ok(solve(&segments, base, &parameters, width, height))
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct StraightenEstimate {
+    pub rotation: f64,
+    pub confidence: f64,
+    pub accepted: bool,
+}
+
+// Brings a freshly loaded image into the orientation the fine rotation is
+// applied to, small enough for line detection.
+pub fn straighten_preview(image: &DynamicImage, adjustments: &Value, is_raw: bool) -> DynamicImage {
+    let preview = downscale_f32_image(image, STRAIGHTEN_PREVIEW_DIM, STRAIGHTEN_PREVIEW_DIM);
+    let preview = lens_correction::apply_lens_correction(preview, adjustments, is_raw);
+    let preview = apply_coarse_rotation(
+        preview,
+        adjustments["orientationSteps"].as_u64().unwrap_or(0) as u8,
+    );
+    let preview = apply_flip(
+        preview,
+        adjustments["flipHorizontal"].as_bool().unwrap_or(false),
+        adjustments["flipVertical"].as_bool().unwrap_or(false),
+    );
+    projection::apply_projection(preview, adjustments)
+}
+
+// Clockwise tilt of a segment relative to the axis it was classified against.
+fn deviation_degrees(segment: &Segment) -> f64 {
+    let (dx, dy) = (
+        segment.end.0 - segment.start.0,
+        segment.end.1 - segment.start.1,
+    );
+    let mut angle = dy.atan2(dx).to_degrees();
+    if segment.orientation == LineOrientation::Vertical {
+        angle -= 90.0;
+    }
+    while angle > 90.0 {
+        angle -= 180.0;
+    }
+    while angle <= -90.0 {
+        angle += 180.0;
+    }
+    angle
+}
+
+fn fit_line(points: &[(f64, f64)]) -> Option<(f64, f64)> {
+    let n = points.len() as f64;
+    let (mx, my) = points
+        .iter()
+        .fold((0.0, 0.0), |acc, p| (acc.0 + p.0 / n, acc.1 + p.1 / n));
+    let (sxx, sxy) = points.iter().fold((0.0, 0.0), |acc, p| {
+        let dx = p.0 - mx;
+        (acc.0 + dx * dx, acc.1 + dx * (p.1 - my))
+    });
+    (sxx > 1e-9).then(|| {
+        let slope = sxy / sxx;
+        (slope, my - slope * mx)
+    })
+}
+
+// The sky mask boundary is only trusted when it is close to a straight line
+// across most of the frame, which rules out trees and skylines.
+fn sky_horizon_segment(mask: &GrayImage) -> Option<Segment> {
+    let (width, height) = mask.dimensions();
+    let points: Vec<(f64, f64)> = (0..width)
+        .filter(|&x| mask.get_pixel(x, 0)[0] >= 128)
+        .filter_map(|x| {
+            (0..height)
+                .find(|&y| mask.get_pixel(x, y)[0] < 128)
+                .map(|y| (x as f64, y as f64))
+        })
+        .collect();
+    if points.len() < width as usize / 2 {
+        return None;
+    }
+
+    let (slope, intercept) = fit_line(&points)?;
+    let mut residuals: Vec<f64> = points
+        .iter()
+        .map(|p| (p.1 - (slope * p.0 + intercept)).abs())
+        .collect();
+    residuals.sort_by(f64::total_cmp);
+    let cutoff = (residuals[residuals.len() / 2] * 2.5).max(2.0);
+
+    let inliers: Vec<(f64, f64)> = points
+        .iter()
+        .copied()
+        .filter(|p| (p.1 - (slope * p.0 + intercept)).abs() <= cutoff)
+        .collect();
+    if inliers.len() < width as usize / 2 {
+        return None;
+    }
+
+    let (slope, intercept) = fit_line(&inliers)?;
+    let rms = (inliers
+        .iter()
+        .map(|p| (p.1 - (slope * p.0 + intercept)).powi(2))
+        .sum::<f64>()
+        / inliers.len() as f64)
+        .sqrt();
+    if rms > height as f64 * 0.01 {
+        return None;
+    }
+
+    let (x0, x1) = inliers.iter().fold((f64::MAX, f64::MIN), |acc, p| {
+        (acc.0.min(p.0), acc.1.max(p.0))
+    });
+    Some(Segment {
+        start: (x0, slope * x0 + intercept),
+        end: (x1, slope * x1 + intercept),
+        weight: inliers.len() as f64 * SKY_HORIZON_WEIGHT,
+        orientation: LineOrientation::Horizontal,
+    })
+}
+
+pub fn estimate_straighten(
+    image: &DynamicImage,
+    is_linear: bool,
+    sky_mask: Option<&GrayImage>,
+) -> StraightenEstimate {
+    let (width, height) = (image.width(), image.height());
+    let mut segments = detect_segments(&to_gray(image, is_linear));
+    if let Some(horizon) = sky_mask
+        .filter(|mask| mask.dimensions() == (width, height))
+        .and_then(sky_horizon_segment)
+    {
+        segments.push(horizon);
+    }
+
+    // Converging verticals from keystoning pull in opposite directions, so
+    // they count for less than horizontals.
+    let candidates: Vec<(f64, f64)> = segments
+        .iter()
+        .map(|segment| {
+            let weight = match segment.orientation {
+                LineOrientation::Horizontal => segment.weight,
+                LineOrientation::Vertical => segment.weight * VERTICAL_STRAIGHTEN_WEIGHT,
+            };
+            (deviation_degrees(segment), weight)
+        })
+        .filter(|(deviation, _)| deviation.abs() <= MAX_STRAIGHTEN_DEGREES)
+        .collect();
+
+    let total_weight: f64 = candidates.iter().map(|(_, w)| w).sum();
+    if total_weight <= 0.0 {
+        return StraightenEstimate {
+            rotation: 0.0,
+            confidence: 0.0,
+            accepted: false,
+        };
+    }
+
+    let support = |center: f64| {
+        candidates
+            .iter()
+            .filter(|(d, _)| (d - center).abs() <= STRAIGHTEN_TOLERANCE_DEGREES)
+            .fold((0.0, 0.0), |acc, (d, w)| (acc.0 + w, acc.1 + d * w))
+    };
+    let (inlier_weight, weighted_sum) = candidates
+        .iter()
+        .map(|(d, _)| support(*d))
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .unwrap_or((0.0, 0.0));
+    let deviation = weighted_sum / inlier_weight;
+
+    let agreement = inlier_weight / total_weight;
+    let coverage = (inlier_weight / width.max(height) as f64).min(1.0);
+    let confidence = (agreement * coverage).clamp(0.0, 1.0);
+
+    log::info!(
+        "Auto straighten: {:.2} degrees from {} segments, confidence {:.2}",
+        -deviation,
+        candidates.len(),
+        confidence
+    );
+
+    StraightenEstimate {
+        rotation: (-deviation * 100.0).round() / 100.0,
+        confidence: (confidence * 100.0).round() / 100.0,
+        accepted: confidence >= MIN_STRAIGHTEN_CONFIDENCE,
+    }
+}
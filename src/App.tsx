@@ -35,6 +35,9 @@ import {
   Users,
   Gauge,
   Grip,
+  Ruler,
+  SunMedium,
+  Layers,
 } from 'lucide-react';
 import TitleBar from './window/TitleBar';
 import CommunityPage from './components/panel/CommunityPage';
@@ -47,6 +50,7 @@ import { ImageDimensions } from './hooks/useImageRenderSize';
 import RightPanelSwitcher from './components/panel/right/RightPanelSwitcher';
 import MetadataPanel from './components/panel/right/MetadataPanel';
 import CropPanel from './components/panel/right/CropPanel';
+import LiquifyPanel from './components/panel/right/LiquifyPanel';
 import PresetsPanel from './components/panel/right/PresetsPanel';
 import AIPanel from './components/panel/right/AIPanel';
 import ExportPanel from './components/panel/right/ExportPanel';
@@ -61,6 +65,8 @@ import ConfirmModal from './components/modals/ConfirmModal';
 import ImportSettingsModal from './components/modals/ImportSettingsModal';
 import RenameFileModal from './components/modals/RenameFileModal';
 import PanoramaModal from './components/modals/PanoramaModal';
+import HdrModal from './components/modals/HdrModal';
+import FocusStackModal from './components/modals/FocusStackModal';
 import DenoiseModal from './components/modals/DenoiseModal';
 import CollageModal from './components/modals/CollageModal';
 import CopyPasteSettingsModal from './components/modals/CopyPasteSettingsModal';
@@ -74,11 +80,16 @@ import {
   COLOR_LABELS,
   Coord,
   COPYABLE_ADJUSTMENT_KEYS,
+  getPerspectiveParams,
+  getProjectionParams,
   INITIAL_ADJUSTMENTS,
+  LiquifyMesh,
+  LiquifyTool,
   MaskContainer,
   normalizeLoadedAdjustments,
   PasteMode,
   CopyPasteSettings,
+  UprightGuide,
 } from './utils/adjustments';
 import { generatePaletteFromImage } from './utils/palette';
 import { useKeyboardShortcuts } from './hooks/useKeyboardShortcuts';
@@ -89,6 +100,8 @@ import {
   ExportState,
   IMPORT_TIMEOUT,
   ImportState,
+  INITIAL_SOFT_PROOF_SETTINGS,
+  SoftProofSettings,
   Status,
 } from './components/panel/right/ExportImportProperties';
 import {
@@ -100,12 +113,14 @@ import {
   Option,
   OPTION_SEPARATOR,
   LibraryViewMode,
+  LiquifySettings,
   Panel,
   Progress,
   RawStatus,
   SelectedImage,
   SortCriteria,
   SortDirection,
+  StraightenEstimate,
   SupportedTypes,
   Theme,
   TransformState,
@@ -163,6 +178,24 @@ interface PanoramaModalState {
   stitchingSourcePaths: Array<string>;
 }
 
+interface HdrModalState {
+  error: string | null;
+  finalImageBase64: string | null;
+  isOpen: boolean;
+  progressMessage: string | null;
+  sourcePaths: Array<string>;
+}
+
+interface FocusStackModalState {
+  depthMapBase64: string | null;
+  error: string | null;
+  finalImageBase64: string | null;
+  isOpen: boolean;
+  progressMessage: string | null;
+  sourcePaths: Array<string>;
+  warnings: Array<string>;
+}
+
 interface DenoiseModalState {
   isOpen: boolean;
   isProcessing: boolean;
@@ -335,6 +368,8 @@ function App() {
   const [thumbnailAspectRatio, setThumbnailAspectRatio] = useState(ThumbnailAspectRatio.Cover);
   const [copiedAdjustments, setCopiedAdjustments] = useState<Adjustments | null>(null);
   const [isStraightenActive, setIsStraightenActive] = useState(false);
+  const [isUprightGuideActive, setIsUprightGuideActive] = useState(false);
+  const [uprightGuides, setUprightGuides] = useState<Array<UprightGuide>>([]);
   const [isWbPickerActive, setIsWbPickerActive] = useState(false);
   const [copiedFilePaths, setCopiedFilePaths] = useState<Array<string>>([]);
   const [aiModelDownloadStatus, setAiModelDownloadStatus] = useState<string | null>(null);
@@ -354,6 +389,11 @@ function App() {
     feather: 50,
     tool: ToolType.Brush,
   });
+  const [liquifySettings, setLiquifySettings] = useState<LiquifySettings>({
+    size: 100,
+    strength: 50,
+    tool: LiquifyTool.Forward,
+  });
   const [isCreateFolderModalOpen, setIsCreateFolderModalOpen] = useState(false);
   const [isRenameFolderModalOpen, setIsRenameFolderModalOpen] = useState(false);
   const [isRenameFileModalOpen, setIsRenameFileModalOpen] = useState(false);
@@ -371,6 +411,22 @@ function App() {
     progressMessage: '',
     stitchingSourcePaths: [],
   });
+  const [hdrModalState, setHdrModalState] = useState<HdrModalState>({
+    error: null,
+    finalImageBase64: null,
+    isOpen: false,
+    progressMessage: '',
+    sourcePaths: [],
+  });
+  const [focusStackModalState, setFocusStackModalState] = useState<FocusStackModalState>({
+    depthMapBase64: null,
+    error: null,
+    finalImageBase64: null,
+    isOpen: false,
+    progressMessage: '',
+    sourcePaths: [],
+    warnings: [],
+  });
   const [denoiseModalState, setDenoiseModalState] = useState<DenoiseModalState>({
     isOpen: false,
     isProcessing: false,
@@ -404,6 +460,8 @@ function App() {
   const isProgrammaticZoom = useRef(false);
   const isInitialMount = useRef(true);
   const currentFolderPathRef = useRef<string>(currentFolderPath);
+  const adjustmentsRef = useRef<Adjustments>(adjustments);
+  const liquifyQueueRef = useRef<Promise<void>>(Promise.resolve());
 
   const [exportState, setExportState] = useState<ExportState>({
     errorMessage: '',
@@ -411,6 +469,8 @@ function App() {
     status: Status.Idle,
   });
 
+  const [softProofSettings, setSoftProofSettings] = useState<SoftProofSettings>(INITIAL_SOFT_PROOF_SETTINGS);
+
   const [importState, setImportState] = useState<ImportState>({
     errorMessage: '',
     path: '',
@@ -422,6 +482,10 @@ function App() {
     currentFolderPathRef.current = currentFolderPath;
   }, [currentFolderPath]);
 
+  useEffect(() => {
+    adjustmentsRef.current = adjustments;
+  }, [adjustments]);
+
   useEffect(() => {
     if (!isCopied) {
       return;
@@ -465,6 +529,30 @@ function App() {
     [setAdjustments],
   );
 
+  const handleAddUprightGuide = useCallback((guide: UprightGuide) => {
+    setUprightGuides((prev: Array<UprightGuide>) => (prev.length >= 4 ? prev : [...prev, guide]));
+  }, []);
+
+  // Strokes are queued so each one is applied on top of the mesh the previous one produced.
+  const handleLiquifyStroke = useCallback(
+    (points: Array<Coord>, size: number) => {
+      liquifyQueueRef.current = liquifyQueueRef.current.then(async () => {
+        try {
+          const mesh: LiquifyMesh = await invoke(Invokes.ApplyLiquifyStroke, {
+            jsAdjustments: adjustmentsRef.current,
+            stroke: { points, size, strength: liquifySettings.strength, tool: liquifySettings.tool },
+          });
+          adjustmentsRef.current = { ...adjustmentsRef.current, liquify: mesh };
+          setAdjustments((prev: Adjustments) => ({ ...prev, liquify: mesh }));
+        } catch (err) {
+          console.error('Failed to apply liquify stroke:', err);
+          setError(`Failed to apply liquify stroke: ${err}`);
+        }
+      });
+    },
+    [liquifySettings.strength, liquifySettings.tool, setAdjustments],
+  );
+
   const toggleWbPicker = useCallback(() => {
     setIsWbPickerActive((prev) => !prev);
   }, []);
@@ -473,6 +561,23 @@ function App() {
     //setIsWbPickerActive(false); // lets keep it active
   }, []);
 
+  const handleSampleRawWhiteBalance = useCallback(
+    async (point: Coord) => {
+      try {
+        const whiteBalance: Partial<Adjustments> = await invoke(Invokes.SampleWhiteBalance, {
+          jsAdjustments: adjustmentsRef.current,
+          x: point.x,
+          y: point.y,
+        });
+        setAdjustments((prev: Adjustments) => ({ ...prev, ...whiteBalance }));
+      } catch (err) {
+        console.error('Failed to sample white balance:', err);
+        setError(`Failed to sample white balance: ${err}`);
+      }
+    },
+    [setAdjustments],
+  );
+
   useEffect(() => {
     setLiveAdjustments(historyAdjustments);
   }, [historyAdjustments]);
@@ -489,13 +594,28 @@ function App() {
   const geometricAdjustmentsKey = useMemo(() => {
     if (!adjustments) return '';
     const { crop, rotation, flipHorizontal, flipVertical, orientationSteps } = adjustments;
-    return JSON.stringify({ crop, rotation, flipHorizontal, flipVertical, orientationSteps });
+    const perspective = getPerspectiveParams(adjustments);
+    const projection = getProjectionParams(adjustments);
+    return JSON.stringify({ crop, rotation, flipHorizontal, flipVertical, orientationSteps, perspective, projection });
   }, [
     adjustments?.crop,
     adjustments?.rotation,
     adjustments?.flipHorizontal,
     adjustments?.flipVertical,
     adjustments?.orientationSteps,
+    adjustments?.perspectiveVertical,
+    adjustments?.perspectiveHorizontal,
+    adjustments?.perspectiveRotate,
+    adjustments?.perspectiveAspect,
+    adjustments?.perspectiveScale,
+    adjustments?.perspectiveXOffset,
+    adjustments?.perspectiveYOffset,
+    adjustments?.projectionSource,
+    adjustments?.projectionTarget,
+    adjustments?.projectionFocalLength,
+    adjustments?.projectionCropFactor,
+    adjustments?.projectionPaniniDistance,
+    adjustments?.projectionScale,
   ]);
 
   const visualAdjustmentsKey = useMemo(() => {
@@ -676,7 +796,10 @@ function App() {
           endPoint: [endPoint.x, endPoint.y],
           flipHorizontal: adjustments.flipHorizontal,
           flipVertical: adjustments.flipVertical,
+          liquify: adjustments.liquify,
           orientationSteps: adjustments.orientationSteps,
+          perspective: getPerspectiveParams(adjustments),
+          projection: getProjectionParams(adjustments),
           path: selectedImage.path,
           rotation: adjustments.rotation,
           startPoint: [startPoint.x, startPoint.y],
@@ -800,7 +923,10 @@ function App() {
         endPoint: [endPoint.x, endPoint.y],
         flipHorizontal: adjustments.flipHorizontal,
         flipVertical: adjustments.flipVertical,
+        liquify: adjustments.liquify,
         orientationSteps: adjustments.orientationSteps,
+        perspective: getPerspectiveParams(adjustments),
+        projection: getProjectionParams(adjustments),
         path: selectedImage.path,
         rotation: adjustments.rotation,
         startPoint: [startPoint.x, startPoint.y],
@@ -830,7 +956,10 @@ function App() {
       const newParameters = await invoke(Invokes.GenerateAiForegroundMask, {
         flipHorizontal: adjustments.flipHorizontal,
         flipVertical: adjustments.flipVertical,
+        liquify: adjustments.liquify,
         orientationSteps: adjustments.orientationSteps,
+        perspective: getPerspectiveParams(adjustments),
+        projection: getProjectionParams(adjustments),
         rotation: adjustments.rotation,
       });
 
@@ -858,7 +987,10 @@ function App() {
       const newParameters = await invoke(Invokes.GenerateAiSkyMask, {
         flipHorizontal: adjustments.flipHorizontal,
         flipVertical: adjustments.flipVertical,
+        liquify: adjustments.liquify,
         orientationSteps: adjustments.orientationSteps,
+        perspective: getPerspectiveParams(adjustments),
+        projection: getProjectionParams(adjustments),
         rotation: adjustments.rotation,
       });
 
@@ -1923,6 +2055,26 @@ function App() {
     }
   };
 
+  const handleAutoStraighten = async () => {
+    if (!selectedImage) {
+      return;
+    }
+    try {
+      const estimate: StraightenEstimate = await invoke(Invokes.CalculateAutoStraighten, {
+        jsAdjustments: adjustments,
+      });
+      if (!estimate.accepted) {
+        setError('Could not find a reliable horizon or vertical lines to straighten.');
+        return;
+      }
+      setIsStraightenActive(false);
+      setAdjustments((prev: Adjustments) => ({ ...prev, rotation: estimate.rotation, crop: null }));
+    } catch (err) {
+      console.error('Failed to calculate auto straighten:', err);
+      setError(`Failed to straighten image: ${err}`);
+    }
+  };
+
   const handleRate = useCallback(
     (newRating: number, paths?: Array<string>) => {
       const pathsToRate =
@@ -2251,6 +2403,8 @@ function App() {
     isCopyPasteSettingsModalOpen ||
     confirmModalState.isOpen ||
     panoramaModalState.isOpen ||
+    hdrModalState.isOpen ||
+    focusStackModalState.isOpen ||
     cullingModalState.isOpen ||
     collageModalState.isOpen;
 
@@ -2314,6 +2468,14 @@ function App() {
           setIsAdjusting(false);
         }
       }),
+      listen('preview-update-embedded', (event: any) => {
+        if (isEffectActive) {
+          const imageData = new Uint8Array(event.payload);
+          const blob = new Blob([imageData], { type: 'image/jpeg' });
+          const url = URL.createObjectURL(blob);
+          setFinalPreviewUrl(url);
+        }
+      }),
       listen('preview-update-uncropped', (event: any) => {
         if (isEffectActive) {
           const imageData = new Uint8Array(event.payload);
@@ -2572,6 +2734,101 @@ function App() {
     };
   }, []);
 
+  useEffect(() => {
+    let isEffectActive = true;
+
+    // A merge that was cancelled can still report before it stops, so events
+    // only update a modal that is still open.
+    const unlistenProgress = listen('hdr-progress', (event: any) => {
+      if (isEffectActive) {
+        setHdrModalState((prev: HdrModalState) =>
+          prev.isOpen ? { ...prev, error: null, finalImageBase64: null, progressMessage: event.payload } : prev,
+        );
+      }
+    });
+
+    const unlistenComplete = listen('hdr-complete', (event: any) => {
+      if (isEffectActive) {
+        const { base64 } = event.payload;
+        setHdrModalState((prev: HdrModalState) =>
+          prev.isOpen ? { ...prev, error: null, finalImageBase64: base64, progressMessage: 'HDR Ready' } : prev,
+        );
+      }
+    });
+
+    const unlistenError = listen('hdr-error', (event: any) => {
+      if (isEffectActive) {
+        setHdrModalState((prev: HdrModalState) =>
+          prev.isOpen
+            ? { ...prev, error: String(event.payload), finalImageBase64: null, progressMessage: 'An error occurred.' }
+            : prev,
+        );
+      }
+    });
+
+    return () => {
+      isEffectActive = false;
+      unlistenProgress.then((f: any) => f());
+      unlistenComplete.then((f: any) => f());
+      unlistenError.then((f: any) => f());
+    };
+  }, []);
+
+  useEffect(() => {
+    let isEffectActive = true;
+
+    const unlistenProgress = listen('focus-stack-progress', (event: any) => {
+      if (isEffectActive) {
+        setFocusStackModalState((prev: FocusStackModalState) =>
+          prev.isOpen ? { ...prev, error: null, finalImageBase64: null, progressMessage: event.payload } : prev,
+        );
+      }
+    });
+
+    const unlistenWarning = listen('focus-stack-warning', (event: any) => {
+      if (isEffectActive) {
+        setFocusStackModalState((prev: FocusStackModalState) =>
+          prev.isOpen ? { ...prev, warnings: [...prev.warnings, String(event.payload)] } : prev,
+        );
+      }
+    });
+
+    const unlistenComplete = listen('focus-stack-complete', (event: any) => {
+      if (isEffectActive) {
+        const { base64, depthMapBase64 } = event.payload;
+        setFocusStackModalState((prev: FocusStackModalState) =>
+          prev.isOpen
+            ? {
+                ...prev,
+                depthMapBase64,
+                error: null,
+                finalImageBase64: base64,
+                progressMessage: 'Focus Stack Ready',
+              }
+            : prev,
+        );
+      }
+    });
+
+    const unlistenError = listen('focus-stack-error', (event: any) => {
+      if (isEffectActive) {
+        setFocusStackModalState((prev: FocusStackModalState) =>
+          prev.isOpen
+            ? { ...prev, error: String(event.payload), finalImageBase64: null, progressMessage: 'An error occurred.' }
+            : prev,
+        );
+      }
+    });
+
+    return () => {
+      isEffectActive = false;
+      unlistenProgress.then((f: any) => f());
+      unlistenWarning.then((f: any) => f());
+      unlistenComplete.then((f: any) => f());
+      unlistenError.then((f: any) => f());
+    };
+  }, []);
+
   useEffect(() => {
     let isEffectActive = true;
 
@@ -2613,7 +2870,7 @@ function App() {
     };
   }, []);
 
-  const handleSavePanorama = async (): Promise<string> => {
+  const handleSavePanorama = async (asDng = false): Promise<string> => {
     if (panoramaModalState.stitchingSourcePaths.length === 0) {
       const err = 'Source paths for panorama not found.';
       setPanoramaModalState((prev: PanoramaModalState) => ({ ...prev, error: err }));
@@ -2623,6 +2880,7 @@ function App() {
     try {
       const savedPath: string = await invoke(Invokes.SavePanorama, {
         firstPathStr: panoramaModalState.stitchingSourcePaths[0],
+        asDng,
       });
       await refreshImageList();
       return savedPath;
@@ -2633,6 +2891,48 @@ function App() {
     }
   };
 
+  const handleSaveHdr = async (asDng = false): Promise<string> => {
+    if (hdrModalState.sourcePaths.length === 0) {
+      const err = 'Source paths for HDR merge not found.';
+      setHdrModalState((prev: HdrModalState) => ({ ...prev, error: err }));
+      throw new Error(err);
+    }
+
+    try {
+      const savedPath: string = await invoke(Invokes.SaveHdr, {
+        firstPathStr: hdrModalState.sourcePaths[0],
+        asDng,
+      });
+      await refreshImageList();
+      return savedPath;
+    } catch (err) {
+      console.error('Failed to save HDR image:', err);
+      setHdrModalState((prev: HdrModalState) => ({ ...prev, error: String(err) }));
+      throw err;
+    }
+  };
+
+  const handleSaveFocusStack = async (asDng = false): Promise<string> => {
+    if (focusStackModalState.sourcePaths.length === 0) {
+      const err = 'Source paths for focus stack not found.';
+      setFocusStackModalState((prev: FocusStackModalState) => ({ ...prev, error: err }));
+      throw new Error(err);
+    }
+
+    try {
+      const savedPath: string = await invoke(Invokes.SaveFocusStack, {
+        firstPathStr: focusStackModalState.sourcePaths[0],
+        asDng,
+      });
+      await refreshImageList();
+      return savedPath;
+    } catch (err) {
+      console.error('Failed to save focus stack:', err);
+      setFocusStackModalState((prev: FocusStackModalState) => ({ ...prev, error: String(err) }));
+      throw err;
+    }
+  };
+
   const handleApplyDenoise = useCallback(async (intensity: number) => {
     if (!denoiseModalState.targetPath) return;
     
@@ -2657,10 +2957,11 @@ function App() {
     }
   }, [denoiseModalState.targetPath]);
 
-  const handleSaveDenoisedImage = async (): Promise<string> => {
+  const handleSaveDenoisedImage = async (asDng = false): Promise<string> => {
     if (!denoiseModalState.targetPath) throw new Error("No target path");
     const savedPath = await invoke<string>(Invokes.SaveDenoisedImage, {
-        originalPathStr: denoiseModalState.targetPath
+        originalPathStr: denoiseModalState.targetPath,
+        asDng,
     });
     await refreshImageList();
     return savedPath;
@@ -2692,6 +2993,22 @@ function App() {
     };
   }, [adjustments, selectedImage?.path, selectedImage?.isReady, applyAdjustments, debouncedSave]);
 
+  useEffect(() => {
+    const { enabled, profilePath, ...settings } = softProofSettings;
+    const payload = enabled && profilePath ? { ...settings, profilePath } : null;
+    invoke(Invokes.SetSoftProof, { settings: payload })
+      .then(() => {
+        if (selectedImage?.isReady) {
+          applyAdjustments(adjustments);
+        }
+      })
+      .catch((err) => {
+        console.error('Failed to set soft proof:', err);
+        setError(`Soft proofing failed: ${err}`);
+        setSoftProofSettings((prev: SoftProofSettings) => ({ ...prev, enabled: false }));
+      });
+  }, [softProofSettings]);
+
   useEffect(() => {
     if (activeRightPanel === Panel.Crop && selectedImage?.isReady) {
       debouncedGenerateUncroppedPreview(adjustments);
@@ -2913,6 +3230,9 @@ function App() {
             if (currentSelected && currentSelected.path === selectedImage.path) {
             return {
                 ...currentSelected,
+                asShotWhiteBalance: loadImageResult.as_shot_white_balance,
+                detectedLens: loadImageResult.detected_lens,
+                lensOptics: loadImageResult.lens_optics,
                 exif: loadImageResult.exif,
                 height: loadImageResult.height,
                 isRaw: loadImageResult.is_raw,
@@ -3127,6 +3447,7 @@ function App() {
       },
       { type: OPTION_SEPARATOR },
       { label: 'Auto Adjust Image', icon: Aperture, onClick: handleAutoAdjustments },
+      { label: 'Auto Straighten', icon: Ruler, onClick: handleAutoStraighten },
       {
         label: 'Rating',
         icon: Star,
@@ -3280,10 +3601,10 @@ function App() {
       }
     };
 
-    const handleApplyAutoAdjustmentsToSelection = () => {
+    const handleApplyAutoAdjustmentsToSelection = (includeStraighten: boolean) => {
       if (finalSelection.length === 0) return;
 
-      invoke(Invokes.ApplyAutoAdjustmentsToPaths, { paths: finalSelection })
+      invoke(Invokes.ApplyAutoAdjustmentsToPaths, { paths: finalSelection, includeStraighten })
         .then(async () => {
           if (selectedImage && finalSelection.includes(selectedImage.path)) {
             const metadata: Metadata = await invoke(Invokes.LoadMetadata, { path: selectedImage.path });
@@ -3379,7 +3700,12 @@ function App() {
           {
             label: autoAdjustLabel,
             icon: Aperture,
-            onClick: handleApplyAutoAdjustmentsToSelection,
+            onClick: () => handleApplyAutoAdjustmentsToSelection(false),
+          },
+          {
+            label: `${autoAdjustLabel} & Straighten`,
+            icon: Ruler,
+            onClick: () => handleApplyAutoAdjustmentsToSelection(true),
           },
           {
             disabled: !isSingleSelection,
@@ -3424,6 +3750,46 @@ function App() {
               });
             },
           },
+          {
+            disabled: selectionCount < 2,
+            icon: SunMedium,
+            label: 'Merge to HDR',
+            onClick: () => {
+              setHdrModalState({
+                error: null,
+                finalImageBase64: null,
+                isOpen: true,
+                progressMessage: 'Starting HDR merge...',
+                sourcePaths: finalSelection,
+              });
+              invoke(Invokes.MergeHdr, { paths: finalSelection }).catch((err) => {
+                setHdrModalState((prev: HdrModalState) =>
+                  prev.isOpen ? { ...prev, error: String(err), progressMessage: 'Failed to start.' } : prev,
+                );
+              });
+            },
+          },
+          {
+            disabled: selectionCount < 2,
+            icon: Layers,
+            label: 'Focus Stack',
+            onClick: () => {
+              setFocusStackModalState({
+                depthMapBase64: null,
+                error: null,
+                finalImageBase64: null,
+                isOpen: true,
+                progressMessage: 'Starting focus stack...',
+                sourcePaths: finalSelection,
+                warnings: [],
+              });
+              invoke(Invokes.FocusStackImages, { paths: finalSelection }).catch((err) => {
+                setFocusStackModalState((prev: FocusStackModalState) =>
+                  prev.isOpen ? { ...prev, error: String(err), progressMessage: 'Failed to start.' } : prev,
+                );
+              });
+            },
+          },
           {
             icon: LayoutTemplate,
             label: collageLabel,
@@ -3778,11 +4144,15 @@ function App() {
               isLoading={isViewLoading}
               isMaskControlHovered={isMaskControlHovered}
               isStraightenActive={isStraightenActive}
+              isUprightGuideActive={isUprightGuideActive}
               isWaveformVisible={isWaveformVisible}
+              liquifySettings={liquifySettings}
+              onAddUprightGuide={handleAddUprightGuide}
               onBackToLibrary={handleBackToLibrary}
               onCloseWaveform={() => setIsWaveformVisible(false)}
               onContextMenu={handleEditorContextMenu}
               onGenerateAiMask={handleGenerateAiMask}
+              onLiquifyStroke={handleLiquifyStroke}
               onQuickErase={handleQuickErase}
               onRedo={redo}
               onSelectAiSubMask={setActiveAiSubMaskId}
@@ -3795,6 +4165,7 @@ function App() {
               renderedRightPanel={renderedRightPanel}
               selectedImage={selectedImage}
               isWbPickerActive={isWbPickerActive}
+              onSampleRawWhiteBalance={selectedImage?.asShotWhiteBalance ? handleSampleRawWhiteBalance : undefined}
               onWbPicked={handleWbPicked}
               setAdjustments={setAdjustments}
               setShowOriginal={setShowOriginal}
@@ -3805,6 +4176,7 @@ function App() {
               transformedOriginalUrl={transformedOriginalUrl}
               uncroppedAdjustedPreviewUrl={uncroppedAdjustedPreviewUrl}
               updateSubMask={updateSubMask}
+              uprightGuides={uprightGuides}
               waveform={waveform}
               onDisplaySizeChange={handleDisplaySizeChange}
               onInitialFitScale={setInitialFitScale}
@@ -3897,9 +4269,23 @@ function App() {
                         <CropPanel
                           adjustments={adjustments}
                           isStraightenActive={isStraightenActive}
+                          isUprightGuideActive={isUprightGuideActive}
+                          onAutoStraighten={handleAutoStraighten}
                           selectedImage={selectedImage}
                           setAdjustments={setAdjustments}
                           setIsStraightenActive={setIsStraightenActive}
+                          setIsUprightGuideActive={setIsUprightGuideActive}
+                          setUprightGuides={setUprightGuides}
+                          uprightGuides={uprightGuides}
+                        />
+                      )}
+                      {renderedRightPanel === Panel.Liquify && (
+                        <LiquifyPanel
+                          adjustments={adjustments}
+                          liquifySettings={liquifySettings}
+                          selectedImage={selectedImage}
+                          setAdjustments={setAdjustments}
+                          setLiquifySettings={setLiquifySettings}
                         />
                       )}
                       {renderedRightPanel === Panel.Masks && (
@@ -3944,6 +4330,8 @@ function App() {
                           multiSelectedPaths={multiSelectedPaths}
                           selectedImage={selectedImage}
                           setExportState={setExportState}
+                          setSoftProofSettings={setSoftProofSettings}
+                          softProofSettings={softProofSettings}
                         />
                       )}
                       {renderedRightPanel === Panel.Ai && (
@@ -4167,6 +4555,54 @@ function App() {
         onSave={handleSavePanorama}
         progressMessage={panoramaModalState.progressMessage}
       />
+      <HdrModal
+        error={hdrModalState.error}
+        finalImageBase64={hdrModalState.finalImageBase64}
+        isOpen={hdrModalState.isOpen}
+        onCancel={() => {
+          invoke(Invokes.CancelHdrMerge).catch((err) => console.error('Failed to cancel HDR merge:', err));
+        }}
+        onClose={() =>
+          setHdrModalState({
+            isOpen: false,
+            progressMessage: '',
+            finalImageBase64: null,
+            error: null,
+            sourcePaths: [],
+          })
+        }
+        onOpenFile={(path: string) => {
+          handleImageSelect(path);
+        }}
+        onSave={handleSaveHdr}
+        progressMessage={hdrModalState.progressMessage}
+      />
+      <FocusStackModal
+        depthMapBase64={focusStackModalState.depthMapBase64}
+        error={focusStackModalState.error}
+        finalImageBase64={focusStackModalState.finalImageBase64}
+        isOpen={focusStackModalState.isOpen}
+        onCancel={() => {
+          invoke(Invokes.CancelFocusStack).catch((err) => console.error('Failed to cancel focus stack:', err));
+        }}
+        onClose={() =>
+          setFocusStackModalState({
+            depthMapBase64: null,
+            isOpen: false,
+            progressMessage: '',
+            finalImageBase64: null,
+            error: null,
+            sourcePaths: [],
+            warnings: [],
+          })
+        }
+        onOpenFile={(path: string) => {
+          handleImageSelect(path);
+        }}
+        onSave={handleSaveFocusStack}
+        progressMessage={focusStackModalState.progressMessage}
+        warnings={focusStackModalState.warnings}
+      />
       <DenoiseModal 
         isOpen={denoiseModalState.isOpen}
         onClose={() => setDenoiseModalState(prev => ({ ...prev, isOpen: false }))}
@@ -1,15 +1,22 @@
 import { useState } from 'react';
 import { Pipette } from 'lucide-react';
 import Slider from '../ui/Slider';
+import Switch from '../ui/Switch';
 import ColorWheel from '../ui/ColorWheel';
 import {
+  BLACK_AND_WHITE_PRESETS,
+  BlackAndWhitePreset,
   ColorAdjustment,
   ColorCalibration,
+  GrayMix,
   HueSatLum,
   INITIAL_ADJUSTMENTS,
 } from '../../utils/adjustments';
 import { Adjustments, ColorGrading } from '../../utils/adjustments';
-import { AppSettings } from '../ui/AppProperties';
+import { AppSettings, SelectedImage } from '../ui/AppProperties';
+
+const MIN_WB_KELVIN = 2000;
+const MAX_WB_KELVIN = 15000;
 
 interface ColorProps {
   color: string;
@@ -22,6 +29,7 @@ interface ColorPanelProps {
   appSettings: AppSettings | null;
   isForMask?: boolean;
   isWbPickerActive?: boolean;
+  selectedImage?: SelectedImage;
   toggleWbPicker?: () => void;
 }
 
@@ -218,21 +226,177 @@ const ColorCalibrationPanel = ({ adjustments, setAdjustments }: ColorPanelProps)
   );
 };
 
+const MIXER_CHANNELS: Array<ColorProps> = [
+  { name: 'red', color: '#f87171' },
+  { name: 'green', color: '#4ade80' },
+  { name: 'blue', color: '#60a5fa' },
+];
+
+const ChannelMixerPanel = ({ adjustments, setAdjustments }: ColorPanelProps) => {
+  const [activeOutput, setActiveOutput] = useState('red');
+  const channelMixer = adjustments.channelMixer || INITIAL_ADJUSTMENTS.channelMixer;
+
+  const handleWeightChange = (input: string, value: string) => {
+    setAdjustments((prev: Partial<Adjustments>) => {
+      const mixer = prev.channelMixer || INITIAL_ADJUSTMENTS.channelMixer;
+      return {
+        ...prev,
+        channelMixer: { ...mixer, [activeOutput]: { ...mixer[activeOutput], [input]: parseFloat(value) } },
+      };
+    });
+  };
+
+  const handlePreserveLuminosityChange = (preserveLuminosity: boolean) => {
+    setAdjustments((prev: Partial<Adjustments>) => ({
+      ...prev,
+      channelMixer: { ...(prev.channelMixer || INITIAL_ADJUSTMENTS.channelMixer), preserveLuminosity },
+    }));
+  };
+
+  return (
+    <div className="p-2 bg-bg-tertiary rounded-md mt-4">
+      <p className="text-md font-semibold mb-3 text-primary">Channel Mixer</p>
+      <p className="text-sm font-medium mb-3 text-secondary">Output Channel</p>
+      <div className="flex justify-center gap-6 mb-4 px-1">
+        {MIXER_CHANNELS.map(({ name, color }) => (
+          <ColorSwatch
+            color={color}
+            isActive={activeOutput === name}
+            key={name}
+            name={name}
+            onClick={setActiveOutput}
+          />
+        ))}
+      </div>
+      {MIXER_CHANNELS.map(({ name }) => (
+        <Slider
+          defaultValue={name === activeOutput ? 100 : 0}
+          key={name}
+          label={name.charAt(0).toUpperCase() + name.slice(1)}
+          max={200}
+          min={-200}
+          onChange={(e: any) => handleWeightChange(name, e.target.value)}
+          step={1}
+          value={channelMixer[activeOutput]?.[name] ?? (name === activeOutput ? 100 : 0)}
+        />
+      ))}
+      <div className="mt-3">
+        <Switch
+          checked={channelMixer.preserveLuminosity}
+          label="Preserve Luminosity"
+          onChange={handlePreserveLuminosityChange}
+        />
+      </div>
+    </div>
+  );
+};
+
+const BlackAndWhitePanel = ({ adjustments, setAdjustments }: ColorPanelProps) => {
+  const blackAndWhite = adjustments.blackAndWhite || INITIAL_ADJUSTMENTS.blackAndWhite;
+
+  const handleChange = (key: string, value: boolean | number) => {
+    setAdjustments((prev: Partial<Adjustments>) => ({
+      ...prev,
+      blackAndWhite: { ...(prev.blackAndWhite || INITIAL_ADJUSTMENTS.blackAndWhite), [key]: value },
+    }));
+  };
+
+  const handlePresetClick = (mix: GrayMix) => {
+    setAdjustments((prev: Partial<Adjustments>) => ({
+      ...prev,
+      blackAndWhite: { ...(prev.blackAndWhite || INITIAL_ADJUSTMENTS.blackAndWhite), ...mix, enabled: true },
+    }));
+  };
+
+  return (
+    <div className="p-2 bg-bg-tertiary rounded-md mt-4">
+      <p className="text-md font-semibold mb-3 text-primary">Black & White</p>
+      <Switch
+        checked={blackAndWhite.enabled}
+        label="Convert to Black & White"
+        onChange={(enabled: boolean) => handleChange('enabled', enabled)}
+      />
+      {blackAndWhite.enabled && (
+        <>
+          <div className="grid grid-cols-3 gap-1 my-3">
+            {BLACK_AND_WHITE_PRESETS.map(({ mix, name }: BlackAndWhitePreset) => (
+              <button
+                className="px-1 py-1 text-xs rounded-md bg-surface hover:bg-card-active transition-colors"
+                key={name}
+                onClick={() => handlePresetClick(mix)}
+              >
+                {name}
+              </button>
+            ))}
+          </div>
+          {HSL_COLORS.map(({ name }) => (
+            <Slider
+              defaultValue={0}
+              key={name}
+              label={name.charAt(0).toUpperCase() + name.slice(1)}
+              max={100}
+              min={-100}
+              onChange={(e: any) => handleChange(name, parseFloat(e.target.value))}
+              step={1}
+              value={blackAndWhite[name] as number}
+            />
+          ))}
+          <p className="text-sm font-medium mt-3 mb-1 text-secondary">Toning</p>
+          <Slider
+            defaultValue={40}
+            label="Hue"
+            max={360}
+            min={0}
+            onChange={(e: any) => handleChange('toningHue', parseFloat(e.target.value))}
+            step={1}
+            value={blackAndWhite.toningHue}
+          />
+          <Slider
+            defaultValue={0}
+            label="Saturation"
+            max={100}
+            min={0}
+            onChange={(e: any) => handleChange('toningSaturation', parseFloat(e.target.value))}
+            step={1}
+            value={blackAndWhite.toningSaturation}
+          />
+        </>
+      )}
+    </div>
+  );
+};
+
 export default function ColorPanel({
   adjustments,
   setAdjustments,
   appSettings,
   isForMask = false,
   isWbPickerActive = false,
+  selectedImage,
   toggleWbPicker,
 }: ColorPanelProps) {
   const [activeColor, setActiveColor] = useState('reds');
   const adjustmentVisibility = appSettings?.adjustmentVisibility || {};
+  const asShotWhiteBalance = isForMask ? null : selectedImage?.asShotWhiteBalance;
+  const asShotKelvin = Math.round(asShotWhiteBalance?.asShotKelvin ?? 0);
+  const asShotTint = Math.round(asShotWhiteBalance?.asShotTint ?? 0);
 
   const handleGlobalChange = (key: ColorAdjustment, value: string) => {
     setAdjustments((prev: Partial<Adjustments>) => ({ ...prev, [key]: parseFloat(value) }));
   };
 
+  // RAW white balance is absolute, so the relative sliders are cleared once it is set.
+  const handleRawWhiteBalanceChange = (key: ColorAdjustment, value: string) => {
+    setAdjustments((prev: Partial<Adjustments>) => ({
+      ...prev,
+      temperature: 0,
+      tint: 0,
+      whiteBalanceKelvin: prev.whiteBalanceKelvin ?? asShotKelvin,
+      whiteBalanceTint: prev.whiteBalanceTint ?? asShotTint,
+      [key]: parseFloat(value),
+    }));
+  };
+
   const handleHslChange = (key: ColorAdjustment, value: string) => {
     setAdjustments((prev: Partial<Adjustments>) => ({
       ...prev,
@@ -267,22 +431,47 @@ export default function ColorPanel({
             </button>
           )}
         </div>
-        <Slider
-          label="Temperature"
-          max={100}
-          min={-100}
-          onChange={(e: any) => handleGlobalChange(ColorAdjustment.Temperature, e.target.value)}
-          step={1}
-          value={adjustments.temperature || 0}
-        />
-        <Slider
-          label="Tint"
-          max={100}
-          min={-100}
-          onChange={(e: any) => handleGlobalChange(ColorAdjustment.Tint, e.target.value)}
-          step={1}
-          value={adjustments.tint || 0}
-        />
+        {asShotWhiteBalance ? (
+          <>
+            <Slider
+              defaultValue={asShotKelvin}
+              label="Temperature"
+              max={MAX_WB_KELVIN}
+              min={MIN_WB_KELVIN}
+              onChange={(e: any) => handleRawWhiteBalanceChange(ColorAdjustment.WhiteBalanceKelvin, e.target.value)}
+              step={50}
+              value={adjustments.whiteBalanceKelvin ?? asShotKelvin}
+            />
+            <Slider
+              defaultValue={asShotTint}
+              label="Tint"
+              max={150}
+              min={-150}
+              onChange={(e: any) => handleRawWhiteBalanceChange(ColorAdjustment.WhiteBalanceTint, e.target.value)}
+              step={1}
+              value={adjustments.whiteBalanceTint ?? asShotTint}
+            />
+          </>
+        ) : (
+          <>
+            <Slider
+              label="Temperature"
+              max={100}
+              min={-100}
+              onChange={(e: any) => handleGlobalChange(ColorAdjustment.Temperature, e.target.value)}
+              step={1}
+              value={adjustments.temperature || 0}
+            />
+            <Slider
+              label="Tint"
+              max={100}
+              min={-100}
+              onChange={(e: any) => handleGlobalChange(ColorAdjustment.Tint, e.target.value)}
+              step={1}
+              value={adjustments.tint || 0}
+            />
+          </>
+        )}
       </div>
 
       <div className="mb-4 p-2 bg-bg-tertiary rounded-md">
@@ -352,6 +541,13 @@ export default function ColorPanel({
       {!isForMask && adjustmentVisibility.colorCalibration !== false && (
         <ColorCalibrationPanel adjustments={adjustments} setAdjustments={setAdjustments} appSettings={appSettings} />
       )}
+
+      {!isForMask && (
+        <>
+          <ChannelMixerPanel adjustments={adjustments} setAdjustments={setAdjustments} appSettings={appSettings} />
+          <BlackAndWhitePanel adjustments={adjustments} setAdjustments={setAdjustments} appSettings={appSettings} />
+        </>
+      )}
     </div>
   );
 }
\ No newline at end of file
@@ -0,0 +1,235 @@
+import { useEffect, useRef, useState } from 'react';
+import clsx from 'clsx';
+import { RotateCcw } from 'lucide-react';
+import { Adjustments, ColorCurve, Coord } from '../../utils/adjustments';
+
+const MAX_POINTS = 32;
+const PATH_SAMPLES = 96;
+
+interface ColorCurveOption {
+  background: string;
+  curve: ColorCurve;
+  label: string;
+  periodic: boolean;
+}
+
+const HUE_GRADIENT = 'linear-gradient(to right, #f00, #ff0, #0f0, #0ff, #00f, #f0f, #f00)';
+const LUM_GRADIENT = 'linear-gradient(to right, #000, #fff)';
+const SAT_GRADIENT = 'linear-gradient(to right, #808080, #f00)';
+
+const COLOR_CURVE_OPTIONS: Array<ColorCurveOption> = [
+  { background: HUE_GRADIENT, curve: ColorCurve.HueHue, label: 'Hue vs Hue', periodic: true },
+  { background: HUE_GRADIENT, curve: ColorCurve.HueSat, label: 'Hue vs Sat', periodic: true },
+  { background: HUE_GRADIENT, curve: ColorCurve.HueLum, label: 'Hue vs Lum', periodic: true },
+  { background: LUM_GRADIENT, curve: ColorCurve.LumSat, label: 'Lum vs Sat', periodic: false },
+  { background: SAT_GRADIENT, curve: ColorCurve.SatSat, label: 'Sat vs Sat', periodic: false },
+];
+
+interface ColorCurvesProps {
+  adjustments: Adjustments;
+  setAdjustments(updater: (prev: any) => any): void;
+}
+
+// Mirrors eval_color_curve in the shader so the drawn curve matches the render.
+function evalColorCurve(points: Array<Coord>, x: number, periodic: boolean) {
+  const n = points.length;
+  if (n === 0) {
+    return 0.5;
+  }
+  if (n === 1) {
+    return points[0].y;
+  }
+
+  const pointAt = (index: number) => {
+    if (!periodic) {
+      return points[Math.max(0, Math.min(n - 1, index))];
+    }
+    if (index < 0) {
+      return { x: points[index + n].x - 1, y: points[index + n].y };
+    }
+    if (index >= n) {
+      return { x: points[index - n].x + 1, y: points[index - n].y };
+    }
+    return points[index];
+  };
+
+  let k = -1;
+  points.forEach((p: Coord, i: number) => {
+    if (p.x <= x) {
+      k = i;
+    }
+  });
+  if (!periodic) {
+    if (k < 0) {
+      return points[0].y;
+    }
+    if (k >= n - 1) {
+      return points[n - 1].y;
+    }
+  }
+
+  const [p0, p1, p2, p3] = [pointAt(k - 1), pointAt(k), pointAt(k + 1), pointAt(k + 2)];
+  const dx = p2.x - p1.x;
+  if (dx <= 0) {
+    return p1.y;
+  }
+  const m1 = (p2.y - p0.y) / Math.max(0.0001, p2.x - p0.x);
+  const m2 = (p3.y - p1.y) / Math.max(0.0001, p3.x - p1.x);
+  const t = (x - p1.x) / dx;
+  const t2 = t * t;
+  const t3 = t2 * t;
+  return (
+    (2 * t3 - 3 * t2 + 1) * p1.y + (t3 - 2 * t2 + t) * m1 * dx + (-2 * t3 + 3 * t2) * p2.y + (t3 - t2) * m2 * dx
+  );
+}
+
+function getColorCurvePath(points: Array<Coord>, periodic: boolean) {
+  const path = Array.from({ length: PATH_SAMPLES + 1 }, (_, i: number) => {
+    const x = i / PATH_SAMPLES;
+    const y = Math.max(0, Math.min(1, evalColorCurve(points, x, periodic)));
+    return `${x * 255},${255 - y * 255}`;
+  });
+  return `M${path.join(' L')}`;
+}
+
+export default function ColorCurves({ adjustments, setAdjustments }: ColorCurvesProps) {
+  const [activeCurve, setActiveCurve] = useState<ColorCurve>(ColorCurve.HueHue);
+  const [draggingPointIndex, setDraggingPointIndex] = useState<number | null>(null);
+  const svgRef = useRef<SVGSVGElement>(null);
+
+  const option = COLOR_CURVE_OPTIONS.find((o: ColorCurveOption) => o.curve === activeCurve) ?? COLOR_CURVE_OPTIONS[0];
+  const points: Array<Coord> = adjustments?.colorCurves?.[activeCurve] ?? [];
+
+  const updatePoints = (newPoints: Array<Coord>) => {
+    setAdjustments((prev: Adjustments) => ({
+      ...prev,
+      colorCurves: { ...prev.colorCurves, [activeCurve]: newPoints },
+    }));
+  };
+
+  const getMousePos = (e: any) => {
+    const svg = svgRef.current;
+    if (!svg) {
+      return { x: 0, y: 0.5 };
+    }
+    const rect = svg.getBoundingClientRect();
+    const x = Math.max(0, Math.min(1, (e.clientX - rect.left) / rect.width));
+    const y = Math.max(0, Math.min(1, 1 - (e.clientY - rect.top) / rect.height));
+    return { x, y };
+  };
+
+  useEffect(() => {
+    if (draggingPointIndex === null) {
+      return;
+    }
+
+    const moveHandler = (e: MouseEvent) => {
+      const { x, y } = getMousePos(e);
+      const prevX = draggingPointIndex > 0 ? points[draggingPointIndex - 1].x + 0.005 : 0;
+      const nextX = draggingPointIndex < points.length - 1 ? points[draggingPointIndex + 1].x - 0.005 : 1;
+      const newPoints = [...points];
+      newPoints[draggingPointIndex] = { x: Math.max(prevX, Math.min(nextX, x)), y };
+      updatePoints(newPoints);
+    };
+    const upHandler = () => setDraggingPointIndex(null);
+
+    window.addEventListener('mousemove', moveHandler);
+    window.addEventListener('mouseup', upHandler);
+    return () => {
+      window.removeEventListener('mousemove', moveHandler);
+      window.removeEventListener('mouseup', upHandler);
+    };
+  }, [draggingPointIndex, points, activeCurve]);
+
+  useEffect(() => {
+    setDraggingPointIndex(null);
+  }, [activeCurve]);
+
+  const handleContainerMouseDown = (e: any) => {
+    if (e.button !== 0 || points.length >= MAX_POINTS) {
+      return;
+    }
+    const { x } = getMousePos(e);
+    const newPoint = { x, y: Math.max(0, Math.min(1, evalColorCurve(points, x, option.periodic))) };
+    const newPoints = [...points, newPoint].sort((a: Coord, b: Coord) => a.x - b.x);
+    updatePoints(newPoints);
+    setDraggingPointIndex(newPoints.indexOf(newPoint));
+  };
+
+  const handlePointDoubleClick = (e: any, index: number) => {
+    e.stopPropagation();
+    updatePoints(points.filter((_: Coord, i: number) => i !== index));
+  };
+
+  return (
+    <div className="select-none mt-4">
+      <div className="flex items-center justify-between mb-2">
+        <p className="text-sm font-semibold text-text-primary">Color Curves</p>
+        <button
+          className="p-1.5 rounded-full hover:bg-surface disabled:opacity-50 disabled:cursor-not-allowed"
+          disabled={points.length === 0}
+          onClick={() => updatePoints([])}
+          title="Reset Curve"
+        >
+          <RotateCcw size={14} />
+        </button>
+      </div>
+      <div className="grid grid-cols-3 gap-1 mb-2">
+        {COLOR_CURVE_OPTIONS.map(({ curve, label }: ColorCurveOption) => (
+          <button
+            className={clsx(
+              'px-1 py-1 text-xs rounded-md transition-colors',
+              activeCurve === curve ? 'bg-accent text-button-text' : 'bg-surface hover:bg-card-active',
+            )}
+            key={curve}
+            onClick={() => setActiveCurve(curve)}
+          >
+            {label}
+          </button>
+        ))}
+      </div>
+
+      <div
+        className="w-full aspect-[2/1] bg-surface-secondary p-1 rounded-md relative"
+        onMouseDown={handleContainerMouseDown}
+      >
+        <div className="absolute left-1 right-1 bottom-1 h-1.5 rounded-sm" style={{ background: option.background }} />
+        <svg className="w-full h-full overflow-visible" preserveAspectRatio="none" ref={svgRef} viewBox="0 0 255 255">
+          <line
+            stroke="rgba(255,255,255,0.2)"
+            strokeDasharray="2 2"
+            strokeWidth="1"
+            vectorEffect="non-scaling-stroke"
+            x1="0"
+            x2="255"
+            y1="127.5"
+            y2="127.5"
+          />
+          <path
+            d={getColorCurvePath(points, option.periodic)}
+            fill="none"
+            stroke="rgb(var(--color-accent))"
+            strokeWidth="2"
+            vectorEffect="non-scaling-stroke"
+          />
+        </svg>
+        {points.map((p: Coord, i: number) => (
+          <div
+            className="absolute w-3 h-3 -ml-1.5 -mt-1.5 rounded-full bg-accent border-2 border-[#1e1e1e] cursor-pointer"
+            key={i}
+            onDoubleClick={(e: any) => handlePointDoubleClick(e, i)}
+            onMouseDown={(e: any) => {
+              e.preventDefault();
+              e.stopPropagation();
+              setDraggingPointIndex(i);
+            }}
+            style={{
+              left: `calc(0.25rem + (100% - 0.5rem) * ${p.x})`,
+              top: `calc(0.25rem + (100% - 0.5rem) * ${1 - p.y})`,
+            }}
+          />
+        ))}
+      </div>
+    </div>
+  );
+}
@@ -2,8 +2,10 @@ import { useState, useRef, useEffect } from 'react';
 import { motion, AnimatePresence } from 'framer-motion';
 import { AlertOctagon } from 'lucide-react';
 import clsx from 'clsx';
-import { ActiveChannel, Adjustments, Coord } from '../../utils/adjustments';
+import { ActiveChannel, Adjustments, Coord, ParametricCurve } from '../../utils/adjustments';
 import { Theme } from '../ui/AppProperties';
+import Slider from '../ui/Slider';
+import ColorCurves from './ColorCurves';
 
 export interface ChannelConfig {
   [index: string]: ColorData;
@@ -13,6 +15,18 @@ export interface ChannelConfig {
   [ActiveChannel.Blue]: ColorData;
 }
 
+interface ParametricRegion {
+  key: ParametricCurve;
+  label: string;
+}
+
+const PARAMETRIC_REGIONS: Array<ParametricRegion> = [
+  { key: ParametricCurve.Highlights, label: 'Highlights' },
+  { key: ParametricCurve.Lights, label: 'Lights' },
+  { key: ParametricCurve.Darks, label: 'Darks' },
+  { key: ParametricCurve.Shadows, label: 'Shadows' },
+];
+
 interface ColorData {
   color: string;
   data: any;
@@ -268,6 +282,10 @@ export default function CurveGraph({ adjustments, setAdjustments, histogram, the
     }));
   };
 
+  const handleParametricChange = (key: ParametricCurve, value: string) => {
+    setAdjustments((prev: Adjustments) => ({ ...prev, [key]: parseFloat(value) }));
+  };
+
   return (
     <div className="select-none">
       <div className="flex items-center justify-between gap-1 mb-2 mt-2">
@@ -364,6 +382,53 @@ export default function CurveGraph({ adjustments, setAdjustments, histogram, the
           ))}
         </svg>
       </div>
+
+      {!isMasksView && (
+        <div className="mt-4 space-y-1">
+          <p className="text-sm font-semibold text-text-primary mb-2">Regions</p>
+          {PARAMETRIC_REGIONS.map(({ key, label }: ParametricRegion) => (
+            <Slider
+              defaultValue={0}
+              key={key}
+              label={label}
+              max={100}
+              min={-100}
+              onChange={(e: any) => handleParametricChange(key, e.target.value)}
+              step={1}
+              value={adjustments[key] ?? 0}
+            />
+          ))}
+          <Slider
+            defaultValue={25}
+            label="Shadow Split"
+            max={(adjustments.parametricMidtoneSplit ?? 50) - 5}
+            min={5}
+            onChange={(e: any) => handleParametricChange(ParametricCurve.ShadowSplit, e.target.value)}
+            step={1}
+            value={adjustments.parametricShadowSplit ?? 25}
+          />
+          <Slider
+            defaultValue={50}
+            label="Midtone Split"
+            max={(adjustments.parametricHighlightSplit ?? 75) - 5}
+            min={(adjustments.parametricShadowSplit ?? 25) + 5}
+            onChange={(e: any) => handleParametricChange(ParametricCurve.MidtoneSplit, e.target.value)}
+            step={1}
+            value={adjustments.parametricMidtoneSplit ?? 50}
+          />
+          <Slider
+            defaultValue={75}
+            label="Highlight Split"
+            max={95}
+            min={(adjustments.parametricMidtoneSplit ?? 50) + 5}
+            onChange={(e: any) => handleParametricChange(ParametricCurve.HighlightSplit, e.target.value)}
+            step={1}
+            value={adjustments.parametricHighlightSplit ?? 75}
+          />
+        </div>
+      )}
+
+      <ColorCurves adjustments={adjustments} setAdjustments={setAdjustments} />
     </div>
   );
 }
\ No newline at end of file
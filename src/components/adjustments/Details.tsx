@@ -1,25 +1,79 @@
+import { useEffect, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
 import Slider from '../ui/Slider';
-import { Adjustments, DetailsAdjustment, Effect } from '../../utils/adjustments';
-import { AppSettings } from '../ui/AppProperties';
+import Switch from '../ui/Switch';
+import Dropdown from '../ui/Dropdown';
+import { Adjustments, DetailsAdjustment, Effect, LensProfile } from '../../utils/adjustments';
+import { AppSettings, Invokes, LensSummary, SelectedImage } from '../ui/AppProperties';
 
 interface DetailsPanelProps {
   adjustments: Adjustments;
   setAdjustments(adjustments: Partial<Adjustments>): any;
   appSettings: AppSettings | null;
   isForMask?: boolean;
+  selectedImage?: SelectedImage | null;
 }
 
+const lensKey = (maker: string, model: string) => `${maker}|${model}`;
+
 export default function DetailsPanel({
   adjustments,
   setAdjustments,
   appSettings,
   isForMask = false,
+  selectedImage = null,
 }: DetailsPanelProps) {
+  const [lensProfiles, setLensProfiles] = useState<Array<LensSummary>>([]);
+
+  useEffect(() => {
+    if (isForMask) {
+      return;
+    }
+    invoke(Invokes.ListLensProfiles)
+      .then((profiles: any) => setLensProfiles(profiles))
+      .catch((err) => console.error('Failed to load lens profiles:', err));
+  }, [isForMask]);
+
   const handleAdjustmentChange = (key: string, value: string) => {
     const numericValue = parseInt(value, 10);
     setAdjustments((prev: Partial<Adjustments>) => ({ ...prev, [key]: numericValue }));
   };
 
+  const detectedLens = selectedImage?.detectedLens || null;
+  const lensProfile: LensProfile | null = adjustments.lensProfile || null;
+
+  const handleLensCorrectionToggle = (enabled: boolean) => {
+    setAdjustments((prev: Partial<Adjustments>) => ({
+      ...prev,
+      lensProfile: enabled ? detectedLens : null,
+    }));
+  };
+
+  const handleLensProfileSelect = (key: string) => {
+    const lens = lensProfiles.find((profile: LensSummary) => lensKey(profile.maker, profile.model) === key);
+    if (!lens) {
+      return;
+    }
+    const base = lensProfile || detectedLens;
+    const exifFocal = parseFloat(selectedImage?.exif?.FocalLength);
+    const exifAperture = parseFloat(String(selectedImage?.exif?.FNumber || '').replace('f/', ''));
+    setAdjustments((prev: Partial<Adjustments>) => ({
+      ...prev,
+      lensProfile: {
+        aperture: base?.aperture || (isNaN(exifAperture) ? 0 : exifAperture),
+        cameraCropFactor: base?.cameraCropFactor || lens.cropFactor,
+        focalLength: base?.focalLength || (isNaN(exifFocal) ? 0 : exifFocal),
+        lensMaker: lens.maker,
+        lensModel: lens.model,
+      },
+    }));
+  };
+
+  const lensOptions = lensProfiles.map((profile: LensSummary) => ({
+    label: `${profile.maker} ${profile.model}`,
+    value: lensKey(profile.maker, profile.model),
+  }));
+
   const adjustmentVisibility = appSettings?.adjustmentVisibility || {};
 
   return (
@@ -102,9 +156,64 @@ export default function DetailsPanel({
       )}
       */}
 
+      {!isForMask && adjustmentVisibility.lensCorrections !== false && (
+        <div className="mt-4 p-2 bg-bg-tertiary rounded-md">
+          <p className="text-md font-semibold mb-2 text-primary">Lens Corrections</p>
+          <Switch
+            checked={!!lensProfile}
+            disabled={!lensProfile && !detectedLens}
+            label="Enable Profile Corrections"
+            onChange={handleLensCorrectionToggle}
+          />
+          {!lensProfile && !detectedLens && (
+            <p className="text-xs text-text-secondary mt-1">No matching profile found. Pick a lens below.</p>
+          )}
+          {lensOptions.length > 0 && (
+            <div className="mt-2 mb-2">
+              <Dropdown
+                onChange={handleLensProfileSelect}
+                options={lensOptions}
+                placeholder="Select lens profile"
+                value={lensProfile ? lensKey(lensProfile.lensMaker, lensProfile.lensModel) : ''}
+              />
+            </div>
+          )}
+          {lensProfile && (
+            <>
+              <Slider
+                label="Distortion"
+                max={200}
+                min={0}
+                onChange={(e: any) => handleAdjustmentChange(DetailsAdjustment.LensDistortionAmount, e.target.value)}
+                step={1}
+                value={adjustments.lensDistortionAmount ?? 100}
+              />
+              <Slider
+                label="Vignetting"
+                max={200}
+                min={0}
+                onChange={(e: any) => handleAdjustmentChange(DetailsAdjustment.LensVignettingAmount, e.target.value)}
+                step={1}
+                value={adjustments.lensVignettingAmount ?? 100}
+              />
+              <Slider
+                label="Chromatic Aberration"
+                max={200}
+                min={0}
+                onChange={(e: any) => handleAdjustmentChange(DetailsAdjustment.LensTcaAmount, e.target.value)}
+                step={1}
+                value={adjustments.lensTcaAmount ?? 100}
+              />
+            </>
+          )}
+        </div>
+      )}
+
       {adjustmentVisibility.chromaticAberration !== false && (
         <div className="mt-4 p-2 bg-bg-tertiary rounded-md">
-          <p className="text-md font-semibold mb-2 text-primary">Chromatic Aberration</p>
+          <p className="text-md font-semibold mb-2 text-primary">
+            {lensProfile ? 'Chromatic Aberration Fine-tune' : 'Chromatic Aberration'}
+          </p>
           <Slider
             label="Red/Cyan"
             max={100}
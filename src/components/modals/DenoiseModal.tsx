@@ -7,7 +7,7 @@ interface DenoiseModalProps {
   isOpen: boolean;
   onClose(): void;
   onDenoise(intensity: number): void;
-  onSave(): Promise<string>;
+  onSave(asDng?: boolean): Promise<string>;
   onOpenFile(path: string): void;
   error: string | null;
   previewBase64: string | null;
@@ -226,10 +226,10 @@ export default function DenoiseModal({
     onDenoise(intensity / 100);
   };
 
-  const handleSave = async () => {
+  const handleSave = async (asDng = false) => {
     setIsSaving(true);
     try {
-      const path = await onSave();
+      const path = await onSave(asDng);
       setSavedPath(path);
     } catch (e) {
       console.error(e);
@@ -351,7 +351,13 @@ export default function DenoiseModal({
             </Button>
 
             {previewBase64 && (
-                <Button onClick={handleSave} disabled={isSaving || isProcessing}>
+                <Button onClick={() => handleSave(true)} disabled={isSaving || isProcessing} variant="secondary">
+                    Save as DNG
+                </Button>
+            )}
+
+            {previewBase64 && (
+                <Button onClick={() => handleSave()} disabled={isSaving || isProcessing}>
                     {isSaving ? <Loader2 className="animate-spin mr-2" size={16} /> : <Save className="mr-2" size={16} />}
                     Save Image
                 </Button>
@@ -8,7 +8,7 @@ interface PanoramaModalProps {
   isOpen: boolean;
   onClose(): void;
   onOpenFile(path: string): void;
-  onSave(): Promise<string>;
+  onSave(asDng?: boolean): Promise<string>;
   progressMessage: string | null;
 }
 
@@ -49,10 +49,10 @@ export default function PanoramaModal({
     onClose();
   }, [onClose, isSaving]);
 
-  const handleSave = async () => {
+  const handleSave = async (asDng = false) => {
     setIsSaving(true);
     try {
-      const path = await onSave();
+      const path = await onSave(asDng);
       setSavedPath(path);
     } catch (e) {
       // Error handling can be added here if needed
@@ -147,7 +147,10 @@ export default function PanoramaModal({
           >
             Cancel
           </button>
-          <Button onClick={handleSave} disabled={isSaving}>
+          <Button onClick={() => handleSave(true)} disabled={isSaving} variant="secondary">
+            Save as DNG
+          </Button>
+          <Button onClick={() => handleSave()} disabled={isSaving}>
             {isSaving ? <Loader2 className="animate-spin mr-2" /> : <Save size={16} className="mr-2" />}
             {isSaving ? 'Saving...' : 'Save Panorama'}
           </Button>
@@ -7,13 +7,31 @@ import { invoke } from '@tauri-apps/api/core';
 import debounce from 'lodash.debounce';
 import { AnimatePresence } from 'framer-motion';
 import { ImageDimensions, useImageRenderSize } from '../../hooks/useImageRenderSize';
-import { Adjustments, AiPatch, Coord, MaskContainer } from '../../utils/adjustments';
+import {
+  Adjustments,
+  AiPatch,
+  Coord,
+  getPerspectiveParams,
+  getProjectionParams,
+  isPerspectiveActive,
+  isProjectionActive,
+  MaskContainer,
+  UprightGuide,
+} from '../../utils/adjustments';
 import FullScreenViewer from './editor/FullScreenViewer';
 import EditorToolbar from './editor/EditorToolbar';
 import ImageCanvas from './editor/ImageCanvas';
 import Waveform from './editor/Waveform';
 import { Mask, SubMask } from './right/Masks';
-import { BrushSettings, Invokes, Panel, SelectedImage, TransformState, WaveformData } from '../ui/AppProperties';
+import {
+  BrushSettings,
+  Invokes,
+  LiquifySettings,
+  Panel,
+  SelectedImage,
+  TransformState,
+  WaveformData,
+} from '../ui/AppProperties';
 
 interface EditorProps {
   activeAiPatchContainerId: string | null;
@@ -33,11 +51,15 @@ interface EditorProps {
   isLoading: boolean;
   isMaskControlHovered: boolean;
   isStraightenActive: boolean;
+  isUprightGuideActive: boolean;
   isWaveformVisible: boolean;
+  liquifySettings: LiquifySettings;
+  onAddUprightGuide(guide: UprightGuide): void;
   onBackToLibrary(): void;
   onCloseWaveform(): void;
   onContextMenu(event: any): void;
   onGenerateAiMask(subMaskId: string, startPoint: Coord, endPoint: Coord): void;
+  onLiquifyStroke(points: Array<Coord>, size: number): void;
   onQuickErase(subMaskId: string | null, startPoint: Coord, endpoint: Coord): void;
   onRedo(): void;
   onSelectAiSubMask(id: string | null): void;
@@ -58,6 +80,7 @@ interface EditorProps {
   transformedOriginalUrl: string | null;
   uncroppedAdjustedPreviewUrl: string | null;
   updateSubMask(id: string | null, subMask: Partial<SubMask>): void;
+  uprightGuides: Array<UprightGuide>;
   waveform: WaveformData | null;
   onDisplaySizeChange?(size: any): void;
   onInitialFitScale?(scale: number): void;
@@ -66,6 +89,7 @@ interface EditorProps {
   fullResolutionUrl?: string | null;
   isLoadingFullRes?: boolean;
   isWbPickerActive?: boolean;
+  onSampleRawWhiteBalance?(point: Coord): void;
   onWbPicked?: () => void;
 }
 
@@ -87,11 +111,15 @@ export default function Editor({
   isLoading,
   isMaskControlHovered,
   isStraightenActive,
+  isUprightGuideActive,
   isWaveformVisible,
+  liquifySettings,
+  onAddUprightGuide,
   onBackToLibrary,
   onCloseWaveform,
   onContextMenu,
   onGenerateAiMask,
+  onLiquifyStroke,
   onQuickErase,
   onRedo,
   onSelectAiSubMask,
@@ -111,6 +139,7 @@ export default function Editor({
   transformedOriginalUrl,
   uncroppedAdjustedPreviewUrl,
   updateSubMask,
+  uprightGuides,
   waveform,
   onDisplaySizeChange,
   onInitialFitScale,
@@ -119,6 +148,7 @@ export default function Editor({
   fullResolutionUrl,
   isLoadingFullRes,
   isWbPickerActive = false,
+  onSampleRawWhiteBalance,
   onWbPicked,
 }: EditorProps) {
   const [crop, setCrop] = useState<Crop | null>(null);
@@ -204,6 +234,7 @@ export default function Editor({
   const isCropping = activeRightPanel === Panel.Crop;
   const isMasking = activeRightPanel === Panel.Masks;
   const isAiEditing = activeRightPanel === Panel.Ai;
+  const isLiquifying = activeRightPanel === Panel.Liquify;
 
   const hasDisplayableImage = finalPreviewUrl || selectedImage.originalUrl || selectedImage.thumbnailUrl;
   const showSpinner = isLoading && !hasDisplayableImage;
@@ -324,6 +355,9 @@ export default function Editor({
     return () => clearTimeout(timer);
   }, [showSpinner]);
 
+  const perspectiveKey = JSON.stringify(getPerspectiveParams(adjustments));
+  const projectionKey = JSON.stringify(getProjectionParams(adjustments));
+
   useEffect(() => {
     if (!isCropping || !selectedImage?.width) {
       return;
@@ -335,7 +369,9 @@ export default function Editor({
       crop === null ||
       prevCropParams.current?.rotation !== rotation ||
       prevCropParams.current?.aspectRatio !== aspectRatio ||
-      prevCropParams.current?.orientationSteps !== orientationSteps;
+      prevCropParams.current?.orientationSteps !== orientationSteps ||
+      prevCropParams.current?.perspectiveKey !== perspectiveKey ||
+      prevCropParams.current?.projectionKey !== projectionKey;
 
     if (needsRecalc) {
       const { width: imgWidth, height: imgHeight } = selectedImage;
@@ -347,6 +383,18 @@ export default function Editor({
         return;
       }
 
+      if (isPerspectiveActive(adjustments) || isProjectionActive(adjustments)) {
+        prevCropParams.current = { rotation, aspectRatio, orientationSteps, perspectiveKey, projectionKey };
+        invoke(Invokes.CalculateMaxCrop, { jsAdjustments: adjustments, aspectRatio: A })
+          .then((maxPixelCrop: any) => {
+            setAdjustments((prev: Partial<Adjustments>) =>
+              JSON.stringify(prev.crop) !== JSON.stringify(maxPixelCrop) ? { ...prev, crop: maxPixelCrop } : prev,
+            );
+          })
+          .catch((err) => console.error('Failed to calculate max crop:', err));
+        return;
+      }
+
       const angle = Math.abs(rotation);
       const rad = ((angle % 180) * Math.PI) / 180;
       const sin = Math.sin(rad);
@@ -362,7 +410,7 @@ export default function Editor({
         height: Math.round(h_c),
       };
 
-      prevCropParams.current = { rotation, aspectRatio, orientationSteps };
+      prevCropParams.current = { rotation, aspectRatio, orientationSteps, perspectiveKey, projectionKey };
       if (JSON.stringify(crop) !== JSON.stringify(maxPixelCrop)) {
         setAdjustments((prev: Partial<Adjustments>) => ({ ...prev, crop: maxPixelCrop }));
       }
@@ -373,10 +421,36 @@ export default function Editor({
     adjustments.orientationSteps,
     adjustments.rotation,
     isCropping,
+    perspectiveKey,
+    projectionKey,
     selectedImage,
     setAdjustments,
   ]);
 
+  const constrainKey = JSON.stringify([
+    adjustments.crop,
+    adjustments.rotation,
+    adjustments.orientationSteps,
+    perspectiveKey,
+    projectionKey,
+  ]);
+
+  useEffect(() => {
+    if (isCropping || !adjustments.constrainCrop || !selectedImage?.width) {
+      return;
+    }
+
+    invoke(Invokes.ConstrainCrop, { jsAdjustments: adjustments })
+      .then((constrainedCrop: any) => {
+        setAdjustments((prev: Partial<Adjustments>) =>
+          constrainedCrop && JSON.stringify(prev.crop) !== JSON.stringify(constrainedCrop)
+            ? { ...prev, crop: constrainedCrop }
+            : prev,
+        );
+      })
+      .catch((err) => console.error('Failed to constrain crop:', err));
+  }, [adjustments.constrainCrop, constrainKey, isCropping, selectedImage?.width, setAdjustments]);
+
   useEffect(() => {
     if (!isCropping || !selectedImage?.width) {
       setCrop(null);
@@ -437,7 +511,7 @@ export default function Editor({
   const toggleShowOriginal = useCallback(() => setShowOriginal((prev: boolean) => !prev), [setShowOriginal]);
 
   const doubleClickProps: any = useMemo(() => {
-    if (isCropping || isMasking || isAiEditing) {
+    if (isCropping || isMasking || isAiEditing || isLiquifying) {
       return {
         disabled: true,
       };
@@ -447,7 +521,7 @@ export default function Editor({
       animationType: 'easeOut',
       mode: transformState.scale >= 2 ? 'reset' : 'zoomIn',
     };
-  }, [isCropping, isMasking, isAiEditing, transformState.scale]);
+  }, [isCropping, isMasking, isAiEditing, isLiquifying, transformState.scale]);
 
   if (!selectedImage) {
     return (
@@ -476,6 +550,7 @@ export default function Editor({
   const isPanningDisabled =
     isMaskHovered ||
     isCropping ||
+    isLiquifying ||
     (isMasking && (activeSubMask?.type === Mask.Brush || activeSubMask?.type === Mask.AiSubject)) ||
     (isAiEditing &&
       (activeSubMask?.type === Mask.Brush ||
@@ -566,14 +641,19 @@ export default function Editor({
                 isAdjusting={isAdjusting}
                 isAiEditing={isAiEditing}
                 isCropping={isCropping}
+                isLiquifying={isLiquifying}
                 isMaskControlHovered={isMaskControlHovered}
                 isMasking={isMasking}
                 isStraightenActive={isStraightenActive}
+                isUprightGuideActive={isUprightGuideActive}
+                liquifySettings={liquifySettings}
                 maskOverlayUrl={maskOverlayUrl}
                 onGenerateAiMask={onGenerateAiMask}
+                onLiquifyStroke={onLiquifyStroke}
                 onQuickErase={onQuickErase}
                 onSelectAiSubMask={onSelectAiSubMask}
                 onSelectMask={onSelectMask}
+                onAddUprightGuide={onAddUprightGuide}
                 onStraighten={onStraighten}
                 selectedImage={selectedImage}
                 setCrop={handleCropChange}
@@ -582,10 +662,12 @@ export default function Editor({
                 transformedOriginalUrl={transformedOriginalUrl}
                 uncroppedAdjustedPreviewUrl={uncroppedAdjustedPreviewUrl}
                 updateSubMask={updateSubMask}
+                uprightGuides={uprightGuides}
                 fullResolutionUrl={fullResolutionUrl}
                 isFullResolution={isFullResolution}
                 isLoadingFullRes={isLoadingFullRes}
                 isWbPickerActive={isWbPickerActive}
+                onSampleRawWhiteBalance={onSampleRawWhiteBalance}
                 onWbPicked={onWbPicked}
                 setAdjustments={setAdjustments}
               />
@@ -926,6 +926,18 @@ export default function SettingsPanel({
                       />
                     </SettingItem>
 
+                    <SettingItem
+                      label="Embedded RAW Previews"
+                      description="Use the preview JPEG embedded in RAW files for unedited thumbnails, culling and the first frame when opening an image. Culling falls back to fully developing every file when any RAW file in the selection lacks a preview large enough to analyze. Disable to always fully develop RAW files."
+                    >
+                      <Switch
+                        checked={!(appSettings?.forceFullRawDevelopment ?? false)}
+                        id="embedded-preview-toggle"
+                        label="Use Embedded Previews"
+                        onChange={(checked) => onSettingsChange({ ...appSettings, forceFullRawDevelopment: !checked })}
+                      />
+                    </SettingItem>
+
                     <SettingItem
                       label="Processing Backend"
                       description="Select the graphics API. 'Auto' is recommended. May fix crashes on some systems."
@@ -4,9 +4,16 @@ import 'react-image-crop/dist/ReactCrop.css';
 import { Stage, Layer, Ellipse, Line, Transformer, Group, Circle, Rect } from 'react-konva';
 import { PercentCrop, Crop } from 'react-image-crop';
 import clsx from 'clsx';
-import { Adjustments, AiPatch, Coord, MaskContainer } from '../../../utils/adjustments';
+import {
+  Adjustments,
+  AiPatch,
+  Coord,
+  isPerspectiveActive,
+  MaskContainer,
+  UprightGuide,
+} from '../../../utils/adjustments';
 import { Mask, SubMask, SubMaskMode, ToolType } from '../right/Masks';
-import { BrushSettings, SelectedImage } from '../../ui/AppProperties';
+import { BrushSettings, LiquifySettings, SelectedImage } from '../../ui/AppProperties';
 import { RenderSize } from '../../../hooks/useImageRenderSize';
 
 interface CursorPreview {
@@ -36,11 +43,16 @@ interface ImageCanvasProps {
   isAdjusting: boolean;
   isAiEditing: boolean;
   isCropping: boolean;
+  isLiquifying: boolean;
   isMaskControlHovered: boolean;
   isMasking: boolean;
   isStraightenActive: boolean;
+  isUprightGuideActive: boolean;
+  liquifySettings: LiquifySettings;
   maskOverlayUrl: string | null;
+  onAddUprightGuide(guide: UprightGuide): void;
   onGenerateAiMask(id: string | null, start: Coord, end: Coord): void;
+  onLiquifyStroke(points: Array<Coord>, size: number): void;
   onQuickErase(subMaskId: string | null, startPoint: Coord, endpoint: Coord): void;
   onSelectAiSubMask(id: string | null): void;
   onSelectMask(id: string | null): void;
@@ -52,10 +64,12 @@ interface ImageCanvasProps {
   transformedOriginalUrl: string | null;
   uncroppedAdjustedPreviewUrl: string | null;
   updateSubMask(id: string | null, subMask: Partial<SubMask>): void;
+  uprightGuides: Array<UprightGuide>;
   fullResolutionUrl?: string | null;
   isFullResolution?: boolean;
   isLoadingFullRes?: boolean;
   isWbPickerActive?: boolean;
+  onSampleRawWhiteBalance?(point: Coord): void;
   onWbPicked?: () => void;
   setAdjustments(fn: (prev: Adjustments) => Adjustments): void;
 }
@@ -473,11 +487,16 @@ const ImageCanvas = memo(
     isAdjusting,
     isAiEditing,
     isCropping,
+    isLiquifying,
     isMaskControlHovered,
     isMasking,
     isStraightenActive,
+    isUprightGuideActive,
+    liquifySettings,
     maskOverlayUrl,
+    onAddUprightGuide,
     onGenerateAiMask,
+    onLiquifyStroke,
     onQuickErase,
     onSelectAiSubMask,
     onSelectMask,
@@ -489,10 +508,12 @@ const ImageCanvas = memo(
     transformedOriginalUrl,
     uncroppedAdjustedPreviewUrl,
     updateSubMask,
+    uprightGuides,
     fullResolutionUrl,
     isFullResolution,
     isLoadingFullRes,
     isWbPickerActive = false,
+    onSampleRawWhiteBalance,
     onWbPicked,
     setAdjustments,
   }: ImageCanvasProps) => {
@@ -509,6 +530,8 @@ const ImageCanvas = memo(
     const [cursorPreview, setCursorPreview] = useState<CursorPreview>({ x: 0, y: 0, visible: false });
     const [straightenLine, setStraightenLine] = useState<any>(null);
     const isStraightening = useRef(false);
+    const [uprightGuideLine, setUprightGuideLine] = useState<UprightGuide | null>(null);
+    const isDrawingUprightGuide = useRef(false);
 
     const activeContainer = useMemo(() => {
       if (isMasking) {
@@ -544,7 +567,7 @@ const ImageCanvas = memo(
     const isAiSubjectActive =
       (isMasking || isAiEditing) &&
       (activeSubMask?.type === Mask.AiSubject || activeSubMask?.type === Mask.QuickEraser);
-    const isToolActive = isBrushActive || isAiSubjectActive;
+    const isToolActive = isBrushActive || isAiSubjectActive || isLiquifying;
 
     useEffect(() => {
       if (isToolActive) {
@@ -672,6 +695,13 @@ const ImageCanvas = memo(
       
       if (x < 0 || x > imgLogicalWidth || y < 0 || y > imgLogicalHeight) return;
 
+      // RAW files are balanced in the sensor domain, so the backend samples the developed image.
+      if (onSampleRawWhiteBalance) {
+        onSampleRawWhiteBalance({ x: x + (adjustments.crop?.x || 0), y: y + (adjustments.crop?.y || 0) });
+        onWbPicked();
+        return;
+      }
+
       const img = new Image();
       img.crossOrigin = "Anonymous";
       img.src = finalPreviewUrl;
@@ -740,7 +770,15 @@ const ImageCanvas = memo(
 
         onWbPicked();
       };
-    }, [isWbPickerActive, finalPreviewUrl, imageRenderSize, onWbPicked, setAdjustments]);
+    }, [
+      adjustments.crop,
+      isWbPickerActive,
+      finalPreviewUrl,
+      imageRenderSize,
+      onSampleRawWhiteBalance,
+      onWbPicked,
+      setAdjustments,
+    ]);
 
     const handleMouseDown = useCallback(
       (e: any) => {
@@ -783,7 +821,7 @@ const ImageCanvas = memo(
           }
         }
       },
-      [isWbPickerActive, handleWbClick, isBrushActive, isAiSubjectActive, brushSettings, onSelectMask, onSelectAiSubMask, isMasking, isAiEditing],
+      [isWbPickerActive, handleWbClick, isToolActive, isBrushActive, isAiSubjectActive, brushSettings, onSelectMask, onSelectAiSubMask, isMasking, isAiEditing],
     );
 
     const handleMouseMove = useCallback(
@@ -854,7 +892,12 @@ const ImageCanvas = memo(
 
       const activeId = isMasking ? activeMaskId : activeAiSubMaskId;
 
-      if (activeSubMask?.type === Mask.AiSubject || activeSubMask?.type === Mask.QuickEraser) {
+      if (isLiquifying) {
+        onLiquifyStroke(
+          line.points.map((p: Coord) => ({ x: p.x / scale + cropX, y: p.y / scale + cropY })),
+          liquifySettings.size / scale,
+        );
+      } else if (activeSubMask?.type === Mask.AiSubject || activeSubMask?.type === Mask.QuickEraser) {
         const points = line.points;
         if (points.length > 1) {
           const xs = points.map((p: Coord) => p.x);
@@ -902,8 +945,11 @@ const ImageCanvas = memo(
       imageRenderSize.scale,
       isAiEditing,
       isBrushActive,
+      isLiquifying,
       isMasking,
+      liquifySettings.size,
       onGenerateAiMask,
+      onLiquifyStroke,
       onQuickErase,
       updateSubMask,
     ]);
@@ -1027,6 +1073,59 @@ const ImageCanvas = memo(
       }
     };
 
+    const getUprightGuideScale = () => {
+      const orientationSteps = adjustments.orientationSteps || 0;
+      const effectiveWidth =
+        orientationSteps === 1 || orientationSteps === 3 ? selectedImage.height : selectedImage.width;
+      const renderWidth = uncroppedImageRenderSize?.width ?? 0;
+      return renderWidth > 0 ? effectiveWidth / renderWidth : 1;
+    };
+
+    const handleUprightGuideMouseDown = (e: any) => {
+      if (e.evt.button !== 0 || uprightGuides.length >= 4) {
+        return;
+      }
+
+      isDrawingUprightGuide.current = true;
+      const pos = e.target.getStage().getPointerPosition();
+      setUprightGuideLine({ start: pos, end: pos });
+    };
+
+    const handleUprightGuideMouseMove = (e: any) => {
+      if (!isDrawingUprightGuide.current) {
+        return;
+      }
+
+      const pos = e.target.getStage().getPointerPosition();
+      setUprightGuideLine((prev: UprightGuide | null) => (prev ? { ...prev, end: pos } : null));
+    };
+
+    const handleUprightGuideMouseUp = () => {
+      if (!isDrawingUprightGuide.current) {
+        return;
+      }
+      isDrawingUprightGuide.current = false;
+
+      if (uprightGuideLine) {
+        const { start, end } = uprightGuideLine;
+        if (Math.hypot(end.x - start.x, end.y - start.y) > 5) {
+          const scale = getUprightGuideScale();
+          onAddUprightGuide({
+            start: { x: start.x * scale, y: start.y * scale },
+            end: { x: end.x * scale, y: end.y * scale },
+          });
+        }
+      }
+      setUprightGuideLine(null);
+    };
+
+    const handleUprightGuideMouseLeave = () => {
+      if (isDrawingUprightGuide.current) {
+        isDrawingUprightGuide.current = false;
+        setUprightGuideLine(null);
+      }
+    };
+
     const cropPreviewUrl = uncroppedAdjustedPreviewUrl || selectedImage.originalUrl;
     const isContentReady = layers.length > 0 && layers.some((l) => l.url);
 
@@ -1057,7 +1156,13 @@ const ImageCanvas = memo(
       return { width: renderWidth, height: renderHeight };
     }, [selectedImage?.width, selectedImage?.height, imageRenderSize, adjustments.orientationSteps]);
 
+    const perspectiveActive = isPerspectiveActive(adjustments);
+
     const cropImageTransforms = useMemo(() => {
+      if (perspectiveActive && uncroppedAdjustedPreviewUrl) {
+        // The backend bakes flip, rotation and perspective into the preview.
+        return 'none';
+      }
       const transforms = [`rotate(${adjustments.rotation || 0}deg)`];
       if (adjustments.flipHorizontal) {
         transforms.push('scaleX(-1)');
@@ -1066,7 +1171,13 @@ const ImageCanvas = memo(
         transforms.push('scaleY(-1)');
       }
       return transforms.join(' ');
-    }, [adjustments.rotation, adjustments.flipHorizontal, adjustments.flipVertical]);
+    }, [
+      adjustments.rotation,
+      adjustments.flipHorizontal,
+      adjustments.flipVertical,
+      perspectiveActive,
+      uncroppedAdjustedPreviewUrl,
+    ]);
 
     return (
       <div className="relative" style={{ width: '100%', height: '100%' }}>
@@ -1187,6 +1298,17 @@ const ImageCanvas = memo(
                   y={cursorPreview.y}
                 />
               )}
+              {isLiquifying && cursorPreview.visible && (
+                <Circle
+                  listening={false}
+                  perfectDrawEnabled={false}
+                  radius={liquifySettings.size / 2}
+                  stroke="#0ea5e9"
+                  strokeWidth={1}
+                  x={cursorPreview.x}
+                  y={cursorPreview.y}
+                />
+              )}
             </Layer>
           </Stage>
         </div>
@@ -1211,7 +1333,7 @@ const ImageCanvas = memo(
                 crop={crop}
                 onChange={setCrop}
                 onComplete={handleCropComplete}
-                ruleOfThirds={!isStraightenActive}
+                ruleOfThirds={!isStraightenActive && !isUprightGuideActive}
               >
                 <img
                   alt="Crop preview"
@@ -1255,6 +1377,52 @@ const ImageCanvas = memo(
                   </Layer>
                 </Stage>
               )}
+
+              {isUprightGuideActive && (
+                <Stage
+                  height={uncroppedImageRenderSize.height}
+                  onMouseDown={handleUprightGuideMouseDown}
+                  onMouseLeave={handleUprightGuideMouseLeave}
+                  onMouseMove={handleUprightGuideMouseMove}
+                  onMouseUp={handleUprightGuideMouseUp}
+                  style={{ position: 'absolute', top: 0, left: 0, zIndex: 10, cursor: 'crosshair' }}
+                  width={uncroppedImageRenderSize.width}
+                >
+                  <Layer>
+                    {uprightGuides.map((guide: UprightGuide, index: number) => {
+                      const scale = getUprightGuideScale();
+                      return (
+                        <Line
+                          key={index}
+                          listening={false}
+                          points={[
+                            guide.start.x / scale,
+                            guide.start.y / scale,
+                            guide.end.x / scale,
+                            guide.end.y / scale,
+                          ]}
+                          stroke="#f59e0b"
+                          strokeWidth={2}
+                        />
+                      );
+                    })}
+                    {uprightGuideLine && (
+                      <Line
+                        dash={[4, 4]}
+                        listening={false}
+                        points={[
+                          uprightGuideLine.start.x,
+                          uprightGuideLine.start.y,
+                          uprightGuideLine.end.x,
+                          uprightGuideLine.end.y,
+                        ]}
+                        stroke="#f59e0b"
+                        strokeWidth={2}
+                      />
+                    )}
+                  </Layer>
+                </Stage>
+              )}
             </div>
           )}
         </div>
@@ -211,6 +211,7 @@ export default function Controls({
                   appSettings={appSettings}
                   isWbPickerActive={isWbPickerActive}
                   toggleWbPicker={toggleWbPicker}
+                  selectedImage={selectedImage}
                 />
               </CollapsibleSection>
             </div>
@@ -1,17 +1,30 @@
 import { useState, useEffect, useCallback, useMemo } from 'react';
+import { invoke } from '@tauri-apps/api/core';
 import {
   FlipHorizontal,
   FlipVertical,
+  Loader2,
   RectangleHorizontal,
   RectangleVertical,
   RotateCcw,
   RotateCw,
   Ruler,
+  Wand2,
   X,
 } from 'lucide-react';
-import { Adjustments, INITIAL_ADJUSTMENTS } from '../../../utils/adjustments';
+import {
+  Adjustments,
+  INITIAL_ADJUSTMENTS,
+  PERSPECTIVE_KEYS,
+  Projection,
+  PROJECTION_KEYS,
+  UprightGuide,
+} from '../../../utils/adjustments';
 import clsx from 'clsx';
-import { Orientation, SelectedImage } from '../../ui/AppProperties';
+import { Invokes, Orientation, SelectedImage } from '../../ui/AppProperties';
+import Dropdown, { OptionItem } from '../../ui/Dropdown';
+import Slider from '../../ui/Slider';
+import Switch from '../../ui/Switch';
 
 const BASE_RATIO = 1.618;
 const ORIGINAL_RATIO = 0;
@@ -19,9 +32,21 @@ const ORIGINAL_RATIO = 0;
 interface CropPanelProps {
   adjustments: Adjustments;
   isStraightenActive: boolean;
+  isUprightGuideActive: boolean;
+  onAutoStraighten(): void;
   selectedImage: SelectedImage;
   setAdjustments(adjustments: Partial<Adjustments>): void;
   setIsStraightenActive(active: any): void;
+  setIsUprightGuideActive(active: any): void;
+  setUprightGuides(guides: Array<UprightGuide>): void;
+  uprightGuides: Array<UprightGuide>;
+}
+
+enum UprightMode {
+  Full = 'full',
+  Guided = 'guided',
+  Level = 'level',
+  Vertical = 'vertical',
 }
 
 interface CropPreset {
@@ -29,6 +54,46 @@ interface CropPreset {
   value: number | null;
 }
 
+interface PerspectiveControl {
+  defaultValue: number;
+  key: string;
+  label: string;
+  max: number;
+  min: number;
+  step: number;
+}
+
+const PERSPECTIVE_CONTROLS: Array<PerspectiveControl> = [
+  { key: 'perspectiveVertical', label: 'Vertical', min: -100, max: 100, step: 1, defaultValue: 0 },
+  { key: 'perspectiveHorizontal', label: 'Horizontal', min: -100, max: 100, step: 1, defaultValue: 0 },
+  { key: 'perspectiveRotate', label: 'Rotate', min: -10, max: 10, step: 0.1, defaultValue: 0 },
+  { key: 'perspectiveAspect', label: 'Aspect', min: -100, max: 100, step: 1, defaultValue: 0 },
+  { key: 'perspectiveScale', label: 'Scale', min: 50, max: 150, step: 1, defaultValue: 100 },
+  { key: 'perspectiveXOffset', label: 'X Offset', min: -100, max: 100, step: 1, defaultValue: 0 },
+  { key: 'perspectiveYOffset', label: 'Y Offset', min: -100, max: 100, step: 1, defaultValue: 0 },
+];
+
+const PROJECTION_SOURCES: Array<OptionItem> = [
+  { label: 'Rectilinear', value: Projection.Rectilinear },
+  { label: 'Fisheye (Equisolid)', value: Projection.Equisolid },
+  { label: 'Fisheye (Equidistant)', value: Projection.Equidistant },
+  { label: 'Fisheye (Orthographic)', value: Projection.Orthographic },
+  { label: 'Fisheye (Stereographic)', value: Projection.Stereographic },
+];
+
+const PROJECTION_TARGETS: Array<OptionItem> = [
+  { label: 'Rectilinear', value: Projection.Rectilinear },
+  { label: 'Panini', value: Projection.Panini },
+  { label: 'Mercator', value: Projection.Mercator },
+  { label: 'Stereographic', value: Projection.Stereographic },
+];
+
+const UPRIGHT_MODES: Array<{ mode: UprightMode; label: string }> = [
+  { mode: UprightMode.Level, label: 'Level' },
+  { mode: UprightMode.Vertical, label: 'Vertical' },
+  { mode: UprightMode.Full, label: 'Full' },
+];
+
 const PRESETS: Array<CropPreset> = [
   { name: 'Free', value: null },
   { name: 'Original', value: ORIGINAL_RATIO },
@@ -44,12 +109,26 @@ const PRESETS: Array<CropPreset> = [
 export default function CropPanel({
   adjustments,
   isStraightenActive,
+  isUprightGuideActive,
+  onAutoStraighten,
   selectedImage,
   setAdjustments,
   setIsStraightenActive,
+  setIsUprightGuideActive,
+  setUprightGuides,
+  uprightGuides,
 }: CropPanelProps) {
   const [customW, setCustomW] = useState('');
   const [customH, setCustomH] = useState('');
+  const [uprightMode, setUprightMode] = useState<UprightMode | null>(null);
+  const [uprightError, setUprightError] = useState<string | null>(null);
+
+  useEffect(() => {
+    return () => {
+      setIsUprightGuideActive(false);
+      setUprightGuides([]);
+    };
+  }, [selectedImage?.path, setIsUprightGuideActive, setUprightGuides]);
 
   const { aspectRatio, rotation = 0, flipHorizontal = false, flipVertical = false, orientationSteps = 0 } = adjustments;
 
@@ -204,6 +283,73 @@ export default function CropPanel({
       flipVertical: INITIAL_ADJUSTMENTS.flipVertical || false,
       orientationSteps: INITIAL_ADJUSTMENTS.orientationSteps || 0,
       rotation: INITIAL_ADJUSTMENTS.rotation || 0,
+      ...Object.fromEntries(PERSPECTIVE_KEYS.map((key: string) => [key, INITIAL_ADJUSTMENTS[key]])),
+      ...Object.fromEntries(PROJECTION_KEYS.map((key: string) => [key, INITIAL_ADJUSTMENTS[key]])),
+    }));
+  };
+
+  const handlePerspectiveChange = (key: string, value: string) => {
+    setAdjustments((prev: Partial<Adjustments>) => ({ ...prev, [key]: parseFloat(value) }));
+  };
+
+  const resetPerspective = () => {
+    setAdjustments((prev: Partial<Adjustments>) => ({
+      ...prev,
+      ...Object.fromEntries(PERSPECTIVE_KEYS.map((key: string) => [key, INITIAL_ADJUSTMENTS[key]])),
+    }));
+  };
+
+  const handleUpright = async (mode: UprightMode) => {
+    setUprightMode(mode);
+    setUprightError(null);
+    try {
+      const result: Partial<Adjustments> = await invoke(Invokes.CalculateUpright, {
+        jsAdjustments: adjustments,
+        mode,
+        guides: mode === UprightMode.Guided ? uprightGuides : null,
+      });
+      setAdjustments((prev: Partial<Adjustments>) => ({ ...prev, ...result, crop: null }));
+      if (mode === UprightMode.Guided) {
+        setIsUprightGuideActive(false);
+        setUprightGuides([]);
+      }
+    } catch (err) {
+      console.error('Failed to calculate upright correction:', err);
+      setUprightError(String(err));
+    } finally {
+      setUprightMode(null);
+    }
+  };
+
+  const toggleUprightGuides = () => {
+    setIsStraightenActive(false);
+    setUprightGuides([]);
+    setIsUprightGuideActive((isActive: boolean) => !isActive);
+  };
+
+  const projectionSource = adjustments.projectionSource ?? Projection.Rectilinear;
+  const projectionTarget = adjustments.projectionTarget ?? Projection.Rectilinear;
+
+  const handleProjectionChange = (key: string, value: Projection) => {
+    setAdjustments((prev: Partial<Adjustments>) => {
+      const next: Partial<Adjustments> = { ...prev, [key]: value, crop: null };
+      if (!prev.projectionFocalLength && selectedImage?.lensOptics) {
+        next.projectionFocalLength = selectedImage.lensOptics.focalLength;
+        next.projectionCropFactor = selectedImage.lensOptics.cropFactor;
+      }
+      return next;
+    });
+  };
+
+  const handleProjectionValueChange = (key: string, value: string) => {
+    setAdjustments((prev: Partial<Adjustments>) => ({ ...prev, [key]: parseFloat(value) }));
+  };
+
+  const resetProjection = () => {
+    setAdjustments((prev: Partial<Adjustments>) => ({
+      ...prev,
+      ...Object.fromEntries(PROJECTION_KEYS.map((key: string) => [key, INITIAL_ADJUSTMENTS[key]])),
+      crop: null,
     }));
   };
 
@@ -333,6 +479,14 @@ export default function CropPanel({
                   </div>
                 </div>
               </div>
+              <Switch
+                checked={!!adjustments.constrainCrop}
+                label="Constrain to Image"
+                tooltip="Keep the crop inside the image after rotation and perspective correction"
+                onChange={(checked: boolean) =>
+                  setAdjustments((prev: Partial<Adjustments>) => ({ ...prev, constrainCrop: checked }))
+                }
+              />
             </div>
 
             <div className="space-y-3">
@@ -358,6 +512,153 @@ export default function CropPanel({
               />
             </div>
 
+            <div className="space-y-2">
+              <div className="flex justify-between items-center">
+                <p className="text-sm font-semibold text-text-primary">Perspective</p>
+                <button
+                  className="p-1.5 rounded-full hover:bg-surface"
+                  onClick={resetPerspective}
+                  title="Reset Perspective"
+                >
+                  <RotateCcw size={14} />
+                </button>
+              </div>
+              {PERSPECTIVE_CONTROLS.map((control: PerspectiveControl) => (
+                <Slider
+                  defaultValue={control.defaultValue}
+                  key={control.key}
+                  label={control.label}
+                  max={control.max}
+                  min={control.min}
+                  onChange={(e: any) => handlePerspectiveChange(control.key, e.target.value)}
+                  step={control.step}
+                  value={adjustments[control.key] ?? control.defaultValue}
+                />
+              ))}
+            </div>
+
+            <div className="space-y-2">
+              <div className="flex justify-between items-center">
+                <p className="text-sm font-semibold text-text-primary">Projection</p>
+                <button
+                  className="p-1.5 rounded-full hover:bg-surface"
+                  onClick={resetProjection}
+                  title="Reset Projection"
+                >
+                  <RotateCcw size={14} />
+                </button>
+              </div>
+              <div className="grid grid-cols-2 gap-2">
+                <div>
+                  <p className="text-xs mb-1 text-text-tertiary">Lens</p>
+                  <Dropdown
+                    onChange={(value: Projection) => handleProjectionChange('projectionSource', value)}
+                    options={PROJECTION_SOURCES}
+                    value={projectionSource}
+                  />
+                </div>
+                <div>
+                  <p className="text-xs mb-1 text-text-tertiary">Output</p>
+                  <Dropdown
+                    onChange={(value: Projection) => handleProjectionChange('projectionTarget', value)}
+                    options={PROJECTION_TARGETS}
+                    value={projectionTarget}
+                  />
+                </div>
+              </div>
+              {projectionSource !== projectionTarget && (
+                <>
+                  <Slider
+                    defaultValue={selectedImage.lensOptics?.focalLength ?? 0}
+                    label="Focal Length (mm)"
+                    max={50}
+                    min={0}
+                    onChange={(e: any) => handleProjectionValueChange('projectionFocalLength', e.target.value)}
+                    step={0.5}
+                    value={adjustments.projectionFocalLength ?? 0}
+                  />
+                  <Slider
+                    defaultValue={selectedImage.lensOptics?.cropFactor ?? 1}
+                    label="Crop Factor"
+                    max={6}
+                    min={0.5}
+                    onChange={(e: any) => handleProjectionValueChange('projectionCropFactor', e.target.value)}
+                    step={0.01}
+                    value={adjustments.projectionCropFactor ?? 1}
+                  />
+                  {projectionTarget === Projection.Panini && (
+                    <Slider
+                      defaultValue={100}
+                      label="Panini Compression"
+                      max={100}
+                      min={0}
+                      onChange={(e: any) => handleProjectionValueChange('projectionPaniniDistance', e.target.value)}
+                      step={1}
+                      value={adjustments.projectionPaniniDistance ?? 100}
+                    />
+                  )}
+                  <Slider
+                    defaultValue={100}
+                    label="Scale"
+                    max={200}
+                    min={50}
+                    onChange={(e: any) => handleProjectionValueChange('projectionScale', e.target.value)}
+                    step={1}
+                    value={adjustments.projectionScale ?? 100}
+                  />
+                </>
+              )}
+            </div>
+
+            <div className="space-y-3">
+              <p className="text-sm font-semibold text-text-primary">Upright</p>
+              <div className="grid grid-cols-3 gap-2">
+                {UPRIGHT_MODES.map(({ mode, label }) => (
+                  <button
+                    className="flex items-center justify-center gap-1.5 px-2 py-1.5 text-sm rounded-md transition-colors bg-surface hover:bg-card-active disabled:opacity-50 disabled:cursor-not-allowed"
+                    disabled={uprightMode !== null}
+                    key={mode}
+                    onClick={() => handleUpright(mode)}
+                  >
+                    {uprightMode === mode && <Loader2 size={14} className="animate-spin" />}
+                    {label}
+                  </button>
+                ))}
+              </div>
+              <div className="grid grid-cols-2 gap-2">
+                <button
+                  className={clsx(
+                    'px-2 py-1.5 text-sm rounded-md transition-colors',
+                    isUprightGuideActive ? 'bg-accent text-button-text' : 'bg-surface hover:bg-card-active',
+                  )}
+                  onClick={toggleUprightGuides}
+                >
+                  {isUprightGuideActive ? 'Cancel Guides' : 'Guided'}
+                </button>
+                <button
+                  className="flex items-center justify-center gap-1.5 px-2 py-1.5 text-sm rounded-md transition-colors bg-surface hover:bg-card-active disabled:opacity-50 disabled:cursor-not-allowed"
+                  disabled={!isUprightGuideActive || uprightGuides.length < 2 || uprightMode !== null}
+                  onClick={() => handleUpright(UprightMode.Guided)}
+                >
+                  {uprightMode === UprightMode.Guided && <Loader2 size={14} className="animate-spin" />}
+                  Apply ({uprightGuides.length}/4)
+                </button>
+              </div>
+              {isUprightGuideActive && (
+                <div className="flex justify-between items-center text-xs text-text-tertiary">
+                  <span>Draw 2–4 lines that should be vertical or horizontal.</span>
+                  <button
+                    className="hover:text-text-primary disabled:opacity-50"
+                    disabled={uprightGuides.length === 0}
+                    onClick={() => setUprightGuides([])}
+                  >
+                    Clear
+                  </button>
+                </div>
+              )}
+              {uprightError && <p className="text-xs text-red-400">{uprightError}</p>}
+            </div>
+
             <div className="space-y-4">
               <p className="text-sm mb-3 font-semibold text-text-primary">Tools</p>
               <div className="grid grid-cols-2 gap-2">
@@ -417,6 +718,7 @@ export default function CropPanel({
                     setIsStraightenActive((isActive: boolean) => {
                       const willBeActive = !isActive;
                       if (willBeActive) {
+                        setIsUprightGuideActive(false);
                         setAdjustments((prev: Partial<Adjustments>) => ({ ...prev, rotation: 0 }));
                       }
                       return willBeActive;
@@ -438,6 +740,13 @@ export default function CropPanel({
                     </span>
                   </span>
                 </button>
+                <button
+                  className="flex flex-col items-center justify-center p-3 rounded-lg transition-colors bg-surface text-text-secondary hover:bg-card-active hover:text-text-primary"
+                  onClick={onAutoStraighten}
+                >
+                  <Wand2 size={20} className="transition-none" />
+                  <span className="text-xs mt-1.5 transition-none">Auto Straighten</span>
+                </button>
               </div>
             </div>
           </>
@@ -5,6 +5,7 @@ export const EXPORT_TIMEOUT = 4000;
 export const IMPORT_TIMEOUT = 5000;
 
 export enum FileFormats {
+  Avif = 'avif',
   Jpeg = 'jpeg',
   Png = 'png',
   Tiff = 'tiff',
@@ -14,8 +15,66 @@ export const FILE_FORMATS: Array<FileFormat> = [
   { id: FileFormats.Jpeg, name: 'JPEG', extensions: ['jpg', 'jpeg'] },
   { id: FileFormats.Png, name: 'PNG', extensions: ['png'] },
   { id: FileFormats.Tiff, name: 'TIFF', extensions: ['tiff'] },
+  { id: FileFormats.Avif, name: 'AVIF', extensions: ['avif'] },
 ];
 
+export enum OutputColorSpace {
+  AdobeRgb = 'adobeRgb',
+  DisplayP3 = 'displayP3',
+  ProPhotoRgb = 'proPhotoRgb',
+  Rec2020 = 'rec2020',
+  Srgb = 'srgb',
+}
+
+export enum RenderingIntent {
+  Perceptual = 'perceptual',
+  RelativeColorimetric = 'relativeColorimetric',
+}
+
+export const COLOR_SPACE_OPTIONS = [
+  { label: 'sRGB', value: OutputColorSpace.Srgb },
+  { label: 'Display P3', value: OutputColorSpace.DisplayP3 },
+  { label: 'Adobe RGB (1998)', value: OutputColorSpace.AdobeRgb },
+  { label: 'ProPhoto RGB', value: OutputColorSpace.ProPhotoRgb },
+  { label: 'Rec. 2020', value: OutputColorSpace.Rec2020 },
+];
+
+export const RENDERING_INTENT_OPTIONS = [
+  { label: 'Perceptual', value: RenderingIntent.Perceptual },
+  { label: 'Relative Colorimetric', value: RenderingIntent.RelativeColorimetric },
+];
+
+export interface SoftProofSettings {
+  blackPointCompensation: boolean;
+  enabled: boolean;
+  gamutWarning: boolean;
+  profilePath: string | null;
+  renderingIntent: RenderingIntent;
+}
+
+export const INITIAL_SOFT_PROOF_SETTINGS: SoftProofSettings = {
+  blackPointCompensation: true,
+  enabled: false,
+  gamutWarning: false,
+  profilePath: null,
+  renderingIntent: RenderingIntent.Perceptual,
+};
+
+export enum HdrTransfer {
+  Hlg = 'hlg',
+  Pq = 'pq',
+}
+
+export const HDR_TRANSFER_OPTIONS = [
+  { label: 'PQ (HDR10)', value: HdrTransfer.Pq },
+  { label: 'HLG', value: HdrTransfer.Hlg },
+];
+
+export interface HdrExportSettings {
+  headroomStops: number;
+  transfer: HdrTransfer;
+}
+
 export const FILENAME_VARIABLES: Array<string> = [
   '{original_filename}',
   '{sequence}',
@@ -27,7 +86,9 @@ export const FILENAME_VARIABLES: Array<string> = [
 ];
 
 export interface ExportSettings {
+  colorSpace: OutputColorSpace;
   filenameTemplate: string | null;
+  hdr: HdrExportSettings | null;
   jpegQuality: number;
   keepMetadata: boolean;
   resize: {
@@ -35,6 +96,7 @@ export interface ExportSettings {
     value: number;
     dontEnlarge: boolean;
   } | null;
+  renderingIntent: RenderingIntent;
   stripGps: boolean;
   watermark: WatermarkSettings | null;
 }
@@ -11,11 +11,18 @@ import { Adjustments } from '../../../utils/adjustments';
 import {
   ExportSettings,
   FileFormat,
+  COLOR_SPACE_OPTIONS,
   FILE_FORMATS,
   FILENAME_VARIABLES,
   Status,
   ExportState,
   FileFormats,
+  HDR_TRANSFER_OPTIONS,
+  HdrTransfer,
+  OutputColorSpace,
+  RENDERING_INTENT_OPTIONS,
+  RenderingIntent,
+  SoftProofSettings,
   WatermarkAnchor,
 } from './ExportImportProperties';
 import { Invokes, SelectedImage } from '../../ui/AppProperties';
@@ -26,6 +33,8 @@ interface ExportPanelProps {
   multiSelectedPaths: Array<string>;
   selectedImage: SelectedImage;
   setExportState(state: any): void;
+  setSoftProofSettings(updater: (prev: SoftProofSettings) => SoftProofSettings): void;
+  softProofSettings: SoftProofSettings;
 }
 
 interface SectionProps {
@@ -162,6 +171,8 @@ export default function ExportPanel({
   multiSelectedPaths,
   selectedImage,
   setExportState,
+  setSoftProofSettings,
+  softProofSettings,
 }: ExportPanelProps) {
   const [fileFormat, setFileFormat] = useState<string>('jpeg');
   const [jpegQuality, setJpegQuality] = useState<number>(90);
@@ -171,6 +182,11 @@ export default function ExportPanel({
   const [dontEnlarge, setDontEnlarge] = useState<boolean>(true);
   const [keepMetadata, setKeepMetadata] = useState<boolean>(true);
   const [stripGps, setStripGps] = useState<boolean>(true);
+  const [colorSpace, setColorSpace] = useState<OutputColorSpace>(OutputColorSpace.Srgb);
+  const [renderingIntent, setRenderingIntent] = useState<RenderingIntent>(RenderingIntent.Perceptual);
+  const [enableHdr, setEnableHdr] = useState<boolean>(false);
+  const [hdrHeadroomStops, setHdrHeadroomStops] = useState<number>(2);
+  const [hdrTransfer, setHdrTransfer] = useState<HdrTransfer>(HdrTransfer.Pq);
   const [filenameTemplate, setFilenameTemplate] = useState<string>('{original_filename}_edited');
   const [estimatedSize, setEstimatedSize] = useState<number | null>(null);
   const [isEstimating, setIsEstimating] = useState<boolean>(false);
@@ -183,6 +199,11 @@ export default function ExportPanel({
   const [watermarkImageAspectRatio, setWatermarkImageAspectRatio] = useState(1);
   const filenameInputRef = useRef<HTMLInputElement>(null);
 
+  const supportsHdr = fileFormat === FileFormats.Jpeg || fileFormat === FileFormats.Avif;
+  const isHdrExport = enableHdr && supportsHdr;
+  const hdrSettings = isHdrExport ? { headroomStops: hdrHeadroomStops, transfer: hdrTransfer } : null;
+  const exportColorSpace = fileFormat === FileFormats.Avif || isHdrExport ? OutputColorSpace.Srgb : colorSpace;
+
   const { status, progress, errorMessage } = exportState;
   const isExporting = status === Status.Exporting;
 
@@ -197,6 +218,24 @@ export default function ExportPanel({
   const numImages = pathsToExport.length;
   const isBatchMode = numImages > 1;
 
+  const updateSoftProof = (changes: Partial<SoftProofSettings>) => {
+    setSoftProofSettings((prev: SoftProofSettings) => ({ ...prev, ...changes }));
+  };
+
+  const handleSelectProofProfile = async () => {
+    try {
+      const selected = await open({
+        multiple: false,
+        filters: [{ name: 'ICC Profiles', extensions: ['icc', 'icm'] }],
+      });
+      if (typeof selected === 'string') {
+        updateSoftProof({ enabled: true, profilePath: selected });
+      }
+    } catch (err) {
+      console.error('Failed to open ICC profile dialog:', err);
+    }
+  };
+
   const imageAspectRatio = useMemo(() => {
     if (selectedImage && selectedImage.width && selectedImage.height) {
       return selectedImage.width / selectedImage.height;
@@ -266,9 +305,12 @@ export default function ExportPanel({
 
   useEffect(() => {
     const exportSettings: ExportSettings = {
+      colorSpace: exportColorSpace,
       filenameTemplate,
+      hdr: hdrSettings,
       jpegQuality,
       keepMetadata,
+      renderingIntent,
       resize: enableResize ? { mode: resizeMode, value: resizeValue, dontEnlarge } : null,
       stripGps,
       watermark:
@@ -296,6 +338,11 @@ export default function ExportPanel({
     dontEnlarge,
     keepMetadata,
     stripGps,
+    colorSpace,
+    renderingIntent,
+    enableHdr,
+    hdrHeadroomStops,
+    hdrTransfer,
     filenameTemplate,
     enableWatermark,
     watermarkPath,
@@ -340,9 +387,12 @@ export default function ExportPanel({
     }
 
     const exportSettings: ExportSettings = {
+      colorSpace: exportColorSpace,
       filenameTemplate: finalFilenameTemplate,
+      hdr: hdrSettings,
       jpegQuality: jpegQuality,
       keepMetadata,
+      renderingIntent,
       resize: enableResize ? { mode: resizeMode, value: resizeValue, dontEnlarge } : null,
       stripGps,
       watermark:
@@ -423,7 +473,7 @@ export default function ExportPanel({
         {canExport ? (
           <>
             <Section title="File Settings">
-              <div className="grid grid-cols-3 gap-2">
+              <div className="grid grid-cols-4 gap-2">
                 {FILE_FORMATS.map((format: FileFormat) => (
                   <button
                     className={`px-2 py-1.5 text-sm rounded-md transition-colors ${
@@ -437,7 +487,7 @@ export default function ExportPanel({
                   </button>
                 ))}
               </div>
-              {fileFormat === FileFormats.Jpeg && (
+              {(fileFormat === FileFormats.Jpeg || fileFormat === FileFormats.Avif) && (
                 <div className={isExporting ? 'opacity-50 pointer-events-none' : ''}>
                   <Slider
                     defaultValue={90}
@@ -450,6 +500,61 @@ export default function ExportPanel({
                   />
                 </div>
               )}
+              {supportsHdr && (
+                <div className={isExporting ? 'opacity-50 pointer-events-none' : ''}>
+                  <Switch
+                    checked={enableHdr}
+                    label={fileFormat === FileFormats.Jpeg ? 'HDR Gain Map (Ultra HDR)' : 'HDR (Rec. 2020)'}
+                    onChange={setEnableHdr}
+                  />
+                  {isHdrExport && (
+                    <div className="mt-2 space-y-2">
+                      <Slider
+                        defaultValue={2}
+                        label="HDR Headroom (stops)"
+                        max={4}
+                        min={1}
+                        onChange={(e) => setHdrHeadroomStops(parseFloat(e.target.value))}
+                        step={0.5}
+                        value={hdrHeadroomStops}
+                      />
+                      {fileFormat === FileFormats.Jpeg && colorSpace !== OutputColorSpace.Srgb && (
+                        <p className="text-xs text-text-secondary">HDR gain map exports use an sRGB base image.</p>
+                      )}
+                      {fileFormat === FileFormats.Avif && (
+                        <div className="flex items-center justify-between gap-2">
+                          <span className="text-sm">Transfer</span>
+                          <div className="w-44">
+                            <Dropdown onChange={setHdrTransfer} options={HDR_TRANSFER_OPTIONS} value={hdrTransfer} />
+                          </div>
+                        </div>
+                      )}
+                    </div>
+                  )}
+                </div>
+              )}
+              {fileFormat !== FileFormats.Avif && !isHdrExport && (
+                <div className={isExporting ? 'opacity-50 pointer-events-none' : ''}>
+                  <div className="flex items-center justify-between gap-2">
+                    <span className="text-sm">Color Space</span>
+                    <div className="w-44">
+                      <Dropdown options={COLOR_SPACE_OPTIONS} value={colorSpace} onChange={setColorSpace} />
+                    </div>
+                  </div>
+                  {colorSpace !== OutputColorSpace.Srgb && (
+                    <div className="flex items-center justify-between gap-2 mt-2">
+                      <span className="text-sm">Rendering Intent</span>
+                      <div className="w-44">
+                        <Dropdown
+                          onChange={setRenderingIntent}
+                          options={RENDERING_INTENT_OPTIONS}
+                          value={renderingIntent}
+                        />
+                      </div>
+                    </div>
+                  )}
+                </div>
+              )}
             </Section>
 
             {isBatchMode && (
@@ -583,6 +688,54 @@ export default function ExportPanel({
                 </div>
               )}
             </Section>
+
+            {isEditorContext && (
+              <Section title="Soft Proofing">
+                <div className="flex items-center justify-between gap-2">
+                  <span className="text-sm truncate" title={softProofSettings.profilePath ?? undefined}>
+                    {softProofSettings.profilePath
+                      ? softProofSettings.profilePath.split(/[\\/]/).pop()
+                      : 'No output profile selected'}
+                  </span>
+                  <button
+                    className="px-3 py-1.5 text-sm rounded-md bg-surface hover:bg-card-active flex-shrink-0"
+                    onClick={handleSelectProofProfile}
+                  >
+                    Choose ICC...
+                  </button>
+                </div>
+                <Switch
+                  checked={softProofSettings.enabled}
+                  disabled={!softProofSettings.profilePath}
+                  label="Simulate Output"
+                  onChange={(enabled: boolean) => updateSoftProof({ enabled })}
+                />
+                {softProofSettings.enabled && (
+                  <>
+                    <div className="flex items-center justify-between gap-2">
+                      <span className="text-sm">Rendering Intent</span>
+                      <div className="w-44">
+                        <Dropdown
+                          onChange={(renderingIntent: RenderingIntent) => updateSoftProof({ renderingIntent })}
+                          options={RENDERING_INTENT_OPTIONS}
+                          value={softProofSettings.renderingIntent}
+                        />
+                      </div>
+                    </div>
+                    <Switch
+                      checked={softProofSettings.blackPointCompensation}
+                      label="Black Point Compensation"
+                      onChange={(blackPointCompensation: boolean) => updateSoftProof({ blackPointCompensation })}
+                    />
+                    <Switch
+                      checked={softProofSettings.gamutWarning}
+                      label="Gamut Warning"
+                      onChange={(gamutWarning: boolean) => updateSoftProof({ gamutWarning })}
+                    />
+                  </>
+                )}
+              </Section>
+            )}
           </>
         ) : (
           <p className="text-center text-text-tertiary mt-4">No image selected for export.</p>
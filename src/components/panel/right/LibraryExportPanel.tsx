@@ -9,12 +9,18 @@ import Slider from '../../ui/Slider';
 import ImagePicker from '../../ui/ImagePicker';
 import {
   FileFormat,
+  COLOR_SPACE_OPTIONS,
   FILE_FORMATS,
   FILENAME_VARIABLES,
   Status,
   ExportSettings,
   ExportState,
   FileFormats,
+  HDR_TRANSFER_OPTIONS,
+  HdrTransfer,
+  OutputColorSpace,
+  RENDERING_INTENT_OPTIONS,
+  RenderingIntent,
   WatermarkAnchor,
 } from './ExportImportProperties';
 import { Invokes, ImageFile } from '../../ui/AppProperties';
@@ -172,6 +178,11 @@ export default function LibraryExportPanel({
   const [dontEnlarge, setDontEnlarge] = useState(true);
   const [keepMetadata, setKeepMetadata] = useState(true);
   const [stripGps, setStripGps] = useState(true);
+  const [colorSpace, setColorSpace] = useState(OutputColorSpace.Srgb);
+  const [renderingIntent, setRenderingIntent] = useState(RenderingIntent.Perceptual);
+  const [enableHdr, setEnableHdr] = useState(false);
+  const [hdrHeadroomStops, setHdrHeadroomStops] = useState(2);
+  const [hdrTransfer, setHdrTransfer] = useState(HdrTransfer.Pq);
   const [filenameTemplate, setFilenameTemplate] = useState('{original_filename}_edited');
   const [estimatedSize, setEstimatedSize] = useState<number | null>(null);
   const [isEstimating, setIsEstimating] = useState<boolean>(false);
@@ -184,6 +195,11 @@ export default function LibraryExportPanel({
   const [watermarkImageAspectRatio, setWatermarkImageAspectRatio] = useState(1);
   const filenameInputRef = useRef<HTMLInputElement>(null);
 
+  const supportsHdr = fileFormat === FileFormats.Jpeg || fileFormat === FileFormats.Avif;
+  const isHdrExport = enableHdr && supportsHdr;
+  const hdrSettings = isHdrExport ? { headroomStops: hdrHeadroomStops, transfer: hdrTransfer } : null;
+  const exportColorSpace = fileFormat === FileFormats.Avif ? OutputColorSpace.Srgb : colorSpace;
+
   const { status, progress, errorMessage } = exportState;
   const isExporting = status === Status.Exporting;
 
@@ -274,9 +290,12 @@ export default function LibraryExportPanel({
     }
 
     const exportSettings: ExportSettings = {
+      colorSpace: exportColorSpace,
       filenameTemplate,
+      hdr: hdrSettings,
       jpegQuality,
       keepMetadata,
+      renderingIntent,
       resize: enableResize ? { mode: resizeMode, value: resizeValue, dontEnlarge } : null,
       stripGps,
       watermark:
@@ -305,6 +324,11 @@ export default function LibraryExportPanel({
     dontEnlarge,
     keepMetadata,
     stripGps,
+    colorSpace,
+    renderingIntent,
+    enableHdr,
+    hdrHeadroomStops,
+    hdrTransfer,
     filenameTemplate,
     enableWatermark,
     watermarkPath,
@@ -349,9 +373,12 @@ export default function LibraryExportPanel({
     }
 
     const exportSettings: ExportSettings = {
+      colorSpace: exportColorSpace,
       filenameTemplate: finalFilenameTemplate,
+      hdr: hdrSettings,
       jpegQuality: jpegQuality,
       keepMetadata,
+      renderingIntent,
       resize: enableResize ? { mode: resizeMode, value: resizeValue, dontEnlarge } : null,
       stripGps,
       watermark:
@@ -419,7 +446,7 @@ export default function LibraryExportPanel({
         {canExport ? (
           <>
             <Section title="File Settings">
-              <div className="grid grid-cols-3 gap-2">
+              <div className="grid grid-cols-4 gap-2">
                 {FILE_FORMATS.map((format: FileFormat) => (
                   <button
                     className={`px-2 py-1.5 text-sm rounded-md transition-colors ${
@@ -433,7 +460,7 @@ export default function LibraryExportPanel({
                   </button>
                 ))}
               </div>
-              {fileFormat === FileFormats.Jpeg && (
+              {(fileFormat === FileFormats.Jpeg || fileFormat === FileFormats.Avif) && (
                 <div className={isExporting ? 'opacity-50 pointer-events-none' : ''}>
                   <Slider
                     defaultValue={90}
@@ -446,6 +473,58 @@ export default function LibraryExportPanel({
                   />
                 </div>
               )}
+              {supportsHdr && (
+                <div className={isExporting ? 'opacity-50 pointer-events-none' : ''}>
+                  <Switch
+                    checked={enableHdr}
+                    label={fileFormat === FileFormats.Jpeg ? 'HDR Gain Map (Ultra HDR)' : 'HDR (Rec. 2020)'}
+                    onChange={setEnableHdr}
+                  />
+                  {isHdrExport && (
+                    <div className="mt-2 space-y-2">
+                      <Slider
+                        defaultValue={2}
+                        label="HDR Headroom (stops)"
+                        max={4}
+                        min={1}
+                        onChange={(e) => setHdrHeadroomStops(parseFloat(e.target.value))}
+                        step={0.5}
+                        value={hdrHeadroomStops}
+                      />
+                      {fileFormat === FileFormats.Avif && (
+                        <div className="flex items-center justify-between gap-2">
+                          <span className="text-sm">Transfer</span>
+                          <div className="w-44">
+                            <Dropdown onChange={setHdrTransfer} options={HDR_TRANSFER_OPTIONS} value={hdrTransfer} />
+                          </div>
+                        </div>
+                      )}
+                    </div>
+                  )}
+                </div>
+              )}
+              {fileFormat !== FileFormats.Avif && !isHdrExport && (
+                <div className={isExporting ? 'opacity-50 pointer-events-none' : ''}>
+                  <div className="flex items-center justify-between gap-2">
+                    <span className="text-sm">Color Space</span>
+                    <div className="w-44">
+                      <Dropdown options={COLOR_SPACE_OPTIONS} value={colorSpace} onChange={setColorSpace} />
+                    </div>
+                  </div>
+                  {colorSpace !== OutputColorSpace.Srgb && (
+                    <div className="flex items-center justify-between gap-2 mt-2">
+                      <span className="text-sm">Rendering Intent</span>
+                      <div className="w-44">
+                        <Dropdown
+                          onChange={setRenderingIntent}
+                          options={RENDERING_INTENT_OPTIONS}
+                          value={renderingIntent}
+                        />
+                      </div>
+                    </div>
+                  )}
+                </div>
+              )}
             </Section>
 
             <Section title="File Naming">
@@ -0,0 +1,110 @@
+import { ArrowRightLeft, Eraser, Maximize2, Minimize2, RotateCcw } from 'lucide-react';
+import clsx from 'clsx';
+import { Adjustments, LiquifyTool } from '../../../utils/adjustments';
+import Slider from '../../ui/Slider';
+import { LiquifySettings, SelectedImage } from '../../ui/AppProperties';
+
+interface LiquifyPanelProps {
+  adjustments: Adjustments;
+  liquifySettings: LiquifySettings;
+  selectedImage: SelectedImage;
+  setAdjustments(adjustments: any): void;
+  setLiquifySettings(settings: LiquifySettings): void;
+}
+
+interface ToolOption {
+  icon: any;
+  label: string;
+  tool: LiquifyTool;
+}
+
+const TOOLS: Array<ToolOption> = [
+  { icon: ArrowRightLeft, label: 'Forward Warp', tool: LiquifyTool.Forward },
+  { icon: Maximize2, label: 'Bloat', tool: LiquifyTool.Bloat },
+  { icon: Minimize2, label: 'Pucker', tool: LiquifyTool.Pucker },
+  { icon: Eraser, label: 'Reconstruct', tool: LiquifyTool.Reconstruct },
+];
+
+export default function LiquifyPanel({
+  adjustments,
+  liquifySettings,
+  selectedImage,
+  setAdjustments,
+  setLiquifySettings,
+}: LiquifyPanelProps) {
+  const handleReset = () => {
+    setAdjustments((prev: Adjustments) => ({ ...prev, liquify: null }));
+  };
+
+  return (
+    <div className="flex flex-col h-full">
+      <div className="p-4 flex justify-between items-center flex-shrink-0 border-b border-surface">
+        <h2 className="text-xl font-bold text-primary text-shadow-shiny">Liquify</h2>
+        <button
+          className="p-2 rounded-full hover:bg-surface transition-colors disabled:opacity-50 disabled:cursor-not-allowed"
+          disabled={!adjustments.liquify}
+          onClick={handleReset}
+          title="Reset Liquify"
+        >
+          <RotateCcw size={18} />
+        </button>
+      </div>
+
+      <div className="flex-grow overflow-y-auto p-4 text-text-secondary space-y-6">
+        {selectedImage ? (
+          <>
+            <div className="space-y-3">
+              <p className="text-sm font-semibold text-text-primary">Tool</p>
+              <div className="grid grid-cols-2 gap-2">
+                {TOOLS.map(({ icon: Icon, label, tool }) => (
+                  <button
+                    className={clsx(
+                      'flex flex-col items-center justify-center p-3 rounded-lg transition-colors',
+                      liquifySettings.tool === tool
+                        ? 'bg-accent text-button-text'
+                        : 'bg-surface text-text-secondary hover:bg-card-active hover:text-text-primary',
+                    )}
+                    key={tool}
+                    onClick={() => setLiquifySettings({ ...liquifySettings, tool })}
+                  >
+                    <Icon size={20} />
+                    <span className="text-xs mt-1.5">{label}</span>
+                  </button>
+                ))}
+              </div>
+            </div>
+
+            <div className="space-y-2">
+              <p className="text-sm font-semibold text-text-primary">Brush</p>
+              <Slider
+                defaultValue={100}
+                label="Size"
+                max={500}
+                min={10}
+                onChange={(e: any) => setLiquifySettings({ ...liquifySettings, size: Number(e.target.value) })}
+                step={1}
+                value={liquifySettings.size}
+              />
+              <Slider
+                defaultValue={50}
+                label="Strength"
+                max={100}
+                min={1}
+                onChange={(e: any) => setLiquifySettings({ ...liquifySettings, strength: Number(e.target.value) })}
+                step={1}
+                value={liquifySettings.strength}
+              />
+            </div>
+
+            <p className="text-xs text-text-tertiary">
+              Paint on the image to warp it. Strokes are stored as a displacement mesh and can be undone or
+              reconstructed at any time.
+            </p>
+          </>
+        ) : (
+          <p className="text-center text-text-tertiary mt-4">No image selected.</p>
+        )}
+      </div>
+    </div>
+  );
+}
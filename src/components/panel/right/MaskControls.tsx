@@ -430,7 +430,7 @@ export default function MaskControls({
 
     const handleReset = () => {
       const resetValues: any = {};
-      for (const key of sectionKeys) {
+      for (const key of sectionKeys.filter((key: string) => key in INITIAL_MASK_ADJUSTMENTS)) {
         resetValues[key] = JSON.parse(JSON.stringify(INITIAL_MASK_ADJUSTMENTS[key]));
       }
       setMaskContainerAdjustments((prev: any) => ({
@@ -1,4 +1,4 @@
-import { SlidersHorizontal, Info, Scaling, BrainCircuit, Bookmark, Save, Layers } from 'lucide-react';
+import { SlidersHorizontal, Info, Scaling, BrainCircuit, Bookmark, Save, Layers, Waves } from 'lucide-react';
 import { Panel } from '../../ui/AppProperties';
 
 interface PanelOptions {
@@ -16,6 +16,7 @@ const panelOptions: Array<PanelOptions> = [
   { id: Panel.Metadata, icon: Info, title: 'Metadata' },
   { id: Panel.Adjustments, icon: SlidersHorizontal, title: 'Adjustments' },
   { id: Panel.Crop, icon: Scaling, title: 'Crop' },
+  { id: Panel.Liquify, icon: Waves, title: 'Liquify' },
   { id: Panel.Masks, icon: Layers, title: 'Masks' },
   { id: Panel.Presets, icon: Bookmark, title: 'Presets' },
   { id: Panel.Ai, icon: BrainCircuit, title: 'AI Tools' },
@@ -1,5 +1,5 @@
 import React from 'react';
-import { Adjustments, Color } from '../../utils/adjustments';
+import { Adjustments, Color, LensProfile, LiquifyTool } from '../../utils/adjustments';
 import { ToolType } from '../panel/right/Masks';
 
 export const GLOBAL_KEYS = [' ', 'ArrowUp', 'ArrowDown', 'f', 'b', 'w'];
@@ -11,14 +11,20 @@ export enum Invokes {
   ApplyAdjustmentsToPaths = 'apply_adjustments_to_paths',
   ApplyAutoAdjustmentsToPaths = 'apply_auto_adjustments_to_paths',
   ApplyDenoising = 'apply_denoising',
+  ApplyLiquifyStroke = 'apply_liquify_stroke',
   BatchExportImages = 'batch_export_images',
   CalculateAutoAdjustments = 'calculate_auto_adjustments',
+  CalculateAutoStraighten = 'calculate_auto_straighten',
+  CalculateMaxCrop = 'calculate_max_crop',
   CancelExport = 'cancel_export',
+  CancelFocusStack = 'cancel_focus_stack',
+  CancelHdrMerge = 'cancel_hdr_merge',
   CheckComfyuiStatus = 'check_comfyui_status',
   ClearAllSidecars = 'clear_all_sidecars',
   ClearAiTags = 'clear_ai_tags',
   ClearAllTags = 'clear_all_tags',
   ClearThumbnailCache = 'clear_thumbnail_cache',
+  ConstrainCrop = 'constrain_crop',
   CopyFiles = 'copy_files',
   CreateFolder = 'create_folder',
   CreateVirtualCopy = 'create_virtual_copy',
@@ -28,6 +34,7 @@ export enum Invokes {
   EstimateBatchExportSize = 'estimate_batch_export_size',
   EstimateExportSize = 'estimate_export_size',
   ExportImage = 'export_image',
+  FocusStackImages = 'focus_stack_images',
   GenerateAiForegroundMask = 'generate_ai_foreground_mask',
   GenerateAiSkyMask = 'generate_ai_sky_mask',
   GenerateAiSubjectMask = 'generate_ai_subject_mask',
@@ -51,10 +58,13 @@ export enum Invokes {
   InvokeGenerativeReplaseWithMaskDef = 'invoke_generative_replace_with_mask_def',
   ListImagesInDir = 'list_images_in_dir',
   ListImagesRecursive = 'list_images_recursive',
+  ListLensProfiles = 'list_lens_profiles',
+  CalculateUpright = 'calculate_upright',
   LoadImage = 'load_image',
   LoadMetadata = 'load_metadata',
   LoadPresets = 'load_presets',
   LoadSettings = 'load_settings',
+  MergeHdr = 'merge_hdr',
   MoveFiles = 'move_files',
   ReadExifForPaths = 'read_exif_for_paths',
   RemoveTagForPaths = 'remove_tag_for_paths',
@@ -64,10 +74,13 @@ export enum Invokes {
   SaveMetadataAndUpdateThumbnail = 'save_metadata_and_update_thumbnail',
   SaveCollage = 'save_collage',
   SaveDenoisedImage = 'save_denoised_image',
+  SaveFocusStack = 'save_focus_stack',
+  SaveHdr = 'save_hdr',
   SavePanorama = 'save_panorama',
   SavePresets = 'save_presets',
   SaveSettings = 'save_settings',
   SetColorLabelForPaths = 'set_color_label_for_paths',
+  SetSoftProof = 'set_soft_proof',
   ShowInFinder = 'show_in_finder',
   StartBackgroundIndexing = 'start_background_indexing',
   StitchPanorama = 'stitch_panorama',
@@ -77,6 +90,7 @@ export enum Invokes {
   GenerateAllCommunityPreviews = 'generate_all_community_previews',
   SaveCommunityPreset = 'save_community_preset',
   SaveTempFile = 'save_temp_file',
+  SampleWhiteBalance = 'sample_white_balance',
 }
 
 export enum Panel {
@@ -84,6 +98,7 @@ export enum Panel {
   Ai = 'ai',
   Crop = 'crop',
   Export = 'export',
+  Liquify = 'liquify',
   Masks = 'masks',
   Metadata = 'metadata',
   Presets = 'presets',
@@ -153,6 +168,7 @@ export interface AppSettings {
   rawHighlightCompression?: number;
   processingBackend?: string;
   linuxGpuOptimization?: boolean;
+  forceFullRawDevelopment?: boolean;
 }
 
 export interface BrushSettings {
@@ -161,6 +177,12 @@ export interface BrushSettings {
   tool: ToolType;
 }
 
+export interface LiquifySettings {
+  size: number;
+  strength: number;
+  tool: LiquifyTool;
+}
+
 export enum LibraryViewMode {
   Flat = 'flat',
   Recursive = 'recursive',
@@ -216,11 +238,39 @@ export interface Progress {
   total: number;
 }
 
+export interface LensSummary {
+  cropFactor: number;
+  hasDistortion: boolean;
+  hasTca: boolean;
+  hasVignetting: boolean;
+  maker: string;
+  model: string;
+}
+
+export interface StraightenEstimate {
+  accepted: boolean;
+  confidence: number;
+  rotation: number;
+}
+
+export interface LensOptics {
+  cropFactor: number;
+  focalLength: number;
+}
+
+export interface RawWhiteBalance {
+  asShotKelvin: number;
+  asShotTint: number;
+}
+
 export interface SelectedImage {
+  asShotWhiteBalance?: RawWhiteBalance | null;
+  detectedLens?: LensProfile | null;
   exif: any;
   height: number;
   isRaw: boolean;
   isReady: boolean;
+  lensOptics?: LensOptics | null;
   metadata?: any;
   original_base64?: string;
   originalUrl: string | null;
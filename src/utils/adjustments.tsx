@@ -9,6 +9,14 @@ export enum ActiveChannel {
   Red = 'red',
 }
 
+export enum ColorCurve {
+  HueHue = 'hueHue',
+  HueLum = 'hueLum',
+  HueSat = 'hueSat',
+  LumSat = 'lumSat',
+  SatSat = 'satSat',
+}
+
 export enum DisplayMode {
   Blue = 'blue',
   Green = 'green',
@@ -22,6 +30,23 @@ export enum PasteMode {
   Replace = 'replace',
 }
 
+export enum LiquifyTool {
+  Bloat = 'bloat',
+  Forward = 'forward',
+  Pucker = 'pucker',
+  Reconstruct = 'reconstruct',
+}
+
+export enum Projection {
+  Equidistant = 'equidistant',
+  Equisolid = 'equisolid',
+  Mercator = 'mercator',
+  Orthographic = 'orthographic',
+  Panini = 'panini',
+  Rectilinear = 'rectilinear',
+  Stereographic = 'stereographic',
+}
+
 export interface CopyPasteSettings {
   mode: PasteMode;
   includedAdjustments: Array<string>;
@@ -46,6 +71,8 @@ export enum ColorAdjustment {
   Temperature = 'temperature',
   Tint = 'tint',
   Vibrance = 'vibrance',
+  WhiteBalanceKelvin = 'whiteBalanceKelvin',
+  WhiteBalanceTint = 'whiteBalanceTint',
 }
 
 export enum ColorGrading {
@@ -62,12 +89,25 @@ export enum DetailsAdjustment {
   Structure = 'structure',
   Centré = 'centré',
   ColorNoiseReduction = 'colorNoiseReduction',
+  LensDistortionAmount = 'lensDistortionAmount',
+  LensTcaAmount = 'lensTcaAmount',
+  LensVignettingAmount = 'lensVignettingAmount',
   LumaNoiseReduction = 'lumaNoiseReduction',
   Sharpness = 'sharpness',
   ChromaticAberrationRedCyan = 'chromaticAberrationRedCyan',
   ChromaticAberrationBlueYellow = 'chromaticAberrationBlueYellow',
 }
 
+export enum ParametricCurve {
+  Darks = 'parametricDarks',
+  HighlightSplit = 'parametricHighlightSplit',
+  Highlights = 'parametricHighlights',
+  Lights = 'parametricLights',
+  MidtoneSplit = 'parametricMidtoneSplit',
+  Shadows = 'parametricShadows',
+  ShadowSplit = 'parametricShadowSplit',
+}
+
 export enum Effect {
   EnableNegativeConversion = 'enableNegativeConversion',
   FilmBaseColor = 'filmBaseColor',
@@ -94,19 +134,69 @@ export interface ColorCalibration {
   blueSaturation: number;
 }
 
+export interface ChannelMixerOutput {
+  [index: string]: number;
+  blue: number;
+  green: number;
+  red: number;
+}
+
+export interface ChannelMixer {
+  blue: ChannelMixerOutput;
+  green: ChannelMixerOutput;
+  preserveLuminosity: boolean;
+  red: ChannelMixerOutput;
+}
+
+export interface GrayMix {
+  [index: string]: number;
+  aquas: number;
+  blues: number;
+  greens: number;
+  magentas: number;
+  oranges: number;
+  purples: number;
+  reds: number;
+  yellows: number;
+}
+
+export interface BlackAndWhite {
+  [index: string]: boolean | number;
+  aquas: number;
+  blues: number;
+  enabled: boolean;
+  greens: number;
+  magentas: number;
+  oranges: number;
+  purples: number;
+  reds: number;
+  toningHue: number;
+  toningSaturation: number;
+  yellows: number;
+}
+
+export interface BlackAndWhitePreset {
+  mix: GrayMix;
+  name: string;
+}
+
 export interface Adjustments {
   [index: string]: any;
   aiPatches: Array<AiPatch>;
   aspectRatio: number | null;
+  blackAndWhite: BlackAndWhite;
   blacks: number;
   brightness: number;
   centré: number;
+  channelMixer: ChannelMixer;
   clarity: number;
   chromaticAberrationBlueYellow: number;
   chromaticAberrationRedCyan: number;
   colorCalibration: ColorCalibration;
+  colorCurves: ColorCurves;
   colorGrading: ColorGradingProps;
   colorNoiseReduction: number;
+  constrainCrop: boolean;
   contrast: number;
   curves: Curves;
   crop: Crop | null;
@@ -121,6 +211,11 @@ export interface Adjustments {
   grainSize: number;
   highlights: number;
   hsl: Hsl;
+  lensDistortionAmount: number;
+  lensProfile: LensProfile | null;
+  lensTcaAmount: number;
+  lensVignettingAmount: number;
+  liquify: LiquifyMesh | null;
   lumaNoiseReduction: number;
   lutData?: string | null;
   lutIntensity?: number;
@@ -132,6 +227,26 @@ export interface Adjustments {
   negativeGreenBalance: number;
   negativeRedBalance: number;
   orientationSteps: number;
+  parametricDarks: number;
+  parametricHighlightSplit: number;
+  parametricHighlights: number;
+  parametricLights: number;
+  parametricMidtoneSplit: number;
+  parametricShadows: number;
+  parametricShadowSplit: number;
+  perspectiveAspect: number;
+  perspectiveHorizontal: number;
+  perspectiveRotate: number;
+  perspectiveScale: number;
+  perspectiveVertical: number;
+  perspectiveXOffset: number;
+  perspectiveYOffset: number;
+  projectionCropFactor: number;
+  projectionFocalLength: number;
+  projectionPaniniDistance: number;
+  projectionScale: number;
+  projectionSource: Projection;
+  projectionTarget: Projection;
   rating: number;
   rotation: number;
   saturation: number;
@@ -148,9 +263,19 @@ export interface Adjustments {
   vignetteFeather: number;
   vignetteMidpoint: number;
   vignetteRoundness: number;
+  whiteBalanceKelvin: number | null;
+  whiteBalanceTint: number | null;
   whites: number;
 }
 
+export interface LensProfile {
+  aperture: number;
+  cameraCropFactor: number;
+  focalLength: number;
+  lensMaker: string;
+  lensModel: string;
+}
+
 export interface AiPatch {
   id: string;
   isLoading: boolean;
@@ -180,6 +305,17 @@ export interface Coord {
   y: number;
 }
 
+export interface UprightGuide {
+  end: Coord;
+  start: Coord;
+}
+
+export interface LiquifyMesh {
+  columns: number;
+  nodes: string;
+  rows: number;
+}
+
 export interface Curves {
   [index: string]: Array<Coord>;
   blue: Array<Coord>;
@@ -188,6 +324,15 @@ export interface Curves {
   red: Array<Coord>;
 }
 
+export interface ColorCurves {
+  [index: string]: Array<Coord>;
+  hueHue: Array<Coord>;
+  hueLum: Array<Coord>;
+  hueSat: Array<Coord>;
+  lumSat: Array<Coord>;
+  satSat: Array<Coord>;
+}
+
 export interface HueSatLum {
   hue: number;
   saturation: number;
@@ -211,6 +356,7 @@ export interface MaskAdjustments {
   blacks: number;
   brightness: number;
   clarity: number;
+  colorCurves: ColorCurves;
   colorGrading: ColorGradingProps;
   colorNoiseReduction: number;
   contrast: number;
@@ -286,10 +432,64 @@ const INITIAL_COLOR_CALIBRATION: ColorCalibration = {
   blueSaturation: 0,
 };
 
+const INITIAL_CHANNEL_MIXER: ChannelMixer = {
+  blue: { blue: 100, green: 0, red: 0 },
+  green: { blue: 0, green: 100, red: 0 },
+  preserveLuminosity: true,
+  red: { blue: 0, green: 0, red: 100 },
+};
+
+const NEUTRAL_GRAY_MIX: GrayMix = {
+  aquas: 0,
+  blues: 0,
+  greens: 0,
+  magentas: 0,
+  oranges: 0,
+  purples: 0,
+  reds: 0,
+  yellows: 0,
+};
+
+const INITIAL_BLACK_AND_WHITE: BlackAndWhite = {
+  ...NEUTRAL_GRAY_MIX,
+  enabled: false,
+  toningHue: 40,
+  toningSaturation: 0,
+};
+
+export const BLACK_AND_WHITE_PRESETS: Array<BlackAndWhitePreset> = [
+  { mix: NEUTRAL_GRAY_MIX, name: 'Neutral' },
+  {
+    mix: { aquas: -30, blues: -50, greens: -20, magentas: 20, oranges: 30, purples: -10, reds: 40, yellows: 10 },
+    name: 'Red Filter',
+  },
+  {
+    mix: { aquas: -15, blues: -30, greens: 0, magentas: 0, oranges: 20, purples: -15, reds: 15, yellows: 30 },
+    name: 'Yellow Filter',
+  },
+  {
+    mix: { aquas: 20, blues: -10, greens: 40, magentas: -20, oranges: -10, purples: -20, reds: -20, yellows: 20 },
+    name: 'Green Filter',
+  },
+  {
+    mix: { aquas: 20, blues: 40, greens: -10, magentas: 0, oranges: -30, purples: 30, reds: -30, yellows: -20 },
+    name: 'Blue Filter',
+  },
+];
+
+const INITIAL_COLOR_CURVES: ColorCurves = {
+  hueHue: [],
+  hueLum: [],
+  hueSat: [],
+  lumSat: [],
+  satSat: [],
+};
+
 export const INITIAL_MASK_ADJUSTMENTS: MaskAdjustments = {
   blacks: 0,
   brightness: 0,
   clarity: 0,
+  colorCurves: { ...INITIAL_COLOR_CURVES },
   colorGrading: { ...INITIAL_COLOR_GRADING },
   colorNoiseReduction: 0,
   contrast: 0,
@@ -354,15 +554,19 @@ export const INITIAL_MASK_CONTAINER: MaskContainer = {
 export const INITIAL_ADJUSTMENTS: Adjustments = {
   aiPatches: [],
   aspectRatio: null,
+  blackAndWhite: { ...INITIAL_BLACK_AND_WHITE },
   blacks: 0,
   brightness: 0,
   centré: 0,
+  channelMixer: { ...INITIAL_CHANNEL_MIXER },
   clarity: 0,
   chromaticAberrationBlueYellow: 0,
   chromaticAberrationRedCyan: 0,
   colorCalibration: { ...INITIAL_COLOR_CALIBRATION },
+  colorCurves: { ...INITIAL_COLOR_CURVES },
   colorGrading: { ...INITIAL_COLOR_GRADING },
   colorNoiseReduction: 0,
+  constrainCrop: false,
   contrast: 0,
   crop: null,
   curves: {
@@ -403,6 +607,11 @@ export const INITIAL_ADJUSTMENTS: Adjustments = {
     reds: { hue: 0, saturation: 0, luminance: 0 },
     yellows: { hue: 0, saturation: 0, luminance: 0 },
   },
+  lensDistortionAmount: 100,
+  lensProfile: null,
+  lensTcaAmount: 100,
+  lensVignettingAmount: 100,
+  liquify: null,
   lumaNoiseReduction: 0,
   lutData: null,
   lutIntensity: 100,
@@ -414,6 +623,26 @@ export const INITIAL_ADJUSTMENTS: Adjustments = {
   negativeGreenBalance: 0,
   negativeRedBalance: 0,
   orientationSteps: 0,
+  parametricDarks: 0,
+  parametricHighlightSplit: 75,
+  parametricHighlights: 0,
+  parametricLights: 0,
+  parametricMidtoneSplit: 50,
+  parametricShadows: 0,
+  parametricShadowSplit: 25,
+  perspectiveAspect: 0,
+  perspectiveHorizontal: 0,
+  perspectiveRotate: 0,
+  perspectiveScale: 100,
+  perspectiveVertical: 0,
+  perspectiveXOffset: 0,
+  perspectiveYOffset: 0,
+  projectionCropFactor: 1,
+  projectionFocalLength: 0,
+  projectionPaniniDistance: 100,
+  projectionScale: 100,
+  projectionSource: Projection.Rectilinear,
+  projectionTarget: Projection.Rectilinear,
   rating: 0,
   rotation: 0,
   saturation: 0,
@@ -436,6 +665,8 @@ export const INITIAL_ADJUSTMENTS: Adjustments = {
   vignetteFeather: 50,
   vignetteMidpoint: 50,
   vignetteRoundness: 0,
+  whiteBalanceKelvin: null,
+  whiteBalanceTint: null,
   whites: 0,
 };
 
@@ -462,6 +693,7 @@ export const normalizeLoadedAdjustments = (loadedAdjustments: Adjustments): any
         colorGrading: { ...INITIAL_MASK_ADJUSTMENTS.colorGrading, ...(containerAdjustments.colorGrading || {}) },
         hsl: { ...INITIAL_MASK_ADJUSTMENTS.hsl, ...(containerAdjustments.hsl || {}) },
         curves: { ...INITIAL_MASK_ADJUSTMENTS.curves, ...(containerAdjustments.curves || {}) },
+        colorCurves: { ...INITIAL_MASK_ADJUSTMENTS.colorCurves, ...(containerAdjustments.colorCurves || {}) },
         sectionVisibility: {
           ...INITIAL_MASK_ADJUSTMENTS.sectionVisibility,
           ...(containerAdjustments.sectionVisibility || {}),
@@ -479,10 +711,13 @@ export const normalizeLoadedAdjustments = (loadedAdjustments: Adjustments): any
   return {
     ...INITIAL_ADJUSTMENTS,
     ...loadedAdjustments,
+    blackAndWhite: { ...INITIAL_ADJUSTMENTS.blackAndWhite, ...(loadedAdjustments.blackAndWhite || {}) },
+    channelMixer: { ...INITIAL_ADJUSTMENTS.channelMixer, ...(loadedAdjustments.channelMixer || {}) },
     colorCalibration: { ...INITIAL_ADJUSTMENTS.colorCalibration, ...(loadedAdjustments.colorCalibration || {}) },
     colorGrading: { ...INITIAL_ADJUSTMENTS.colorGrading, ...(loadedAdjustments.colorGrading || {}) },
     hsl: { ...INITIAL_ADJUSTMENTS.hsl, ...(loadedAdjustments.hsl || {}) },
     curves: { ...INITIAL_ADJUSTMENTS.curves, ...(loadedAdjustments.curves || {}) },
+    colorCurves: { ...INITIAL_ADJUSTMENTS.colorCurves, ...(loadedAdjustments.colorCurves || {}) },
     masks: normalizedMasks,
     aiPatches: normalizedAiPatches,
     sectionVisibility: {
@@ -492,14 +727,50 @@ export const normalizeLoadedAdjustments = (loadedAdjustments: Adjustments): any
   };
 };
 
+export const PERSPECTIVE_KEYS: Array<string> = [
+  'perspectiveVertical',
+  'perspectiveHorizontal',
+  'perspectiveRotate',
+  'perspectiveAspect',
+  'perspectiveScale',
+  'perspectiveXOffset',
+  'perspectiveYOffset',
+];
+
+export const getPerspectiveParams = (adjustments: Adjustments) =>
+  Object.fromEntries(PERSPECTIVE_KEYS.map((key: string) => [key, adjustments[key] ?? INITIAL_ADJUSTMENTS[key]]));
+
+export const isPerspectiveActive = (adjustments: Adjustments) =>
+  PERSPECTIVE_KEYS.some((key: string) => (adjustments[key] ?? INITIAL_ADJUSTMENTS[key]) !== INITIAL_ADJUSTMENTS[key]);
+
+export const PROJECTION_KEYS: Array<string> = [
+  'projectionSource',
+  'projectionTarget',
+  'projectionFocalLength',
+  'projectionCropFactor',
+  'projectionPaniniDistance',
+  'projectionScale',
+];
+
+export const getProjectionParams = (adjustments: Adjustments) =>
+  Object.fromEntries(PROJECTION_KEYS.map((key: string) => [key, adjustments[key] ?? INITIAL_ADJUSTMENTS[key]]));
+
+export const isProjectionActive = (adjustments: Adjustments) => {
+  const { projectionSource, projectionTarget, projectionFocalLength } = getProjectionParams(adjustments);
+  return projectionSource !== projectionTarget && projectionFocalLength > 0;
+};
+
 export const COPYABLE_ADJUSTMENT_KEYS: Array<string> = [
+  'blackAndWhite',
   BasicAdjustment.Blacks,
   BasicAdjustment.Brightness,
   DetailsAdjustment.Clarity,
   DetailsAdjustment.Centré,
+  'channelMixer',
   DetailsAdjustment.ChromaticAberrationBlueYellow,
   DetailsAdjustment.ChromaticAberrationRedCyan,
   'colorCalibration',
+  'colorCurves',
   ColorAdjustment.ColorGrading,
   DetailsAdjustment.ColorNoiseReduction,
   BasicAdjustment.Contrast,
@@ -513,6 +784,9 @@ export const COPYABLE_ADJUSTMENT_KEYS: Array<string> = [
   Effect.GrainSize,
   BasicAdjustment.Highlights,
   ColorAdjustment.Hsl,
+  DetailsAdjustment.LensDistortionAmount,
+  DetailsAdjustment.LensTcaAmount,
+  DetailsAdjustment.LensVignettingAmount,
   'lutIntensity',
   'lutName',
   'lutPath',
@@ -521,6 +795,13 @@ export const COPYABLE_ADJUSTMENT_KEYS: Array<string> = [
   Effect.NegativeBlueBalance,
   Effect.NegativeGreenBalance,
   Effect.NegativeRedBalance,
+  ParametricCurve.Darks,
+  ParametricCurve.HighlightSplit,
+  ParametricCurve.Highlights,
+  ParametricCurve.Lights,
+  ParametricCurve.MidtoneSplit,
+  ParametricCurve.Shadows,
+  ParametricCurve.ShadowSplit,
   ColorAdjustment.Saturation,
   'sectionVisibility',
   BasicAdjustment.Shadows,
@@ -535,6 +816,8 @@ export const COPYABLE_ADJUSTMENT_KEYS: Array<string> = [
   Effect.VignetteFeather,
   Effect.VignetteMidpoint,
   Effect.VignetteRoundness,
+  ColorAdjustment.WhiteBalanceKelvin,
+  ColorAdjustment.WhiteBalanceTint,
   BasicAdjustment.Whites,
 ];
 
@@ -549,15 +832,29 @@ export const ADJUSTMENT_SECTIONS: Sections = {
     BasicAdjustment.Exposure,
     'toneMapper',
   ],
-  curves: ['curves'],
+  curves: [
+    'curves',
+    'colorCurves',
+    ParametricCurve.Highlights,
+    ParametricCurve.Lights,
+    ParametricCurve.Darks,
+    ParametricCurve.Shadows,
+    ParametricCurve.ShadowSplit,
+    ParametricCurve.MidtoneSplit,
+    ParametricCurve.HighlightSplit,
+  ],
   color: [
     ColorAdjustment.Saturation,
     ColorAdjustment.Temperature,
     ColorAdjustment.Tint,
+    ColorAdjustment.WhiteBalanceKelvin,
+    ColorAdjustment.WhiteBalanceTint,
     ColorAdjustment.Vibrance,
     ColorAdjustment.Hsl,
     ColorAdjustment.ColorGrading,
     'colorCalibration',
+    'channelMixer',
+    'blackAndWhite',
   ],
   details: [
     DetailsAdjustment.Clarity,
@@ -569,6 +866,10 @@ export const ADJUSTMENT_SECTIONS: Sections = {
     DetailsAdjustment.ColorNoiseReduction,
     DetailsAdjustment.ChromaticAberrationRedCyan,
     DetailsAdjustment.ChromaticAberrationBlueYellow,
+    'lensProfile',
+    DetailsAdjustment.LensDistortionAmount,
+    DetailsAdjustment.LensVignettingAmount,
+    DetailsAdjustment.LensTcaAmount,
   ],
   effects: [
     Effect.EnableNegativeConversion,